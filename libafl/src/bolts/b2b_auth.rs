@@ -0,0 +1,357 @@
+//! Pre-shared-key authentication and encryption of llmp broker-to-broker (b2b) connections.
+//!
+//! Before a b2b connection is accepted, both brokers prove to each other that they know the pre-shared key,
+//! bound to fresh nonces of both sides, so that a recorded handshake cannot be replayed.
+//! Both then derive keys for this session only, one per direction, to authenticate (and optionally encrypt)
+//! each forwarded message, together with a message counter against replayed, dropped, or reordered messages.
+
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::Error;
+
+/// The length of the nonces exchanged in the handshake
+pub const B2B_NONCE_LEN: usize = 32;
+
+/// The length of the authentication tag appended to each message of an authenticated, but unencrypted, session
+const B2B_MAC_LEN: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The side of a b2b connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum B2bRole {
+    /// The broker connecting to a remote broker, using [`crate::bolts::llmp::LlmpBroker::connect_b2b`]
+    Connector,
+    /// The broker accepting the connection on its tcp listener
+    Listener,
+}
+
+impl B2bRole {
+    fn label(self) -> &'static [u8] {
+        match self {
+            B2bRole::Connector => b"llmp b2b connector",
+            B2bRole::Listener => b"llmp b2b listener",
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            B2bRole::Connector => B2bRole::Listener,
+            B2bRole::Listener => B2bRole::Connector,
+        }
+    }
+}
+
+/// The pre-shared key of a b2b connection, and whether messages should be encrypted, or only authenticated.
+/// Both brokers need the same key and the same encryption setting.
+#[derive(Clone)]
+pub struct B2bAuth {
+    key: Vec<u8>,
+    encrypt: bool,
+}
+
+impl Debug for B2bAuth {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // Never print the key
+        f.debug_struct("B2bAuth")
+            .field("key", &"<redacted>")
+            .field("encrypt", &self.encrypt)
+            .finish()
+    }
+}
+
+impl B2bAuth {
+    /// Authenticate b2b connections and their messages with the given pre-shared key.
+    /// The key should be a long random secret, such as 32 random bytes, since it is not stretched.
+    pub fn new<K>(key: K) -> Result<Self, Error>
+    where
+        K: Into<Vec<u8>>,
+    {
+        let key = key.into();
+        if key.is_empty() {
+            return Err(Error::illegal_argument(
+                "The pre-shared key for b2b connections must not be empty",
+            ));
+        }
+        Ok(Self {
+            key,
+            encrypt: false,
+        })
+    }
+
+    /// Also encrypt all messages sent over b2b connections, not only authenticate them
+    #[must_use]
+    pub fn with_encryption(mut self, encrypt: bool) -> Self {
+        self.encrypt = encrypt;
+        self
+    }
+
+    /// If messages are encrypted, and not only authenticated
+    #[must_use]
+    pub fn encrypt(&self) -> bool {
+        self.encrypt
+    }
+
+    /// Creates a fresh random nonce for a handshake
+    #[must_use]
+    pub fn new_nonce() -> Vec<u8> {
+        let mut nonce = vec![0; B2B_NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        nonce
+    }
+
+    fn proof_mac(
+        &self,
+        role: B2bRole,
+        connector_nonce: &[u8],
+        listener_nonce: &[u8],
+    ) -> HmacSha256 {
+        let mut mac =
+            <HmacSha256 as Mac>::new_from_slice(&self.key).expect("HMAC accepts keys of any size");
+        mac.update(role.label());
+        mac.update(&[u8::from(self.encrypt)]);
+        mac.update(connector_nonce);
+        mac.update(listener_nonce);
+        mac
+    }
+
+    /// The proof of `role` that it knows the pre-shared key, bound to the nonces of this handshake
+    #[must_use]
+    pub fn proof(&self, role: B2bRole, connector_nonce: &[u8], listener_nonce: &[u8]) -> Vec<u8> {
+        self.proof_mac(role, connector_nonce, listener_nonce)
+            .finalize()
+            .into_bytes()
+            .to_vec()
+    }
+
+    /// Checks the `proof` the peer in `role` sent, in constant time
+    #[must_use]
+    pub fn verify_proof(
+        &self,
+        role: B2bRole,
+        connector_nonce: &[u8],
+        listener_nonce: &[u8],
+        proof: &[u8],
+    ) -> bool {
+        connector_nonce.len() == B2B_NONCE_LEN
+            && listener_nonce.len() == B2B_NONCE_LEN
+            && self
+                .proof_mac(role, connector_nonce, listener_nonce)
+                .verify_slice(proof)
+                .is_ok()
+    }
+
+    /// Derives the keys of a session, after a successful handshake, for the broker in `role`
+    pub fn session(
+        &self,
+        role: B2bRole,
+        connector_nonce: &[u8],
+        listener_nonce: &[u8],
+    ) -> Result<B2bSession, Error> {
+        let salt = [connector_nonce, listener_nonce].concat();
+        let hkdf = Hkdf::<Sha256>::new(Some(&salt), &self.key);
+        let derive = |from: B2bRole| -> Result<[u8; 32], Error> {
+            let mut key = [0; 32];
+            hkdf.expand(from.label(), &mut key)
+                .map_err(|_| Error::illegal_state("Could not derive b2b session key"))?;
+            Ok(key)
+        };
+        let send_key = derive(role)?;
+        let recv_key = derive(role.other())?;
+
+        let keys = if self.encrypt {
+            B2bSessionKeys::Encrypted {
+                send: ChaCha20Poly1305::new(Key::from_slice(&send_key)),
+                recv: ChaCha20Poly1305::new(Key::from_slice(&recv_key)),
+            }
+        } else {
+            B2bSessionKeys::Authenticated {
+                send: <HmacSha256 as Mac>::new_from_slice(&send_key)
+                    .expect("HMAC accepts keys of any size"),
+                recv: <HmacSha256 as Mac>::new_from_slice(&recv_key)
+                    .expect("HMAC accepts keys of any size"),
+            }
+        };
+        Ok(B2bSession {
+            keys,
+            send_counter: 0,
+            recv_counter: 0,
+        })
+    }
+}
+
+// There is only one per b2b connection
+#[allow(clippy::large_enum_variant)]
+enum B2bSessionKeys {
+    Authenticated {
+        send: HmacSha256,
+        recv: HmacSha256,
+    },
+    Encrypted {
+        send: ChaCha20Poly1305,
+        recv: ChaCha20Poly1305,
+    },
+}
+
+/// The keys and message counters of an established b2b connection.
+/// Each message is bound to its position in the stream, so a message can only be accepted once, and in order.
+pub struct B2bSession {
+    keys: B2bSessionKeys,
+    send_counter: u64,
+    recv_counter: u64,
+}
+
+impl Debug for B2bSession {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("B2bSession")
+            .field(
+                "encrypt",
+                &matches!(self.keys, B2bSessionKeys::Encrypted { .. }),
+            )
+            .field("send_counter", &self.send_counter)
+            .field("recv_counter", &self.recv_counter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The `ChaCha20Poly1305` nonce for the message with the given counter
+fn counter_nonce(counter: u64) -> Nonce {
+    let mut nonce = [0; 12];
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    *Nonce::from_slice(&nonce)
+}
+
+impl B2bSession {
+    /// Authenticates, and optionally encrypts, the next outgoing message
+    pub fn seal(&mut self, msg: &[u8]) -> Result<Vec<u8>, Error> {
+        let counter = self.send_counter;
+        self.send_counter += 1;
+        match &self.keys {
+            B2bSessionKeys::Authenticated { send, .. } => {
+                let mut mac = send.clone();
+                mac.update(&counter.to_be_bytes());
+                mac.update(msg);
+                let mut frame = Vec::with_capacity(msg.len() + B2B_MAC_LEN);
+                frame.extend_from_slice(msg);
+                frame.extend_from_slice(&mac.finalize().into_bytes());
+                Ok(frame)
+            }
+            B2bSessionKeys::Encrypted { send, .. } => send
+                .encrypt(&counter_nonce(counter), msg)
+                .map_err(|_| Error::illegal_state("Could not encrypt b2b message")),
+        }
+    }
+
+    /// Checks, and decrypts if needed, the next incoming message.
+    /// Fails if the message was forged, tampered with, or not the next one in the stream.
+    pub fn open(&mut self, frame: &[u8]) -> Result<Vec<u8>, Error> {
+        let counter = self.recv_counter;
+        let msg = match &self.keys {
+            B2bSessionKeys::Authenticated { recv, .. } => {
+                if frame.len() < B2B_MAC_LEN {
+                    return Err(Error::illegal_state("b2b message failed authentication"));
+                }
+                let (msg, tag) = frame.split_at(frame.len() - B2B_MAC_LEN);
+                let mut mac = recv.clone();
+                mac.update(&counter.to_be_bytes());
+                mac.update(msg);
+                mac.verify_slice(tag)
+                    .map_err(|_| Error::illegal_state("b2b message failed authentication"))?;
+                msg.to_vec()
+            }
+            B2bSessionKeys::Encrypted { recv, .. } => recv
+                .decrypt(&counter_nonce(counter), frame)
+                .map_err(|_| Error::illegal_state("b2b message failed authentication"))?,
+        };
+        self.recv_counter += 1;
+        Ok(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{B2bAuth, B2bRole};
+
+    #[test]
+    fn test_b2b_auth() {
+        for encrypt in [false, true] {
+            let auth = B2bAuth::new(*b"correct horse battery staple")
+                .unwrap()
+                .with_encryption(encrypt);
+            let wrong = B2bAuth::new(*b"incorrect horse battery staple")
+                .unwrap()
+                .with_encryption(encrypt);
+            let connector_nonce = B2bAuth::new_nonce();
+            let listener_nonce = B2bAuth::new_nonce();
+
+            let proof = auth.proof(B2bRole::Connector, &connector_nonce, &listener_nonce);
+            assert!(auth.verify_proof(
+                B2bRole::Connector,
+                &connector_nonce,
+                &listener_nonce,
+                &proof
+            ));
+            // Wrong key, wrong role, or a different handshake
+            assert!(!wrong.verify_proof(
+                B2bRole::Connector,
+                &connector_nonce,
+                &listener_nonce,
+                &proof
+            ));
+            assert!(!auth.verify_proof(
+                B2bRole::Listener,
+                &connector_nonce,
+                &listener_nonce,
+                &proof
+            ));
+            assert!(!auth.verify_proof(
+                B2bRole::Connector,
+                &B2bAuth::new_nonce(),
+                &listener_nonce,
+                &proof
+            ));
+            // A different encryption setting fails the handshake, too
+            assert!(!auth.clone().with_encryption(!encrypt).verify_proof(
+                B2bRole::Connector,
+                &connector_nonce,
+                &listener_nonce,
+                &proof
+            ));
+
+            let mut connector = auth
+                .session(B2bRole::Connector, &connector_nonce, &listener_nonce)
+                .unwrap();
+            let mut listener = auth
+                .session(B2bRole::Listener, &connector_nonce, &listener_nonce)
+                .unwrap();
+
+            let first = connector.seal(b"first message").unwrap();
+            assert_eq!(encrypt, !first.starts_with(b"first message"));
+            let second = connector.seal(b"second message").unwrap();
+
+            // Out of order
+            assert!(listener.open(&second).is_err());
+            assert_eq!(listener.open(&first).unwrap(), b"first message");
+            // Replayed
+            assert!(listener.open(&first).is_err());
+
+            let mut tampered = second.clone();
+            tampered[0] ^= 1;
+            assert!(listener.open(&tampered).is_err());
+            assert_eq!(listener.open(&second).unwrap(), b"second message");
+
+            // Each direction has its own key
+            let reply = listener.seal(b"reply").unwrap();
+            assert_eq!(connector.open(&reply).unwrap(), b"reply");
+        }
+    }
+}
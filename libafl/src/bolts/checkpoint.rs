@@ -0,0 +1,172 @@
+//! Periodic on-disk checkpoints of the fuzzer state, to resume a campaign after the machine or the broker went down.
+//! Other than the [`crate::bolts::staterestore::StateRestorer`], checkpoints survive the end of the fuzzing processes.
+
+use core::time::Duration;
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{bolts::current_time, Error};
+
+/// The default interval between two checkpoints
+pub const DEFAULT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Writes checkpoints of a state to a file, at most once per interval.
+/// Each checkpoint is written to a temporary file first, and then moved over the previous one,
+/// so that an interrupted write never leaves an incomplete checkpoint behind.
+#[derive(Debug, Clone)]
+pub struct StateCheckpointer {
+    path: PathBuf,
+    interval: Duration,
+    last_checkpoint: Duration,
+}
+
+impl StateCheckpointer {
+    /// Creates a new [`StateCheckpointer`], writing to `path` at most once per `interval`.
+    /// The interval counts from the last modification of the checkpoint file, if it exists,
+    /// so that clients restarting more often than the interval still write checkpoints.
+    #[must_use]
+    pub fn new<P>(path: P, interval: Duration) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let last_checkpoint = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            interval,
+            last_checkpoint,
+        }
+    }
+
+    /// The path of the checkpoint file
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The interval between two checkpoints
+    #[must_use]
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Writes a checkpoint of `state` if the interval passed since the last one.
+    /// Returns `true` if a checkpoint was written.
+    pub fn maybe_save<S>(&mut self, state: &S) -> Result<bool, Error>
+    where
+        S: Serialize,
+    {
+        if current_time().saturating_sub(self.last_checkpoint) < self.interval {
+            return Ok(false);
+        }
+        self.save(state)?;
+        Ok(true)
+    }
+
+    /// Writes a checkpoint of `state` now
+    pub fn save<S>(&mut self, state: &S) -> Result<(), Error>
+    where
+        S: Serialize,
+    {
+        let serialized = postcard::to_allocvec(state)?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut tmpfile_name = self.path.clone();
+        tmpfile_name.set_file_name(format!(
+            ".{}.tmp",
+            self.path
+                .file_name()
+                .ok_or_else(|| Error::illegal_argument(format!(
+                    "Invalid checkpoint path {}",
+                    self.path.display()
+                )))?
+                .to_string_lossy()
+        ));
+
+        // A leftover of an interrupted checkpoint
+        if tmpfile_name.exists() {
+            fs::remove_file(&tmpfile_name)?;
+        }
+        let mut tmpfile = File::create(&tmpfile_name)?;
+        tmpfile.write_all(&serialized)?;
+        tmpfile.sync_all()?;
+        drop(tmpfile);
+        fs::rename(&tmpfile_name, &self.path)?;
+        // The rename only survives a crash of the machine once the directory is on disk, too
+        #[cfg(unix)]
+        File::open(
+            self.path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or_else(|| Path::new(".")),
+        )?
+        .sync_all()?;
+
+        self.last_checkpoint = current_time();
+        log::info!(
+            "Checkpoint of {} bytes written to {}",
+            serialized.len(),
+            self.path.display()
+        );
+        Ok(())
+    }
+
+    /// Reads the last checkpoint, if any
+    pub fn load<S>(&self) -> Result<Option<S>, Error>
+    where
+        S: DeserializeOwned,
+    {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let serialized = fs::read(&self.path)?;
+        Ok(Some(postcard::from_bytes(&serialized)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+    use core::time::Duration;
+    use std::{env::temp_dir, fs};
+
+    use super::StateCheckpointer;
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let path = temp_dir().join(format!("libafl_test_{}.checkpoint", std::process::id()));
+        let mut checkpointer = StateCheckpointer::new(&path, Duration::from_secs(3600));
+        assert_eq!(checkpointer.load::<(u64, String)>().unwrap(), None);
+
+        // Without a checkpoint file, the first checkpoint is due right away
+        assert!(checkpointer.maybe_save(&(1_u64, "first")).unwrap());
+        assert!(!checkpointer.maybe_save(&(2_u64, "second")).unwrap());
+        assert_eq!(
+            checkpointer.load::<(u64, String)>().unwrap(),
+            Some((1, "first".to_string()))
+        );
+
+        // The interval counts from the existing checkpoint
+        let mut checkpointer = StateCheckpointer::new(&path, Duration::from_secs(3600));
+        assert!(!checkpointer.maybe_save(&(3_u64, "third")).unwrap());
+        checkpointer.save(&(4_u64, "fourth")).unwrap();
+        assert_eq!(
+            checkpointer.load::<(u64, String)>().unwrap(),
+            Some((4, "fourth".to_string()))
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+}
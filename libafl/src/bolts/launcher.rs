@@ -6,6 +6,7 @@
 //!
 //! To connect multiple nodes together via TCP, we can use the `remote_broker_addr`.
 //! (this requires the `llmp_bind_public` compile-time feature for `LibAFL`).
+//! With the `llmp_b2b_auth` feature, set a pre-shared key in `b2b_auth` on all nodes, so that only they can connect to each other.
 //!
 //! On `Unix` systems, the [`Launcher`] will use `fork` if the `fork` feature is used for `LibAFL`.
 //! Else, it will start subsequent nodes with the same commandline, and will set special `env` variables accordingly.
@@ -16,17 +17,21 @@ use core::fmt::{self, Debug, Formatter};
 #[cfg(feature = "std")]
 use core::marker::PhantomData;
 #[cfg(feature = "std")]
-use std::net::SocketAddr;
+use core::time::Duration;
 #[cfg(all(feature = "std", any(windows, not(feature = "fork"))))]
 use std::process::Stdio;
 #[cfg(all(unix, feature = "std", feature = "fork"))]
 use std::{fs::File, os::unix::io::AsRawFd};
+#[cfg(feature = "std")]
+use std::{net::SocketAddr, path::PathBuf};
 
 #[cfg(feature = "std")]
 use serde::de::DeserializeOwned;
 #[cfg(feature = "std")]
 use typed_builder::TypedBuilder;
 
+#[cfg(feature = "llmp_b2b_auth")]
+use crate::bolts::b2b_auth::B2bAuth;
 #[cfg(all(feature = "std", any(windows, not(feature = "fork"))))]
 use crate::bolts::core_affinity::CoreId;
 #[cfg(all(feature = "std", any(windows, not(feature = "fork"))))]
@@ -36,7 +41,7 @@ use crate::bolts::os::{dup2, fork, ForkResult};
 use crate::inputs::UsesInput;
 #[cfg(feature = "std")]
 use crate::{
-    bolts::{core_affinity::Cores, shmem::ShMemProvider},
+    bolts::{checkpoint::DEFAULT_CHECKPOINT_INTERVAL, core_affinity::Cores, shmem::ShMemProvider},
     events::{EventConfig, LlmpRestartingEventManager, ManagerKind, RestartingMgr},
     monitors::Monitor,
     state::{HasClientPerfMonitor, HasExecutions},
@@ -78,12 +83,29 @@ where
     /// clusters.
     #[builder(default = None)]
     remote_broker_addr: Option<SocketAddr>,
+    /// The pre-shared key authenticating the connections to and from other nodes
+    #[cfg(feature = "llmp_b2b_auth")]
+    #[builder(default = None)]
+    b2b_auth: Option<B2bAuth>,
+    /// The file the broker records all llmp messages to, to inspect or replay them later
+    #[builder(default = None)]
+    record_path: Option<PathBuf>,
     /// If this launcher should spawn a new `broker` on `[Self::broker_port]` (default).
     /// The reason you may not want this is, if you already have a [`Launcher`]
     /// with a different configuration (for the same target) running on this machine.
     /// Then, clients launched by this [`Launcher`] can connect to the original `broker`.
     #[builder(default = true)]
     spawn_broker: bool,
+    /// The directory the clients write checkpoints of their state to, as `client_<core id>.checkpoint`,
+    /// to resume the campaign later, e.g. after a reboot
+    #[builder(default = None)]
+    checkpoint_dir: Option<PathBuf>,
+    /// The interval between two checkpoints of each client
+    #[builder(default = DEFAULT_CHECKPOINT_INTERVAL)]
+    checkpoint_interval: Duration,
+    /// If the clients should start from their checkpoints in [`Self::checkpoint_dir`], if any, instead of new states
+    #[builder(default = false)]
+    resume: bool,
     #[builder(setter(skip), default = PhantomData)]
     phantom_data: PhantomData<(&'a S, &'a SP)>,
 }
@@ -96,13 +118,20 @@ where
     S: DeserializeOwned + UsesInput,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Launcher")
+        let mut debug_struct = f.debug_struct("Launcher");
+        debug_struct
             .field("configuration", &self.configuration)
             .field("broker_port", &self.broker_port)
             .field("core", &self.cores)
             .field("spawn_broker", &self.spawn_broker)
-            .field("remote_broker_addr", &self.remote_broker_addr)
+            .field("remote_broker_addr", &self.remote_broker_addr);
+        #[cfg(feature = "llmp_b2b_auth")]
+        debug_struct.field("b2b_auth", &self.b2b_auth);
+        debug_struct
+            .field("record_path", &self.record_path)
             .field("stdout_file", &self.stdout_file)
+            .field("checkpoint_dir", &self.checkpoint_dir)
+            .field("resume", &self.resume)
             .finish_non_exhaustive()
     }
 }
@@ -115,6 +144,13 @@ where
     S: DeserializeOwned + UsesInput + HasExecutions + HasClientPerfMonitor,
     SP: ShMemProvider + 'static,
 {
+    /// The checkpoint file of the client on the given core, if checkpoints are enabled
+    fn checkpoint_path(&self, core_id: usize) -> Option<PathBuf> {
+        self.checkpoint_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("client_{core_id}.checkpoint")))
+    }
+
     /// Launch the broker and the clients and fuzz
     #[cfg(all(unix, feature = "std", feature = "fork"))]
     #[allow(clippy::similar_names)]
@@ -177,6 +213,9 @@ where
                                 cpu_core: Some(*bind_to),
                             })
                             .configuration(self.configuration)
+                            .checkpoint_path(self.checkpoint_path(bind_to.id))
+                            .checkpoint_interval(self.checkpoint_interval)
+                            .resume(self.resume)
                             .build()
                             .launch()?;
 
@@ -191,12 +230,16 @@ where
             log::info!("I am broker!!.");
 
             // TODO we don't want always a broker here, think about using different laucher process to spawn different configurations
-            RestartingMgr::<MT, S, SP>::builder()
+            let builder = RestartingMgr::<MT, S, SP>::builder()
                 .shmem_provider(self.shmem_provider.clone())
                 .monitor(Some(self.monitor.clone()))
                 .broker_port(self.broker_port)
                 .kind(ManagerKind::Broker)
-                .remote_broker_addr(self.remote_broker_addr)
+                .remote_broker_addr(self.remote_broker_addr);
+            #[cfg(feature = "llmp_b2b_auth")]
+            let builder = builder.b2b_auth(self.b2b_auth.clone());
+            builder
+                .record_path(self.record_path.clone())
                 .configuration(self.configuration)
                 .build()
                 .launch()?;
@@ -245,6 +288,9 @@ where
                         cpu_core: Some(CoreId { id: core_id }),
                     })
                     .configuration(self.configuration)
+                    .checkpoint_path(self.checkpoint_path(core_id))
+                    .checkpoint_interval(self.checkpoint_interval)
+                    .resume(self.resume)
                     .build()
                     .launch()?;
 
@@ -289,12 +335,16 @@ where
             #[cfg(feature = "std")]
             log::info!("I am broker!!.");
 
-            RestartingMgr::<MT, S, SP>::builder()
+            let builder = RestartingMgr::<MT, S, SP>::builder()
                 .shmem_provider(self.shmem_provider.clone())
                 .monitor(Some(self.monitor.clone()))
                 .broker_port(self.broker_port)
                 .kind(ManagerKind::Broker)
-                .remote_broker_addr(self.remote_broker_addr)
+                .remote_broker_addr(self.remote_broker_addr);
+            #[cfg(feature = "llmp_b2b_auth")]
+            let builder = builder.b2b_auth(self.b2b_auth.clone());
+            builder
+                .record_path(self.record_path.clone())
                 .configuration(self.configuration)
                 .build()
                 .launch()?;
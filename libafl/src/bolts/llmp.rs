@@ -76,6 +76,8 @@ use core::{
 #[cfg(all(unix, feature = "std"))]
 #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
 use std::os::unix::io::AsRawFd;
+#[cfg(feature = "llmp_b2b_auth")]
+use std::sync::{mpsc::Sender, Arc, RwLock};
 #[cfg(feature = "std")]
 use std::{
     env,
@@ -92,12 +94,19 @@ use backtrace::Backtrace;
 use nix::sys::socket::{self, sockopt::ReusePort};
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "llmp_b2b_auth")]
+use crate::bolts::b2b_auth::{B2bAuth, B2bRole, B2bSession, B2B_NONCE_LEN};
+#[cfg(feature = "std")]
+use crate::bolts::llmp_record::LlmpRecorder;
 #[cfg(unix)]
 use crate::bolts::os::unix_signals::{
     setup_signal_handler, siginfo_t, ucontext_t, Handler, Signal,
 };
 use crate::{
-    bolts::shmem::{ShMem, ShMemDescription, ShMemId, ShMemProvider},
+    bolts::{
+        current_nanos,
+        shmem::{ShMem, ShMemDescription, ShMemId, ShMemProvider},
+    },
     Error,
 };
 
@@ -199,6 +208,20 @@ pub enum TcpRequest {
         /// The hostname of our broker, trying to connect.
         hostname: String,
     },
+    /// We would like to establish a b2b connection, authenticated with a pre-shared key.
+    RemoteBrokerAuthHello {
+        /// The hostname of our broker, trying to connect.
+        hostname: String,
+        /// The random nonce of our side of the handshake
+        nonce: Vec<u8>,
+        /// If we want to encrypt messages, and not only authenticate them
+        encrypt: bool,
+    },
+    /// Our proof that we know the pre-shared key, after a [`TcpResponse::RemoteBrokerChallenge`].
+    RemoteBrokerAuth {
+        /// The proof, bound to the nonces of both sides
+        proof: Vec<u8>,
+    },
 }
 
 impl TryFrom<&Vec<u8>> for TcpRequest {
@@ -255,6 +278,8 @@ pub enum TcpResponse {
         broker_shmem_description: ShMemDescription,
         /// This broker's hostname
         hostname: String,
+        /// Identifies this run of the broker, see [`LlmpBroker::run_id`]
+        broker_run_id: u64,
     },
     /// Notify the client on the other side that it has been accepted.
     LocalClientAccepted {
@@ -262,6 +287,14 @@ pub enum TcpResponse {
         /// Mainly used for client-side deduplication of incoming messages
         client_id: ClientId,
     },
+    /// Answer to a [`TcpRequest::RemoteBrokerAuthHello`]: our proof that we know the pre-shared key,
+    /// and the challenge for the remote broker to prove the same.
+    RemoteBrokerChallenge {
+        /// The random nonce of our side of the handshake
+        nonce: Vec<u8>,
+        /// The proof, bound to the nonces of both sides
+        proof: Vec<u8>,
+    },
     /// Notify the remote broker has been accepted.
     RemoteBrokerAccepted {
         /// The broker id of this element
@@ -435,12 +468,54 @@ fn recv_tcp_msg(stream: &mut TcpStream) -> Result<Vec<u8>, Error> {
     #[cfg(feature = "llmp_debug")]
     log::trace!("LLMP TCP: Receiving payload of size {size}");
 
-    stream
-        .read_exact(&mut bytes)
-        .expect("Failed to read message body");
+    stream.read_exact(&mut bytes)?;
     Ok(bytes)
 }
 
+/// A request to the tcp listener, with the session of the remote broker, if it authenticated
+#[cfg(feature = "std")]
+type TcpIncoming = (TcpStream, TcpRequest, Option<B2bSession>);
+
+/// Without the `llmp_b2b_auth` feature, b2b connections never have an authenticated session
+#[cfg(all(feature = "std", not(feature = "llmp_b2b_auth")))]
+#[derive(Debug)]
+enum B2bSession {}
+
+/// Send one message over a b2b connection, authenticated by the `session`, if any
+#[cfg(feature = "std")]
+fn send_b2b_msg<T>(
+    stream: &mut TcpStream,
+    session: &mut Option<B2bSession>,
+    msg: &T,
+) -> Result<(), Error>
+where
+    T: Serialize,
+{
+    match session {
+        #[cfg(feature = "llmp_b2b_auth")]
+        Some(session) => {
+            let frame = session.seal(&postcard::to_allocvec(msg)?)?;
+            send_tcp_msg(stream, &frame)
+        }
+        #[cfg(not(feature = "llmp_b2b_auth"))]
+        Some(session) => match *session {},
+        None => send_tcp_msg(stream, msg),
+    }
+}
+
+/// Checks a message received from a b2b connection against the `session`, if any
+#[cfg(feature = "std")]
+#[cfg_attr(not(feature = "llmp_b2b_auth"), allow(clippy::unnecessary_wraps))]
+fn open_b2b_msg(session: &mut Option<B2bSession>, bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+    match session {
+        #[cfg(feature = "llmp_b2b_auth")]
+        Some(session) => session.open(&postcard::from_bytes::<Vec<u8>>(&bytes)?),
+        #[cfg(not(feature = "llmp_b2b_auth"))]
+        Some(session) => match *session {},
+        None => Ok(bytes),
+    }
+}
+
 /// In case we don't have enough space, make sure the next page will be large
 /// enough. For now, we want to have at least enough space to store 2 of the
 /// largest messages we encountered (plus message one `new_page` message).
@@ -1760,6 +1835,15 @@ where
     pub llmp_clients: Vec<LlmpReceiver<SP>>,
     /// The ShMemProvider to use
     shmem_provider: SP,
+    /// The pre-shared key remote brokers need to connect to us, and we use to connect to them.
+    /// Shared with the listener thread.
+    #[cfg(feature = "llmp_b2b_auth")]
+    b2b_auth: Arc<RwLock<Option<B2bAuth>>>,
+    /// Records all messages from clients to a log, if set
+    #[cfg(feature = "std")]
+    recorder: Option<LlmpRecorder>,
+    /// Identifies this run of the broker
+    run_id: u64,
 }
 
 /// A signal handler for the [`LlmpBroker`].
@@ -1806,9 +1890,45 @@ where
             },
             llmp_clients: vec![],
             shmem_provider,
+            #[cfg(feature = "llmp_b2b_auth")]
+            b2b_auth: Arc::new(RwLock::new(None)),
+            #[cfg(feature = "std")]
+            recorder: None,
+            run_id: current_nanos(),
         })
     }
 
+    /// Identifies this run of the broker, it is sent to the clients connecting over TCP.
+    /// A broker restarted on the same port has another one, while its maps may get the same ids.
+    #[must_use]
+    pub fn run_id(&self) -> u64 {
+        self.run_id
+    }
+
+    /// Records all messages this broker receives from its clients, before they are passed to the hooks,
+    /// with the given [`LlmpRecorder`], or stops recording, if `None`.
+    /// If writing the log fails, the broker logs the error and stops recording.
+    #[cfg(feature = "std")]
+    pub fn set_recorder(&mut self, recorder: Option<LlmpRecorder>) {
+        self.recorder = recorder;
+    }
+
+    /// The pre-shared key for broker-to-broker connections, if they are authenticated
+    #[cfg(feature = "llmp_b2b_auth")]
+    #[must_use]
+    pub fn b2b_auth(&self) -> Option<B2bAuth> {
+        self.b2b_auth.read().unwrap().clone()
+    }
+
+    /// Authenticate broker-to-broker connections with a pre-shared key, in both directions:
+    /// remote brokers connecting to our listener, as well as [`Self::connect_b2b`], then need the same key.
+    /// Peers failing the handshake, or sending forged messages, are logged and disconnected.
+    /// Set this right after creating the broker, as connections accepted before are not authenticated.
+    #[cfg(feature = "llmp_b2b_auth")]
+    pub fn set_b2b_auth(&mut self, b2b_auth: Option<B2bAuth>) {
+        *self.b2b_auth.write().unwrap() = b2b_auth;
+    }
+
     /// Create a new [`LlmpBroker`] sttaching to a TCP port
     #[cfg(feature = "std")]
     pub fn create_attach_to_tcp(shmem_provider: SP, port: u16) -> Result<Self, Error> {
@@ -1854,11 +1974,15 @@ where
         let mut stream = TcpStream::connect(addr)?;
         log::info!("B2B: Connected to {stream:?}");
 
-        match recv_tcp_msg(&mut stream)?.try_into()? {
+        let remote_hostname = match recv_tcp_msg(&mut stream)?.try_into()? {
             TcpResponse::BrokerConnectHello {
                 broker_shmem_description: _,
                 hostname,
-            } => log::info!("B2B: Connected to {hostname}"),
+                broker_run_id: _,
+            } => {
+                log::info!("B2B: Connected to {hostname}");
+                hostname
+            }
             _ => {
                 return Err(Error::illegal_state(
                     "Unexpected response from B2B server received.".to_string(),
@@ -1871,13 +1995,34 @@ where
             .to_string_lossy()
             .into();
 
-        send_tcp_msg(&mut stream, &TcpRequest::RemoteBrokerHello { hostname })?;
+        #[cfg(feature = "llmp_b2b_auth")]
+        let session = if let Some(b2b_auth) = self.b2b_auth() {
+            Some(Self::b2b_connector_handshake(
+                &mut stream,
+                &b2b_auth,
+                hostname,
+                &remote_hostname,
+            )?)
+        } else {
+            send_tcp_msg(&mut stream, &TcpRequest::RemoteBrokerHello { hostname })?;
+            None
+        };
+        #[cfg(not(feature = "llmp_b2b_auth"))]
+        let session = {
+            send_tcp_msg(&mut stream, &TcpRequest::RemoteBrokerHello { hostname })?;
+            None
+        };
 
         let broker_id = match recv_tcp_msg(&mut stream)?.try_into()? {
             TcpResponse::RemoteBrokerAccepted { broker_id } => {
                 log::info!("B2B: Got Connection Ack, broker_id {broker_id}");
                 broker_id
             }
+            TcpResponse::Error { description } => {
+                return Err(Error::illegal_state(format!(
+                    "B2B: {remote_hostname} refused the connection: {description}"
+                )));
+            }
             _ => {
                 return Err(Error::illegal_state(
                     "Unexpected response from B2B server received.".to_string(),
@@ -1899,6 +2044,7 @@ where
                 .unwrap()
                 .shmem
                 .description(),
+            session,
         )?;
 
         let new_shmem = LlmpSharedMap::existing(
@@ -1945,6 +2091,14 @@ where
                 new_messages |= self.handle_new_msgs(i as u32, on_new_msg)?;
             }
         }
+        // Write the recorded messages, even if no new ones arrive for a while
+        #[cfg(feature = "std")]
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.flush_if_due() {
+                log::error!("Could not record llmp message, stopping to record: {e}");
+                self.recorder = None;
+            }
+        }
         Ok(new_messages)
     }
 
@@ -2104,6 +2258,7 @@ where
         mut stream: TcpStream,
         b2b_client_id: ClientId,
         broker_shmem_description: &ShMemDescription,
+        mut session: Option<B2bSession>,
     ) -> Result<ShMemDescription, Error> {
         let broker_shmem_description = *broker_shmem_description;
 
@@ -2168,8 +2323,9 @@ where
                                 payload.len()
                             );
                             // We got a new message! Forward...
-                            if let Err(e) = send_tcp_msg(
+                            if let Err(e) = send_b2b_msg(
                                 &mut stream,
+                                &mut session,
                                 &TcpRemoteNewMessage {
                                     client_id,
                                     tag,
@@ -2197,6 +2353,15 @@ where
                 // Instead, we catch stream close when/if we next try to send.
                 match recv_tcp_msg(&mut stream) {
                     Ok(val) => {
+                        let val = match open_b2b_msg(&mut session, val) {
+                            Ok(val) => val,
+                            Err(e) => {
+                                log::error!(
+                                    "B2B: Rejected message from broker {peer_address}: {e}, closing the connection"
+                                );
+                                return;
+                            }
+                        };
                         let msg: TcpRemoteNewMessage = val.try_into().expect(
                             "Illegal message received from broker 2 broker connection - shutting down.",
                         );
@@ -2244,7 +2409,203 @@ where
         ret
     }
 
+    /// The handshake of [`Self::connect_b2b`] with a broker requiring authentication.
+    /// Fails if the remote broker does not know the pre-shared key.
+    #[cfg(feature = "llmp_b2b_auth")]
+    fn b2b_connector_handshake(
+        stream: &mut TcpStream,
+        b2b_auth: &B2bAuth,
+        hostname: String,
+        remote_hostname: &str,
+    ) -> Result<B2bSession, Error> {
+        let connector_nonce = B2bAuth::new_nonce();
+        send_tcp_msg(
+            stream,
+            &TcpRequest::RemoteBrokerAuthHello {
+                hostname,
+                nonce: connector_nonce.clone(),
+                encrypt: b2b_auth.encrypt(),
+            },
+        )?;
+
+        let listener_nonce = match recv_tcp_msg(stream)?.try_into()? {
+            TcpResponse::RemoteBrokerChallenge { nonce, proof } => {
+                if !b2b_auth.verify_proof(B2bRole::Listener, &connector_nonce, &nonce, &proof) {
+                    log::error!(
+                        "B2B: Rejected broker {remote_hostname} at {:?}: authentication failed",
+                        stream.peer_addr()
+                    );
+                    return Err(Error::illegal_state(format!(
+                        "B2B: {remote_hostname} failed to authenticate"
+                    )));
+                }
+                nonce
+            }
+            TcpResponse::Error { description } => {
+                return Err(Error::illegal_state(format!(
+                    "B2B: {remote_hostname} refused the connection: {description}"
+                )));
+            }
+            _ => {
+                return Err(Error::illegal_state(
+                    "Unexpected response from B2B server received.".to_string(),
+                ));
+            }
+        };
+
+        send_tcp_msg(
+            stream,
+            &TcpRequest::RemoteBrokerAuth {
+                proof: b2b_auth.proof(B2bRole::Connector, &connector_nonce, &listener_nonce),
+            },
+        )?;
+        b2b_auth.session(B2bRole::Connector, &connector_nonce, &listener_nonce)
+    }
+
+    /// The handshake of our listener with a remote broker, after its [`TcpRequest::RemoteBrokerAuthHello`].
+    /// Fails if the remote broker does not know the pre-shared key.
+    #[cfg(feature = "llmp_b2b_auth")]
+    fn b2b_listener_handshake(
+        stream: &mut TcpStream,
+        b2b_auth: &B2bAuth,
+        connector_nonce: &[u8],
+        encrypt: bool,
+    ) -> Result<B2bSession, Error> {
+        if encrypt != b2b_auth.encrypt() {
+            return Err(Error::illegal_argument(if b2b_auth.encrypt() {
+                "encryption is required"
+            } else {
+                "encryption is not enabled"
+            }));
+        }
+        if connector_nonce.len() != B2B_NONCE_LEN {
+            return Err(Error::illegal_argument("invalid nonce"));
+        }
+
+        let listener_nonce = B2bAuth::new_nonce();
+        send_tcp_msg(
+            stream,
+            &TcpResponse::RemoteBrokerChallenge {
+                nonce: listener_nonce.clone(),
+                proof: b2b_auth.proof(B2bRole::Listener, connector_nonce, &listener_nonce),
+            },
+        )?;
+
+        // Don't let a silent peer block the listener
+        stream.set_read_timeout(Some(_LLMP_B2B_BLOCK_TIME))?;
+        match recv_tcp_msg(stream)?.try_into()? {
+            TcpRequest::RemoteBrokerAuth { proof } => {
+                if b2b_auth.verify_proof(
+                    B2bRole::Connector,
+                    connector_nonce,
+                    &listener_nonce,
+                    &proof,
+                ) {
+                    b2b_auth.session(B2bRole::Listener, connector_nonce, &listener_nonce)
+                } else {
+                    Err(Error::illegal_argument("authentication failed"))
+                }
+            }
+            _ => Err(Error::illegal_argument(
+                "unexpected request during the handshake",
+            )),
+        }
+    }
+
+    /// Accepts a remote broker on the `stream`, and launches the thread proxying its messages.
+    #[cfg(feature = "std")]
+    fn accept_b2b(
+        mut stream: TcpStream,
+        session: Option<B2bSession>,
+        current_client_id: &mut u32,
+        sender: &mut LlmpSender<SP>,
+        broker_shmem_description: &ShMemDescription,
+    ) {
+        // TODO: Clean up broker ids.
+        if send_tcp_msg(
+            &mut stream,
+            &TcpResponse::RemoteBrokerAccepted {
+                broker_id: *current_client_id,
+            },
+        )
+        .is_err()
+        {
+            log::info!("Error accepting broker, ignoring.");
+            return;
+        }
+
+        if let Ok(shmem_description) = Self::b2b_thread_on(
+            stream,
+            *current_client_id,
+            broker_shmem_description,
+            session,
+        ) {
+            if Self::announce_new_client(sender, &shmem_description).is_err() {
+                log::info!("B2B: Error announcing client {shmem_description:?}");
+            };
+            *current_client_id += 1;
+        }
+    }
+
+    /// Rejects a remote broker on the `stream`, logging the reason, and telling the remote broker.
+    #[cfg(feature = "std")]
+    fn reject_b2b(mut stream: TcpStream, hostname: &str, reason: &str) {
+        log::error!(
+            "B2B: Rejected broker {hostname} at {:?}: {reason}",
+            stream.peer_addr()
+        );
+        if let Err(e) = send_tcp_msg(
+            &mut stream,
+            &TcpResponse::Error {
+                description: reason.to_string(),
+            },
+        ) {
+            log::info!("An error occurred sending via tcp {e}");
+        }
+    }
+
+    /// Runs the handshake of a remote broker asking to authenticate in its own thread, not to block the listener,
+    /// and passes the request on to `incoming` if it succeeds. Other requests are passed on right away.
+    #[cfg(feature = "llmp_b2b_auth")]
+    fn authenticate_tcp_request(
+        mut stream: TcpStream,
+        request: TcpRequest,
+        b2b_auth: Option<B2bAuth>,
+        incoming: &Sender<TcpIncoming>,
+    ) {
+        match (request, b2b_auth) {
+            (TcpRequest::RemoteBrokerHello { hostname }, Some(_)) => {
+                Self::reject_b2b(stream, &hostname, "authentication is required");
+            }
+            (
+                TcpRequest::RemoteBrokerAuthHello {
+                    hostname,
+                    nonce,
+                    encrypt,
+                },
+                Some(b2b_auth),
+            ) => {
+                let incoming = incoming.clone();
+                thread::spawn(move || {
+                    match Self::b2b_listener_handshake(&mut stream, &b2b_auth, &nonce, encrypt) {
+                        Ok(session) => {
+                            let request = TcpRequest::RemoteBrokerAuthHello {
+                                hostname,
+                                nonce,
+                                encrypt,
+                            };
+                            drop(incoming.send((stream, request, Some(session))));
+                        }
+                        Err(e) => Self::reject_b2b(stream, &hostname, &e.to_string()),
+                    }
+                });
+            }
+            (request, _) => drop(incoming.send((stream, request, None))),
+        }
+    }
+
     /// handles a single tcp request in the current context.
+    /// Remote brokers asking to authenticate come with the `session` of their successful handshake.
     #[cfg(feature = "std")]
     fn handle_tcp_request(
         mut stream: TcpStream,
@@ -2252,6 +2613,7 @@ where
         current_client_id: &mut u32,
         sender: &mut LlmpSender<SP>,
         broker_shmem_description: &ShMemDescription,
+        session: Option<B2bSession>,
     ) {
         match request {
             TcpRequest::LocalClientHello { shmem_description } => {
@@ -2273,27 +2635,31 @@ where
             TcpRequest::RemoteBrokerHello { hostname } => {
                 log::info!("B2B new client: {hostname}");
 
-                // TODO: Clean up broker ids.
-                if send_tcp_msg(
-                    &mut stream,
-                    &TcpResponse::RemoteBrokerAccepted {
-                        broker_id: *current_client_id,
-                    },
-                )
-                .is_err()
-                {
-                    log::info!("Error accepting broker, ignoring.");
+                Self::accept_b2b(
+                    stream,
+                    None,
+                    current_client_id,
+                    sender,
+                    broker_shmem_description,
+                );
+            }
+            TcpRequest::RemoteBrokerAuthHello { hostname, .. } => {
+                if session.is_none() {
+                    Self::reject_b2b(stream, hostname, "authentication is not enabled");
                     return;
                 }
-
-                if let Ok(shmem_description) =
-                    Self::b2b_thread_on(stream, *current_client_id, broker_shmem_description)
-                {
-                    if Self::announce_new_client(sender, &shmem_description).is_err() {
-                        log::info!("B2B: Error announcing client {shmem_description:?}");
-                    };
-                    *current_client_id += 1;
-                }
+                log::info!("B2B new authenticated client: {hostname}");
+
+                Self::accept_b2b(
+                    stream,
+                    session,
+                    current_client_id,
+                    sender,
+                    broker_shmem_description,
+                );
+            }
+            TcpRequest::RemoteBrokerAuth { .. } => {
+                Self::reject_b2b(stream, "<unknown>", "unexpected authentication");
             }
         };
     }
@@ -2315,9 +2681,12 @@ where
         let broker_hello = TcpResponse::BrokerConnectHello {
             broker_shmem_description,
             hostname,
+            broker_run_id: self.run_id,
         };
 
         let llmp_tcp_id = self.llmp_clients.len() as ClientId;
+        #[cfg(feature = "llmp_b2b_auth")]
+        let b2b_auth = self.b2b_auth.clone();
 
         // Tcp out map sends messages from background thread tcp server to foreground client
         let tcp_out_shmem = LlmpSharedMap::new(
@@ -2327,6 +2696,57 @@ where
         let tcp_out_shmem_description = tcp_out_shmem.shmem.description();
         self.register_client(tcp_out_shmem);
 
+        let (incoming_send, incoming_recv) = channel::<TcpIncoming>();
+
+        // Accepts new connections, and passes their requests on to the thread below.
+        thread::spawn(move || loop {
+            match listener.accept() {
+                ListenerStream::Tcp(mut stream, addr) => {
+                    log::info!(
+                        "New connection: {:?}/{:?}",
+                        addr,
+                        stream.peer_addr().unwrap()
+                    );
+
+                    // Send initial information, without anyone asking.
+                    // This makes it a tiny bit easier to map the  broker map for new Clients.
+                    match send_tcp_msg(&mut stream, &broker_hello) {
+                        Ok(()) => {}
+                        Err(e) => {
+                            log::error!("Error sending initial hello: {e:?}");
+                            continue;
+                        }
+                    }
+
+                    let buf = match recv_tcp_msg(&mut stream) {
+                        Ok(buf) => buf,
+                        Err(e) => {
+                            log::error!("Error receving from tcp: {e:?}");
+                            continue;
+                        }
+                    };
+                    let req: TcpRequest = match buf.try_into() {
+                        Ok(req) => req,
+                        Err(e) => {
+                            log::error!("Could not deserialize tcp message: {e:?}");
+                            continue;
+                        }
+                    };
+
+                    #[cfg(feature = "llmp_b2b_auth")]
+                    Self::authenticate_tcp_request(
+                        stream,
+                        req,
+                        b2b_auth.read().unwrap().clone(),
+                        &incoming_send,
+                    );
+                    #[cfg(not(feature = "llmp_b2b_auth"))]
+                    drop(incoming_send.send((stream, req, None)));
+                }
+                ListenerStream::Empty() => {}
+            }
+        });
+
         let ret = thread::spawn(move || {
             // Create a new ShMemProvider for this background thread.
             let mut shmem_provider_bg = SP::new().unwrap();
@@ -2347,52 +2767,15 @@ where
                 shmem_provider: shmem_provider_bg.clone(),
             };
 
-            loop {
-                match listener.accept() {
-                    ListenerStream::Tcp(mut stream, addr) => {
-                        log::info!(
-                            "New connection: {:?}/{:?}",
-                            addr,
-                            stream.peer_addr().unwrap()
-                        );
-
-                        // Send initial information, without anyone asking.
-                        // This makes it a tiny bit easier to map the  broker map for new Clients.
-                        match send_tcp_msg(&mut stream, &broker_hello) {
-                            Ok(()) => {}
-                            Err(e) => {
-                                log::error!("Error sending initial hello: {e:?}");
-                                continue;
-                            }
-                        }
-
-                        let buf = match recv_tcp_msg(&mut stream) {
-                            Ok(buf) => buf,
-                            Err(e) => {
-                                log::error!("Error receving from tcp: {e:?}");
-                                continue;
-                            }
-                        };
-                        let req = match buf.try_into() {
-                            Ok(req) => req,
-                            Err(e) => {
-                                log::error!("Could not deserialize tcp message: {e:?}");
-                                continue;
-                            }
-                        };
-
-                        Self::handle_tcp_request(
-                            stream,
-                            &req,
-                            &mut current_client_id,
-                            &mut tcp_incoming_sender,
-                            &broker_shmem_description,
-                        );
-                    }
-                    ListenerStream::Empty() => {
-                        continue;
-                    }
-                };
+            for (stream, req, session) in incoming_recv {
+                Self::handle_tcp_request(
+                    stream,
+                    &req,
+                    &mut current_client_id,
+                    &mut tcp_incoming_sender,
+                    &broker_shmem_description,
+                    session,
+                );
             }
         });
 
@@ -2484,6 +2867,16 @@ where
 
                     let map = &mut self.llmp_clients[client_id as usize].current_recv_shmem;
                     let msg_buf = (*msg).try_as_slice(map)?;
+
+                    #[cfg(feature = "std")]
+                    if let Some(recorder) = &mut self.recorder {
+                        if let Err(e) =
+                            recorder.record((*msg).sender, (*msg).tag, (*msg).flags, msg_buf)
+                        {
+                            log::error!("Could not record llmp message, stopping to record: {e}");
+                            self.recorder = None;
+                        }
+                    }
                     if let LlmpMsgHookResult::Handled =
                         (on_new_msg)(client_id, (*msg).tag, (*msg).flags, msg_buf)?
                     {
@@ -2505,6 +2898,8 @@ pub struct LlmpClientDescription {
     sender: LlmpDescription,
     /// Description of the receiver
     receiver: LlmpDescription,
+    /// The run of the broker the client connected to, if known
+    broker_run_id: Option<u64>,
 }
 
 /// Client side of LLMP
@@ -2517,6 +2912,8 @@ where
     pub sender: LlmpSender<SP>,
     /// Incoming (broker) broadcast map
     pub receiver: LlmpReceiver<SP>,
+    /// The run of the broker we connected to over TCP, see [`LlmpBroker::run_id`]
+    broker_run_id: Option<u64>,
 }
 
 /// `n` clients connect to a broker. They share an outgoing map with the broker,
@@ -2547,6 +2944,7 @@ where
                 current_broker_shmem,
                 last_msg_recvd_offset,
             )?,
+            broker_run_id: None,
         })
     }
 
@@ -2562,6 +2960,10 @@ where
                 shmem_provider,
                 &format!("{env_name}_RECEIVER"),
             )?,
+            broker_run_id: env::var(format!("{env_name}_BROKER_RUN_ID"))
+                .ok()
+                .map(|run_id| run_id.parse())
+                .transpose()?,
         })
     }
 
@@ -2569,6 +2971,9 @@ where
     /// A new client can attach to exactly the same state by calling [`LlmpClient::on_existing_shmem()`].
     #[cfg(feature = "std")]
     pub fn to_env(&self, env_name: &str) -> Result<(), Error> {
+        if let Some(broker_run_id) = self.broker_run_id {
+            env::set_var(format!("{env_name}_BROKER_RUN_ID"), format!("{broker_run_id}"));
+        }
         self.sender.to_env(&format!("{env_name}_SENDER"))?;
         self.receiver.to_env(&format!("{env_name}_RECEIVER"))
     }
@@ -2578,9 +2983,17 @@ where
         Ok(LlmpClientDescription {
             sender: self.sender.describe()?,
             receiver: self.receiver.describe()?,
+            broker_run_id: self.broker_run_id,
         })
     }
 
+    /// The run of the broker this client connected to over TCP, see [`LlmpBroker::run_id`].
+    /// `None` for clients created from a shared map, without the handshake.
+    #[must_use]
+    pub fn broker_run_id(&self) -> Option<u64> {
+        self.broker_run_id
+    }
+
     /// Create an existing client from description
     pub fn existing_client_from_description(
         shmem_provider: SP,
@@ -2595,6 +3008,7 @@ where
                 shmem_provider,
                 &description.receiver,
             )?,
+            broker_run_id: description.broker_run_id,
         })
     }
 
@@ -2645,6 +3059,7 @@ where
                 shmem_provider,
                 highest_msg_id: 0,
             },
+            broker_run_id: None,
         })
     }
 
@@ -2766,6 +3181,7 @@ where
         let TcpResponse::BrokerConnectHello {
             broker_shmem_description,
             hostname: _,
+            broker_run_id,
         } = recv_tcp_msg(&mut stream)?.try_into()? else {
             return Err(Error::illegal_state(
                 "Received unexpected Broker Hello".to_string(),
@@ -2778,6 +3194,7 @@ where
 
         // We'll set `sender_id` later
         let mut ret = Self::new(shmem_provider, map, 0)?;
+        ret.broker_run_id = Some(broker_run_id);
 
         let client_hello_req = TcpRequest::LocalClientHello {
             shmem_description: ret.sender.out_shmems.first().unwrap().shmem.description(),
@@ -2870,4 +3287,82 @@ mod tests {
         // We want at least the tcp and sender clients.
         assert_eq!(broker.llmp_clients.len(), 2);
     }
+
+    #[test]
+    #[serial]
+    #[cfg(feature = "llmp_b2b_auth")]
+    pub fn test_llmp_b2b_auth() {
+        use std::{net::TcpStream, time::Instant};
+
+        use super::{
+            recv_tcp_msg, send_tcp_msg, LlmpBroker, TcpRequest, _LLMP_B2B_BLOCK_TIME,
+            LLMP_FLAG_FROM_B2B,
+        };
+        use crate::bolts::b2b_auth::B2bAuth;
+
+        let shmem_provider = StdShMemProvider::new().unwrap();
+        let b2b_auth = B2bAuth::new(*b"our pre-shared key")
+            .unwrap()
+            .with_encryption(true);
+
+        let mut listener = LlmpBroker::new(shmem_provider.clone()).unwrap();
+        listener.set_b2b_auth(Some(b2b_auth.clone()));
+        listener.launch_tcp_listener_on(1338).unwrap();
+
+        // Brokers without the key, or with a different one, are rejected
+        let mut stranger = LlmpBroker::new(shmem_provider.clone()).unwrap();
+        assert!(stranger.connect_b2b("127.0.0.1:1338").is_err());
+        stranger.set_b2b_auth(Some(
+            B2bAuth::new(*b"another pre-shared key")
+                .unwrap()
+                .with_encryption(true),
+        ));
+        assert!(stranger.connect_b2b("127.0.0.1:1338").is_err());
+        stranger.set_b2b_auth(Some(b2b_auth.clone().with_encryption(false)));
+        assert!(stranger.connect_b2b("127.0.0.1:1338").is_err());
+
+        // A peer stalling in the middle of its handshake does not hold up the others
+        let mut silent = TcpStream::connect("127.0.0.1:1338").unwrap();
+        recv_tcp_msg(&mut silent).unwrap();
+        send_tcp_msg(
+            &mut silent,
+            &TcpRequest::RemoteBrokerAuthHello {
+                hostname: "silent".into(),
+                nonce: B2bAuth::new_nonce(),
+                encrypt: true,
+            },
+        )
+        .unwrap();
+
+        let mut connector = LlmpBroker::new(shmem_provider).unwrap();
+        connector.set_b2b_auth(Some(b2b_auth));
+        connector.launch_tcp_listener_on(1339).unwrap();
+        let start = Instant::now();
+        connector.connect_b2b("127.0.0.1:1338").unwrap();
+        assert!(start.elapsed() < _LLMP_B2B_BLOCK_TIME);
+
+        // The message is forwarded to the listener, by the b2b client the connection announced
+        let tag: Tag = 0x1338;
+        connector.send_buf(tag, &[1, 3, 3, 8]).unwrap();
+        let mut received = None;
+        for _ in 0..50 {
+            listener
+                .once(&mut |_sender_id, tag, flags, msg| {
+                    received = Some((tag, flags, msg.to_vec()));
+                    Ok(ForwardToClients)
+                })
+                .unwrap();
+            if received.is_some() {
+                break;
+            }
+            sleep(Duration::from_millis(100));
+        }
+        let (tag2, flags, msg) = received.unwrap();
+        assert_eq!(tag, tag2);
+        assert_ne!(flags & LLMP_FLAG_FROM_B2B, 0);
+        assert_eq!(msg, [1, 3, 3, 8]);
+
+        // The tcp listener client and the connector
+        assert_eq!(listener.llmp_clients.len(), 2);
+    }
 }
@@ -0,0 +1,379 @@
+//! Recording of the messages passing through an [`LlmpBroker`] to a log file, and replay of such logs.
+//!
+//! The [`LlmpRecorder`] writes each message a broker receives from its clients, with its tag, flags,
+//! the id of the client that sent it, and a timestamp. Install it with [`LlmpBroker::set_recorder`].
+//! The [`LlmpReplayer`] reads such a log, to inspect it, or to feed it into a fresh broker or client,
+//! for example to let late-joining nodes catch up, or to reproduce event-processing bugs deterministically.
+//!
+//! The log consists of blocks of messages, which are compressed if the `llmp_compression` feature is enabled.
+//! The broker writes a block at least once a second, if there are new messages,
+//! so a crashed broker loses at most the last second of traffic.
+
+use alloc::{collections::VecDeque, vec::Vec};
+use core::time::Duration;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "llmp_compression")]
+use crate::bolts::compress::GzipCompressor;
+use crate::{
+    bolts::{
+        current_time,
+        llmp::{ClientId, Flags, LlmpBroker, LlmpClient, Tag},
+        shmem::ShMemProvider,
+    },
+    Error,
+};
+
+/// The magic bytes at the start of each llmp log
+const LLMP_RECORD_MAGIC: &[u8; 8] = b"LLMPREC1";
+/// The size of the (uncompressed) messages in a block, after which the block is written
+const LLMP_RECORD_BLOCK_SIZE: usize = 1 << 20;
+/// The maximum time between two blocks, if there are any new messages
+const LLMP_RECORD_BLOCK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A message recorded by the [`LlmpRecorder`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LlmpRecordEntry {
+    /// The time the broker received the message, since the epoch
+    pub time: Duration,
+    /// The client that sent the message, as its receivers see it,
+    /// even if it reached the broker through another broker
+    pub client_id: ClientId,
+    /// The tag of the message
+    pub tag: Tag,
+    /// The flags of the message
+    pub flags: Flags,
+    /// The content of the message
+    pub payload: Vec<u8>,
+}
+
+/// Records llmp messages to a log file, see [`LlmpBroker::set_recorder`]
+#[derive(Debug)]
+pub struct LlmpRecorder {
+    writer: BufWriter<File>,
+    block: Vec<LlmpRecordEntry>,
+    block_size: usize,
+    last_block: Duration,
+    #[cfg(feature = "llmp_compression")]
+    compressor: GzipCompressor,
+}
+
+impl LlmpRecorder {
+    /// Creates a new log file at `path`, overwriting any previous one
+    pub fn new<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(LLMP_RECORD_MAGIC)?;
+        Ok(Self {
+            writer,
+            block: vec![],
+            block_size: 0,
+            last_block: current_time(),
+            #[cfg(feature = "llmp_compression")]
+            compressor: GzipCompressor::new(0),
+        })
+    }
+
+    /// Records one message
+    pub fn record(
+        &mut self,
+        client_id: ClientId,
+        tag: Tag,
+        flags: Flags,
+        payload: &[u8],
+    ) -> Result<(), Error> {
+        let time = current_time();
+        self.block_size += payload.len();
+        self.block.push(LlmpRecordEntry {
+            time,
+            client_id,
+            tag,
+            flags,
+            payload: payload.to_vec(),
+        });
+        if self.block_size >= LLMP_RECORD_BLOCK_SIZE
+            || time.saturating_sub(self.last_block) >= LLMP_RECORD_BLOCK_INTERVAL
+        {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the messages recorded so far to the file, if the last block is older than a second
+    pub fn flush_if_due(&mut self) -> Result<(), Error> {
+        if current_time().saturating_sub(self.last_block) >= LLMP_RECORD_BLOCK_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes all messages recorded so far to the file
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.last_block = current_time();
+        if self.block.is_empty() {
+            return Ok(());
+        }
+
+        let serialized = postcard::to_allocvec(&self.block)?;
+        self.block.clear();
+        self.block_size = 0;
+
+        #[cfg(feature = "llmp_compression")]
+        let (compressed, block) = match self.compressor.compress(&serialized)? {
+            Some(compressed) => (true, compressed),
+            None => (false, serialized),
+        };
+        #[cfg(not(feature = "llmp_compression"))]
+        let (compressed, block) = (false, serialized);
+
+        let len = u32::try_from(block.len()).map_err(|_| {
+            Error::illegal_state(format!("llmp log block too large ({} bytes)", block.len()))
+        })?;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(&[u8::from(compressed)])?;
+        self.writer.write_all(&block)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for LlmpRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            log::error!("Could not write the last messages to the llmp log: {e}");
+        }
+    }
+}
+
+/// Reads the messages of a log written by the [`LlmpRecorder`], in the order the broker received them
+#[derive(Debug)]
+pub struct LlmpReplayer {
+    reader: BufReader<File>,
+    block: VecDeque<LlmpRecordEntry>,
+    #[cfg(feature = "llmp_compression")]
+    compressor: GzipCompressor,
+}
+
+impl LlmpReplayer {
+    /// Opens the log at `path`
+    pub fn new<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0; LLMP_RECORD_MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != LLMP_RECORD_MAGIC {
+            return Err(Error::illegal_argument(format!(
+                "{} is not an llmp log",
+                path.display()
+            )));
+        }
+        Ok(Self {
+            reader,
+            block: VecDeque::new(),
+            #[cfg(feature = "llmp_compression")]
+            compressor: GzipCompressor::new(0),
+        })
+    }
+
+    /// Reads the next block, returns `false` at the end of the log.
+    /// A block cut short, because the broker crashed while writing it, also ends the log.
+    fn read_block(&mut self) -> Result<bool, Error> {
+        let mut header = [0; 5];
+        match self.reader.read_exact(&mut header) {
+            Ok(()) => (),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+        let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+        let mut block = vec![0; len];
+        match self.reader.read_exact(&mut block) {
+            Ok(()) => (),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                log::warn!("The llmp log ends in an incomplete block, ignoring it");
+                return Ok(false);
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        if header[4] != 0 {
+            #[cfg(feature = "llmp_compression")]
+            {
+                block = self.compressor.decompress(&block)?;
+            }
+            #[cfg(not(feature = "llmp_compression"))]
+            return Err(Error::unsupported(
+                "Reading a compressed llmp log requires the llmp_compression feature",
+            ));
+        }
+        self.block = postcard::from_bytes::<Vec<LlmpRecordEntry>>(&block)?.into();
+        Ok(true)
+    }
+
+    /// The next recorded message, or `None` at the end of the log
+    pub fn next_entry(&mut self) -> Result<Option<LlmpRecordEntry>, Error> {
+        while self.block.is_empty() {
+            if !self.read_block()? {
+                return Ok(None);
+            }
+        }
+        Ok(self.block.pop_front())
+    }
+
+    /// Sends all remaining recorded messages with their original tags and flags through the `client`,
+    /// for example connected to a fresh broker, which then passes them through its hooks and to its clients.
+    /// Returns the number of messages sent.
+    pub fn replay_to_client<SP>(&mut self, client: &mut LlmpClient<SP>) -> Result<usize, Error>
+    where
+        SP: ShMemProvider,
+    {
+        let mut count = 0;
+        while let Some(entry) = self.next_entry()? {
+            client.send_buf_with_flags(entry.tag, entry.flags, &entry.payload)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Broadcasts all remaining recorded messages with their original tags and flags to the clients of the `broker`,
+    /// without passing them through the broker hooks.
+    /// Returns the number of messages sent.
+    pub fn replay_to_broker<SP>(&mut self, broker: &mut LlmpBroker<SP>) -> Result<usize, Error>
+    where
+        SP: ShMemProvider + 'static,
+    {
+        let mut count = 0;
+        while let Some(entry) = self.next_entry()? {
+            broker.send_buf_with_flags(entry.tag, entry.flags, &entry.payload)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl Iterator for LlmpReplayer {
+    type Item = Result<LlmpRecordEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry().transpose()
+    }
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use alloc::vec::Vec;
+    use std::{env::temp_dir, fs, thread::sleep, time::Duration};
+
+    use serial_test::serial;
+
+    use super::{LlmpRecorder, LlmpReplayer};
+    use crate::bolts::{
+        llmp::{
+            LlmpConnection::{self, IsBroker, IsClient},
+            LlmpMsgHookResult::ForwardToClients,
+            Tag,
+        },
+        shmem::{ShMemProvider, StdShMemProvider},
+    };
+
+    #[test]
+    #[serial]
+    fn test_llmp_record_replay() {
+        let path = temp_dir().join(format!("libafl_test_{}.llmplog", std::process::id()));
+        let shmem_provider = StdShMemProvider::new().unwrap();
+        let tag: Tag = 0x1340;
+        let messages: Vec<Vec<u8>> = vec![vec![1, 3, 4, 0], vec![0x42; 4096], vec![]];
+
+        let mut broker = match LlmpConnection::on_port(shmem_provider.clone(), 1340).unwrap() {
+            IsClient { client: _ } => panic!("Could not bind to port as broker"),
+            IsBroker { broker } => broker,
+        };
+        broker.set_recorder(Some(LlmpRecorder::new(&path).unwrap()));
+        let mut client = match LlmpConnection::on_port(shmem_provider.clone(), 1340).unwrap() {
+            IsBroker { broker: _ } => panic!("Second connect should be a client!"),
+            IsClient { client } => client,
+        };
+        for (i, msg) in messages.iter().enumerate() {
+            client.send_buf_with_flags(tag, i as u32, msg).unwrap();
+        }
+
+        // Give the (background) tcp thread a few millis to announce the client
+        let mut received = 0;
+        for _ in 0..50 {
+            broker
+                .once(&mut |_sender_id, _tag, _flags, _msg| {
+                    received += 1;
+                    Ok(ForwardToClients)
+                })
+                .unwrap();
+            if received == messages.len() {
+                break;
+            }
+            sleep(Duration::from_millis(100));
+        }
+        assert_eq!(received, messages.len());
+        // A quiet broker still writes the recorded messages after a second
+        sleep(Duration::from_millis(1100));
+        broker.once(&mut |_, _, _, _| Ok(ForwardToClients)).unwrap();
+        let written = LlmpReplayer::new(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(written.len(), messages.len());
+        // Stops recording
+        broker.set_recorder(None);
+
+        let entries = LlmpReplayer::new(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(entries.len(), messages.len());
+        for (i, (entry, msg)) in entries.iter().zip(&messages).enumerate() {
+            assert_eq!(entry.tag, tag);
+            assert_eq!(entry.flags, i as u32);
+            assert_eq!(&entry.payload, msg);
+            assert_eq!(entry.client_id, client.sender.id);
+        }
+
+        // Feed the log to a fresh broker, and its clients
+        let mut fresh_broker = match LlmpConnection::on_port(shmem_provider.clone(), 1341).unwrap()
+        {
+            IsClient { client: _ } => panic!("Could not bind to port as broker"),
+            IsBroker { broker } => broker,
+        };
+        let mut late_client = match LlmpConnection::on_port(shmem_provider, 1341).unwrap() {
+            IsBroker { broker: _ } => panic!("Second connect should be a client!"),
+            IsClient { client } => client,
+        };
+        let mut replayer = LlmpReplayer::new(&path).unwrap();
+        assert_eq!(
+            replayer.replay_to_broker(&mut fresh_broker).unwrap(),
+            messages.len()
+        );
+        let mut replayed = vec![];
+        while replayed.len() < messages.len() {
+            match late_client.recv_buf_with_flags().unwrap() {
+                Some((_sender_id, tag2, flags, msg)) => replayed.push((tag2, flags, msg.to_vec())),
+                None => sleep(Duration::from_millis(10)),
+            }
+        }
+        for (i, (msg, (tag2, flags, msg2))) in messages.iter().zip(&replayed).enumerate() {
+            assert_eq!(tag, *tag2);
+            assert_eq!(flags, &(i as u32));
+            assert_eq!(msg, msg2);
+        }
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,8 +1,12 @@
 //! Bolts are no conceptual fuzzing elements, but they keep libafl-based fuzzers together.
 
 pub mod anymap;
+#[cfg(feature = "llmp_b2b_auth")]
+pub mod b2b_auth;
 #[cfg(feature = "std")]
 pub mod build_id;
+#[cfg(feature = "std")]
+pub mod checkpoint;
 #[cfg(all(
     any(feature = "cli", feature = "frida_cli", feature = "qemu_cli"),
     feature = "std"
@@ -18,6 +22,8 @@ pub mod fs;
 #[cfg(feature = "std")]
 pub mod launcher;
 pub mod llmp;
+#[cfg(feature = "std")]
+pub mod llmp_record;
 #[cfg(all(feature = "std", unix))]
 pub mod minibsod;
 pub mod os;
@@ -207,8 +213,12 @@ pub fn format_duration_hms(duration: &time::Duration) -> String {
 /// The purpose of this module is to alleviate imports of the bolts by adding a glob import.
 #[cfg(feature = "prelude")]
 pub mod bolts_prelude {
+    #[cfg(feature = "llmp_b2b_auth")]
+    pub use super::b2b_auth::*;
     #[cfg(feature = "std")]
     pub use super::build_id::*;
+    #[cfg(feature = "std")]
+    pub use super::checkpoint::*;
     #[cfg(all(
         any(feature = "cli", feature = "frida_cli", feature = "qemu_cli"),
         feature = "std"
@@ -222,6 +232,8 @@ pub mod bolts_prelude {
     pub use super::fs::*;
     #[cfg(feature = "std")]
     pub use super::launcher::*;
+    #[cfg(feature = "std")]
+    pub use super::llmp_record::*;
     #[cfg(all(feature = "std", unix))]
     pub use super::minibsod::*;
     #[cfg(feature = "std")]
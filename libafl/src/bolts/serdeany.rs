@@ -282,6 +282,20 @@ macro_rules! create_serde_registry_for_trait {
                     self.map.contains_key(&unpack_type_id(TypeId::of::<T>()))
                 }
 
+                /// Retains only the elements of the given types, with their ids unpacked by [`unpack_type_id`].
+                #[inline]
+                pub fn retain_type_ids(&mut self, type_ids: &[u64]) {
+                    self.map.retain(|id, _| type_ids.contains(id));
+                }
+
+                /// Moves the elements of `other` with types not in this map yet into this map.
+                #[inline]
+                pub fn insert_missing(&mut self, other: SerdeAnyMap) {
+                    for (id, value) in other.map {
+                        self.map.entry(id).or_insert(value);
+                    }
+                }
+
                 /// Create a new [`SerdeAnyMap`].
                 #[must_use]
                 pub fn new() -> Self {
@@ -0,0 +1,266 @@
+//! Interoperability with the output directories of `AFL++`.
+//! `AFL++` names its testcases like `id:000012,src:000003+000005,time:1234,execs:5678,op:havoc,rep:2,+cov`,
+//! encoding the lineage of each testcase in its filename.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt::Write, time::Duration};
+#[cfg(feature = "std")]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "std")]
+use crate::Error;
+
+/// The information `AFL++` encodes in the filenames of its testcases
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AflTestcaseMetadata {
+    /// The `AFL++` id of this testcase, unique in its directory
+    pub id: usize,
+    /// The `AFL++` ids of the parents of this testcase (two, for splicing)
+    pub src: Vec<usize>,
+    /// The time of discovery, since the start of the campaign
+    pub time: Option<Duration>,
+    /// The number of executions at discovery
+    pub execs: Option<u64>,
+    /// The mutation operator that produced this testcase
+    pub op: Option<String>,
+    /// The number of stacked mutations
+    pub rep: Option<u64>,
+    /// The signal that crashed the target, for crashes
+    pub sig: Option<i32>,
+    /// The original filename, for seeds
+    pub orig: Option<String>,
+    /// The fuzzer this testcase was synced from
+    pub sync: Option<String>,
+    /// If this testcase triggered new coverage (and not only new hit counts)
+    pub cov: bool,
+}
+
+crate::impl_serdeany!(AflTestcaseMetadata);
+
+impl AflTestcaseMetadata {
+    /// Creates a new [`AflTestcaseMetadata`] for the given id
+    #[must_use]
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            ..Self::default()
+        }
+    }
+
+    /// Parse an `AFL++` filename, such as `id:000012,src:000003,time:1234,execs:5678,op:havoc,rep:2`.
+    /// Returns `None` if the filename does not start with an `id`.
+    #[must_use]
+    pub fn parse(filename: &str) -> Option<Self> {
+        let mut fields = filename.split(',');
+        let id = fields.next()?.strip_prefix("id:")?.parse().ok()?;
+        let mut meta = Self::new(id);
+        for field in fields {
+            if field == "+cov" {
+                meta.cov = true;
+                continue;
+            }
+            let Some((key, value)) = field.split_once(':') else {
+                continue;
+            };
+            match key {
+                "src" => {
+                    meta.src = value
+                        .split('+')
+                        .filter_map(|src| src.parse().ok())
+                        .collect();
+                }
+                "time" => meta.time = value.parse().ok().map(Duration::from_millis),
+                "execs" => meta.execs = value.parse().ok(),
+                "op" => meta.op = Some(value.to_string()),
+                "rep" => meta.rep = value.parse().ok(),
+                "sig" => meta.sig = value.parse().ok(),
+                "orig" => meta.orig = Some(value.to_string()),
+                "sync" => meta.sync = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        Some(meta)
+    }
+
+    /// Format this metadata as an `AFL++` filename.
+    /// Fields not set are omitted.
+    #[must_use]
+    pub fn to_filename(&self) -> String {
+        let mut name = format!("id:{:06}", self.id);
+        if let Some(sig) = self.sig {
+            write!(name, ",sig:{sig:02}").unwrap();
+        }
+        if let Some(sync) = &self.sync {
+            write!(name, ",sync:{sync}").unwrap();
+        }
+        if !self.src.is_empty() {
+            let src: Vec<_> = self.src.iter().map(|src| format!("{src:06}")).collect();
+            write!(name, ",src:{}", src.join("+")).unwrap();
+        }
+        if let Some(time) = self.time {
+            write!(name, ",time:{}", time.as_millis()).unwrap();
+        }
+        if let Some(execs) = self.execs {
+            write!(name, ",execs:{execs}").unwrap();
+        }
+        if let Some(op) = &self.op {
+            write!(name, ",op:{op}").unwrap();
+        }
+        if let Some(rep) = self.rep {
+            write!(name, ",rep:{rep}").unwrap();
+        }
+        if let Some(orig) = &self.orig {
+            write!(name, ",orig:{orig}").unwrap();
+        }
+        if self.cov {
+            name.push_str(",+cov");
+        }
+        name
+    }
+}
+
+/// List the testcases of an `AFL++` directory such as `out/<fuzzer>/queue`, sorted by their `AFL++` id.
+/// Files not named like `AFL++` testcases are skipped. A missing directory is considered empty.
+#[cfg(feature = "std")]
+pub fn read_afl_dir<P>(dir: P) -> Result<Vec<(PathBuf, AflTestcaseMetadata)>, Error>
+where
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut entries = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let meta = path
+            .file_name()
+            .and_then(|name| AflTestcaseMetadata::parse(&name.to_string_lossy()));
+        if let Some(meta) = meta {
+            entries.push((path, meta));
+        }
+    }
+    entries.sort_by_key(|(_, meta)| meta.id);
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    #[cfg(feature = "std")]
+    use std::{env::temp_dir, fs};
+
+    use super::AflTestcaseMetadata;
+    #[cfg(feature = "std")]
+    use crate::{
+        bolts::{rands::StdRand, tuples::tuple_list},
+        corpus::{Corpus, CorpusId, InMemoryCorpus, LineageMetadata, OnDiskCorpus},
+        events::NopEventManager,
+        executors::{ExitKind, InProcessExecutor},
+        feedbacks::ConstFeedback,
+        inputs::BytesInput,
+        schedulers::QueueScheduler,
+        state::{HasCorpus, HasMetadata, StdState},
+        StdFuzzer,
+    };
+
+    #[test]
+    fn test_afl_filenames() {
+        let name = "id:000012,src:000003+000005,time:1234,execs:5678,op:splice,rep:2,+cov";
+        let meta = AflTestcaseMetadata::parse(name).unwrap();
+        assert_eq!(meta.id, 12);
+        assert_eq!(meta.src, [3, 5]);
+        assert_eq!(meta.time, Some(Duration::from_millis(1234)));
+        assert_eq!(meta.execs, Some(5678));
+        assert_eq!(meta.op.as_deref(), Some("splice"));
+        assert!(meta.cov);
+        assert_eq!(meta.to_filename(), name);
+
+        let crash = "id:000000,sig:06,src:000002,time:42,execs:100,op:havoc,rep:4";
+        let meta = AflTestcaseMetadata::parse(crash).unwrap();
+        assert_eq!(meta.sig, Some(6));
+        assert_eq!(meta.to_filename(), crash);
+
+        let seed = AflTestcaseMetadata::parse("id:000001,time:0,execs:0,orig:seed.txt").unwrap();
+        assert_eq!(seed.orig.as_deref(), Some("seed.txt"));
+        assert!(seed.src.is_empty());
+
+        assert!(AflTestcaseMetadata::parse("README.txt").is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_import_afl_output() {
+        let dir = temp_dir().join(format!("libafl_test_afl_import_{}", std::process::id()));
+        let queue = dir.join("out").join("queue");
+        fs::create_dir_all(&queue).unwrap();
+        fs::write(queue.join("id:000007,time:0,execs:0,orig:seed"), b"seed").unwrap();
+        fs::write(
+            queue.join("id:000009,src:000007,time:1234,execs:42,op:havoc,rep:2"),
+            b"child",
+        )
+        .unwrap();
+
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            OnDiskCorpus::<BytesInput>::with_afl_names(dir.join("corpus")).unwrap(),
+            InMemoryCorpus::<BytesInput>::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut mgr = NopEventManager::new();
+        let mut harness = |_input: &BytesInput| ExitKind::Ok;
+        let mut executor = InProcessExecutor::new(
+            &mut harness,
+            tuple_list!(),
+            &mut fuzzer,
+            &mut state,
+            &mut mgr,
+        )
+        .unwrap();
+
+        state
+            .import_afl_output(&mut fuzzer, &mut executor, &mut mgr, &dir.join("out"))
+            .unwrap();
+        assert_eq!(state.corpus().count(), 2);
+
+        // The parent is mapped to its new id, in the metadata, the lineage, and the name on disk
+        let child = state
+            .corpus()
+            .get(CorpusId::from(1_usize))
+            .unwrap()
+            .borrow();
+        let meta = child.metadata().get::<AflTestcaseMetadata>().unwrap();
+        assert_eq!(meta.src, [0]);
+        assert_eq!(
+            child.metadata().get::<LineageMetadata>().unwrap().parents,
+            [CorpusId::from(0_usize)]
+        );
+        drop(child);
+        assert!(dir
+            .join("corpus")
+            .join("id:000001,src:000000,time:1234,execs:42,op:havoc,rep:2")
+            .exists());
+        assert!(dir
+            .join("corpus")
+            .join("id:000000,time:0,execs:0,orig:seed")
+            .exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,295 @@
+//! The [`BucketedCorpus`] is a solutions corpus keeping a single testcase per [`CrashBucket`],
+//! so a single bug does not flood the solutions with thousands of testcases.
+
+use alloc::string::String;
+use core::cell::RefCell;
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, Testcase},
+    feedbacks::CrashBucket,
+    inputs::UsesInput,
+    state::HasMetadata,
+    Error,
+};
+
+/// A bucket of the [`BucketedCorpus`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashBucketEntry {
+    /// The bucket
+    pub bucket: CrashBucket,
+    /// The number of objectives in this bucket
+    pub count: u64,
+    /// The id of the testcase stored for this bucket
+    pub representative: CorpusId,
+}
+
+/// The result of adding a testcase to a [`BucketedCorpus`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BucketedAddResult {
+    /// The testcase was stored, as the first of its bucket or without bucket
+    New(CorpusId),
+    /// The testcase was only counted, in the bucket of the testcase with this id
+    DuplicateOf(CorpusId),
+}
+
+/// A corpus for solutions, grouping the testcases by the [`CrashBucket`] added by a
+/// [`crate::feedbacks::CrashBucketFeedback`].
+/// Only the first testcase of each bucket is stored in the inner corpus, the others are only counted.
+/// For each bucket, a summary file is written to the summary directory.
+/// Testcases without a [`CrashBucket`] are all stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "C: serde::Serialize + for<'a> serde::Deserialize<'a>")]
+pub struct BucketedCorpus<C> {
+    inner: C,
+    summary_dir: PathBuf,
+    buckets: HashMap<u64, CrashBucketEntry>,
+}
+
+impl<C> UsesInput for BucketedCorpus<C>
+where
+    C: Corpus,
+{
+    type Input = C::Input;
+}
+
+impl<C> Corpus for BucketedCorpus<C>
+where
+    C: Corpus,
+{
+    /// Returns the number of elements, i.e., of buckets and of testcases without bucket
+    #[inline]
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    /// Add an entry to the corpus, if it's the first of its bucket, and return its index.
+    /// For a bucket already known, the index of the testcase stored for the bucket is returned,
+    /// use [`BucketedCorpus::add_to_bucket`] to tell both cases apart.
+    fn add(&mut self, testcase: Testcase<C::Input>) -> Result<CorpusId, Error> {
+        match self.add_to_bucket(testcase)? {
+            BucketedAddResult::New(idx) | BucketedAddResult::DuplicateOf(idx) => Ok(idx),
+        }
+    }
+
+    /// Replaces the testcase at the given idx
+    #[inline]
+    fn replace(
+        &mut self,
+        idx: CorpusId,
+        testcase: Testcase<C::Input>,
+    ) -> Result<Testcase<C::Input>, Error> {
+        self.inner.replace(idx, testcase)
+    }
+
+    /// Removes an entry from the corpus, and its bucket
+    fn remove(&mut self, idx: CorpusId) -> Result<Testcase<C::Input>, Error> {
+        let testcase = self.inner.remove(idx)?;
+        self.buckets.retain(|_, entry| entry.representative != idx);
+        Ok(testcase)
+    }
+
+    /// Get by id
+    #[inline]
+    fn get(&self, idx: CorpusId) -> Result<&RefCell<Testcase<C::Input>>, Error> {
+        self.inner.get(idx)
+    }
+
+    /// Current testcase scheduled
+    #[inline]
+    fn current(&self) -> &Option<CorpusId> {
+        self.inner.current()
+    }
+
+    /// Current testcase scheduled (mutable)
+    #[inline]
+    fn current_mut(&mut self) -> &mut Option<CorpusId> {
+        self.inner.current_mut()
+    }
+
+    #[inline]
+    fn next(&self, idx: CorpusId) -> Option<CorpusId> {
+        self.inner.next(idx)
+    }
+
+    #[inline]
+    fn prev(&self, idx: CorpusId) -> Option<CorpusId> {
+        self.inner.prev(idx)
+    }
+
+    #[inline]
+    fn first(&self) -> Option<CorpusId> {
+        self.inner.first()
+    }
+
+    #[inline]
+    fn last(&self) -> Option<CorpusId> {
+        self.inner.last()
+    }
+}
+
+impl<C> BucketedCorpus<C>
+where
+    C: Corpus,
+{
+    /// Creates a new [`BucketedCorpus`] storing the representatives in `inner`,
+    /// and the summary of each bucket in `summary_dir`.
+    ///
+    /// Will error, if [`std::fs::create_dir_all()`] failed for `summary_dir`.
+    pub fn new<P>(inner: C, summary_dir: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        fs::create_dir_all(summary_dir.as_ref())?;
+        Ok(Self {
+            inner,
+            summary_dir: summary_dir.as_ref().to_path_buf(),
+            buckets: HashMap::new(),
+        })
+    }
+
+    /// Add an entry to the corpus, if it's the first of its bucket.
+    /// For a bucket already known, the entry is only counted, and the testcase stored for the bucket is returned.
+    pub fn add_to_bucket(
+        &mut self,
+        testcase: Testcase<C::Input>,
+    ) -> Result<BucketedAddResult, Error> {
+        let Some(bucket) = testcase.metadata().get::<CrashBucket>().cloned() else {
+            return Ok(BucketedAddResult::New(self.inner.add(testcase)?));
+        };
+        let id = bucket.id();
+        if let Some(entry) = self.buckets.get_mut(&id) {
+            entry.count += 1;
+            let representative = entry.representative;
+            self.write_summary(id)?;
+            return Ok(BucketedAddResult::DuplicateOf(representative));
+        }
+
+        let idx = self.inner.add(testcase)?;
+        self.buckets.insert(
+            id,
+            CrashBucketEntry {
+                bucket,
+                count: 1,
+                representative: idx,
+            },
+        );
+        self.write_summary(id)?;
+        Ok(BucketedAddResult::New(idx))
+    }
+
+    /// The buckets, by [`CrashBucket::id`]
+    #[must_use]
+    pub fn buckets(&self) -> &HashMap<u64, CrashBucketEntry> {
+        &self.buckets
+    }
+
+    /// The inner corpus, holding a testcase per bucket
+    #[must_use]
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// The path of the summary file of the bucket with the given id
+    #[must_use]
+    pub fn summary_path(&self, id: u64) -> PathBuf {
+        self.summary_dir.join(format!("bucket_{id:016x}.txt"))
+    }
+
+    fn write_summary(&self, id: u64) -> Result<(), Error> {
+        let entry = &self.buckets[&id];
+        let mut summary = String::new();
+        writeln!(summary, "bucket: {id:016x}").unwrap();
+        writeln!(summary, "exit_kind: {:?}", entry.bucket.exit_kind).unwrap();
+        writeln!(summary, "hash: {:016x}", entry.bucket.hash).unwrap();
+        if let Some(bug_type) = &entry.bucket.bug_type {
+            writeln!(summary, "bug_type: {bug_type}").unwrap();
+        }
+        if let Some(signal) = entry.bucket.signal {
+            writeln!(summary, "signal: {signal}").unwrap();
+        }
+        writeln!(summary, "count: {}", entry.count).unwrap();
+        let testcase = self.inner.get(entry.representative)?.borrow();
+        match testcase.filename() {
+            Some(filename) => writeln!(summary, "representative: {filename}").unwrap(),
+            None => writeln!(summary, "representative: #{}", entry.representative).unwrap(),
+        }
+        fs::write(self.summary_path(id), summary)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{BucketedAddResult, BucketedCorpus};
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        executors::ExitKind,
+        feedbacks::CrashBucket,
+        inputs::BytesInput,
+        state::HasMetadata,
+    };
+
+    #[test]
+    fn test_bucketed_corpus() {
+        let dir = "target/.test/bucketed";
+        let _ = fs::remove_dir_all(dir);
+        let mut corpus = BucketedCorpus::new(InMemoryCorpus::<BytesInput>::new(), dir).unwrap();
+
+        let crash = |content: &[u8], hash, bug_type: &str, signal| {
+            let mut testcase = Testcase::new(BytesInput::new(content.to_vec()));
+            testcase.add_metadata(CrashBucket {
+                exit_kind: ExitKind::Crash,
+                hash,
+                bug_type: Some(bug_type.into()),
+                signal: Some(signal),
+            });
+            testcase
+        };
+        let BucketedAddResult::New(first) =
+            corpus.add_to_bucket(crash(b"a", 1, "SEGV", 11)).unwrap()
+        else {
+            panic!("The first crash of a bucket was not stored");
+        };
+        assert_eq!(
+            corpus.add_to_bucket(crash(b"b", 1, "SEGV", 11)).unwrap(),
+            BucketedAddResult::DuplicateOf(first)
+        );
+        corpus
+            .add(crash(b"c", 1, "heap-buffer-overflow", 11))
+            .unwrap();
+        corpus.add(crash(b"d", 2, "SEGV", 11)).unwrap();
+        assert!(matches!(
+            corpus.add_to_bucket(crash(b"e", 1, "SEGV", 7)).unwrap(),
+            BucketedAddResult::New(_)
+        ));
+        corpus
+            .add(Testcase::new(BytesInput::new(b"f".to_vec())))
+            .unwrap();
+
+        assert_eq!(corpus.count(), 5);
+        assert_eq!(corpus.buckets().len(), 4);
+        let id = corpus
+            .buckets()
+            .iter()
+            .find(|(_, entry)| entry.representative == first)
+            .map(|(id, _)| *id)
+            .unwrap();
+        assert_eq!(corpus.buckets()[&id].count, 2);
+        let summary = fs::read_to_string(corpus.summary_path(id)).unwrap();
+        assert!(summary.contains("count: 2"));
+        assert!(summary.contains("bug_type: SEGV"));
+        assert!(summary.contains("signal: 11"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,409 @@
+//! The lineage of testcases: which testcases, stage and mutations each testcase was derived from.
+//! The [`LineageGraph`] exports the genealogy of a corpus as DOT or JSON graph,
+//! to analyze which seeds and mutators pay off.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Write;
+#[cfg(feature = "std")]
+use std::{fs, path::Path};
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    state::{HasCorpus, HasMetadata},
+    Error,
+};
+
+/// The lineage of a [`crate::corpus::Testcase`], added to each new testcase by the fuzzer
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineageMetadata {
+    /// The testcases this testcase was derived from, in the corpus of `client`
+    pub parents: Vec<CorpusId>,
+    /// The client this testcase was received from, `None` if it was found locally
+    pub client: Option<u32>,
+    /// The id of this testcase in the corpus of `client`
+    pub origin_id: Option<CorpusId>,
+    /// The name of the stage that produced this testcase, if known
+    pub stage: Option<String>,
+    /// The mutations that produced this testcase, as logged by a [`crate::mutators::LoggerScheduledMutator`]
+    pub mutations: Vec<String>,
+}
+
+crate::impl_serdeany!(LineageMetadata);
+
+impl LineageMetadata {
+    /// Creates a new [`struct@LineageMetadata`] for a testcase without parents, e.g. a seed
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The lineage of an input found right now: derived from the current testcase of the corpus,
+    /// by the stage and mutations in the [`struct@LineageContextMetadata`] of the state.
+    pub fn from_state<S>(state: &S) -> Self
+    where
+        S: HasCorpus + HasMetadata,
+    {
+        let context = state.metadata().get::<LineageContextMetadata>();
+        let imported = context.map_or(false, |context| context.imported);
+        Self {
+            parents: if imported {
+                vec![]
+            } else {
+                state.corpus().current().iter().copied().collect()
+            },
+            client: None,
+            origin_id: None,
+            stage: context.and_then(|context| context.stage.clone()),
+            mutations: context
+                .map(|context| context.mutations.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The lineage to send to other clients along with the testcase with the given id
+    #[must_use]
+    pub fn to_send(&self, id: CorpusId) -> Self {
+        Self {
+            origin_id: Some(id),
+            ..self.clone()
+        }
+    }
+
+    /// The lineage of a testcase received from the given client
+    #[must_use]
+    pub fn received_from(mut self, client: u32) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// The client this testcase was received from and its id in the corpus of this client
+    #[must_use]
+    pub fn origin(&self) -> Option<(u32, CorpusId)> {
+        self.client.zip(self.origin_id)
+    }
+
+    /// The id in the given corpus of the testcase `origin_id` received from `client`, if any.
+    /// The most recent testcases are searched first.
+    pub fn find_received<C>(
+        corpus: &C,
+        client: u32,
+        origin_id: CorpusId,
+    ) -> Result<Option<CorpusId>, Error>
+    where
+        C: Corpus,
+    {
+        let mut cur_id = corpus.last();
+        while let Some(id) = cur_id {
+            let origin = corpus
+                .get(id)?
+                .borrow()
+                .metadata()
+                .get::<Self>()
+                .and_then(Self::origin);
+            if origin == Some((client, origin_id)) {
+                return Ok(Some(id));
+            }
+            cur_id = corpus.prev(id);
+        }
+        Ok(None)
+    }
+}
+
+/// The stage and mutations producing the inputs currently evaluated,
+/// recorded in the [`struct@LineageMetadata`] of the testcases they turn into
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LineageContextMetadata {
+    /// The name of the current stage
+    pub stage: Option<String>,
+    /// If the current stage imports inputs from outside, instead of deriving them from the current testcase
+    pub imported: bool,
+    /// The mutations applied to the current input
+    pub mutations: Vec<String>,
+}
+
+crate::impl_serdeany!(LineageContextMetadata);
+
+impl LineageContextMetadata {
+    fn get_or_insert<S>(state: &mut S) -> &mut Self
+    where
+        S: HasMetadata,
+    {
+        if !state.has_metadata::<Self>() {
+            state.add_metadata(Self::default());
+        }
+        state.metadata_mut().get_mut::<Self>().unwrap()
+    }
+
+    /// Mark the start of a stage, new testcases will be attributed to it
+    /// and considered derived from the current testcase
+    pub fn enter_stage<S>(state: &mut S, name: &str)
+    where
+        S: HasMetadata,
+    {
+        let context = Self::get_or_insert(state);
+        context.stage = Some(name.to_string());
+        context.imported = false;
+        context.mutations.clear();
+    }
+
+    /// Mark the start of a stage importing inputs from outside, e.g. from disk,
+    /// new testcases will be attributed to it without parents
+    pub fn enter_import_stage<S>(state: &mut S, name: &str)
+    where
+        S: HasMetadata,
+    {
+        Self::enter_stage(state, name);
+        Self::get_or_insert(state).imported = true;
+    }
+
+    /// Mark the end of the current stage
+    pub fn leave_stage<S>(state: &mut S)
+    where
+        S: HasMetadata,
+    {
+        if let Some(context) = state.metadata_mut().get_mut::<Self>() {
+            context.stage = None;
+            context.imported = false;
+            context.mutations.clear();
+        }
+    }
+
+    /// Set the mutations applied to the current input
+    pub fn set_mutations<S>(state: &mut S, mutations: Vec<String>)
+    where
+        S: HasMetadata,
+    {
+        Self::get_or_insert(state).mutations = mutations;
+    }
+}
+
+/// A testcase in a [`LineageGraph`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineageNode {
+    /// The unique name of this node in the graph
+    pub name: String,
+    /// The id of the testcase in its corpus, `None` for testcases only known as parents of received testcases
+    pub id: Option<CorpusId>,
+    /// If this testcase is an objective
+    pub objective: bool,
+    /// The lineage of the testcase, if known
+    pub lineage: Option<LineageMetadata>,
+    /// The names of the parent nodes
+    pub parents: Vec<String>,
+}
+
+/// The genealogy of a corpus and its solutions, built from the [`struct@LineageMetadata`] of their testcases
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LineageGraph {
+    /// The nodes of the graph
+    pub nodes: Vec<LineageNode>,
+}
+
+impl LineageGraph {
+    /// Build the lineage graph of the given corpus and solutions
+    pub fn new<C, SC>(corpus: &C, solutions: &SC) -> Result<Self, Error>
+    where
+        C: Corpus,
+        SC: Corpus,
+    {
+        let mut lineages = vec![];
+        for id in corpus.ids() {
+            let lineage = corpus
+                .get(id)?
+                .borrow()
+                .metadata()
+                .get::<LineageMetadata>()
+                .cloned();
+            lineages.push((id, false, lineage));
+        }
+        for id in solutions.ids() {
+            let lineage = solutions
+                .get(id)?
+                .borrow()
+                .metadata()
+                .get::<LineageMetadata>()
+                .cloned();
+            lineages.push((id, true, lineage));
+        }
+
+        // The parents of received testcases are ids in the corpus of the sender
+        let mut received = HashMap::new();
+        for (id, _, lineage) in &lineages {
+            if let Some(origin) = lineage.as_ref().and_then(LineageMetadata::origin) {
+                received.insert(origin, *id);
+            }
+        }
+
+        let mut graph = Self::default();
+        let mut external = vec![];
+        for (id, objective, lineage) in lineages {
+            let mut parents = vec![];
+            if let Some(lineage) = &lineage {
+                for parent in &lineage.parents {
+                    let name = match lineage.client {
+                        None => Self::node_name(*parent, false),
+                        Some(client) => {
+                            if let Some(local) = received.get(&(client, *parent)) {
+                                Self::node_name(*local, false)
+                            } else {
+                                let name = format!("client{client}_{parent}");
+                                if !external.contains(&name) {
+                                    external.push(name.clone());
+                                }
+                                name
+                            }
+                        }
+                    };
+                    parents.push(name);
+                }
+            }
+            graph.nodes.push(LineageNode {
+                name: Self::node_name(id, objective),
+                id: Some(id),
+                objective,
+                lineage,
+                parents,
+            });
+        }
+        for name in external {
+            graph.nodes.push(LineageNode {
+                name,
+                id: None,
+                objective: false,
+                lineage: None,
+                parents: vec![],
+            });
+        }
+        Ok(graph)
+    }
+
+    fn node_name(id: CorpusId, objective: bool) -> String {
+        if objective {
+            format!("solution{id}")
+        } else {
+            format!("testcase{id}")
+        }
+    }
+
+    /// The graph in the DOT format of graphviz, with an edge from each parent to its children
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph lineage {\n");
+        for node in &self.nodes {
+            let mut label = node.name.clone();
+            if let Some(lineage) = &node.lineage {
+                if let Some(stage) = &lineage.stage {
+                    write!(label, "\\n{stage}").unwrap();
+                }
+                if !lineage.mutations.is_empty() {
+                    write!(label, "\\n{}", lineage.mutations.join(", ")).unwrap();
+                }
+            }
+            let shape = if node.objective {
+                "doubleoctagon"
+            } else if node.id.is_none() {
+                "ellipse"
+            } else {
+                "box"
+            };
+            writeln!(
+                dot,
+                "  \"{}\" [label=\"{}\", shape={shape}];",
+                node.name,
+                label.replace('"', "\\\"")
+            )
+            .unwrap();
+        }
+        for node in &self.nodes {
+            for parent in &node.parents {
+                writeln!(dot, "  \"{parent}\" -> \"{}\";", node.name).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The graph as JSON
+    #[cfg(feature = "std")]
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Write the graph to a file, as JSON if the extension is `.json`, as DOT otherwise
+    #[cfg(feature = "std")]
+    pub fn write_to_file<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let content = if path.extension().map_or(false, |ext| ext == "json") {
+            self.to_json()?
+        } else {
+            self.to_dot()
+        };
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineageGraph, LineageMetadata};
+    use crate::{
+        corpus::{Corpus, CorpusId, InMemoryCorpus, Testcase},
+        inputs::BytesInput,
+        state::HasMetadata,
+    };
+
+    #[test]
+    fn test_lineage_graph() {
+        let mut corpus = InMemoryCorpus::<BytesInput>::new();
+        let mut solutions = InMemoryCorpus::<BytesInput>::new();
+
+        let seed = corpus
+            .add(Testcase::new(BytesInput::new(b"seed".to_vec())))
+            .unwrap();
+
+        let mut child = Testcase::new(BytesInput::new(b"child".to_vec()));
+        child.add_metadata(LineageMetadata {
+            parents: vec![seed],
+            stage: Some("StdMutationalStage".into()),
+            mutations: vec!["BitFlipMutator".into()],
+            ..LineageMetadata::new()
+        });
+        let child = corpus.add(child).unwrap();
+
+        // A testcase received from client 3, derived from its testcase 7 we never received
+        let mut received = Testcase::new(BytesInput::new(b"received".to_vec()));
+        received.add_metadata(
+            LineageMetadata {
+                parents: vec![CorpusId::from(7_usize)],
+                ..LineageMetadata::new()
+            }
+            .to_send(CorpusId::from(9_usize))
+            .received_from(3),
+        );
+        corpus.add(received).unwrap();
+
+        let mut crash = Testcase::new(BytesInput::new(b"crash".to_vec()));
+        crash.add_metadata(LineageMetadata {
+            parents: vec![child],
+            ..LineageMetadata::new()
+        });
+        solutions.add(crash).unwrap();
+
+        let graph = LineageGraph::new(&corpus, &solutions).unwrap();
+        assert_eq!(graph.nodes.len(), 5);
+        let dot = graph.to_dot();
+        assert!(dot.contains("\"testcase0\" -> \"testcase1\";"));
+        assert!(dot.contains("\"testcase1\" -> \"solution0\";"));
+        assert!(dot.contains("\"client3_7\" -> \"testcase2\";"));
+        assert!(dot.contains("StdMutationalStage\\nBitFlipMutator"));
+    }
+}
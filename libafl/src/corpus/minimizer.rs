@@ -6,18 +6,26 @@ use alloc::{
     vec::Vec,
 };
 use core::{hash::Hash, marker::PhantomData};
+#[cfg(feature = "std")]
+use std::{fs, path::Path, thread, time::Duration};
 
-use hashbrown::{HashMap, HashSet};
+use ahash::RandomState;
+use hashbrown::{hash_map::Entry, HashMap, HashSet};
 use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "cmin")]
 use z3::{ast::Bool, Config, Context, Optimize};
 
+#[cfg(feature = "std")]
+use crate::bolts::{core_affinity::Cores, fs::write_file_atomic};
 use crate::{
     bolts::{
         tuples::{MatchName, Named},
         AsIter,
     },
-    corpus::Corpus,
+    corpus::{Corpus, CorpusId},
     executors::{Executor, HasObservers},
+    inputs::Input,
     observers::{MapObserver, ObserversTuple},
     schedulers::{LenTimeMulTestcaseScore, Scheduler, TestcaseScore},
     state::{HasCorpus, HasMetadata, UsesState},
@@ -49,6 +57,7 @@ where
 /// Minimizes a corpus according to coverage maps, weighting by the specified `TestcaseScore`.
 ///
 /// Algorithm based on WMOPT: <https://hexhive.epfl.ch/publications/files/21ISSTA2.pdf>
+#[cfg(feature = "cmin")]
 #[derive(Debug)]
 pub struct MapCorpusMinimizer<E, O, T, TS>
 where
@@ -61,9 +70,11 @@ where
 }
 
 /// Standard corpus minimizer, which weights inputs by length and time.
+#[cfg(feature = "cmin")]
 pub type StdCorpusMinimizer<E, O, T> =
     MapCorpusMinimizer<E, O, T, LenTimeMulTestcaseScore<<E as UsesState>::State>>;
 
+#[cfg(feature = "cmin")]
 impl<E, O, T, TS> MapCorpusMinimizer<E, O, T, TS>
 where
     E: UsesState,
@@ -83,6 +94,7 @@ where
     }
 }
 
+#[cfg(feature = "cmin")]
 impl<E, O, T, TS> CorpusMinimizer<E> for MapCorpusMinimizer<E, O, T, TS>
 where
     E: UsesState,
@@ -118,11 +130,7 @@ where
                 let weight = TS::compute(&mut *testcase, state)?
                     .to_u64()
                     .expect("Weight must be computable.");
-                let input = testcase
-                    .input()
-                    .as_ref()
-                    .expect("Input must be available.")
-                    .clone();
+                let input = testcase.load_input()?.clone();
                 (weight, input)
             };
 
@@ -206,3 +214,554 @@ where
         res
     }
 }
+
+/// The AFL hitcount bucket of the given hitcount, from `1` for a single hit to `8` for `128` hits or more.
+/// Returns `0` for no hit.
+#[must_use]
+pub fn hitcount_bucket(hitcount: u64) -> u8 {
+    match hitcount {
+        0 => 0,
+        1 => 1,
+        2 => 2,
+        3 => 3,
+        4..=7 => 4,
+        8..=15 => 5,
+        16..=31 => 6,
+        32..=127 => 7,
+        _ => 8,
+    }
+}
+
+/// The best testcase covering each edge, as selected by a [`GreedyCorpusMinimizer`].
+///
+/// Testcases are identified by `K`: their [`CorpusId`] when the whole corpus is minimized in one
+/// process, or their name when the corpus is traced in shards by several clients of a `Launcher`.
+/// The winners of all shards are then merged with [`CminWinners::merge`].
+///
+/// The edges of each winning testcase are kept, so that [`CminWinners::kept`] can drop the
+/// winners made redundant by the others, like the second pass of `afl-cmin`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CminWinners<K>
+where
+    K: Eq + Hash,
+{
+    /// The score and the key of the best testcase for each edge
+    winners: HashMap<u64, (u64, K)>,
+    /// The number of edges won and all the edges covered by each winning testcase
+    traces: HashMap<K, (usize, Vec<u64>)>,
+    /// The number of testcases covering each edge
+    hits: HashMap<u64, u64>,
+}
+
+impl<K> Default for CminWinners<K>
+where
+    K: Eq + Hash,
+{
+    fn default() -> Self {
+        Self {
+            winners: HashMap::new(),
+            traces: HashMap::new(),
+            hits: HashMap::new(),
+        }
+    }
+}
+
+impl<K> CminWinners<K>
+where
+    K: Clone + Eq + Hash + Ord,
+{
+    /// Creates a new, empty, [`CminWinners`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Offer a testcase covering the given edges.
+    /// For each edge, the testcase with the lowest score wins, ties are broken by the lowest key.
+    /// Offers of a key already offered, e.g. of identical inputs keyed by their content, are merged:
+    /// the key keeps the edges it already won, and wins more with a lower score.
+    pub fn offer(&mut self, score: u64, key: &K, edges: Vec<u64>) {
+        for edge in &edges {
+            *self.hits.entry(*edge).or_default() += 1;
+        }
+        match self.traces.entry(key.clone()) {
+            Entry::Occupied(mut entry) => {
+                let trace = &mut entry.get_mut().1;
+                trace.extend(&edges);
+                trace.sort_unstable();
+                trace.dedup();
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((0, edges.clone()));
+            }
+        }
+        for edge in edges {
+            self.offer_edge(edge, score, key);
+        }
+        self.drop_losers();
+    }
+
+    /// Offer a testcase, whose trace is already in `traces`, for a single edge
+    fn offer_edge(&mut self, edge: u64, score: u64, key: &K) {
+        if let Some(winner) = self.winners.get(&edge) {
+            if (score, key) >= (winner.0, &winner.1) {
+                return;
+            }
+        }
+        if let Some((_, previous)) = self.winners.insert(edge, (score, key.clone())) {
+            if let Some((wins, _)) = self.traces.get_mut(&previous) {
+                *wins -= 1;
+            }
+        }
+        if let Some((wins, _)) = self.traces.get_mut(key) {
+            *wins += 1;
+        }
+    }
+
+    /// Forget the traces of the testcases not winning any edge
+    fn drop_losers(&mut self) {
+        self.traces.retain(|_, (wins, _)| *wins > 0);
+    }
+
+    /// Merge the winners of another shard into these winners
+    pub fn merge(&mut self, other: Self) {
+        for (edge, hits) in other.hits {
+            *self.hits.entry(edge).or_default() += hits;
+        }
+        let mut traces = other.traces;
+        for (edge, (score, key)) in other.winners {
+            if let Some((_, trace)) = traces.remove(&key) {
+                self.traces.entry(key.clone()).or_insert((0, trace));
+            }
+            self.offer_edge(edge, score, &key);
+        }
+        self.drop_losers();
+    }
+
+    /// The number of edges covered
+    #[must_use]
+    pub fn edges(&self) -> usize {
+        self.winners.len()
+    }
+
+    /// The testcases to keep, like `afl-cmin` does: going from the rarest edge to the most
+    /// common one, the winner of each edge not yet covered by the kept testcases is kept.
+    #[must_use]
+    pub fn kept(&self) -> HashSet<K> {
+        let mut edges: Vec<_> = self.winners.keys().copied().collect();
+        edges.sort_unstable_by_key(|edge| (self.hits.get(edge).copied().unwrap_or(0), *edge));
+
+        let mut covered = HashSet::new();
+        let mut kept = HashSet::new();
+        for edge in edges {
+            if covered.contains(&edge) {
+                continue;
+            }
+            let key = &self.winners[&edge].1;
+            covered.extend(self.traces[key].1.iter().copied());
+            kept.insert(key.clone());
+        }
+        kept
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K> CminWinners<K>
+where
+    K: Clone + Eq + Hash + Ord + Serialize + for<'de> Deserialize<'de>,
+{
+    /// Atomically write the winners to a file, e.g. at the end of a shard
+    pub fn to_file<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        write_file_atomic(path, &postcard::to_allocvec(self)?)
+    }
+
+    /// Read winners written by [`CminWinners::to_file`]
+    pub fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(postcard::from_bytes(&fs::read(path)?)?)
+    }
+
+    /// Read and merge all the winners written to the given directory, one file per shard
+    pub fn from_dir<P>(dir: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut winners = Self::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            // skip the temporary files of `to_file`
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if path.is_file() && !hidden {
+                winners.merge(Self::from_file(path)?);
+            }
+        }
+        Ok(winners)
+    }
+}
+
+/// Minimizes a corpus according to coverage maps, with the greedy algorithm of `afl-cmin`:
+/// for each edge, the testcase with the lowest [`TestcaseScore`] covering it is kept,
+/// all the testcases not winning any edge are removed.
+///
+/// Unlike the `MapCorpusMinimizer` of the `cmin` feature, this does not need z3 and runs
+/// in linear time, so it scales to huge corpora. The result is not guaranteed to be minimal.
+///
+/// To minimize in parallel, each client of a `Launcher` calls
+/// [`GreedyCorpusMinimizer::minimize_launched`]. It traces its shard of the corpus with
+/// [`GreedyCorpusMinimizer::minimize_shard`] and writes the resulting [`CminWinners`] to a shared
+/// directory with [`CminWinners::to_file`]. The merged winners are then applied to the corpus with
+/// [`GreedyCorpusMinimizer::keep_winners`].
+#[derive(Debug)]
+pub struct GreedyCorpusMinimizer<E, O, T, TS>
+where
+    E: UsesState,
+    E::State: HasCorpus + HasMetadata,
+    TS: TestcaseScore<E::State>,
+{
+    obs_name: String,
+    hitcounts: bool,
+    phantom: PhantomData<(E, O, T, TS)>,
+}
+
+/// Greedy corpus minimizer, which weights inputs by length and time, like `afl-cmin`.
+pub type StdGreedyCorpusMinimizer<E, O, T> =
+    GreedyCorpusMinimizer<E, O, T, LenTimeMulTestcaseScore<<E as UsesState>::State>>;
+
+impl<E, O, T, TS> GreedyCorpusMinimizer<E, O, T, TS>
+where
+    E: UsesState,
+    for<'a> O: MapObserver<Entry = T> + AsIter<'a, Item = T>,
+    E::State: HasMetadata + HasCorpus,
+    T: Copy + PartialEq + ToPrimitive,
+    TS: TestcaseScore<E::State>,
+{
+    /// Constructs a new `GreedyCorpusMinimizer` keeping a testcase for each edge covered by the
+    /// given observer, regardless of its hitcount.
+    pub fn new(obs: &O) -> Self
+    where
+        O: Named,
+    {
+        Self {
+            obs_name: obs.name().to_string(),
+            hitcounts: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Constructs a new `GreedyCorpusMinimizer` keeping a testcase for each edge and each AFL
+    /// hitcount bucket of the edge covered by the given observer, like `afl-cmin` does by default.
+    pub fn with_hitcounts(obs: &O) -> Self
+    where
+        O: Named,
+    {
+        Self {
+            obs_name: obs.name().to_string(),
+            hitcounts: true,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The name identifying a testcase across clients, the name generated by its input
+    fn testcase_name(state: &E::State, idx: CorpusId) -> Result<String, Error> {
+        let mut testcase = state.corpus().get(idx)?.borrow_mut();
+        Ok(testcase.load_input()?.generate_name(idx.0))
+    }
+
+    /// Execute the testcases with a key, offering them for all the edges they cover
+    fn trace<EM, K, Z>(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        manager: &mut EM,
+        state: &mut E::State,
+        mut key: impl FnMut(&E::State, CorpusId) -> Result<Option<K>, Error>,
+    ) -> Result<CminWinners<K>, Error>
+    where
+        E: Executor<EM, Z> + HasObservers,
+        EM: UsesState<State = E::State>,
+        K: Clone + Eq + Hash + Ord,
+        Z: UsesState<State = E::State>,
+    {
+        let mut winners = CminWinners::new();
+
+        let mut cur_id = state.corpus().first();
+        while let Some(idx) = cur_id {
+            cur_id = state.corpus().next(idx);
+            let Some(key) = key(state, idx)? else {
+                continue;
+            };
+
+            let (score, input) = {
+                let mut testcase = state.corpus().get(idx)?.borrow_mut();
+                let score = TS::compute(&mut *testcase, state)?
+                    .to_u64()
+                    .expect("Weight must be computable.");
+                let input = testcase.load_input()?.clone();
+                (score, input)
+            };
+
+            // Execute the input; we cannot rely on the metadata already being present.
+            executor.observers_mut().pre_exec_all(state, &input)?;
+            let kind = executor.run_target(fuzzer, state, manager, &input)?;
+            executor
+                .observers_mut()
+                .post_exec_all(state, &input, &kind)?;
+
+            let obs: &O = executor
+                .observers()
+                .match_name::<O>(&self.obs_name)
+                .expect("Observer must be present.");
+            let initial = obs.initial();
+            let edges = obs
+                .as_iter()
+                .copied()
+                .enumerate()
+                .filter(|(_, e)| *e != initial)
+                .map(|(i, e)| {
+                    let bucket = if self.hitcounts {
+                        hitcount_bucket(e.to_u64().unwrap_or(u64::MAX))
+                    } else {
+                        0
+                    };
+                    ((i as u64) << 8) | u64::from(bucket)
+                })
+                .collect();
+            winners.offer(score, &key, edges);
+        }
+
+        Ok(winners)
+    }
+
+    /// Trace the testcases of the given shard of the corpus, out of `shards` shards.
+    /// Testcases are assigned to shards by the hash of their name, so that clients loading the
+    /// same inputs in a different order still trace disjoint shards.
+    pub fn minimize_shard<EM, Z>(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        manager: &mut EM,
+        state: &mut E::State,
+        shard: usize,
+        shards: usize,
+    ) -> Result<CminWinners<String>, Error>
+    where
+        E: Executor<EM, Z> + HasObservers,
+        EM: UsesState<State = E::State>,
+        Z: UsesState<State = E::State>,
+    {
+        if shard >= shards {
+            return Err(Error::illegal_argument(format!(
+                "Shard {shard} out of range for {shards} shards"
+            )));
+        }
+        self.trace(fuzzer, executor, manager, state, |state, idx| {
+            let name = Self::testcase_name(state, idx)?;
+            let hash = RandomState::with_seeds(0, 0, 0, 0).hash_one(&name);
+            Ok((hash % shards as u64 == shard as u64).then_some(name))
+        })
+    }
+
+    /// Minimize the corpus of a client spawned by a [`crate::bolts::launcher::Launcher`], in parallel
+    /// with the other clients.
+    ///
+    /// Call it from the `run_client` closure, once the initial inputs are loaded, with the `cores`
+    /// given to the `Launcher` and the `core_id` passed to the closure. Each client traces its shard
+    /// of the corpus and writes its winners to `dir`, which must be shared by all the clients and
+    /// empty at start. Each client then waits for the winners of all the others, and keeps the
+    /// merged winners in its corpus, so that all clients end up with the same minimized corpus.
+    #[cfg(feature = "std")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn minimize_launched<CS, EM, P, Z>(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        manager: &mut EM,
+        state: &mut E::State,
+        cores: &Cores,
+        core_id: usize,
+        dir: P,
+    ) -> Result<(), Error>
+    where
+        E: Executor<EM, Z> + HasObservers,
+        CS: Scheduler<State = E::State>,
+        EM: UsesState<State = E::State>,
+        P: AsRef<Path>,
+        Z: HasScheduler<Scheduler = CS, State = E::State>,
+    {
+        let dir = dir.as_ref();
+        let shards = cores.ids.len();
+        let shard = cores
+            .ids
+            .iter()
+            .position(|core| core.id == core_id)
+            .ok_or_else(|| {
+                Error::illegal_argument(format!("Core {core_id} is not one of {cores:?}"))
+            })?;
+
+        let winners = self.minimize_shard(fuzzer, executor, manager, state, shard, shards)?;
+        fs::create_dir_all(dir)?;
+        winners.to_file(dir.join(format!("shard_{shard}")))?;
+
+        let paths: Vec<_> = (0..shards)
+            .map(|shard| dir.join(format!("shard_{shard}")))
+            .collect();
+        while !paths.iter().all(|path| path.exists()) {
+            thread::sleep(Duration::from_millis(100));
+        }
+        let mut merged = CminWinners::new();
+        for path in paths {
+            merged.merge(CminWinners::from_file(path)?);
+        }
+        self.keep_winners(fuzzer, state, &merged)
+    }
+
+    /// Remove all testcases not kept by the given winners, identified by their name
+    pub fn keep_winners<CS, Z>(
+        &self,
+        fuzzer: &mut Z,
+        state: &mut E::State,
+        winners: &CminWinners<String>,
+    ) -> Result<(), Error>
+    where
+        CS: Scheduler<State = E::State>,
+        Z: HasScheduler<Scheduler = CS, State = E::State>,
+    {
+        let kept = winners.kept();
+        let mut removed = vec![];
+        for idx in state.corpus().ids() {
+            if !kept.contains(&Self::testcase_name(state, idx)?) {
+                removed.push(idx);
+            }
+        }
+        Self::remove_all(fuzzer, state, removed)
+    }
+
+    fn remove_all<CS, Z>(
+        fuzzer: &mut Z,
+        state: &mut E::State,
+        mut removed: Vec<CorpusId>,
+    ) -> Result<(), Error>
+    where
+        CS: Scheduler<State = E::State>,
+        Z: HasScheduler<Scheduler = CS, State = E::State>,
+    {
+        // reverse order; if indexes are stored in a vec, we need to remove from back to front
+        removed.sort_unstable_by(|idx1, idx2| idx2.cmp(idx1));
+        for idx in removed {
+            let removed = state.corpus_mut().remove(idx)?;
+            // scheduler needs to know we've removed the input, or it will continue to try
+            // to use now-missing inputs
+            fuzzer
+                .scheduler_mut()
+                .on_remove(state, idx, &Some(removed))?;
+        }
+        Ok(())
+    }
+}
+
+impl<E, O, T, TS> CorpusMinimizer<E> for GreedyCorpusMinimizer<E, O, T, TS>
+where
+    E: UsesState,
+    for<'a> O: MapObserver<Entry = T> + AsIter<'a, Item = T>,
+    E::State: HasMetadata + HasCorpus,
+    T: Copy + PartialEq + ToPrimitive,
+    TS: TestcaseScore<E::State>,
+{
+    fn minimize<CS, EM, Z>(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        manager: &mut EM,
+        state: &mut E::State,
+    ) -> Result<(), Error>
+    where
+        E: Executor<EM, Z> + HasObservers,
+        CS: Scheduler<State = E::State>,
+        EM: UsesState<State = E::State>,
+        Z: HasScheduler<Scheduler = CS, State = E::State>,
+    {
+        let winners = self.trace(fuzzer, executor, manager, state, |_, idx| Ok(Some(idx)))?;
+        let kept = winners.kept();
+        let removed = state
+            .corpus()
+            .ids()
+            .filter(|idx| !kept.contains(idx))
+            .collect();
+        Self::remove_all(fuzzer, state, removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec, vec::Vec};
+
+    use super::{hitcount_bucket, CminWinners};
+
+    #[test]
+    fn test_cmin_winners() {
+        assert_eq!(hitcount_bucket(0), 0);
+        assert_eq!(hitcount_bucket(3), 3);
+        assert_eq!(hitcount_bucket(5), 4);
+        assert_eq!(hitcount_bucket(300), 8);
+
+        let mut shard0 = CminWinners::new();
+        shard0.offer(10, &"a".to_string(), vec![1, 2]);
+        shard0.offer(5, &"b".to_string(), vec![2]);
+        let mut shard1 = CminWinners::new();
+        shard1.offer(10, &"0".to_string(), vec![1]);
+        shard1.offer(50, &"c".to_string(), vec![3]);
+        shard0.merge(shard1);
+
+        assert_eq!(shard0.edges(), 3);
+        let mut kept: Vec<_> = shard0.kept().into_iter().collect();
+        kept.sort();
+        assert_eq!(kept, ["0", "b", "c"]);
+    }
+
+    #[test]
+    fn test_cmin_winners_redundant() {
+        // 0 wins edge 1 and 1 wins edge 2, but they are redundant with 2, the only testcase
+        // covering edge 3, which is kept first as it is the rarest edge
+        let mut winners = CminWinners::new();
+        winners.offer(1, &0_usize, vec![1]);
+        winners.offer(1, &1_usize, vec![2]);
+        winners.offer(2, &2_usize, vec![1, 2, 3]);
+        winners.offer(1, &3_usize, vec![1, 2]);
+        winners.offer(3, &4_usize, vec![1, 2, 4]);
+
+        assert_eq!(winners.edges(), 4);
+        let mut kept: Vec<_> = winners.kept().into_iter().collect();
+        kept.sort_unstable();
+        assert_eq!(kept, [2, 4]);
+    }
+
+    #[test]
+    fn test_cmin_winners_duplicates() {
+        // The same input offered again, at the same score
+        let mut winners = CminWinners::new();
+        winners.offer(1, &0_usize, vec![1, 2]);
+        winners.offer(1, &0_usize, vec![1, 2]);
+        winners.offer(2, &1_usize, vec![2, 3]);
+        assert_eq!(winners.edges(), 3);
+        let mut kept: Vec<_> = winners.kept().into_iter().collect();
+        kept.sort_unstable();
+        assert_eq!(kept, [0, 1]);
+
+        // The same input offered again, at a lower score
+        let mut winners = CminWinners::new();
+        winners.offer(2, &0_usize, vec![1, 2]);
+        winners.offer(3, &1_usize, vec![2, 3]);
+        winners.offer(1, &0_usize, vec![1, 2]);
+        assert_eq!(winners.edges(), 3);
+        let mut kept: Vec<_> = winners.kept().into_iter().collect();
+        kept.sort_unstable();
+        assert_eq!(kept, [0, 1]);
+    }
+}
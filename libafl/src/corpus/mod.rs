@@ -16,11 +16,25 @@ pub mod cached;
 #[cfg(feature = "std")]
 pub use cached::CachedOnDiskCorpus;
 
-#[cfg(feature = "cmin")]
+pub mod afl;
+pub use afl::AflTestcaseMetadata;
+
+pub mod lineage;
+pub use lineage::{LineageContextMetadata, LineageGraph, LineageMetadata};
+
+#[cfg(feature = "std")]
+pub mod packed;
+#[cfg(feature = "std")]
+pub use packed::PackedCorpus;
+
+#[cfg(feature = "std")]
+pub mod bucketed;
+#[cfg(feature = "std")]
+pub use bucketed::{BucketedAddResult, BucketedCorpus};
+
 pub mod minimizer;
 use core::{cell::RefCell, fmt};
 
-#[cfg(feature = "cmin")]
 pub use minimizer::*;
 use serde::{Deserialize, Serialize};
 
@@ -118,6 +132,12 @@ pub trait Corpus: UsesInput + Serialize + for<'de> Deserialize<'de> {
             .nth(nth)
             .expect("Failed to get the {nth} CorpusId")
     }
+
+    /// Persist the changes to the testcases that were not written yet, for corpora reloaded from disk.
+    /// The restarting event managers call it before serializing the state.
+    fn flush(&self) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 /// `Iterator` over the ids of a `Corpus`
@@ -3,6 +3,7 @@
 //! For a lower memory footprint, consider using [`crate::corpus::CachedOnDiskCorpus`]
 //! which only stores a certain number of testcases and removes additional ones in a FIFO manner.
 
+use alloc::string::String;
 use core::{cell::RefCell, time::Duration};
 #[cfg(feature = "std")]
 use std::{fs, fs::File, io::Write};
@@ -17,7 +18,7 @@ use serde::{Deserialize, Serialize};
 use crate::bolts::compress::GzipCompressor;
 use crate::{
     bolts::serdeany::SerdeAnyMap,
-    corpus::{Corpus, CorpusId, InMemoryCorpus, Testcase},
+    corpus::{AflTestcaseMetadata, Corpus, CorpusId, InMemoryCorpus, Testcase},
     inputs::{Input, UsesInput},
     state::HasMetadata,
     Error,
@@ -42,9 +43,9 @@ pub enum OnDiskMetadataFormat {
 #[cfg(feature = "std")]
 #[derive(Debug, Serialize)]
 pub struct OnDiskMetadata<'a> {
-    metadata: &'a SerdeAnyMap,
-    exec_time: &'a Option<Duration>,
-    executions: &'a usize,
+    pub(crate) metadata: &'a SerdeAnyMap,
+    pub(crate) exec_time: &'a Option<Duration>,
+    pub(crate) executions: &'a usize,
 }
 
 /// A corpus able to store [`Testcase`]s to disk, and load them from disk, when they are being used.
@@ -60,6 +61,7 @@ where
     inner: InMemoryCorpus<I>,
     dir_path: PathBuf,
     meta_format: Option<OnDiskMetadataFormat>,
+    afl_names: bool,
 }
 
 impl<I> UsesInput for OnDiskCorpus<I>
@@ -194,6 +196,22 @@ where
         Self::_new(dir_path.as_ref(), None)
     }
 
+    /// Creates an [`OnDiskCorpus`] naming files like `AFL++` does, e.g. `id:000012,src:000003,time:1234,execs:5678`,
+    /// so that `afl-cmin` and other `AFL++` tools work on it.
+    /// It does not store .metadata files, as `AFL++` tools would consider them testcases.
+    ///
+    /// The fields of the name are taken from the [`AflTestcaseMetadata`] of the testcase, if any.
+    ///
+    /// Will error, if [`std::fs::create_dir_all()`] failed for `dir_path`.
+    pub fn with_afl_names<P>(dir_path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut corpus = Self::_new(dir_path.as_ref(), None)?;
+        corpus.afl_names = true;
+        Ok(corpus)
+    }
+
     /// Private fn to crate a new corpus at the given (non-generic) path with the given optional `meta_format`
     fn _new(dir_path: &Path, meta_format: Option<OnDiskMetadataFormat>) -> Result<Self, Error> {
         fs::create_dir_all(dir_path)?;
@@ -201,13 +219,32 @@ where
             inner: InMemoryCorpus::new(),
             dir_path: dir_path.into(),
             meta_format,
+            afl_names: false,
         })
     }
 
+    /// Generate an `AFL++` style filename for a testcase
+    fn afl_name(testcase: &Testcase<I>, idx: CorpusId) -> String {
+        let mut meta = testcase
+            .metadata()
+            .get::<AflTestcaseMetadata>()
+            .cloned()
+            .unwrap_or_else(|| AflTestcaseMetadata {
+                execs: Some(*testcase.executions() as u64),
+                ..AflTestcaseMetadata::default()
+            });
+        meta.id = idx.0;
+        meta.to_filename()
+    }
+
     fn save_testcase(&self, testcase: &mut Testcase<I>, idx: CorpusId) -> Result<(), Error> {
         if testcase.filename().is_none() {
             // TODO walk entry metadata to ask for pieces of filename (e.g. :havoc in AFL)
-            let file_orig = testcase.input().as_ref().unwrap().generate_name(idx.0);
+            let file_orig = if self.afl_names {
+                Self::afl_name(testcase, idx)
+            } else {
+                testcase.input().as_ref().unwrap().generate_name(idx.0)
+            };
             let mut file = file_orig.clone();
 
             let mut ctr = 2;
@@ -0,0 +1,745 @@
+//! The packed corpus stores all [`Testcase`]s in a single append-only archive file, next to an index file.
+//! Compared to the [`crate::corpus::OnDiskCorpus`], it does not create (at least) one file per [`Testcase`],
+//! which makes it suitable for corpora of millions of entries.
+//! Only the metadata of the [`Testcase`]s, and a bounded cache of their inputs, are kept in memory.
+//!
+//! Removed and replaced entries are marked as dead in the index (tombstones),
+//! and the space they occupy is reclaimed by [`PackedCorpus::compact`].
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    string::ToString,
+    vec::Vec,
+};
+use core::{
+    cell::{Cell, RefCell},
+    time::Duration,
+};
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use ahash::RandomState;
+use hashbrown::HashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    bolts::serdeany::SerdeAnyMap,
+    corpus::{ondisk::OnDiskMetadata, Corpus, CorpusId, InMemoryCorpus, OnDiskCorpus, Testcase},
+    inputs::{Input, UsesInput},
+    state::HasMetadata,
+    Error,
+};
+
+/// The size of an entry of the index file: `id`, `offset` and `len`, as little-endian `u64`s
+const INDEX_ENTRY_SIZE: usize = 24;
+/// The `len` of an index entry marking its id as removed
+const TOMBSTONE: u64 = u64::MAX;
+/// The size of the header of a record: the length of its metadata, as a little-endian `u64`
+const RECORD_HEADER_SIZE: u64 = 8;
+
+/// The owned counterpart of [`OnDiskMetadata`]
+#[derive(Deserialize)]
+struct StoredMetadata {
+    metadata: SerdeAnyMap,
+    exec_time: Option<Duration>,
+    executions: usize,
+}
+
+impl StoredMetadata {
+    fn into_testcase<I>(self, input: Option<I>) -> Testcase<I>
+    where
+        I: Input,
+    {
+        let mut testcase = Testcase::default();
+        if let Some(input) = input {
+            testcase.set_input(input);
+        }
+        *testcase.metadata_mut() = self.metadata;
+        *testcase.exec_time_mut() = self.exec_time;
+        *testcase.executions_mut() = self.executions;
+        testcase
+    }
+}
+
+/// Where the live record of a [`Testcase`] is in the archive.
+///
+/// A record is the length of the metadata, the metadata, and the input, the latter two serialized with postcard.
+#[derive(Clone, Copy, Debug)]
+struct PackedEntry {
+    offset: u64,
+    len: u64,
+    meta_len: u64,
+    /// The hash of the stored metadata, to know if it changed since
+    meta_hash: u64,
+}
+
+impl PackedEntry {
+    /// The offset and length of the input in the archive
+    fn input_range(&self) -> (u64, u64) {
+        let start = RECORD_HEADER_SIZE + self.meta_len;
+        (self.offset + start, self.len - start)
+    }
+}
+
+/// What is serialized of a [`PackedCorpus`], the [`Testcase`]s are reloaded from the archive
+#[derive(Serialize, Deserialize)]
+struct PackedCorpusConfig {
+    archive_path: PathBuf,
+    cache_max_len: usize,
+    current: Option<CorpusId>,
+}
+
+/// A corpus storing [`Testcase`]s, with their metadata, in a single append-only archive file.
+///
+/// The archive is accompanied by an index file with the same name and the `.idx` extension.
+/// At most `cache_max_len` inputs are kept in memory, the eviction policy is FIFO.
+/// Metadata is written when a [`Testcase`] is added or replaced, and the changes to it when its input
+/// gets evicted, or on [`Corpus::flush`], which the restarting event managers call before serializing the state.
+///
+/// Only the path of the archive is serialized, the [`Testcase`]s are reloaded from it.
+#[derive(Clone, Debug)]
+pub struct PackedCorpus<I>
+where
+    I: Input,
+{
+    inner: InMemoryCorpus<I>,
+    archive_path: PathBuf,
+    /// The live records in the archive
+    index: RefCell<HashMap<CorpusId, PackedEntry>>,
+    dead_bytes: Cell<u64>,
+    cached_indexes: RefCell<VecDeque<CorpusId>>,
+    cache_max_len: usize,
+}
+
+impl<I> UsesInput for PackedCorpus<I>
+where
+    I: Input,
+{
+    type Input = I;
+}
+
+impl<I> Corpus for PackedCorpus<I>
+where
+    I: Input,
+{
+    /// Returns the number of elements
+    #[inline]
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    /// Add an entry to the corpus and return its index
+    #[inline]
+    fn add(&mut self, testcase: Testcase<I>) -> Result<CorpusId, Error> {
+        let idx = self.inner.add(testcase)?;
+        let appended = self.append_record(idx, &self.inner.get(idx)?.borrow());
+        if let Err(err) = appended {
+            drop(self.inner.remove(idx)?);
+            return Err(err);
+        }
+        self.cache(idx)?;
+        Ok(idx)
+    }
+
+    /// Replaces the testcase at the given idx
+    #[inline]
+    fn replace(&mut self, idx: CorpusId, testcase: Testcase<I>) -> Result<Testcase<I>, Error> {
+        // Load the input of the replaced testcase, to return it whole
+        self.get(idx)?;
+        let entry = self.inner.replace(idx, testcase)?;
+        let appended = self.append_record(idx, &self.inner.get(idx)?.borrow());
+        if let Err(err) = appended {
+            drop(self.inner.replace(idx, entry)?);
+            return Err(err);
+        }
+        Ok(entry)
+    }
+
+    /// Removes an entry from the corpus, returning it if it was present.
+    #[inline]
+    fn remove(&mut self, idx: CorpusId) -> Result<Testcase<I>, Error> {
+        self.get(idx)?;
+        self.append_index_entry(idx, 0, TOMBSTONE)?;
+        let entry = self.inner.remove(idx)?;
+        self.cached_indexes.get_mut().retain(|e| *e != idx);
+        if let Some(old) = self.index.get_mut().remove(&idx) {
+            *self.dead_bytes.get_mut() += old.len;
+        }
+        Ok(entry)
+    }
+
+    /// Get by id, loading its input from the archive if it is not cached
+    #[inline]
+    fn get(&self, idx: CorpusId) -> Result<&RefCell<Testcase<I>>, Error> {
+        let testcase = { self.inner.get(idx)? };
+        if testcase.borrow().input().is_none() {
+            let input = self.load_input(idx)?;
+            testcase.borrow_mut().set_input(input);
+            self.cache(idx)?;
+        }
+        Ok(testcase)
+    }
+
+    /// Current testcase scheduled
+    #[inline]
+    fn current(&self) -> &Option<CorpusId> {
+        self.inner.current()
+    }
+
+    /// Current testcase scheduled (mutable)
+    #[inline]
+    fn current_mut(&mut self) -> &mut Option<CorpusId> {
+        self.inner.current_mut()
+    }
+
+    #[inline]
+    fn next(&self, idx: CorpusId) -> Option<CorpusId> {
+        self.inner.next(idx)
+    }
+
+    #[inline]
+    fn prev(&self, idx: CorpusId) -> Option<CorpusId> {
+        self.inner.prev(idx)
+    }
+
+    #[inline]
+    fn first(&self) -> Option<CorpusId> {
+        self.inner.first()
+    }
+
+    #[inline]
+    fn last(&self) -> Option<CorpusId> {
+        self.inner.last()
+    }
+
+    #[inline]
+    fn nth(&self, nth: usize) -> CorpusId {
+        self.inner.nth(nth)
+    }
+
+    /// Write the metadata of the [`Testcase`]s that changed since they were last written to the archive
+    fn flush(&self) -> Result<(), Error> {
+        for idx in self.inner.ids() {
+            self.store_metadata(idx, &self.inner.get(idx)?.borrow())?;
+        }
+        Ok(())
+    }
+}
+
+impl<I> Serialize for PackedCorpus<I>
+where
+    I: Input,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // The testcases are reloaded from the archive, see `Corpus::flush`
+        PackedCorpusConfig {
+            archive_path: self.archive_path.clone(),
+            cache_max_len: self.cache_max_len,
+            current: *self.current(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, I> Deserialize<'de> for PackedCorpus<I>
+where
+    I: Input,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let config = PackedCorpusConfig::deserialize(deserializer)?;
+        let mut corpus = Self::new(config.archive_path, config.cache_max_len)
+            .map_err(serde::de::Error::custom)?;
+        *corpus.current_mut() = config.current;
+        Ok(corpus)
+    }
+}
+
+impl<I> PackedCorpus<I>
+where
+    I: Input,
+{
+    /// Creates a [`PackedCorpus`] backed by the archive at `archive_path`, keeping at most `cache_max_len` inputs in memory.
+    ///
+    /// If the archive already exists, the metadata of all its live entries is loaded, and they keep their ids.
+    pub fn new<P>(archive_path: P, cache_max_len: usize) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        if cache_max_len == 0 {
+            return Err(Error::illegal_argument(
+                "The max cache len in PackedCorpus cannot be 0",
+            ));
+        }
+        let archive_path = archive_path.as_ref().to_path_buf();
+        if let Some(parent) = archive_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut corpus = Self {
+            inner: InMemoryCorpus::new(),
+            archive_path,
+            index: RefCell::new(HashMap::new()),
+            dead_bytes: Cell::new(0),
+            cached_indexes: RefCell::new(VecDeque::new()),
+            cache_max_len,
+        };
+        if corpus.archive_path.exists() {
+            corpus.load()?;
+        } else {
+            File::create(&corpus.archive_path)?;
+            File::create(corpus.index_path())?;
+        }
+        Ok(corpus)
+    }
+
+    /// The path of the archive file
+    #[must_use]
+    pub fn archive_path(&self) -> &Path {
+        &self.archive_path
+    }
+
+    /// The path of the index file
+    #[must_use]
+    pub fn index_path(&self) -> PathBuf {
+        let mut path = OsString::from(self.archive_path.as_os_str());
+        path.push(".idx");
+        path.into()
+    }
+
+    /// The number of bytes in the archive occupied by removed or replaced entries
+    #[must_use]
+    pub fn dead_bytes(&self) -> u64 {
+        self.dead_bytes.get()
+    }
+
+    /// Rewrite the archive with only the live entries and their current metadata, reclaiming the space of dead entries.
+    pub fn compact(&mut self) -> Result<(), Error> {
+        let mut archive_tmp = self.archive_path.clone().into_os_string();
+        archive_tmp.push(".tmp");
+        let mut index_tmp = self.index_path().into_os_string();
+        index_tmp.push(".tmp");
+
+        let mut archive = File::create(&archive_tmp)?;
+        let mut index_file = File::create(&index_tmp)?;
+        let mut index = HashMap::with_capacity(self.inner.count());
+        let mut offset = 0;
+        for idx in self.inner.ids() {
+            let (meta, input) = self.serialize_record(idx, &self.inner.get(idx)?.borrow())?;
+            let entry = write_record(&mut archive, offset, &meta, &input)?;
+            index_file.write_all(&index_entry(idx, offset, entry.len))?;
+            index.insert(idx, entry);
+            offset += entry.len;
+        }
+        archive.sync_all()?;
+        index_file.sync_all()?;
+
+        fs::rename(&archive_tmp, &self.archive_path)?;
+        fs::rename(&index_tmp, self.index_path())?;
+        *self.index.get_mut() = index;
+        self.dead_bytes.set(0);
+        Ok(())
+    }
+
+    /// Export all the [`Testcase`]s to the plain directory layout of the [`OnDiskCorpus`]
+    pub fn export_to_dir<P>(&self, dir_path: P) -> Result<OnDiskCorpus<I>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut ondisk = OnDiskCorpus::new(dir_path)?;
+        for idx in self.inner.ids() {
+            let testcase = self.get(idx)?.borrow();
+            let mut exported = Testcase::new(testcase.input().clone().unwrap());
+            *exported.metadata_mut() = testcase.metadata().clone();
+            *exported.exec_time_mut() = *testcase.exec_time();
+            *exported.executions_mut() = *testcase.executions();
+            ondisk.add(exported)?;
+        }
+        Ok(ondisk)
+    }
+
+    /// Import all the [`Testcase`]s of a directory in the plain layout of the [`OnDiskCorpus`], returning their number.
+    ///
+    /// Hidden files are skipped, `.<filename>.metadata` files are loaded if they are postcard or (non-compressed) json.
+    pub fn import_from_dir<P>(&mut self, dir_path: P) -> Result<usize, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut paths = fs::read_dir(dir_path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        paths.sort();
+
+        let mut count = 0;
+        for path in paths {
+            let Some(file_name) = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+            if file_name.starts_with('.') || !path.is_file() {
+                continue;
+            }
+            let input = I::from_file(&path)?;
+            let meta = fs::read(path.with_file_name(format!(".{file_name}.metadata")))
+                .ok()
+                .and_then(|bytes| {
+                    serde_json::from_slice::<StoredMetadata>(&bytes)
+                        .ok()
+                        .or_else(|| postcard::from_bytes::<StoredMetadata>(&bytes).ok())
+                });
+            let testcase = match meta {
+                Some(meta) => meta.into_testcase(Some(input)),
+                None => Testcase::new(input),
+            };
+            self.add(testcase)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Load the metadata of the live entries of the archive, keeping their ids
+    fn load(&mut self) -> Result<(), Error> {
+        let index_bytes = fs::read(self.index_path())?;
+        if index_bytes.len() % INDEX_ENTRY_SIZE != 0 {
+            log::warn!(
+                "Ignoring a truncated entry in the index of {}",
+                self.archive_path.display()
+            );
+        }
+
+        let mut live = BTreeMap::new();
+        let mut next_id = 0;
+        for entry in index_bytes.chunks_exact(INDEX_ENTRY_SIZE) {
+            let id = u64::from_le_bytes(entry[0..8].try_into().unwrap());
+            let offset = u64::from_le_bytes(entry[8..16].try_into().unwrap());
+            let len = u64::from_le_bytes(entry[16..24].try_into().unwrap());
+            next_id = next_id.max(id + 1);
+            if len == TOMBSTONE {
+                live.remove(&id);
+            } else {
+                live.insert(id, (offset, len));
+            }
+        }
+
+        let mut archive = File::open(&self.archive_path)?;
+        let archive_len = archive.metadata()?.len();
+        let mut live_bytes = 0;
+        // Ids are assigned in order, so the removed ones are added as placeholders, and removed again
+        let mut placeholders = vec![];
+        for id in 0..next_id {
+            let testcase = match live.get(&id) {
+                Some(&(offset, len)) => {
+                    let (entry, meta) =
+                        self.read_metadata(&mut archive, archive_len, offset, len)?;
+                    live_bytes += len;
+                    self.index
+                        .get_mut()
+                        .insert(CorpusId::from(id as usize), entry);
+                    postcard::from_bytes::<StoredMetadata>(&meta)?.into_testcase(None)
+                }
+                None => Testcase::default(),
+            };
+            let idx = self.inner.add(testcase)?;
+            debug_assert_eq!(idx.0 as u64, id);
+            if !live.contains_key(&id) {
+                placeholders.push(idx);
+            }
+        }
+        for idx in placeholders {
+            drop(self.inner.remove(idx)?);
+        }
+        self.dead_bytes.set(archive_len - live_bytes);
+        Ok(())
+    }
+
+    /// Read the metadata of the record at the given offset of the archive
+    fn read_metadata(
+        &self,
+        archive: &mut File,
+        archive_len: u64,
+        offset: u64,
+        len: u64,
+    ) -> Result<(PackedEntry, Vec<u8>), Error> {
+        let out_of_bounds = || {
+            Error::illegal_state(format!(
+                "The index of {} refers to data past the end of the archive",
+                self.archive_path.display()
+            ))
+        };
+        if offset
+            .checked_add(len)
+            .map_or(true, |end| end > archive_len)
+            || len < RECORD_HEADER_SIZE
+        {
+            return Err(out_of_bounds());
+        }
+        let mut header = [0; RECORD_HEADER_SIZE as usize];
+        archive.seek(SeekFrom::Start(offset))?;
+        archive.read_exact(&mut header)?;
+        let meta_len = u64::from_le_bytes(header);
+        if meta_len > len - RECORD_HEADER_SIZE {
+            return Err(out_of_bounds());
+        }
+        let mut meta = vec![0; meta_len as usize];
+        archive.read_exact(&mut meta)?;
+        let entry = PackedEntry {
+            offset,
+            len,
+            meta_len,
+            meta_hash: RandomState::with_seeds(0, 0, 0, 0).hash_one(&meta),
+        };
+        Ok((entry, meta))
+    }
+
+    /// Load the input of the testcase at the given index from the archive
+    fn load_input(&self, idx: CorpusId) -> Result<I, Error> {
+        Ok(postcard::from_bytes(&self.read_input_bytes(idx)?)?)
+    }
+
+    /// Read the serialized input of the testcase at the given index from the archive
+    fn read_input_bytes(&self, idx: CorpusId) -> Result<Vec<u8>, Error> {
+        let entry =
+            *self.index.borrow().get(&idx).ok_or_else(|| {
+                Error::key_not_found(format!("Testcase {idx} is not in the archive"))
+            })?;
+        let (offset, len) = entry.input_range();
+        let mut input = vec![0; len as usize];
+        let mut archive = File::open(&self.archive_path)?;
+        archive.seek(SeekFrom::Start(offset))?;
+        archive.read_exact(&mut input)?;
+        Ok(input)
+    }
+
+    /// Keep the input of the testcase at the given index in memory, evicting the oldest cached inputs
+    fn cache(&self, idx: CorpusId) -> Result<(), Error> {
+        if self.cached_indexes.borrow().contains(&idx) {
+            return Ok(());
+        }
+        let mut borrowed_num = 0;
+        while self.cached_indexes.borrow().len() >= self.cache_max_len {
+            let removed = self.cached_indexes.borrow_mut().pop_front().unwrap();
+            if let Ok(mut borrowed) = self.inner.get(removed)?.try_borrow_mut() {
+                self.store_metadata(removed, &borrowed)?;
+                *borrowed.input_mut() = None;
+            } else {
+                self.cached_indexes.borrow_mut().push_back(removed);
+                borrowed_num += 1;
+                if self.cache_max_len == borrowed_num {
+                    break;
+                }
+            }
+        }
+        self.cached_indexes.borrow_mut().push_back(idx);
+        Ok(())
+    }
+
+    /// Serialize the metadata and the input of the testcase at the given index
+    fn serialize_record(
+        &self,
+        idx: CorpusId,
+        testcase: &Testcase<I>,
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let meta = postcard::to_allocvec(&OnDiskMetadata {
+            metadata: testcase.metadata(),
+            exec_time: testcase.exec_time(),
+            executions: testcase.executions(),
+        })?;
+        let input = match testcase.input() {
+            Some(input) => postcard::to_allocvec(input)?,
+            None => self.read_input_bytes(idx)?,
+        };
+        Ok((meta, input))
+    }
+
+    /// Append the testcase at the given index to the archive, if its metadata changed since it was written
+    fn store_metadata(&self, idx: CorpusId, testcase: &Testcase<I>) -> Result<(), Error> {
+        let meta = postcard::to_allocvec(&OnDiskMetadata {
+            metadata: testcase.metadata(),
+            exec_time: testcase.exec_time(),
+            executions: testcase.executions(),
+        })?;
+        let unchanged = self.index.borrow().get(&idx).is_some_and(|entry| {
+            entry.meta_hash == RandomState::with_seeds(0, 0, 0, 0).hash_one(&meta)
+        });
+        if unchanged {
+            Ok(())
+        } else {
+            self.append_record(idx, testcase)
+        }
+    }
+
+    /// Append the testcase at the given index to the archive
+    fn append_record(&self, idx: CorpusId, testcase: &Testcase<I>) -> Result<(), Error> {
+        let (meta, input) = self.serialize_record(idx, testcase)?;
+        let mut archive = OpenOptions::new().append(true).open(&self.archive_path)?;
+        // Data of a previously failed append may follow the last record
+        let offset = archive.metadata()?.len();
+        let entry = write_record(&mut archive, offset, &meta, &input)?;
+        self.append_index_entry(idx, offset, entry.len)?;
+        if let Some(old) = self.index.borrow_mut().insert(idx, entry) {
+            self.dead_bytes.set(self.dead_bytes.get() + old.len);
+        }
+        Ok(())
+    }
+
+    /// Append an entry to the index file
+    fn append_index_entry(&self, idx: CorpusId, offset: u64, len: u64) -> Result<(), Error> {
+        OpenOptions::new()
+            .append(true)
+            .open(self.index_path())?
+            .write_all(&index_entry(idx, offset, len))?;
+        Ok(())
+    }
+}
+
+/// Write a record at the given offset of the archive
+fn write_record(
+    archive: &mut File,
+    offset: u64,
+    meta: &[u8],
+    input: &[u8],
+) -> Result<PackedEntry, Error> {
+    let meta_len = meta.len() as u64;
+    let mut record = Vec::with_capacity(RECORD_HEADER_SIZE as usize + meta.len() + input.len());
+    record.extend_from_slice(&meta_len.to_le_bytes());
+    record.extend_from_slice(meta);
+    record.extend_from_slice(input);
+    archive.write_all(&record)?;
+    Ok(PackedEntry {
+        offset,
+        len: record.len() as u64,
+        meta_len,
+        meta_hash: RandomState::with_seeds(0, 0, 0, 0).hash_one(meta),
+    })
+}
+
+/// Encode an entry of the index file
+fn index_entry(idx: CorpusId, offset: u64, len: u64) -> [u8; INDEX_ENTRY_SIZE] {
+    let mut entry = [0; INDEX_ENTRY_SIZE];
+    entry[0..8].copy_from_slice(&(idx.0 as u64).to_le_bytes());
+    entry[8..16].copy_from_slice(&offset.to_le_bytes());
+    entry[16..24].copy_from_slice(&len.to_le_bytes());
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use std::fs;
+
+    use super::PackedCorpus;
+    use crate::{
+        corpus::{Corpus, CorpusId, Testcase},
+        inputs::{BytesInput, HasBytesVec},
+    };
+
+    fn contents(corpus: &PackedCorpus<BytesInput>) -> Vec<(CorpusId, Vec<u8>, usize)> {
+        corpus
+            .ids()
+            .map(|idx| {
+                let testcase = corpus.get(idx).unwrap().borrow();
+                (
+                    idx,
+                    testcase.input().as_ref().unwrap().bytes().to_vec(),
+                    *testcase.executions(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_packed_corpus() {
+        let dir = "target/.test/packed";
+        let _ = fs::remove_dir_all(dir);
+        let archive = format!("{dir}/corpus.pack");
+
+        let mut corpus = PackedCorpus::<BytesInput>::new(&archive, 2).unwrap();
+        let ids = (0..4_u8)
+            .map(|i| {
+                let mut testcase = Testcase::new(BytesInput::new(vec![i; 4]));
+                *testcase.executions_mut() = i.into();
+                corpus.add(testcase).unwrap()
+            })
+            .collect::<Vec<_>>();
+        corpus.remove(ids[1]).unwrap();
+        corpus
+            .replace(ids[2], Testcase::new(BytesInput::new(b"new".to_vec())))
+            .unwrap();
+        assert!(corpus.dead_bytes() > 0);
+
+        // Only the cached inputs are in memory, the others are loaded back from the archive
+        let in_memory = |corpus: &PackedCorpus<BytesInput>| {
+            corpus
+                .ids()
+                .filter(|idx| corpus.inner.get(*idx).unwrap().borrow().input().is_some())
+                .count()
+        };
+        assert!(in_memory(&corpus) <= 2);
+        let expected = [
+            (ids[0], vec![0; 4], 0),
+            (ids[2], b"new".to_vec(), 0),
+            (ids[3], vec![3; 4], 42),
+        ];
+        *corpus.get(ids[3]).unwrap().borrow_mut().executions_mut() = 42;
+        assert_eq!(contents(&corpus), expected);
+        assert!(in_memory(&corpus) <= 2);
+
+        // Reopening keeps the ids, and the metadata changed since the testcase was added
+        corpus.flush().unwrap();
+        let reopened = PackedCorpus::<BytesInput>::new(&archive, 2).unwrap();
+        assert_eq!(contents(&reopened), expected);
+        assert_eq!(reopened.dead_bytes(), corpus.dead_bytes());
+
+        // Only the archive path is serialized, the testcases are reloaded from it.
+        // Serializing writes nothing, changes since the last flush are not seen after reloading.
+        *corpus.current_mut() = Some(ids[2]);
+        *corpus.get(ids[0]).unwrap().borrow_mut().executions_mut() = 7;
+        let archive_len = fs::metadata(&archive).unwrap().len();
+        let serialized = postcard::to_allocvec(&corpus).unwrap();
+        assert!(serialized.len() < 64);
+        assert_eq!(fs::metadata(&archive).unwrap().len(), archive_len);
+        let deserialized: PackedCorpus<BytesInput> = postcard::from_bytes(&serialized).unwrap();
+        assert_eq!(contents(&deserialized), expected);
+        assert_eq!(*deserialized.current(), Some(ids[2]));
+
+        let mut compacted = deserialized;
+        compacted.compact().unwrap();
+        assert_eq!(compacted.dead_bytes(), 0);
+        assert_eq!(contents(&compacted), expected);
+
+        // Round trip through the plain directory layout
+        compacted.export_to_dir(format!("{dir}/exported")).unwrap();
+        let mut imported =
+            PackedCorpus::<BytesInput>::new(format!("{dir}/imported.pack"), 2).unwrap();
+        assert_eq!(
+            imported.import_from_dir(format!("{dir}/exported")).unwrap(),
+            3
+        );
+        assert_eq!(
+            imported
+                .ids()
+                .map(|idx| *imported.get(idx).unwrap().borrow().executions())
+                .sum::<usize>(),
+            42
+        );
+
+        // A failed write leaves the corpus as it was
+        fs::remove_file(imported.archive_path()).unwrap();
+        assert!(imported
+            .add(Testcase::new(BytesInput::new(vec![4; 4])))
+            .is_err());
+        assert_eq!(imported.count(), 3);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
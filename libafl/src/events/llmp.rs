@@ -9,8 +9,12 @@ use alloc::{
 use core::sync::atomic::{compiler_fence, Ordering};
 use core::{marker::PhantomData, time::Duration};
 #[cfg(feature = "std")]
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::{
+    net::{SocketAddr, ToSocketAddrs},
+    path::PathBuf,
+};
 
+use hashbrown::HashMap;
 use serde::Deserialize;
 #[cfg(feature = "std")]
 use serde::{de::DeserializeOwned, Serialize};
@@ -18,19 +22,27 @@ use serde::{de::DeserializeOwned, Serialize};
 use typed_builder::TypedBuilder;
 
 use super::{CustomBufEventResult, CustomBufHandlerFn};
+#[cfg(feature = "llmp_b2b_auth")]
+use crate::bolts::b2b_auth::B2bAuth;
 #[cfg(feature = "std")]
 use crate::bolts::core_affinity::CoreId;
 #[cfg(all(feature = "std", any(windows, not(feature = "fork"))))]
 use crate::bolts::os::startable_self;
 #[cfg(all(feature = "std", feature = "fork", unix))]
 use crate::bolts::os::{fork, ForkResult};
+#[cfg(feature = "std")]
+use crate::bolts::{
+    checkpoint::{StateCheckpointer, DEFAULT_CHECKPOINT_INTERVAL},
+    llmp::{LlmpConnection, LlmpDescription, LlmpReceiver},
+    llmp_record::{LlmpRecorder, LlmpReplayer},
+    shmem::StdShMemProvider,
+    staterestore::StateRestorer,
+};
 #[cfg(feature = "llmp_compression")]
 use crate::bolts::{
     compress::GzipCompressor,
     llmp::{LLMP_FLAG_COMPRESSED, LLMP_FLAG_INITIALIZED},
 };
-#[cfg(feature = "std")]
-use crate::bolts::{llmp::LlmpConnection, shmem::StdShMemProvider, staterestore::StateRestorer};
 #[cfg(all(unix, feature = "std"))]
 use crate::{
     bolts::os::unix_signals::setup_signal_handler,
@@ -38,20 +50,24 @@ use crate::{
 };
 use crate::{
     bolts::{
-        llmp::{self, LlmpClient, LlmpClientDescription, Tag},
-        shmem::ShMemProvider,
+        llmp::{self, ClientId, LlmpClient, LlmpClientDescription, LlmpSender, LlmpSharedMap, Tag},
+        shmem::{ShMem, ShMemProvider},
     },
     events::{
         BrokerEventResult, Event, EventConfig, EventFirer, EventManager, EventManagerId,
-        EventProcessor, EventRestarter, HasCustomBufHandlers, HasEventManagerId, ProgressReporter,
+        EventProcessor, EventRestarter, HasCustomBufHandlers, HasEventManagerId,
+        ImportedTestcaseMetadata, ProgressReporter, CLIENT_COMMAND_HELLO_TAG, CLIENT_COMMAND_TAG,
+        OBJECTIVE_INPUT_TAG,
     },
     executors::{Executor, HasObservers},
     fuzzer::{EvaluatorObservers, ExecutionProcessor},
     inputs::{Input, InputConverter, UsesInput},
-    monitors::Monitor,
-    state::{HasClientPerfMonitor, HasExecutions, HasMetadata, UsesState},
+    monitors::{add_client_input, add_objective_input, Monitor},
+    state::{HasClientPerfMonitor, HasCorpus, HasExecutions, HasMetadata, UsesState},
     Error,
 };
+#[cfg(feature = "std")]
+use crate::{corpus::Corpus, state::HasSolutions};
 
 /// Forward this to the client
 const _LLMP_TAG_EVENT_TO_CLIENT: Tag = 0x2C11E471;
@@ -122,10 +138,27 @@ where
         self.llmp.connect_b2b(addr)
     }
 
+    /// Authenticate broker-to-broker connections with a pre-shared key, see [`llmp::LlmpBroker::set_b2b_auth`]
+    #[cfg(feature = "llmp_b2b_auth")]
+    pub fn set_b2b_auth(&mut self, b2b_auth: Option<B2bAuth>) {
+        self.llmp.set_b2b_auth(b2b_auth);
+    }
+
+    /// Record all messages from the clients to a log, see [`llmp::LlmpBroker::set_recorder`]
+    #[cfg(feature = "std")]
+    pub fn set_recorder(&mut self, recorder: Option<LlmpRecorder>) {
+        self.llmp.set_recorder(recorder);
+    }
+
     /// Run forever in the broker
     #[cfg(not(feature = "llmp_broker_timeouts"))]
     pub fn broker_loop(&mut self) -> Result<(), Error> {
         let monitor = &mut self.monitor;
+        let mut commands = if monitor.issues_client_commands() {
+            Some(ClientCommandSender::new(&mut self.llmp)?)
+        } else {
+            None
+        };
         #[cfg(feature = "llmp_compression")]
         let compressor = &self.compressor;
         self.llmp.loop_forever(
@@ -143,7 +176,12 @@ where
                         msg
                     };
                     let event: Event<I> = postcard::from_bytes(event_bytes)?;
-                    match Self::handle_in_broker(monitor, client_id, &event)? {
+                    let result =
+                        Self::handle_in_broker(monitor, commands.as_mut(), client_id, &event)?;
+                    if let Some(commands) = &mut commands {
+                        commands.send::<I, MT>(monitor)?;
+                    }
+                    match result {
                         BrokerEventResult::Forward => Ok(llmp::LlmpMsgHookResult::ForwardToClients),
                         BrokerEventResult::Handled => Ok(llmp::LlmpMsgHookResult::Handled),
                     }
@@ -161,6 +199,11 @@ where
     #[cfg(feature = "llmp_broker_timeouts")]
     pub fn broker_loop(&mut self) -> Result<(), Error> {
         let monitor = &mut self.monitor;
+        let mut commands = if monitor.issues_client_commands() {
+            Some(ClientCommandSender::new(&mut self.llmp)?)
+        } else {
+            None
+        };
         #[cfg(feature = "llmp_compression")]
         let compressor = &self.compressor;
         self.llmp.loop_with_timeouts(
@@ -179,7 +222,12 @@ where
                             msg
                         };
                         let event: Event<I> = postcard::from_bytes(event_bytes)?;
-                        match Self::handle_in_broker(monitor, client_id, &event)? {
+                        let result =
+                            Self::handle_in_broker(monitor, commands.as_mut(), client_id, &event)?;
+                        if let Some(commands) = &mut commands {
+                            commands.send::<I, MT>(monitor)?;
+                        }
+                        match result {
                             BrokerEventResult::Forward => {
                                 Ok(llmp::LlmpMsgHookResult::ForwardToClients)
                             }
@@ -190,6 +238,9 @@ where
                     }
                 } else {
                     monitor.display("Broker".into(), 0);
+                    if let Some(commands) = &mut commands {
+                        commands.send::<I, MT>(monitor)?;
+                    }
                     Ok(llmp::LlmpMsgHookResult::Handled)
                 }
             },
@@ -204,22 +255,30 @@ where
     #[allow(clippy::unnecessary_wraps)]
     fn handle_in_broker(
         monitor: &mut MT,
+        commands: Option<&mut ClientCommandSender<SP>>,
         client_id: u32,
         event: &Event<I>,
     ) -> Result<BrokerEventResult, Error> {
         match &event {
             Event::NewTestcase {
-                input: _,
+                input,
                 client_config: _,
                 exit_kind: _,
                 corpus_size,
                 observers_buf: _,
                 time,
                 executions,
+                lineage,
+                metadata: _,
             } => {
                 let client = monitor.client_stats_mut_for(client_id);
                 client.update_corpus_size(*corpus_size as u64);
                 client.update_executions(*executions as u64, *time);
+                let idx = lineage
+                    .as_ref()
+                    .and_then(|lineage| lineage.origin_id)
+                    .map(|id| id.0);
+                add_client_input(monitor, client_id, false, idx, *time, input);
                 monitor.display(event.name().to_string(), client_id);
                 Ok(BrokerEventResult::Forward)
             }
@@ -279,17 +338,105 @@ where
                 message,
                 phantom: _,
             } => {
-                let (_, _) = (severity_level, message);
-                // TODO rely on Monitor
                 log::log!((*severity_level).into(), "{message}");
+                monitor.add_log(client_id, *severity_level, message);
+                Ok(BrokerEventResult::Handled)
+            }
+            Event::CustomBuf { tag, buf } if tag == OBJECTIVE_INPUT_TAG => {
+                add_objective_input::<_, I>(monitor, client_id, buf);
                 Ok(BrokerEventResult::Handled)
             }
-            Event::CustomBuf { .. } => Ok(BrokerEventResult::Forward),
-            //_ => Ok(BrokerEventResult::Forward),
+            Event::CustomBuf { tag, buf } if tag == CLIENT_COMMAND_HELLO_TAG => {
+                if let Some(commands) = commands {
+                    commands.add_client(client_id, buf);
+                }
+                Ok(BrokerEventResult::Handled)
+            }
+            // Only pass on the commands of our own monitor, not the ones of remote brokers
+            Event::CustomBuf { tag, .. } if tag == CLIENT_COMMAND_TAG => {
+                if commands.is_some_and(|commands| commands.id == client_id) {
+                    Ok(BrokerEventResult::Forward)
+                } else {
+                    Ok(BrokerEventResult::Handled)
+                }
+            }
+            Event::UpdateTestcaseMetadata { .. } | Event::CustomBuf { .. } => {
+                Ok(BrokerEventResult::Forward)
+            } //_ => Ok(BrokerEventResult::Forward),
         }
     }
 }
 
+/// The id [`ClientCommandSender`] sends with, never handed out to a client
+const CLIENT_COMMAND_SENDER_ID: ClientId = ClientId::MAX;
+
+/// Passes the [`super::ClientCommand`]s the user issues in the monitor on to the clients accepting them,
+/// see [`CLIENT_COMMAND_TAG`].
+///
+/// It sends them through a page of its own, registered in the broker like the page of a client.
+/// The broker only forwards the commands from this page, so they never reach the clients of other brokers.
+#[derive(Debug)]
+struct ClientCommandSender<SP>
+where
+    SP: ShMemProvider,
+{
+    sender: LlmpSender<SP>,
+    /// The id of the page of this sender in the broker
+    id: ClientId,
+    /// The event manager ids of the clients accepting commands, by their id in the broker and monitor
+    mgr_ids: HashMap<ClientId, usize>,
+}
+
+impl<SP> ClientCommandSender<SP>
+where
+    SP: ShMemProvider + 'static,
+{
+    /// Registers the page of a new sender in the broker
+    fn new(llmp: &mut llmp::LlmpBroker<SP>) -> Result<Self, Error> {
+        let mut shmem_provider = SP::new()?;
+        let sender = LlmpSender::new(shmem_provider.clone(), CLIENT_COMMAND_SENDER_ID, false)?;
+        let page = LlmpSharedMap::existing(
+            shmem_provider.shmem_from_description(sender.out_shmems[0].shmem.description())?,
+        );
+        let id = llmp.llmp_clients.len() as ClientId;
+        llmp.register_client(page);
+        Ok(Self {
+            sender,
+            id,
+            mgr_ids: HashMap::new(),
+        })
+    }
+
+    /// Remembers the event manager id a client announced with a [`CLIENT_COMMAND_HELLO_TAG`] event
+    fn add_client(&mut self, client_id: ClientId, buf: &[u8]) {
+        match postcard::from_bytes(buf) {
+            Ok(mgr_id) => drop(self.mgr_ids.insert(client_id, mgr_id)),
+            Err(e) => log::warn!("Could not read the hello of client {client_id}: {e}"),
+        }
+    }
+
+    /// Sends the commands the user issued in the monitor since the last call
+    fn send<I, MT>(&mut self, monitor: &mut MT) -> Result<(), Error>
+    where
+        I: Input,
+        MT: Monitor,
+    {
+        for (client_id, command) in monitor.take_client_commands() {
+            let Some(mgr_id) = self.mgr_ids.get(&client_id) else {
+                log::warn!("Client {client_id} does not accept commands, it runs no PauseStage");
+                continue;
+            };
+            let event: Event<I> = Event::CustomBuf {
+                buf: postcard::to_allocvec(&(*mgr_id, command))?,
+                tag: CLIENT_COMMAND_TAG.to_string(),
+            };
+            self.sender
+                .send_buf(LLMP_TAG_EVENT_TO_BOTH, &postcard::to_allocvec(&event)?)?;
+        }
+        Ok(())
+    }
+}
+
 /// An [`EventManager`] that forwards all events to other attached fuzzers on shared maps or via tcp,
 /// using low-level message passing, [`crate::bolts::llmp`].
 pub struct LlmpEventManager<S, SP>
@@ -393,6 +540,64 @@ where
         self.llmp.describe()
     }
 
+    /// Writes a checkpoint of `state`, if one is due, see [`StateCheckpointer::maybe_save`].
+    /// Along with the state, it saves the configuration of this client, and how far it got in the messages of the broker,
+    /// identified by its [`llmp::LlmpBroker::run_id`].
+    #[cfg(feature = "std")]
+    pub fn maybe_checkpoint(
+        &self,
+        checkpointer: &mut StateCheckpointer,
+        state: &S,
+    ) -> Result<bool, Error>
+    where
+        S: Serialize,
+    {
+        checkpointer.maybe_save(&(
+            state,
+            self.configuration,
+            self.llmp.receiver.describe()?,
+            self.llmp.broker_run_id(),
+        ))
+    }
+
+    /// Reads the state of the last checkpoint written with [`Self::maybe_checkpoint`], if any.
+    /// Fails if the checkpoint was written by a client with another configuration.
+    /// If this client is connected to the same run of the broker as the checkpointed one, it continues to receive
+    /// its messages where the checkpointed client stopped, instead of getting all of them again.
+    #[cfg(feature = "std")]
+    pub fn resume_from_checkpoint(
+        &mut self,
+        checkpointer: &StateCheckpointer,
+        shmem_provider: SP,
+    ) -> Result<Option<S>, Error>
+    where
+        S: DeserializeOwned,
+    {
+        let Some((state, configuration, receiver, broker_run_id)) =
+            checkpointer.load::<(S, EventConfig, LlmpDescription, Option<u64>)>()?
+        else {
+            return Ok(None);
+        };
+        if !configuration.match_with(&self.configuration) {
+            return Err(Error::illegal_state(format!(
+                "The checkpoint {} was written with another configuration ({configuration:?}) than the one of this client ({:?})",
+                checkpointer.path().display(),
+                self.configuration
+            )));
+        }
+        // The maps of a gone broker may be reused by another one, only resume in the same run of the broker
+        if broker_run_id.is_some() && broker_run_id == self.llmp.broker_run_id() {
+            match LlmpReceiver::on_existing_from_description(shmem_provider, &receiver) {
+                Ok(receiver) => self.llmp.receiver = receiver,
+                Err(e) => log::info!("The broker of the checkpoint is gone, receiving all messages of the current one ({e})"),
+            }
+        } else {
+            log::info!("The checkpoint is from another run of the broker, receiving all messages of the current one");
+        }
+        log::info!("Resuming from {}", checkpointer.path().display());
+        Ok(Some(state))
+    }
+
     /// Create an existing client from description
     pub fn existing_client_from_description(
         shmem_provider: SP,
@@ -426,6 +631,7 @@ where
         event: Event<S::Input>,
     ) -> Result<(), Error>
     where
+        S: HasCorpus + HasMetadata,
         E: Executor<Self, Z> + HasObservers<State = S>,
         for<'a> E::Observers: Deserialize<'a>,
         Z: ExecutionProcessor<E::Observers, State = S> + EvaluatorObservers<E::Observers>,
@@ -439,25 +645,37 @@ where
                 observers_buf,
                 time: _,
                 executions: _,
+                lineage,
+                metadata,
             } => {
                 log::info!("Received new Testcase from {_client_id} ({client_config:?})");
 
-                let _res = if client_config.match_with(&self.configuration)
-                    && observers_buf.is_some()
-                {
-                    let observers: E::Observers =
-                        postcard::from_bytes(observers_buf.as_ref().unwrap())?;
-                    fuzzer.process_execution(state, self, input, &observers, &exit_kind, false)?
-                } else {
-                    fuzzer.evaluate_input_with_observers::<E, Self>(
-                        state, executor, self, input, false,
-                    )?
-                };
-                if let Some(item) = _res.1 {
+                ImportedTestcaseMetadata::enter(state, _client_id, lineage, metadata);
+                let _res =
+                    if client_config.match_with(&self.configuration) && observers_buf.is_some() {
+                        postcard::from_bytes(observers_buf.as_ref().unwrap())
+                            .map_err(Error::from)
+                            .and_then(|observers: E::Observers| {
+                                fuzzer.process_execution(
+                                    state, self, input, &observers, &exit_kind, false,
+                                )
+                            })
+                    } else {
+                        fuzzer.evaluate_input_with_observers::<E, Self>(
+                            state, executor, self, input, false,
+                        )
+                    };
+                ImportedTestcaseMetadata::leave(state);
+                if let Some(item) = _res?.1 {
                     log::info!("Added received Testcase as item #{item}");
                 }
                 Ok(())
             }
+            Event::UpdateTestcaseMetadata {
+                corpus_id,
+                metadata,
+                phantom: _,
+            } => ImportedTestcaseMetadata::on_update(state, _client_id, corpus_id, metadata),
             Event::CustomBuf { tag, buf } => {
                 for handler in &mut self.custom_buf_handlers {
                     if handler(state, &tag, &buf)? == CustomBufEventResult::Handled {
@@ -542,7 +760,7 @@ where
 
 impl<E, S, SP, Z> EventProcessor<E, Z> for LlmpEventManager<S, SP>
 where
-    S: UsesInput + HasClientPerfMonitor + HasExecutions,
+    S: UsesInput + HasClientPerfMonitor + HasExecutions + HasMetadata + HasCorpus,
     SP: ShMemProvider,
     E: HasObservers<State = S> + Executor<Self, Z>,
     for<'a> E::Observers: Deserialize<'a>,
@@ -585,11 +803,58 @@ where
     }
 }
 
+#[cfg(feature = "std")]
+impl<S, SP> LlmpEventManager<S, SP>
+where
+    S: UsesInput + HasClientPerfMonitor + HasExecutions + HasMetadata + HasCorpus,
+    SP: ShMemProvider,
+{
+    /// Handles all remaining events of a log recorded in a broker by an [`LlmpRecorder`],
+    /// in the order the broker received them, as if they arrived from the broker right now.
+    /// This lets a late-joining client catch up, or reproduces the processing of a campaign's events.
+    /// Returns the number of events handled.
+    pub fn replay<E, Z>(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut S,
+        executor: &mut E,
+        replayer: &mut LlmpReplayer,
+    ) -> Result<usize, Error>
+    where
+        E: HasObservers<State = S> + Executor<Self, Z>,
+        for<'a> E::Observers: Deserialize<'a>,
+        Z: EvaluatorObservers<E::Observers, State = S>
+            + ExecutionProcessor<E::Observers, State = S>,
+    {
+        let mut count = 0;
+        while let Some(entry) = replayer.next_entry()? {
+            if entry.tag != LLMP_TAG_EVENT_TO_BOTH {
+                continue;
+            }
+            #[cfg(not(feature = "llmp_compression"))]
+            let event_bytes = &entry.payload;
+            #[cfg(feature = "llmp_compression")]
+            let compressed;
+            #[cfg(feature = "llmp_compression")]
+            let event_bytes = if entry.flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
+                compressed = self.compressor.decompress(&entry.payload)?;
+                &compressed
+            } else {
+                &entry.payload
+            };
+            let event: Event<S::Input> = postcard::from_bytes(event_bytes)?;
+            self.handle_in_client(fuzzer, executor, state, entry.client_id, event)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
 impl<E, S, SP, Z> EventManager<E, Z> for LlmpEventManager<S, SP>
 where
     E: HasObservers<State = S> + Executor<Self, Z>,
     for<'a> E::Observers: Deserialize<'a>,
-    S: UsesInput + HasExecutions + HasClientPerfMonitor + HasMetadata,
+    S: UsesInput + HasExecutions + HasClientPerfMonitor + HasMetadata + HasCorpus,
     SP: ShMemProvider,
     Z: EvaluatorObservers<E::Observers, State = S> + ExecutionProcessor<E::Observers, State = S>,
 {
@@ -641,6 +906,8 @@ where
     llmp_mgr: LlmpEventManager<S, SP>,
     /// The staterestorer to serialize the state for the next runner
     staterestorer: StateRestorer<SP>,
+    /// The checkpointer writing the state to disk periodically, to resume later
+    checkpointer: Option<StateCheckpointer>,
 }
 
 #[cfg(feature = "std")]
@@ -658,6 +925,19 @@ where
     S: UsesInput + HasExecutions + HasClientPerfMonitor + HasMetadata + Serialize,
     SP: ShMemProvider,
 {
+    /// Report the progress, and write a checkpoint of the state if one is due
+    fn maybe_report_progress(
+        &mut self,
+        state: &mut S,
+        last_report_time: Duration,
+        monitor_timeout: Duration,
+    ) -> Result<Duration, Error> {
+        if let Some(checkpointer) = &mut self.checkpointer {
+            self.llmp_mgr.maybe_checkpoint(checkpointer, state)?;
+        }
+        self.llmp_mgr
+            .maybe_report_progress(state, last_report_time, monitor_timeout)
+    }
 }
 
 #[cfg(feature = "std")]
@@ -684,7 +964,7 @@ where
 #[cfg(feature = "std")]
 impl<S, SP> EventRestarter for LlmpRestartingEventManager<S, SP>
 where
-    S: UsesInput + HasExecutions + HasClientPerfMonitor + Serialize,
+    S: UsesInput + HasExecutions + HasClientPerfMonitor + HasCorpus + HasSolutions + Serialize,
     SP: ShMemProvider,
     //CE: CustomEvent<I>,
 {
@@ -697,6 +977,9 @@ where
 
     /// Reset the single page (we reuse it over and over from pos 0), then send the current state to the next runner.
     fn on_restart(&mut self, state: &mut S) -> Result<(), Error> {
+        // The corpora may only serialize what the next runner reloads from disk
+        state.corpus().flush()?;
+        state.solutions().flush()?;
         // First, reset the page to 0 so the next iteration can read read from the beginning of this page
         self.staterestorer.reset();
         self.staterestorer
@@ -709,7 +992,7 @@ impl<E, S, SP, Z> EventProcessor<E, Z> for LlmpRestartingEventManager<S, SP>
 where
     E: HasObservers<State = S> + Executor<LlmpEventManager<S, SP>, Z>,
     for<'a> E::Observers: Deserialize<'a>,
-    S: UsesInput + HasExecutions + HasClientPerfMonitor,
+    S: UsesInput + HasExecutions + HasClientPerfMonitor + HasMetadata + HasCorpus,
     SP: ShMemProvider + 'static,
     Z: EvaluatorObservers<E::Observers, State = S> + ExecutionProcessor<E::Observers>, //CE: CustomEvent<I>,
 {
@@ -718,12 +1001,42 @@ where
     }
 }
 
+#[cfg(feature = "std")]
+impl<S, SP> LlmpRestartingEventManager<S, SP>
+where
+    S: UsesInput + HasExecutions + HasClientPerfMonitor + HasMetadata + HasCorpus,
+    SP: ShMemProvider + 'static,
+{
+    /// Handles all remaining events of a recorded broker log, see [`LlmpEventManager::replay`]
+    pub fn replay<E, Z>(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut S,
+        executor: &mut E,
+        replayer: &mut LlmpReplayer,
+    ) -> Result<usize, Error>
+    where
+        E: HasObservers<State = S> + Executor<LlmpEventManager<S, SP>, Z>,
+        for<'a> E::Observers: Deserialize<'a>,
+        Z: EvaluatorObservers<E::Observers, State = S>
+            + ExecutionProcessor<E::Observers, State = S>,
+    {
+        self.llmp_mgr.replay(fuzzer, state, executor, replayer)
+    }
+}
+
 #[cfg(feature = "std")]
 impl<E, S, SP, Z> EventManager<E, Z> for LlmpRestartingEventManager<S, SP>
 where
     E: HasObservers<State = S> + Executor<LlmpEventManager<S, SP>, Z>,
     for<'a> E::Observers: Deserialize<'a>,
-    S: UsesInput + HasExecutions + HasClientPerfMonitor + HasMetadata + Serialize,
+    S: UsesInput
+        + HasExecutions
+        + HasClientPerfMonitor
+        + HasMetadata
+        + HasCorpus
+        + HasSolutions
+        + Serialize,
     SP: ShMemProvider + 'static,
     Z: EvaluatorObservers<E::Observers, State = S> + ExecutionProcessor<E::Observers>, //CE: CustomEvent<I>,
 {
@@ -758,9 +1071,20 @@ where
         Self {
             llmp_mgr,
             staterestorer,
+            checkpointer: None,
         }
     }
 
+    /// Get the checkpointer, if checkpoints are enabled
+    pub fn checkpointer(&self) -> Option<&StateCheckpointer> {
+        self.checkpointer.as_ref()
+    }
+
+    /// Set the checkpointer writing the state to disk periodically, when reporting the progress
+    pub fn set_checkpointer(&mut self, checkpointer: Option<StateCheckpointer>) {
+        self.checkpointer = checkpointer;
+    }
+
     /// Get the staterestorer
     pub fn staterestorer(&self) -> &StateRestorer<SP> {
         &self.staterestorer
@@ -837,9 +1161,26 @@ where
     /// The address to connect to
     #[builder(default = None)]
     remote_broker_addr: Option<SocketAddr>,
+    /// The pre-shared key authenticating broker-to-broker connections, in both directions.
+    /// Without it, any process that can reach the broker port can connect as remote broker.
+    #[cfg(feature = "llmp_b2b_auth")]
+    #[builder(default = None)]
+    b2b_auth: Option<B2bAuth>,
+    /// The file the broker records all messages from its clients to, see [`LlmpRecorder`]
+    #[builder(default = None)]
+    record_path: Option<PathBuf>,
     /// The type of manager to build
     #[builder(default = ManagerKind::Any)]
     kind: ManagerKind,
+    /// The file the client writes checkpoints of its state to, to resume the campaign later
+    #[builder(default = None)]
+    checkpoint_path: Option<PathBuf>,
+    /// The interval between two checkpoints
+    #[builder(default = DEFAULT_CHECKPOINT_INTERVAL)]
+    checkpoint_interval: Duration,
+    /// If the client should start from the state in the checkpoint file, if any, instead of a new state
+    #[builder(default = false)]
+    resume: bool,
     #[builder(setter(skip), default = PhantomData)]
     phantom_data: PhantomData<S>,
 }
@@ -858,8 +1199,17 @@ where
         let (staterestorer, new_shmem_provider, core_id) = if std::env::var(_ENV_FUZZER_SENDER)
             .is_err()
         {
+            #[cfg(feature = "llmp_b2b_auth")]
+            let b2b_auth = self.b2b_auth.clone();
+            let record_path = self.record_path.clone();
             let broker_things = |mut broker: LlmpEventBroker<S::Input, MT, SP>,
                                  remote_broker_addr| {
+                #[cfg(feature = "llmp_b2b_auth")]
+                broker.set_b2b_auth(b2b_auth.clone());
+                if let Some(record_path) = &record_path {
+                    log::info!("Recording all llmp messages to {}", record_path.display());
+                    broker.set_recorder(Some(LlmpRecorder::new(record_path)?));
+                }
                 if let Some(remote_broker_addr) = remote_broker_addr {
                     log::info!("B2b: Connecting to {:?}", &remote_broker_addr);
                     broker.connect_b2b(remote_broker_addr)?;
@@ -1017,6 +1367,11 @@ where
             core_id.set_affinity()?;
         }
 
+        let checkpointer = self
+            .checkpoint_path
+            .as_ref()
+            .map(|path| StateCheckpointer::new(path, self.checkpoint_interval));
+
         // If we're restarting, deserialize the old state.
         let (state, mut mgr) = if let Some((state, mgr_description)) = staterestorer.restore()? {
             (
@@ -1033,16 +1388,24 @@ where
         } else {
             log::info!("First run. Let's set it all up");
             // Mgr to send and receive msgs from/to all other fuzzer instances
-            let mgr = LlmpEventManager::<S, SP>::existing_client_from_env(
-                new_shmem_provider,
+            let mut mgr = LlmpEventManager::<S, SP>::existing_client_from_env(
+                new_shmem_provider.clone(),
                 _ENV_FUZZER_BROKER_CLIENT_INITIAL,
                 self.configuration,
             )?;
 
-            (None, LlmpRestartingEventManager::new(mgr, staterestorer))
+            let state = match &checkpointer {
+                Some(checkpointer) if self.resume => {
+                    mgr.resume_from_checkpoint(checkpointer, new_shmem_provider)?
+                }
+                _ => None,
+            };
+
+            (state, LlmpRestartingEventManager::new(mgr, staterestorer))
         };
         // We reset the staterestorer, the next staterestorer and receiver (after crash) will reuse the page from the initial message.
         mgr.staterestorer.reset();
+        mgr.set_checkpointer(checkpointer);
 
         /* TODO: Not sure if this is needed
         // We commit an empty NO_RESTART message to this buf, against infinite loops,
@@ -1192,6 +1555,7 @@ where
         event: Event<DI>,
     ) -> Result<(), Error>
     where
+        S: HasCorpus + HasMetadata,
         E: Executor<EM, Z> + HasObservers<State = S>,
         EM: UsesState<State = S> + EventFirer,
         for<'a> E::Observers: Deserialize<'a>,
@@ -1206,25 +1570,30 @@ where
                 observers_buf: _, // Useless as we are converting between types
                 time: _,
                 executions: _,
+                lineage,
+                metadata,
             } => {
                 log::info!("Received new Testcase to convert from {_client_id}");
 
                 let Some(converter) = self.converter_back.as_mut() else {
                     return Ok(());
                 };
+                let input = converter.convert(input)?;
 
-                let _res = fuzzer.evaluate_input_with_observers::<E, EM>(
-                    state,
-                    executor,
-                    manager,
-                    converter.convert(input)?,
-                    false,
-                )?;
-                if let Some(item) = _res.1 {
+                ImportedTestcaseMetadata::enter(state, _client_id, lineage, metadata);
+                let _res = fuzzer
+                    .evaluate_input_with_observers::<E, EM>(state, executor, manager, input, false);
+                ImportedTestcaseMetadata::leave(state);
+                if let Some(item) = _res?.1 {
                     log::info!("Added received Testcase as item #{item}");
                 }
                 Ok(())
             }
+            Event::UpdateTestcaseMetadata {
+                corpus_id,
+                metadata,
+                phantom: _,
+            } => ImportedTestcaseMetadata::on_update(state, _client_id, corpus_id, metadata),
             Event::CustomBuf { tag, buf } => {
                 for handler in &mut self.custom_buf_handlers {
                     if handler(state, &tag, &buf)? == CustomBufEventResult::Handled {
@@ -1250,6 +1619,7 @@ where
         manager: &mut EM,
     ) -> Result<usize, Error>
     where
+        S: HasCorpus + HasMetadata,
         E: Executor<EM, Z> + HasObservers<State = S>,
         EM: UsesState<State = S> + EventFirer,
         for<'a> E::Observers: Deserialize<'a>,
@@ -1326,6 +1696,8 @@ where
                 observers_buf,
                 time,
                 executions,
+                lineage,
+                metadata,
             } => Event::NewTestcase {
                 input: self.converter.as_mut().unwrap().convert(input)?,
                 client_config,
@@ -1334,6 +1706,8 @@ where
                 observers_buf,
                 time,
                 executions,
+                lineage,
+                metadata,
             },
             Event::CustomBuf { buf, tag } => Event::CustomBuf { buf, tag },
             _ => {
@@ -1378,6 +1752,8 @@ where
                 observers_buf,
                 time,
                 executions,
+                lineage,
+                metadata,
             } => Event::NewTestcase {
                 input: self.converter.as_mut().unwrap().convert(input)?,
                 client_config,
@@ -1386,6 +1762,8 @@ where
                 observers_buf,
                 time,
                 executions,
+                lineage,
+                metadata,
             },
             Event::CustomBuf { buf, tag } => Event::CustomBuf { buf, tag },
             _ => {
@@ -1401,13 +1779,18 @@ where
 #[cfg(test)]
 #[cfg(feature = "std")]
 mod tests {
-    use core::sync::atomic::{compiler_fence, Ordering};
+    use core::{
+        sync::atomic::{compiler_fence, Ordering},
+        time::Duration,
+    };
+    use std::{env::temp_dir, fs};
 
     use serial_test::serial;
 
     use crate::{
         bolts::{
-            llmp::{LlmpClient, LlmpSharedMap},
+            checkpoint::StateCheckpointer,
+            llmp::{LlmpBroker, LlmpClient, LlmpSharedMap},
             rands::StdRand,
             shmem::{ShMemProvider, StdShMemProvider},
             staterestore::StateRestorer,
@@ -1422,7 +1805,7 @@ mod tests {
         mutators::BitFlipMutator,
         schedulers::RandScheduler,
         stages::StdMutationalStage,
-        state::StdState,
+        state::{HasCorpus, StdState},
         StdFuzzer,
     };
 
@@ -1517,4 +1900,71 @@ mod tests {
                 .unwrap();
         }
     }
+
+    #[test]
+    #[serial]
+    fn test_mgr_checkpoint_resume() {
+        let mut corpus = InMemoryCorpus::<BytesInput>::new();
+        corpus.add(Testcase::new(vec![0; 4].into())).unwrap();
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let state = StdState::new(
+            StdRand::with_seed(0),
+            corpus,
+            InMemoryCorpus::<BytesInput>::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+
+        let shmem_provider = StdShMemProvider::new().unwrap();
+        let mut broker = LlmpBroker::create_attach_to_tcp(shmem_provider.clone(), 1338).unwrap();
+        let mut other_broker =
+            LlmpBroker::create_attach_to_tcp(shmem_provider.clone(), 1339).unwrap();
+        assert_ne!(broker.run_id(), other_broker.run_id());
+        let new_mgr = |port: u16, configuration: &str| {
+            let mut llmp_client =
+                LlmpClient::create_attach_to_tcp(shmem_provider.clone(), port).unwrap();
+            // A little hack for CI. Don't do that in a real-world scenario.
+            unsafe {
+                llmp_client.mark_safe_to_unmap();
+            }
+            LlmpEventManager::<StdState<_, _, _, _>, _>::new(llmp_client, configuration.into())
+                .unwrap()
+        };
+
+        // The checkpointed client already received the message of the broker
+        let mut mgr = new_mgr(1338, "fuzzer");
+        broker.send_buf(0x1337, b"message").unwrap();
+        assert!(mgr.llmp.recv_buf().unwrap().is_some());
+        let path = temp_dir().join(format!("libafl_test_{}_mgr.checkpoint", std::process::id()));
+        let mut checkpointer = StateCheckpointer::new(&path, Duration::from_secs(3600));
+        assert!(mgr.maybe_checkpoint(&mut checkpointer, &state).unwrap());
+
+        // In the same run of the broker, the resumed client continues after it
+        let mut resumed_mgr = new_mgr(1338, "fuzzer");
+        let resumed = resumed_mgr
+            .resume_from_checkpoint(&checkpointer, shmem_provider.clone())
+            .unwrap()
+            .unwrap();
+        assert_eq!(resumed.corpus().count(), 1);
+        assert!(resumed_mgr.llmp.recv_buf().unwrap().is_none());
+
+        // With another broker, the client keeps receiving from it
+        let mut other_broker_mgr = new_mgr(1339, "fuzzer");
+        other_broker.send_buf(0x1337, b"message").unwrap();
+        assert!(other_broker_mgr
+            .resume_from_checkpoint(&checkpointer, shmem_provider.clone())
+            .unwrap()
+            .is_some());
+        assert!(other_broker_mgr.llmp.recv_buf().unwrap().is_some());
+
+        // A client of another fuzzer must not pick up the checkpoint
+        let mut other_mgr = new_mgr(1338, "other");
+        assert!(other_mgr
+            .resume_from_checkpoint(&checkpointer, shmem_provider.clone())
+            .is_err());
+
+        fs::remove_file(path).unwrap();
+    }
 }
@@ -3,10 +3,15 @@
 pub mod simple;
 pub use simple::*;
 pub mod llmp;
-use alloc::{boxed::Box, string::String, vec::Vec};
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
 #[cfg(all(unix, feature = "std"))]
 use core::ffi::c_void;
 use core::{
+    any::TypeId,
     fmt,
     hash::{BuildHasher, Hasher},
     marker::PhantomData,
@@ -24,12 +29,17 @@ use crate::bolts::os::unix_signals::{siginfo_t, ucontext_t, Handler, Signal};
 #[cfg(all(unix, feature = "std"))]
 use crate::bolts::{shmem::ShMemProvider, staterestore::StateRestorer};
 use crate::{
-    bolts::current_time,
+    bolts::{
+        anymap::unpack_type_id,
+        current_time,
+        serdeany::{SerdeAny, SerdeAnyMap},
+    },
+    corpus::{Corpus, CorpusId, LineageMetadata, Testcase},
     executors::ExitKind,
     inputs::Input,
     monitors::UserStats,
     observers::ObserversTuple,
-    state::{HasClientPerfMonitor, HasExecutions, HasMetadata},
+    state::{HasClientPerfMonitor, HasCorpus, HasExecutions, HasMetadata},
     Error,
 };
 
@@ -119,8 +129,8 @@ pub struct EventManagerId {
 use crate::monitors::ClientPerfMonitor;
 use crate::{inputs::UsesInput, state::UsesState};
 
-/// The log event severity
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+/// The log event severity, ordered from the least to the most severe
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogSeverity {
     /// Debug severity
     Debug,
@@ -154,6 +164,36 @@ impl fmt::Display for LogSeverity {
     }
 }
 
+/// The tag of the [`Event::CustomBuf`] events passing the [`ClientCommand`]s of the monitor in the broker
+/// to its clients, as `(event manager id, command)`.
+/// The broker sends them only to its own clients, which accept them with [`crate::stages::PauseStage`].
+pub const CLIENT_COMMAND_TAG: &str = "client_command";
+/// The tag of the [`Event::CustomBuf`] events clients announce their event manager id with,
+/// to receive the [`ClientCommand`]s of the monitor in the broker
+pub const CLIENT_COMMAND_HELLO_TAG: &str = "client_command_hello";
+/// The tag of the [`Event::CustomBuf`] events passing the input of a new objective to the monitor in the broker,
+/// as `(objective corpus size, input)`, see [`crate::monitors::Monitor::wants_inputs`]
+pub const OBJECTIVE_INPUT_TAG: &str = "objective_input";
+
+/// A command of a monitor in the broker to a client, see [`CLIENT_COMMAND_TAG`]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientCommand {
+    /// Stop fuzzing until the client is resumed.
+    /// A paused client keeps handling the events of the others.
+    Pause,
+    /// Continue fuzzing after a pause
+    Resume,
+}
+
+impl fmt::Display for ClientCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientCommand::Pause => write!(f, "Pause"),
+            ClientCommand::Resume => write!(f, "Resume"),
+        }
+    }
+}
+
 /// The result of a custom buf handler added using [`HasCustomBufHandlers::add_custom_buf_handler`]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CustomBufEventResult {
@@ -245,6 +285,146 @@ impl From<String> for EventConfig {
     }
 }
 
+/// The types of testcase metadata sent to the other clients along with the testcases, in [`Event::NewTestcase`]
+/// and [`Event::UpdateTestcaseMetadata`], for them not to compute it again, e.g. with a [`crate::stages::GeneralizationStage`].
+/// Without this metadata in the state, no testcase metadata is sent.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TestcaseMetadataSelection {
+    type_ids: Vec<u64>,
+}
+
+crate::impl_serdeany!(TestcaseMetadataSelection);
+
+impl TestcaseMetadataSelection {
+    /// Creates a new [`struct@TestcaseMetadataSelection`], selecting no metadata
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects the metadata of type `T`
+    #[must_use]
+    pub fn with<T>(mut self) -> Self
+    where
+        T: SerdeAny,
+    {
+        self.select::<T>();
+        self
+    }
+
+    /// Selects the metadata of type `T`
+    pub fn select<T>(&mut self)
+    where
+        T: SerdeAny,
+    {
+        let type_id = unpack_type_id(TypeId::of::<T>());
+        if !self.type_ids.contains(&type_id) {
+            self.type_ids.push(type_id);
+        }
+    }
+
+    /// The selected elements of `metadata`, or `None` if there are none
+    #[must_use]
+    pub fn filter(&self, metadata: &SerdeAnyMap) -> Option<SerdeAnyMap> {
+        if self.type_ids.is_empty() {
+            return None;
+        }
+        let mut selected = metadata.clone();
+        selected.retain_type_ids(&self.type_ids);
+        (!selected.is_empty()).then_some(selected)
+    }
+
+    /// The metadata of the testcase `id` to send to the other clients, as selected in the state
+    pub fn metadata_to_send<S>(state: &S, id: CorpusId) -> Result<Option<SerdeAnyMap>, Error>
+    where
+        S: HasCorpus + HasMetadata,
+    {
+        let Some(selection) = state.metadata().get::<Self>() else {
+            return Ok(None);
+        };
+        let testcase = state.corpus().get(id)?.borrow();
+        Ok(selection.filter(testcase.metadata()))
+    }
+}
+
+/// The lineage and the metadata received along with the testcase being imported from another client.
+///
+/// The event manager stores it in the state, with [`ImportedTestcaseMetadata::enter`], while it
+/// evaluates the received input. If the input turns into a testcase, the fuzzer attaches it, with
+/// [`ImportedTestcaseMetadata::attach`], before the testcase is added to the corpus: the
+/// scheduler then already sees the metadata of the sender, e.g. its scheduler metadata.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ImportedTestcaseMetadata {
+    lineage: LineageMetadata,
+    metadata: Option<SerdeAnyMap>,
+}
+
+crate::impl_serdeany!(ImportedTestcaseMetadata);
+
+impl ImportedTestcaseMetadata {
+    /// Stores the lineage and the metadata received from `client` in the state, until
+    /// [`ImportedTestcaseMetadata::leave`] is called
+    pub fn enter<S>(
+        state: &mut S,
+        client: u32,
+        lineage: Option<LineageMetadata>,
+        metadata: Option<SerdeAnyMap>,
+    ) where
+        S: HasMetadata,
+    {
+        state.add_metadata(Self {
+            lineage: lineage.unwrap_or_default().received_from(client),
+            metadata,
+        });
+    }
+
+    /// Removes the received lineage and metadata from the state, if not attached to a testcase
+    pub fn leave<S>(state: &mut S)
+    where
+        S: HasMetadata,
+    {
+        drop(state.metadata_mut().remove::<Self>());
+    }
+
+    /// Attaches the received lineage and metadata, if any, to the testcase about to be added to the
+    /// corpus. The lineage computed locally does not apply to received testcases, while the metadata
+    /// the testcase already got from this client, e.g. from its feedbacks, is kept.
+    pub fn attach<S>(state: &mut S, testcase: &mut Testcase<S::Input>)
+    where
+        S: HasMetadata + UsesInput,
+    {
+        if let Some(imported) = state.metadata_mut().remove::<Self>() {
+            let imported = *imported;
+            testcase.add_metadata(imported.lineage);
+            if let Some(metadata) = imported.metadata {
+                testcase.metadata_mut().insert_missing(metadata);
+            }
+        }
+    }
+
+    /// Attaches the metadata of an [`Event::UpdateTestcaseMetadata`] event of `client` to the
+    /// testcase it refers to, if imported, keeping the metadata the testcase already has
+    pub fn on_update<S>(
+        state: &mut S,
+        client: u32,
+        corpus_id: CorpusId,
+        metadata: SerdeAnyMap,
+    ) -> Result<(), Error>
+    where
+        S: HasCorpus,
+    {
+        if let Some(id) = LineageMetadata::find_received(state.corpus(), client, corpus_id)? {
+            state
+                .corpus()
+                .get(id)?
+                .borrow_mut()
+                .metadata_mut()
+                .insert_missing(metadata);
+        }
+        Ok(())
+    }
+}
+
 /*
 /// A custom event, for own messages, with own handler.
 pub trait CustomEvent<I>: SerdeAny
@@ -267,8 +447,6 @@ pub enum Event<I>
 where
     I: Input,
 {
-    // TODO use an ID to keep track of the original index in the sender Corpus
-    // The sender can then use it to send Testcase metadata with CustomEvent
     /// A fuzzer found a new testcase. Rejoice!
     NewTestcase {
         /// The input for the new testcase
@@ -285,6 +463,20 @@ where
         time: Duration,
         /// The executions of this client
         executions: usize,
+        /// The lineage of the new testcase, if tracked, with its id in the corpus of the sender
+        /// to match [`Event::UpdateTestcaseMetadata`] events
+        lineage: Option<LineageMetadata>,
+        /// The testcase metadata selected by the [`TestcaseMetadataSelection`] of the sender
+        metadata: Option<SerdeAnyMap>,
+    },
+    /// New metadata for a testcase sent before, e.g. computed by a stage after it was found
+    UpdateTestcaseMetadata {
+        /// The id of the testcase in the corpus of the sender
+        corpus_id: CorpusId,
+        /// The testcase metadata selected by the [`TestcaseMetadataSelection`] of the sender
+        metadata: SerdeAnyMap,
+        /// [`PhantomData`]
+        phantom: PhantomData<I>,
     },
     /// New stats event to monitor.
     UpdateExecStats {
@@ -359,7 +551,10 @@ where
                 observers_buf: _,
                 time: _,
                 executions: _,
+                lineage: _,
+                metadata: _,
             } => "Testcase",
+            Event::UpdateTestcaseMetadata { .. } => "TestcaseMetadata",
             Event::UpdateExecStats {
                 time: _,
                 executions: _,
@@ -391,6 +586,20 @@ where
     }
 }
 
+impl<I> Event<I>
+where
+    I: Input,
+{
+    /// The [`Event::CustomBuf`] passing the input of a new objective to the monitor in the broker,
+    /// to be fired right after the [`Event::Objective`], see [`OBJECTIVE_INPUT_TAG`]
+    pub fn objective_input(objective_size: usize, input: &I) -> Result<Self, Error> {
+        Ok(Event::CustomBuf {
+            buf: postcard::to_allocvec(&(objective_size, input))?,
+            tag: OBJECTIVE_INPUT_TAG.to_string(),
+        })
+    }
+}
+
 /// [`EventFirer`] fire an event.
 pub trait EventFirer: UsesState {
     /// Send off an [`Event`] to the broker
@@ -637,12 +846,22 @@ mod tests {
     use crate::{
         bolts::{
             current_time,
+            rands::StdRand,
+            serdeany::SerdeAnyMap,
             tuples::{tuple_list, Named},
         },
-        events::{Event, EventConfig},
+        corpus::{Corpus, CorpusId, InMemoryCorpus, LineageMetadata, SchedulerTestcaseMetaData},
+        events::{
+            Event, EventConfig, ImportedTestcaseMetadata, NopEventManager,
+            TestcaseMetadataSelection,
+        },
         executors::ExitKind,
-        inputs::bytes::BytesInput,
+        feedbacks::{ConstFeedback, MapIndexesMetadata},
+        fuzzer::{ExecutionProcessor, StdFuzzer},
+        inputs::{bytes::BytesInput, GeneralizedInputMetadata},
         observers::StdMapObserver,
+        schedulers::{powersched::PowerSchedule, PowerQueueScheduler},
+        state::{HasCorpus, HasMetadata, StdState},
     };
 
     static mut MAP: [u32; 4] = [0; 4];
@@ -662,6 +881,8 @@ mod tests {
             client_config: EventConfig::AlwaysUnique,
             time: current_time(),
             executions: 0,
+            lineage: Some(LineageMetadata::new().to_send(CorpusId::from(0_usize))),
+            metadata: None,
         };
 
         let serialized = postcard::to_allocvec(&e).unwrap();
@@ -676,7 +897,10 @@ mod tests {
                 client_config: _,
                 time: _,
                 executions: _,
+                lineage,
+                metadata: _,
             } => {
+                assert_eq!(lineage.unwrap().origin_id, Some(CorpusId::from(0_usize)));
                 let o: tuple_list_type!(StdMapObserver::<u32, false>) =
                     postcard::from_bytes(observers_buf.as_ref().unwrap()).unwrap();
                 assert_eq!("test", o.0.name());
@@ -684,6 +908,105 @@ mod tests {
             _ => panic!("mistmatch"),
         };
     }
+
+    #[test]
+    fn test_testcase_metadata_selection() {
+        let mut metadata = SerdeAnyMap::new();
+        metadata.insert(LineageMetadata::new());
+        metadata.insert(MapIndexesMetadata::new(vec![1]));
+
+        assert!(TestcaseMetadataSelection::new().filter(&metadata).is_none());
+        let selected = TestcaseMetadataSelection::new()
+            .with::<LineageMetadata>()
+            .filter(&metadata)
+            .unwrap();
+        assert_eq!(selected.len(), 1);
+        assert!(selected.contains::<LineageMetadata>());
+
+        let mut received = SerdeAnyMap::new();
+        received.insert(MapIndexesMetadata::new(vec![2]));
+        received.insert_missing(selected);
+        assert_eq!(received.len(), 2);
+    }
+
+    #[test]
+    fn test_import_testcase_metadata() {
+        let mut feedback = ConstFeedback::new(true);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::<BytesInput>::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut fuzzer = StdFuzzer::new(
+            PowerQueueScheduler::new(PowerSchedule::FAST),
+            feedback,
+            objective,
+        );
+        let mut mgr = NopEventManager::new();
+        let observers = tuple_list!();
+
+        // The testcase 7 of client 3, at depth 5 in its corpus
+        let mut metadata = SerdeAnyMap::new();
+        metadata.insert(SchedulerTestcaseMetaData::new(5));
+        ImportedTestcaseMetadata::enter(
+            &mut state,
+            3,
+            Some(LineageMetadata::new().to_send(CorpusId::from(7_usize))),
+            Some(metadata),
+        );
+        let (_, idx) = fuzzer
+            .process_execution(
+                &mut state,
+                &mut mgr,
+                BytesInput::new(vec![1]),
+                &observers,
+                &ExitKind::Ok,
+                false,
+            )
+            .unwrap();
+        ImportedTestcaseMetadata::leave(&mut state);
+        let idx = idx.unwrap();
+        assert!(!state.has_metadata::<ImportedTestcaseMetadata>());
+
+        // The scheduler keeps the metadata of the sender
+        {
+            let testcase = state.corpus().get(idx).unwrap().borrow();
+            let lineage = testcase.metadata().get::<LineageMetadata>().unwrap();
+            assert_eq!(lineage.client, Some(3));
+            assert_eq!(lineage.origin_id, Some(CorpusId::from(7_usize)));
+            let scheduler = testcase
+                .metadata()
+                .get::<SchedulerTestcaseMetaData>()
+                .unwrap();
+            assert_eq!(scheduler.depth(), 5);
+        }
+
+        // The metadata computed later by the sender follows
+        let mut metadata = SerdeAnyMap::new();
+        metadata.insert(MapIndexesMetadata::new(vec![1, 2]));
+        ImportedTestcaseMetadata::on_update(&mut state, 3, CorpusId::from(7_usize), metadata)
+            .unwrap();
+        // Not the testcase 7 of another client
+        let mut metadata = SerdeAnyMap::new();
+        metadata.insert(GeneralizedInputMetadata::generalized_from_options(&[]));
+        ImportedTestcaseMetadata::on_update(&mut state, 4, CorpusId::from(7_usize), metadata)
+            .unwrap();
+
+        let testcase = state.corpus().get(idx).unwrap().borrow();
+        assert_eq!(
+            testcase
+                .metadata()
+                .get::<MapIndexesMetadata>()
+                .unwrap()
+                .list,
+            [1, 2]
+        );
+        assert!(!testcase.metadata().contains::<GeneralizedInputMetadata>());
+    }
 }
 
 /// `EventManager` Python bindings
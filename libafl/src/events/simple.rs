@@ -36,10 +36,11 @@ use crate::{
 use crate::{
     events::{
         BrokerEventResult, Event, EventFirer, EventManager, EventManagerId, EventProcessor,
-        EventRestarter, HasEventManagerId,
+        EventRestarter, HasEventManagerId, CLIENT_COMMAND_HELLO_TAG, CLIENT_COMMAND_TAG,
+        OBJECTIVE_INPUT_TAG,
     },
     inputs::UsesInput,
-    monitors::Monitor,
+    monitors::{add_client_input, add_objective_input, Monitor},
     state::{HasClientPerfMonitor, HasExecutions, HasMetadata, UsesState},
     Error,
 };
@@ -121,6 +122,8 @@ where
         state: &mut S,
         _executor: &mut E,
     ) -> Result<usize, Error> {
+        self.pass_client_commands()?;
+
         let count = self.events.len();
         while !self.events.is_empty() {
             let event = self.events.pop().unwrap();
@@ -205,13 +208,15 @@ where
     ) -> Result<BrokerEventResult, Error> {
         match event {
             Event::NewTestcase {
-                input: _,
+                input,
                 client_config: _,
                 exit_kind: _,
                 corpus_size,
                 observers_buf: _,
                 time,
                 executions,
+                lineage,
+                metadata: _,
             } => {
                 monitor
                     .client_stats_mut_for(0)
@@ -219,6 +224,11 @@ where
                 monitor
                     .client_stats_mut_for(0)
                     .update_executions(*executions as u64, *time);
+                let idx = lineage
+                    .as_ref()
+                    .and_then(|lineage| lineage.origin_id)
+                    .map(|id| id.0);
+                add_client_input(monitor, 0, false, idx, *time, input);
                 monitor.display(event.name().to_string(), 0);
                 Ok(BrokerEventResult::Handled)
             }
@@ -272,8 +282,17 @@ where
                 message,
                 phantom: _,
             } => {
-                let (_, _) = (message, severity_level);
                 log::log!((*severity_level).into(), "{message}");
+                monitor.add_log(0, *severity_level, message);
+                Ok(BrokerEventResult::Handled)
+            }
+            Event::UpdateTestcaseMetadata { .. } => Ok(BrokerEventResult::Handled),
+            Event::CustomBuf { tag, buf } if tag == OBJECTIVE_INPUT_TAG => {
+                add_objective_input::<_, S::Input>(monitor, 0, buf);
+                Ok(BrokerEventResult::Handled)
+            }
+            // There is only one client, which always gets the commands
+            Event::CustomBuf { tag, .. } if tag == CLIENT_COMMAND_HELLO_TAG => {
                 Ok(BrokerEventResult::Handled)
             }
             Event::CustomBuf { .. } => Ok(BrokerEventResult::Forward),
@@ -281,6 +300,18 @@ where
         }
     }
 
+    /// Passes the commands the user issued in the monitor on to the client, see [`CLIENT_COMMAND_TAG`].
+    /// There is only one client, so all commands are meant for it.
+    fn pass_client_commands(&mut self) -> Result<(), Error> {
+        for (_, command) in self.monitor.take_client_commands() {
+            self.events.push(Event::CustomBuf {
+                buf: postcard::to_allocvec(&(0_usize, command))?,
+                tag: CLIENT_COMMAND_TAG.to_string(),
+            });
+        }
+        Ok(())
+    }
+
     // Handle arriving events in the client
     #[allow(clippy::needless_pass_by_value, clippy::unused_self)]
     fn handle_in_client(&mut self, state: &mut S, event: Event<S::Input>) -> Result<(), Error> {
@@ -342,11 +373,14 @@ where
 #[cfg(feature = "std")]
 impl<MT, S, SP> EventRestarter for SimpleRestartingEventManager<MT, S, SP>
 where
-    S: UsesInput + Serialize,
+    S: UsesInput + HasCorpus + HasSolutions + Serialize,
     SP: ShMemProvider,
 {
     /// Reset the single page (we reuse it over and over from pos 0), then send the current state to the next runner.
     fn on_restart(&mut self, state: &mut S) -> Result<(), Error> {
+        // The corpora may only serialize what the next runner reloads from disk
+        state.corpus().flush()?;
+        state.solutions().flush()?;
         // First, reset the page to 0 so the next iteration can read read from the beginning of this page
         self.staterestorer.reset();
         self.staterestorer.save(state)
@@ -374,7 +408,13 @@ where
 impl<E, MT, S, SP, Z> EventManager<E, Z> for SimpleRestartingEventManager<MT, S, SP>
 where
     MT: Monitor,
-    S: UsesInput + HasExecutions + HasClientPerfMonitor + HasMetadata + Serialize,
+    S: UsesInput
+        + HasExecutions
+        + HasClientPerfMonitor
+        + HasMetadata
+        + HasCorpus
+        + HasSolutions
+        + Serialize,
     SP: ShMemProvider,
 {
 }
@@ -0,0 +1,174 @@
+//! An [`Executor`] wrapper applying the `afl_custom_post_process` hook of an AFL++ custom mutator
+//! to each input right before it is executed, see [`crate::mutators::afl_custom`].
+
+use alloc::rc::Rc;
+use core::fmt::Debug;
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::HasBytesVec,
+    mutators::AflCustomMutatorLibrary,
+    observers::UsesObservers,
+    state::UsesState,
+    Error,
+};
+
+/// Wraps an [`Executor`] to run each input transformed with `afl_custom_post_process`, as AFL++ does.
+///
+/// Only the execution sees the transformed bytes: the fuzzer evaluates, and stores in the corpus,
+/// the inputs as they were mutated.
+#[derive(Debug)]
+pub struct AflCustomPostProcessExecutor<E> {
+    executor: E,
+    library: Rc<AflCustomMutatorLibrary>,
+}
+
+impl<E> AflCustomPostProcessExecutor<E> {
+    /// Wraps the given executor, post processing its inputs with the given custom mutator
+    pub fn new(executor: E, library: Rc<AflCustomMutatorLibrary>) -> Self {
+        Self { executor, library }
+    }
+
+    /// The wrapped executor
+    pub fn inner(&mut self) -> &mut E {
+        &mut self.executor
+    }
+}
+
+impl<E, EM, Z> Executor<EM, Z> for AflCustomPostProcessExecutor<E>
+where
+    E: Executor<EM, Z> + Debug,
+    E::Input: HasBytesVec + Clone,
+    EM: UsesState<State = E::State>,
+    Z: UsesState<State = E::State>,
+{
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        if !self.library.has_post_process() {
+            return self.executor.run_target(fuzzer, state, mgr, input);
+        }
+        let mut post_processed = input.clone();
+        *post_processed.bytes_mut() = self.library.post_process(input.bytes());
+        self.executor
+            .run_target(fuzzer, state, mgr, &post_processed)
+    }
+}
+
+impl<E> UsesState for AflCustomPostProcessExecutor<E>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E> UsesObservers for AflCustomPostProcessExecutor<E>
+where
+    E: UsesObservers,
+{
+    type Observers = E::Observers;
+}
+
+impl<E> HasObservers for AflCustomPostProcessExecutor<E>
+where
+    E: HasObservers,
+{
+    fn observers(&self) -> &Self::Observers {
+        self.executor.observers()
+    }
+
+    fn observers_mut(&mut self) -> &mut Self::Observers {
+        self.executor.observers_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{rc::Rc, vec::Vec};
+    use core::{cell::RefCell, ffi::c_void, slice};
+
+    use super::AflCustomPostProcessExecutor;
+    use crate::{
+        bolts::{rands::StdRand, tuples::tuple_list},
+        corpus::{Corpus, CorpusId, InMemoryCorpus},
+        events::NopEventManager,
+        executors::{ExitKind, InProcessExecutor},
+        feedbacks::ConstFeedback,
+        fuzzer::Evaluator,
+        inputs::{BytesInput, HasBytesVec},
+        mutators::AflCustomMutatorLibrary,
+        schedulers::QueueScheduler,
+        state::{HasCorpus, StdState},
+        StdFuzzer,
+    };
+
+    std::thread_local! {
+        static POST_PROCESSED: RefCell<Vec<u8>> = RefCell::new(Vec::new());
+    }
+
+    /// Appends a `!`, in a buffer owned by the "custom mutator"
+    unsafe extern "C" fn post_process(
+        _data: *mut c_void,
+        buf: *mut u8,
+        buf_size: usize,
+        out_buf: *mut *mut u8,
+    ) -> usize {
+        POST_PROCESSED.with(|post_processed| {
+            let mut post_processed = post_processed.borrow_mut();
+            post_processed.clear();
+            post_processed.extend_from_slice(slice::from_raw_parts(buf, buf_size));
+            post_processed.push(b'!');
+            *out_buf = post_processed.as_mut_ptr();
+            post_processed.len()
+        })
+    }
+
+    #[test]
+    fn test_afl_custom_post_process() {
+        let library = Rc::new(AflCustomMutatorLibrary::with_post_process(post_process));
+
+        let mut feedback = ConstFeedback::new(true);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::<BytesInput>::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut mgr = NopEventManager::new();
+
+        let executed = RefCell::new(vec![]);
+        let mut harness = |input: &BytesInput| {
+            executed.borrow_mut().push(input.bytes().to_vec());
+            ExitKind::Ok
+        };
+        let mut executor = AflCustomPostProcessExecutor::new(
+            InProcessExecutor::new(
+                &mut harness,
+                tuple_list!(),
+                &mut fuzzer,
+                &mut state,
+                &mut mgr,
+            )
+            .unwrap(),
+            library,
+        );
+
+        let (_, idx) = fuzzer
+            .evaluate_input(&mut state, &mut executor, &mut mgr, b"abc".to_vec().into())
+            .unwrap();
+        assert_eq!(idx, Some(CorpusId::from(0_usize)));
+
+        // The harness ran the post processed input, the corpus keeps the input as it was
+        assert_eq!(*executed.borrow(), [b"abc!".to_vec()]);
+        let stored = state.corpus().get(idx.unwrap()).unwrap().borrow();
+        assert_eq!(stored.input().as_ref().unwrap().bytes(), b"abc");
+    }
+}
@@ -17,7 +17,7 @@ use std::{
     process::{Command, Stdio},
 };
 
-use super::HasObservers;
+use super::{HasObservers, SurvivesCrashes};
 #[cfg(all(feature = "std", unix))]
 use crate::executors::{Executor, ExitKind};
 use crate::{
@@ -372,6 +372,8 @@ where
     type Observers = OT;
 }
 
+impl<EM, OT, S, T, Z> SurvivesCrashes for CommandExecutor<EM, OT, S, T, Z> {}
+
 impl<EM, OT, S, T, Z> HasObservers for CommandExecutor<EM, OT, S, T, Z>
 where
     S: UsesInput,
@@ -31,7 +31,7 @@ use crate::{
         tuples::Prepend,
         AsMutSlice, AsSlice,
     },
-    executors::{Executor, ExitKind, HasObservers},
+    executors::{Executor, ExitKind, HasObservers, SurvivesCrashes},
     inputs::{HasTargetBytes, Input, UsesInput},
     mutators::Tokens,
     observers::{
@@ -979,6 +979,24 @@ impl<'a> ForkserverExecutorBuilder<'a, UnixShMemProvider> {
         self
     }
 
+    #[must_use]
+    /// Run the target as AFL++ `cmplog` binary, logging its comparisons to the given shared map.
+    /// The map should hold an [`crate::observers::cmp::AFLCmpMap`], observed by an
+    /// [`crate::observers::cmp::AFLCmpLogObserver`] of this executor.
+    /// Usually, this executor is then used as tracer, for example in a [`crate::stages::RedQueenStage`].
+    pub fn cmplog_shmem<SHM: ShMem>(mut self, shmem: &SHM) -> Self {
+        self.envs.push((
+            OsString::from("__AFL_CMPLOG_SHM_ID"),
+            OsString::from(shmem.id().to_string()),
+        ));
+        // AFL++ sets this for the cmplog forkserver
+        self.envs.push((
+            OsString::from("___AFL_EINS_ZWEI_POLIZEI___"),
+            OsString::from("1"),
+        ));
+        self
+    }
+
     /// Shmem provider for forkserver's shared memory testcase feature.
     pub fn shmem_provider<SP: ShMemProvider>(
         self,
@@ -1119,6 +1137,14 @@ where
     type Observers = OT;
 }
 
+impl<OT, S, SP> SurvivesCrashes for ForkserverExecutor<OT, S, SP>
+where
+    OT: ObserversTuple<S>,
+    S: UsesInput,
+    SP: ShMemProvider,
+{
+}
+
 impl<OT, S, SP> HasObservers for ForkserverExecutor<OT, S, SP>
 where
     OT: ObserversTuple<S>,
@@ -1195,6 +1221,8 @@ where
     type Observers = E::Observers;
 }
 
+impl<E> SurvivesCrashes for TimeoutForkserverExecutor<E> {}
+
 impl<E> HasObservers for TimeoutForkserverExecutor<E>
 where
     E: HasObservers,
@@ -1212,6 +1240,7 @@ where
 
 #[cfg(test)]
 mod tests {
+    use alloc::string::ToString;
     use std::ffi::OsString;
 
     use serial_test::serial;
@@ -1223,7 +1252,7 @@ mod tests {
             AsMutSlice,
         },
         executors::forkserver::ForkserverExecutorBuilder,
-        observers::{ConstMapObserver, HitcountsMapObserver},
+        observers::{AFLCmpMap, ConstMapObserver, HitcountsMapObserver},
         Error,
     };
 
@@ -1262,4 +1291,23 @@ mod tests {
         };
         assert!(result);
     }
+
+    #[test]
+    #[serial]
+    fn test_forkserver_cmplog_env() {
+        let mut shmem_provider = UnixShMemProvider::new().unwrap();
+        let cmplog_shmem = shmem_provider
+            .new_shmem(core::mem::size_of::<AFLCmpMap>())
+            .unwrap();
+
+        let builder = ForkserverExecutorBuilder::new()
+            .program(OsString::from("echo"))
+            .cmplog_shmem(&cmplog_shmem);
+
+        assert!(builder
+            .envs
+            .iter()
+            .any(|(key, val)| key == "__AFL_CMPLOG_SHM_ID"
+                && *val == OsString::from(cmplog_shmem.id().to_string())));
+    }
 }
@@ -6,6 +6,8 @@
 use alloc::boxed::Box;
 #[cfg(all(unix, feature = "std"))]
 use alloc::vec::Vec;
+#[cfg(unix)]
+use core::sync::atomic::AtomicI32;
 use core::{
     borrow::BorrowMut,
     ffi::c_void,
@@ -41,6 +43,8 @@ use crate::bolts::os::unix_signals::{ucontext_t, Handler, Signal};
 use crate::bolts::os::windows_exceptions::setup_exception_handler;
 #[cfg(all(feature = "std", unix))]
 use crate::bolts::shmem::ShMemProvider;
+#[cfg(all(feature = "std", unix))]
+use crate::executors::SurvivesCrashes;
 use crate::{
     events::{EventFirer, EventRestarter},
     executors::{Executor, ExitKind, HasObservers},
@@ -491,6 +495,12 @@ pub(crate) static mut GLOBAL_STATE: InProcessExecutorHandlerData = InProcessExec
     timeout_input_ptr: null_mut(),
 };
 
+/// The signal of the last crash of the target, `SIGABRT` for a panic.
+/// The crash handlers set it before running the observers, so that they can tell crashes apart by signal,
+/// like the [`crate::observers::BacktraceObserver`].
+#[cfg(unix)]
+pub static CRASH_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
 /// Get the inprocess [`crate::state::State`]
 #[must_use]
 pub fn inprocess_get_state<'a, S>() -> Option<&'a mut S> {
@@ -566,14 +576,16 @@ pub fn run_observers_and_save_state<E, EM, OF, Z>(
             .solutions_mut()
             .add(new_testcase)
             .expect("In run_observers_and_save_state solutions failure.");
+        let objective_size = state.solutions().count();
         event_mgr
-            .fire(
-                state,
-                Event::Objective {
-                    objective_size: state.solutions().count(),
-                },
-            )
+            .fire(state, Event::Objective { objective_size })
             .expect("Could not save state in run_observers_and_save_state");
+        // The input is only shown by the monitor, don't lose the restart over it
+        if let Ok(event) = Event::objective_input(objective_size, input) {
+            event_mgr
+                .fire(state, event)
+                .expect("Could not save state in run_observers_and_save_state");
+        }
     }
 
     event_mgr.on_restart(state).unwrap();
@@ -588,7 +600,7 @@ mod unix_signal_handler {
     use alloc::vec::Vec;
     #[cfg(feature = "std")]
     use alloc::{boxed::Box, string::String};
-    use core::mem::transmute;
+    use core::{mem::transmute, sync::atomic::Ordering};
     #[cfg(feature = "std")]
     use std::{io::Write, panic};
 
@@ -600,7 +612,10 @@ mod unix_signal_handler {
         bolts::os::unix_signals::{ucontext_t, Handler, Signal},
         events::{EventFirer, EventRestarter},
         executors::{
-            inprocess::{run_observers_and_save_state, InProcessExecutorHandlerData, GLOBAL_STATE},
+            inprocess::{
+                run_observers_and_save_state, InProcessExecutorHandlerData, CRASH_SIGNAL,
+                GLOBAL_STATE,
+            },
             Executor, ExitKind, HasObservers,
         },
         feedbacks::Feedback,
@@ -674,6 +689,7 @@ mod unix_signal_handler {
             let data = unsafe { &mut GLOBAL_STATE };
             if data.is_valid() {
                 // We are fuzzing!
+                CRASH_SIGNAL.store(libc::SIGABRT, Ordering::Relaxed);
                 let executor = data.executor_mut::<E>();
                 let state = data.state_mut::<E::State>();
                 let input = data.take_current_input::<<E::State as UsesInput>::Input>();
@@ -758,6 +774,7 @@ mod unix_signal_handler {
 
         log::error!("Crashed with {signal}");
         if data.is_valid() {
+            CRASH_SIGNAL.store(signal as i32, Ordering::Relaxed);
             let executor = data.executor_mut::<E>();
             // disarms timeout in case of TimeoutExecutor
             executor.post_run_reset();
@@ -1784,6 +1801,26 @@ where
     type Observers = OT;
 }
 
+#[cfg(all(feature = "std", unix))]
+impl<'a, H, OT, S, SP> SurvivesCrashes for InProcessForkExecutor<'a, H, OT, S, SP>
+where
+    H: FnMut(&S::Input) -> ExitKind + ?Sized,
+    S: UsesInput,
+    OT: ObserversTuple<S>,
+    SP: ShMemProvider,
+{
+}
+
+#[cfg(all(feature = "std", target_os = "linux"))]
+impl<'a, H, OT, S, SP> SurvivesCrashes for TimeoutInProcessForkExecutor<'a, H, OT, S, SP>
+where
+    H: FnMut(&S::Input) -> ExitKind + ?Sized,
+    S: UsesInput,
+    OT: ObserversTuple<S>,
+    SP: ShMemProvider,
+{
+}
+
 #[cfg(all(feature = "std", unix))]
 impl<'a, H, OT, S, SP> HasObservers for InProcessForkExecutor<'a, H, OT, S, SP>
 where
@@ -1826,11 +1863,12 @@ where
 #[cfg(all(feature = "std", unix))]
 pub mod child_signal_handlers {
     use alloc::boxed::Box;
+    use core::sync::atomic::Ordering;
     use std::panic;
 
     use libc::siginfo_t;
 
-    use super::{InProcessForkExecutorGlobalData, FORK_EXECUTOR_GLOBAL_DATA};
+    use super::{InProcessForkExecutorGlobalData, CRASH_SIGNAL, FORK_EXECUTOR_GLOBAL_DATA};
     use crate::{
         bolts::os::unix_signals::{ucontext_t, Signal},
         executors::{ExitKind, HasObservers},
@@ -1848,6 +1886,7 @@ pub mod child_signal_handlers {
             old_hook(panic_info);
             let data = unsafe { &mut FORK_EXECUTOR_GLOBAL_DATA };
             if data.is_valid() {
+                CRASH_SIGNAL.store(libc::SIGABRT, Ordering::Relaxed);
                 let executor = data.executor_mut::<E>();
                 let observers = executor.observers_mut();
                 let state = data.state_mut::<E::State>();
@@ -1878,6 +1917,7 @@ pub mod child_signal_handlers {
         E: HasObservers,
     {
         if data.is_valid() {
+            CRASH_SIGNAL.store(_signal as i32, Ordering::Relaxed);
             let executor = data.executor_mut::<E>();
             let observers = executor.observers_mut();
             let state = data.state_mut::<E::State>();
@@ -29,6 +29,11 @@ pub use shadow::ShadowExecutor;
 pub mod with_observers;
 pub use with_observers::WithObservers;
 
+#[cfg(all(unix, feature = "std"))]
+pub mod afl_custom;
+#[cfg(all(unix, feature = "std"))]
+pub use afl_custom::AflCustomPostProcessExecutor;
+
 #[cfg(all(feature = "std", any(unix, doc)))]
 pub mod command;
 use core::{fmt::Debug, marker::PhantomData};
@@ -109,6 +114,12 @@ pub trait HasObservers: UsesObservers {
     fn observers_mut(&mut self) -> &mut Self::Observers;
 }
 
+/// An executor running the target in a child process, surviving its crashes and timeouts.
+///
+/// Those are reported as an [`ExitKind`] to the caller, while an [`InProcessExecutor`] handles them
+/// in its signal handlers, storing the input as an objective and restarting the fuzzer.
+pub trait SurvivesCrashes {}
+
 /// An executor takes the given inputs, and runs the harness/target.
 pub trait Executor<EM, Z>: UsesState + Debug
 where
@@ -3,7 +3,7 @@
 use core::fmt::{self, Debug, Formatter};
 
 use crate::{
-    executors::{Executor, ExitKind, HasObservers},
+    executors::{Executor, ExitKind, HasObservers, SurvivesCrashes},
     observers::{ObserversTuple, UsesObservers},
     state::UsesState,
     Error,
@@ -88,6 +88,8 @@ where
     type Observers = E::Observers;
 }
 
+impl<E, SOT> SurvivesCrashes for ShadowExecutor<E, SOT> where E: SurvivesCrashes {}
+
 impl<E, SOT> HasObservers for ShadowExecutor<E, SOT>
 where
     E: HasObservers,
@@ -3,7 +3,7 @@
 use core::fmt::Debug;
 
 use crate::{
-    executors::{Executor, ExitKind, HasObservers},
+    executors::{Executor, ExitKind, HasObservers, SurvivesCrashes},
     observers::{ObserversTuple, UsesObservers},
     state::UsesState,
     Error,
@@ -49,6 +49,8 @@ where
     type Observers = OT;
 }
 
+impl<E, OT> SurvivesCrashes for WithObservers<E, OT> where E: SurvivesCrashes {}
+
 impl<E, OT> HasObservers for WithObservers<E, OT>
 where
     E: HasObservers + Debug,
@@ -0,0 +1,224 @@
+//! The ``CrashBucketFeedback`` sorts objectives into buckets of the same bug,
+//! using the stacktrace hash of an observer, the exit kind, the signal and the sanitizer bug type.
+
+use alloc::string::{String, ToString};
+use core::hash::{BuildHasher, Hasher};
+use std::{fmt::Debug, marker::PhantomData};
+
+use ahash::RandomState;
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bolts::tuples::Named,
+    corpus::Testcase,
+    events::{Event, EventFirer},
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    monitors::UserStats,
+    observers::{ObserverWithHashField, ObserversTuple},
+    state::{HasClientPerfMonitor, HasMetadata, HasNamedMetadata},
+    Error,
+};
+
+/// The prefix of the metadata names
+pub const CRASHBUCKETFEEDBACK_PREFIX: &str = "crashbucketfeedback_metadata_";
+
+/// The bucket of an objective, objectives in the same bucket are likely the same bug.
+/// Added to the objective [`Testcase`] by the [`CrashBucketFeedback`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashBucket {
+    /// How the execution ended, e.g. [`ExitKind::Crash`] or [`ExitKind::Timeout`]
+    pub exit_kind: ExitKind,
+    /// The hash of the (top frames of the) stacktrace
+    pub hash: u64,
+    /// The type of bug reported by the sanitizer, such as `heap-buffer-overflow` or `SEGV`
+    pub bug_type: Option<String>,
+    /// The signal of the crash, if the observer knows it
+    pub signal: Option<i32>,
+}
+
+crate::impl_serdeany!(CrashBucket);
+
+impl CrashBucket {
+    /// A unique id for this bucket
+    #[must_use]
+    pub fn id(&self) -> u64 {
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        hasher.write(format!("{:?}", self.exit_kind).as_bytes());
+        hasher.write_u64(self.hash);
+        if let Some(bug_type) = &self.bug_type {
+            hasher.write(bug_type.as_bytes());
+        }
+        if let Some(signal) = self.signal {
+            hasher.write_i32(signal);
+        }
+        hasher.finish()
+    }
+}
+
+/// The number of objectives seen so far in each bucket, kept by the [`CrashBucketFeedback`]
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+pub struct CrashBucketsMetadata {
+    /// The number of objectives seen in each bucket, by [`CrashBucket::id`]
+    pub counts: HashMap<u64, u64>,
+}
+
+#[rustfmt::skip]
+crate::impl_serdeany!(CrashBucketsMetadata);
+
+impl CrashBucketsMetadata {
+    /// Create a new [`CrashBucketsMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of objectives seen in all buckets
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+}
+
+/// A [`CrashBucketFeedback`] assigns a [`CrashBucket`] to each objective, using the hash of an
+/// [`ObserverWithHashField`] such as the `BacktraceObserver` or the `AsanBacktraceObserver`.
+///
+/// Unlike the `NewHashFeedback`, it considers all crashes interesting: combine it with a `CrashFeedback`
+/// and use a [`crate::corpus::BucketedCorpus`] for the solutions, to keep a single testcase per bucket.
+/// The number of buckets and of objectives are reported to the monitors as `UserStats`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CrashBucketFeedback<O, S> {
+    name: String,
+    observer_name: String,
+    /// The bucket of the last execution
+    bucket: Option<CrashBucket>,
+    o_type: PhantomData<(O, S)>,
+}
+
+impl<O, S> Feedback<S> for CrashBucketFeedback<O, S>
+where
+    O: ObserverWithHashField + Named + Debug,
+    S: UsesInput + Debug + HasNamedMetadata + HasClientPerfMonitor,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(CrashBucketsMetadata::new(), &self.name);
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        manager: &mut EM,
+        _input: &<S as UsesInput>::Input,
+        observers: &OT,
+        exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<O>(&self.observer_name)
+            .expect("A CrashBucketFeedback needs a BacktraceObserver");
+
+        self.bucket = None;
+        if *exit_kind == ExitKind::Ok {
+            return Ok(false);
+        }
+        let Some(hash) = observer.hash() else {
+            return Ok(false);
+        };
+        let bucket = CrashBucket {
+            exit_kind: *exit_kind,
+            hash,
+            bug_type: observer.bug_type().map(ToString::to_string),
+            signal: observer.signal(),
+        };
+
+        let buckets = state
+            .named_metadata_mut()
+            .get_mut::<CrashBucketsMetadata>(&self.name)
+            .unwrap();
+        *buckets.counts.entry(bucket.id()).or_default() += 1;
+        let bucket_count = buckets.counts.len() as u64;
+        let total = buckets.total();
+        self.bucket = Some(bucket);
+
+        manager.fire(
+            state,
+            Event::UpdateUserStats {
+                name: "crash buckets".to_string(),
+                value: UserStats::Number(bucket_count),
+                phantom: PhantomData,
+            },
+        )?;
+        manager.fire(
+            state,
+            Event::UpdateUserStats {
+                name: "bucketed crashes".to_string(),
+                value: UserStats::Number(total),
+                phantom: PhantomData,
+            },
+        )?;
+        Ok(true)
+    }
+
+    fn append_metadata(
+        &mut self,
+        _state: &mut S,
+        testcase: &mut Testcase<<S as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        if let Some(bucket) = self.bucket.take() {
+            testcase.add_metadata(bucket);
+        }
+        Ok(())
+    }
+
+    fn discard_metadata(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.bucket = None;
+        Ok(())
+    }
+}
+
+impl<O, S> Named for CrashBucketFeedback<O, S> {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<O, S> HasObserverName for CrashBucketFeedback<O, S> {
+    #[inline]
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+impl<O, S> CrashBucketFeedback<O, S>
+where
+    O: ObserverWithHashField + Named + Debug,
+{
+    /// Returns a new [`CrashBucketFeedback`].
+    /// Setting an observer name that doesn't exist would eventually trigger a panic.
+    #[must_use]
+    pub fn with_names(name: &str, observer_name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            observer_name: observer_name.to_string(),
+            bucket: None,
+            o_type: PhantomData,
+        }
+    }
+
+    /// Returns a new [`CrashBucketFeedback`].
+    #[must_use]
+    pub fn new(observer: &O) -> Self {
+        Self::with_names(
+            &(CRASHBUCKETFEEDBACK_PREFIX.to_string() + observer.name()),
+            observer.name(),
+        )
+    }
+}
@@ -0,0 +1,179 @@
+//! The ``EntropicFeedback`` collects the feature frequencies of the entropic power schedule of `libFuzzer`,
+//! see [`crate::schedulers::entropic`].
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt::Debug, marker::PhantomData};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bolts::tuples::Named,
+    corpus::Corpus,
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{MapObserver, ObserversTuple},
+    schedulers::{
+        entropic::{
+            remove_rare_features, EntropicMetadata, EntropicTestcaseMetadata,
+            DEFAULT_FEATURE_FREQUENCY_THRESHOLD, DEFAULT_NUMBER_OF_RAREST_FEATURES,
+        },
+        weighted::WeightedScheduleMetadata,
+    },
+    state::{HasClientPerfMonitor, HasCorpus, HasMetadata},
+    Error,
+};
+
+/// The prefix of the name of the [`EntropicFeedback`]
+pub const ENTROPICFEEDBACK_PREFIX: &str = "entropicfeedback_";
+
+/// An [`EntropicFeedback`] counts the hits of each entry of a map observer, the features, and the hits
+/// of the rare features by the mutants of each testcase, for the [`crate::schedulers::testcase_score::EntropicTestcaseScore`].
+///
+/// It never considers an input interesting: combine it with a `MaxMapFeedback` on the same observer.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EntropicFeedback<O, S> {
+    name: String,
+    observer_name: String,
+    number_of_rarest: usize,
+    threshold: u16,
+    scale_per_exec_time: bool,
+    /// The rare features hit by the last execution, kept to avoid allocations
+    #[serde(skip)]
+    hit_rare_features: Vec<usize>,
+    phantom: PhantomData<(O, S)>,
+}
+
+impl<O, S> Feedback<S> for EntropicFeedback<O, S>
+where
+    O: MapObserver,
+    S: UsesInput + HasClientPerfMonitor + HasCorpus + HasMetadata + Debug,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_metadata(EntropicMetadata::new(
+            self.number_of_rarest,
+            self.threshold,
+            self.scale_per_exec_time,
+        ));
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &<S as UsesInput>::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<O>(&self.observer_name)
+            .expect("An EntropicFeedback needs a MapObserver");
+
+        let emeta = state
+            .metadata_mut()
+            .get_mut::<EntropicMetadata>()
+            .ok_or_else(|| Error::key_not_found("EntropicMetadata not found".to_string()))?;
+
+        self.hit_rare_features.clear();
+        let mut removed = vec![];
+        let initial = observer.initial();
+        for i in 0..observer.usable_count() {
+            if *observer.get(i) == initial {
+                continue;
+            }
+            if emeta.global_freq(i).is_none() {
+                removed.append(&mut emeta.add_rare_feature(i));
+            }
+            if emeta.update_feature_frequency(i) {
+                self.hit_rare_features.push(i);
+            }
+        }
+
+        remove_rare_features(state.corpus(), &removed)?;
+
+        // Count the hits of the mutants of the testcase being fuzzed
+        let changed = !removed.is_empty() || !self.hit_rare_features.is_empty();
+        if let Some(idx) = *state.corpus().current() {
+            let mut testcase = state.corpus().get(idx)?.borrow_mut();
+            if !testcase.has_metadata::<EntropicTestcaseMetadata>() {
+                testcase.add_metadata(EntropicTestcaseMetadata::new());
+            }
+            testcase
+                .metadata_mut()
+                .get_mut::<EntropicTestcaseMetadata>()
+                .unwrap()
+                .update(&self.hit_rare_features);
+        }
+
+        // The energies changed, the weights must be recomputed
+        if changed {
+            if let Some(wsmeta) = state.metadata_mut().get_mut::<WeightedScheduleMetadata>() {
+                wsmeta.invalidate();
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+impl<O, S> Named for EntropicFeedback<O, S> {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<O, S> HasObserverName for EntropicFeedback<O, S> {
+    #[inline]
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+impl<O, S> EntropicFeedback<O, S>
+where
+    O: MapObserver,
+{
+    /// Returns a new [`EntropicFeedback`], with the default parameters of `libFuzzer`
+    #[must_use]
+    pub fn new(observer: &O) -> Self {
+        Self::with_params(
+            observer,
+            DEFAULT_NUMBER_OF_RAREST_FEATURES,
+            DEFAULT_FEATURE_FREQUENCY_THRESHOLD,
+            false,
+        )
+    }
+
+    /// Returns a new [`EntropicFeedback`], keeping at least `number_of_rarest` rare features,
+    /// and all features hit less than `threshold` times, as `libFuzzer` with `-entropic_number_of_rarest_features`
+    /// and `-entropic_feature_frequency_threshold`.
+    /// With `scale_per_exec_time`, faster testcases get more energy, as `libFuzzer` with `-entropic_scale_per_exec_time`.
+    #[must_use]
+    pub fn with_params(
+        observer: &O,
+        number_of_rarest: usize,
+        threshold: u16,
+        scale_per_exec_time: bool,
+    ) -> Self {
+        Self {
+            name: ENTROPICFEEDBACK_PREFIX.to_string() + observer.name(),
+            observer_name: observer.name().to_string(),
+            number_of_rarest,
+            threshold,
+            scale_per_exec_time,
+            hit_rare_features: vec![],
+            phantom: PhantomData,
+        }
+    }
+}
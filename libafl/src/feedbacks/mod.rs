@@ -9,6 +9,9 @@ pub use map::*;
 
 pub mod differential;
 pub use differential::DiffFeedback;
+
+pub mod entropic;
+pub use entropic::EntropicFeedback;
 #[cfg(feature = "std")]
 pub mod concolic;
 #[cfg(feature = "std")]
@@ -21,6 +24,11 @@ pub use new_hash_feedback::NewHashFeedback;
 #[cfg(feature = "std")]
 pub use new_hash_feedback::NewHashFeedbackMetadata;
 
+#[cfg(feature = "std")]
+pub mod crash_bucket;
+#[cfg(feature = "std")]
+pub use crash_bucket::{CrashBucket, CrashBucketFeedback, CrashBucketsMetadata};
+
 #[cfg(feature = "nautilus")]
 pub mod nautilus;
 use alloc::string::{String, ToString};
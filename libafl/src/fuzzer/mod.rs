@@ -13,8 +13,11 @@ use crate::monitors::PerfFeature;
 use crate::state::NopState;
 use crate::{
     bolts::current_time,
-    corpus::{Corpus, CorpusId, Testcase},
-    events::{Event, EventConfig, EventFirer, EventProcessor, ProgressReporter},
+    corpus::{Corpus, CorpusId, LineageMetadata, Testcase},
+    events::{
+        Event, EventConfig, EventFirer, EventProcessor, ImportedTestcaseMetadata, ProgressReporter,
+        TestcaseMetadataSelection,
+    },
     executors::{Executor, ExitKind, HasObservers},
     feedbacks::Feedback,
     inputs::UsesInput,
@@ -147,6 +150,22 @@ where
         manager: &mut EM,
         input: <Self::State as UsesInput>::Input,
     ) -> Result<CorpusId, Error>;
+
+    /// Runs the input of the testcase and triggers observers and feedback.
+    /// Adds the testcase, with the metadata it already has, to the corpus even if it's not considered `interesting`.
+    /// Returns the `index` of the new testcase in the corpus.
+    ///
+    /// By default, only the input is added, with [`Evaluator::add_input`], and the metadata is dropped.
+    fn add_testcase(
+        &mut self,
+        state: &mut Self::State,
+        executor: &mut E,
+        manager: &mut EM,
+        mut testcase: Testcase<<Self::State as UsesInput>::Input>,
+    ) -> Result<CorpusId, Error> {
+        let input = testcase.load_input()?.clone();
+        self.add_input(state, executor, manager, input)
+    }
 }
 
 /// The main fuzzer trait.
@@ -327,7 +346,7 @@ where
     F: Feedback<CS::State>,
     OF: Feedback<CS::State>,
     OT: ObserversTuple<CS::State> + Serialize + DeserializeOwned,
-    CS::State: HasCorpus + HasSolutions + HasClientPerfMonitor + HasExecutions,
+    CS::State: HasCorpus + HasSolutions + HasClientPerfMonitor + HasExecutions + HasMetadata,
 {
     /// Evaluate if a set of observation channels has an interesting state
     fn process_execution<EM>(
@@ -384,7 +403,14 @@ where
 
                 // Add the input to the main corpus
                 let mut testcase = Testcase::with_executions(input.clone(), *state.executions());
+                testcase.add_metadata(LineageMetadata::from_state(state));
                 self.feedback_mut().append_metadata(state, &mut testcase)?;
+                ImportedTestcaseMetadata::attach(state, &mut testcase);
+                let lineage = testcase
+                    .metadata()
+                    .get::<LineageMetadata>()
+                    .cloned()
+                    .unwrap();
                 let idx = state.corpus_mut().add(testcase)?;
                 self.scheduler_mut().on_add(state, idx)?;
 
@@ -395,6 +421,7 @@ where
                     } else {
                         Some(manager.serialize_observers::<OT>(observers)?)
                     };
+                    let metadata = TestcaseMetadataSelection::metadata_to_send(state, idx)?;
                     manager.fire(
                         state,
                         Event::NewTestcase {
@@ -405,6 +432,8 @@ where
                             client_config: manager.configuration(),
                             time: current_time(),
                             executions: *state.executions(),
+                            lineage: Some(lineage.to_send(idx)),
+                            metadata,
                         },
                     )?;
                 }
@@ -414,18 +443,19 @@ where
                 // Not interesting
                 self.feedback_mut().discard_metadata(state, &input)?;
 
+                // Kept to pass it on to the monitor, see [`Event::objective_input`]
+                let objective_input = send_events.then(|| input.clone());
+
                 // The input is a solution, add it to the respective corpus
                 let mut testcase = Testcase::with_executions(input, *state.executions());
+                testcase.add_metadata(LineageMetadata::from_state(state));
                 self.objective_mut().append_metadata(state, &mut testcase)?;
                 state.solutions_mut().add(testcase)?;
 
-                if send_events {
-                    manager.fire(
-                        state,
-                        Event::Objective {
-                            objective_size: state.solutions().count(),
-                        },
-                    )?;
+                if let Some(input) = objective_input {
+                    let objective_size = state.solutions().count();
+                    manager.fire(state, Event::Objective { objective_size })?;
+                    manager.fire(state, Event::objective_input(objective_size, &input)?)?;
                 }
 
                 Ok((res, None))
@@ -440,7 +470,7 @@ where
     OT: ObserversTuple<CS::State> + Serialize + DeserializeOwned,
     F: Feedback<CS::State>,
     OF: Feedback<CS::State>,
-    CS::State: HasCorpus + HasSolutions + HasClientPerfMonitor + HasExecutions,
+    CS::State: HasCorpus + HasSolutions + HasClientPerfMonitor + HasExecutions + HasMetadata,
 {
     /// Process one input, adding to the respective corpora if needed and firing the right events
     #[inline]
@@ -470,7 +500,7 @@ where
     F: Feedback<CS::State>,
     OF: Feedback<CS::State>,
     OT: ObserversTuple<CS::State> + Serialize + DeserializeOwned,
-    CS::State: HasCorpus + HasSolutions + HasClientPerfMonitor + HasExecutions,
+    CS::State: HasCorpus + HasSolutions + HasClientPerfMonitor + HasExecutions + HasMetadata,
 {
     /// Process one input, adding to the respective corpora if needed and firing the right events
     #[inline]
@@ -493,6 +523,21 @@ where
         manager: &mut EM,
         input: <CS::State as UsesInput>::Input,
     ) -> Result<CorpusId, Error> {
+        self.add_testcase(state, executor, manager, Testcase::new(input))
+    }
+
+    /// Adds a testcase, with its metadata, even if it's not considered `interesting` by any of the executors
+    fn add_testcase(
+        &mut self,
+        state: &mut CS::State,
+        executor: &mut E,
+        manager: &mut EM,
+        mut testcase: Testcase<<CS::State as UsesInput>::Input>,
+    ) -> Result<CorpusId, Error> {
+        let input = testcase
+            .input()
+            .clone()
+            .ok_or_else(|| Error::empty_optional("The testcase to add has no input"))?;
         let exit_kind = self.execute_input(state, executor, manager, &input)?;
         let observers = executor.observers();
         // Always consider this to be "interesting"
@@ -500,8 +545,16 @@ where
         // Not a solution
         self.objective_mut().discard_metadata(state, &input)?;
 
-        // Add the input to the main corpus
-        let mut testcase = Testcase::with_executions(input.clone(), *state.executions());
+        // Add the input to the main corpus, it was not derived from any testcase, unless its lineage tells otherwise
+        *testcase.executions_mut() = *state.executions();
+        if testcase.metadata().get::<LineageMetadata>().is_none() {
+            testcase.add_metadata(LineageMetadata::new());
+        }
+        let lineage = testcase
+            .metadata()
+            .get::<LineageMetadata>()
+            .cloned()
+            .unwrap();
         self.feedback_mut().append_metadata(state, &mut testcase)?;
         let idx = state.corpus_mut().add(testcase)?;
         self.scheduler_mut().on_add(state, idx)?;
@@ -511,6 +564,7 @@ where
         } else {
             Some(manager.serialize_observers::<OT>(observers)?)
         };
+        let metadata = TestcaseMetadataSelection::metadata_to_send(state, idx)?;
         manager.fire(
             state,
             Event::NewTestcase {
@@ -521,6 +575,8 @@ where
                 client_config: manager.configuration(),
                 time: current_time(),
                 executions: *state.executions(),
+                lineage: Some(lineage.to_send(idx)),
+                metadata,
             },
         )?;
         Ok(idx)
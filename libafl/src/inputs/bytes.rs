@@ -14,10 +14,11 @@ use ahash::RandomState;
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "std")]
-use crate::{bolts::fs::write_file_atomic, Error};
+use crate::bolts::fs::write_file_atomic;
 use crate::{
     bolts::{ownedref::OwnedSlice, HasLen},
     inputs::{HasBytesVec, HasTargetBytes, Input},
+    Error,
 };
 
 /// A bytes input is the basic input
@@ -49,6 +50,11 @@ impl Input for BytesInput {
         Ok(BytesInput::new(bytes))
     }
 
+    /// The raw bytes of this input
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.bytes.clone())
+    }
+
     /// Generate a name for this input
     fn generate_name(&self, _idx: usize) -> String {
         let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
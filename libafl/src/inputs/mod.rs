@@ -12,6 +12,9 @@ pub use gramatron::*;
 pub mod generalized;
 pub use generalized::*;
 
+pub mod multi;
+pub use multi::MultipartInput;
+
 #[cfg(feature = "nautilus")]
 pub mod nautilus;
 use alloc::{
@@ -44,6 +47,11 @@ pub trait Input: Clone + Serialize + serde::de::DeserializeOwned + Debug {
         Err(Error::not_implemented("Not supprted in no_std"))
     }
 
+    /// The bytes of this input, serialized
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(postcard::to_allocvec(self)?)
+    }
+
     /// Generate a name for this input
     fn generate_name(&self, idx: usize) -> String;
 
@@ -73,6 +81,11 @@ pub trait Input: Clone + Serialize + serde::de::DeserializeOwned + Debug {
         Ok(postcard::from_bytes(&bytes)?)
     }
 
+    /// The bytes of this input, as [`Input::to_file`] writes them to a file
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(postcard::to_allocvec(self)?)
+    }
+
     /// Generate a name for this input
     fn generate_name(&self, idx: usize) -> String;
 
@@ -0,0 +1,178 @@
+//! The `MultipartInput` is an input made of several named parts, each one of an inner input type.
+//! It is useful for targets taking multiple independent buffers, e.g. a config and a payload.
+
+use alloc::{string::String, vec::Vec};
+use core::hash::{BuildHasher, Hasher};
+
+use ahash::RandomState;
+use serde::{Deserialize, Serialize};
+
+use crate::inputs::Input;
+
+/// An input composed of multiple parts, each one with a name.
+/// Names do not need to be unique, e.g. for a list of files.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MultipartInput<I> {
+    parts: Vec<I>,
+    names: Vec<String>,
+}
+
+impl<I> MultipartInput<I> {
+    /// Create a new empty [`MultipartInput`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            parts: Vec::new(),
+            names: Vec::new(),
+        }
+    }
+
+    /// Create a new [`MultipartInput`] from the given `(name, part)` pairs
+    pub fn with_parts<N, It>(parts: It) -> Self
+    where
+        N: Into<String>,
+        It: IntoIterator<Item = (N, I)>,
+    {
+        let mut input = Self::new();
+        for (name, part) in parts {
+            input.add_part(name, part);
+        }
+        input
+    }
+
+    /// Add a part with the given name at the end of this input
+    pub fn add_part<N>(&mut self, name: N, part: I)
+    where
+        N: Into<String>,
+    {
+        self.names.push(name.into());
+        self.parts.push(part);
+    }
+
+    /// Remove the part at the given index, returning its name and content
+    pub fn remove_part(&mut self, idx: usize) -> Option<(String, I)> {
+        if idx < self.parts.len() {
+            Some((self.names.remove(idx), self.parts.remove(idx)))
+        } else {
+            None
+        }
+    }
+
+    /// The parts of this input
+    #[must_use]
+    pub fn parts(&self) -> &[I] {
+        &self.parts
+    }
+
+    /// The parts of this input (mutable)
+    #[must_use]
+    pub fn parts_mut(&mut self) -> &mut [I] {
+        &mut self.parts
+    }
+
+    /// The names of the parts, in the same order as the parts
+    #[must_use]
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Get the part at the given index, if any
+    #[must_use]
+    pub fn part(&self, idx: usize) -> Option<&I> {
+        self.parts.get(idx)
+    }
+
+    /// Get the part at the given index, if any (mutable)
+    #[must_use]
+    pub fn part_mut(&mut self, idx: usize) -> Option<&mut I> {
+        self.parts.get_mut(idx)
+    }
+
+    /// Get the indexes of the parts with the given name
+    pub fn part_indexes_by_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.names
+            .iter()
+            .enumerate()
+            .filter(move |(_, n)| *n == name)
+            .map(|(idx, _)| idx)
+    }
+
+    /// Get the parts with the given name
+    pub fn parts_by_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a I> + 'a {
+        self.part_indexes_by_name(name)
+            .map(move |idx| &self.parts[idx])
+    }
+
+    /// Iterate over the `(name, part)` pairs of this input
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &I)> {
+        self.names.iter().map(String::as_str).zip(self.parts.iter())
+    }
+
+    /// The number of parts
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.parts.len()
+    }
+
+    /// Returns `true` if this input has no parts
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+}
+
+impl<I> Input for MultipartInput<I>
+where
+    I: Input,
+{
+    /// Generate a name for this input, from the names of its parts
+    fn generate_name(&self, idx: usize) -> String {
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        for (name, part) in self.iter() {
+            hasher.write(name.as_bytes());
+            hasher.write(part.generate_name(idx).as_bytes());
+        }
+        format!("{:016x}", hasher.finish())
+    }
+
+    fn wrapped_as_testcase(&mut self) {
+        for part in &mut self.parts {
+            part.wrapped_as_testcase();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::MultipartInput;
+    use crate::inputs::{BytesInput, Input};
+
+    #[test]
+    fn test_multipart_input() {
+        let mut input = MultipartInput::with_parts([
+            ("config", BytesInput::new(b"verbose=1".to_vec())),
+            ("file", BytesInput::new(b"AAAA".to_vec())),
+        ]);
+        input.add_part("file", BytesInput::new(b"BBBB".to_vec()));
+
+        assert_eq!(input.len(), 3);
+        assert_eq!(
+            input.part_indexes_by_name("file").collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(input.parts_by_name("config").count(), 1);
+
+        let bytes = postcard::to_allocvec(&input).unwrap();
+        let loaded: MultipartInput<BytesInput> = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(input, loaded);
+        assert_eq!(input.generate_name(0), loaded.generate_name(0));
+
+        assert_eq!(
+            input.remove_part(0),
+            Some(("config".into(), BytesInput::new(b"verbose=1".to_vec())))
+        );
+        assert_eq!(input.names(), ["file", "file"]);
+    }
+}
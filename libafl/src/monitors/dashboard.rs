@@ -0,0 +1,504 @@
+//! A monitor serving a self-contained HTML dashboard, and a JSON API, over HTTP from the broker.
+//!
+//! Unlike the `PrometheusMonitor`, it needs nothing but a browser pointed at the broker.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::time::Duration;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, RwLock},
+    thread,
+};
+
+use futures::executor::block_on;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tide::{http::mime, Body, Request, Response, StatusCode};
+
+#[cfg(feature = "introspection")]
+use crate::monitors::{ClientPerfMonitor, PerfFeature};
+use crate::{
+    bolts::current_time,
+    events::{ClientCommand, LogSeverity},
+    monitors::{ClientInput, ClientStats, Monitor, NopMonitor, UserStats},
+};
+
+/// The dashboard page, polling the JSON API
+const DASHBOARD_HTML: &str = include_str!("dashboard.html");
+
+/// The minimum interval between two updates of the stats served
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The number of points of the history, before its resolution is halved
+const MAX_HISTORY_POINTS: usize = 2048;
+
+/// The number of objectives kept for download, older ones are dropped
+const MAX_OBJECTIVES: usize = 1024;
+
+/// A point in the history of the global stats
+#[derive(Debug, Clone, Copy, Serialize)]
+struct HistoryPoint {
+    /// Seconds since the start of the campaign
+    run_time: u64,
+    corpus: u64,
+    objectives: u64,
+    executions: u64,
+    exec_sec: f64,
+    /// The coverage in percent, if the clients report the coverage stat
+    coverage: Option<f64>,
+}
+
+/// What the dashboard server shows, updated by the [`DashboardMonitor`]
+#[derive(Debug, Default)]
+struct DashboardContext {
+    stats: Value,
+    history: Vec<HistoryPoint>,
+    objectives: VecDeque<(usize, ClientInput)>,
+    next_objective_id: usize,
+}
+
+impl DashboardContext {
+    fn add_objective(&mut self, input: ClientInput) {
+        if self.objectives.len() >= MAX_OBJECTIVES {
+            self.objectives.pop_front();
+        }
+        self.objectives.push_back((self.next_objective_id, input));
+        self.next_objective_id += 1;
+    }
+
+    fn objectives_json(&self) -> Value {
+        self.objectives
+            .iter()
+            .map(|(id, input)| {
+                json!({
+                    "id": id,
+                    "client": input.client_id,
+                    "idx": input.idx,
+                    "time": input.time.as_secs(),
+                    "name": input.name,
+                    "size": input.bytes.len(),
+                    "url": format!("/api/objectives/{id}"),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Wraps a base monitor and serves a self-contained HTML dashboard of the campaign over HTTP,
+/// for fuzzing boxes without a Prometheus and Grafana stack, and the data behind it as JSON:
+///
+/// - `/api/stats`: the global stats, and the stats of each client, including its user stats,
+///   and, with the `introspection` feature, where it spends its time
+/// - `/api/history`: the global corpus size, objectives, executions, speed, and coverage over time
+/// - `/api/objectives`: the latest objectives, each downloadable from `/api/objectives/<id>`
+///
+/// The coverage is read from the ratio stat of the map feedback, `edges` by default.
+/// The history is sampled every 10 seconds by default, at a lower rate the longer the campaign runs.
+#[derive(Debug, Clone)]
+pub struct DashboardMonitor<M>
+where
+    M: Monitor,
+{
+    base: M,
+    context: Arc<RwLock<DashboardContext>>,
+    history_interval: Duration,
+    coverage_stat: String,
+    last_update: Duration,
+    last_sample: Duration,
+}
+
+impl<M> Monitor for DashboardMonitor<M>
+where
+    M: Monitor,
+{
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        self.base.client_stats_mut()
+    }
+
+    fn client_stats(&self) -> &[ClientStats] {
+        self.base.client_stats()
+    }
+
+    fn start_time(&mut self) -> Duration {
+        self.base.start_time()
+    }
+
+    fn display(&mut self, event_msg: String, sender_id: u32) {
+        let cur_time = current_time();
+        if cur_time.saturating_sub(self.last_update) >= STATS_INTERVAL {
+            self.last_update = cur_time;
+            self.update_stats(cur_time);
+        }
+
+        self.base.display(event_msg, sender_id);
+    }
+
+    /// The dashboard keeps the objectives for download
+    fn wants_inputs(&self) -> bool {
+        true
+    }
+
+    fn add_input(&mut self, input: ClientInput) {
+        if input.objective {
+            self.context.write().unwrap().add_objective(input.clone());
+        }
+        self.base.add_input(input);
+    }
+
+    fn add_log(&mut self, sender_id: u32, severity_level: LogSeverity, message: &str) {
+        self.base.add_log(sender_id, severity_level, message);
+    }
+
+    fn issues_client_commands(&self) -> bool {
+        self.base.issues_client_commands()
+    }
+
+    fn take_client_commands(&mut self) -> Vec<(u32, ClientCommand)> {
+        self.base.take_client_commands()
+    }
+}
+
+/// The value and total of a ratio stat
+fn ratio_stat(stat: Option<&UserStats>) -> Option<(u64, u64)> {
+    match stat {
+        Some(UserStats::Ratio(value, total)) if *total > 0 => Some((*value, *total)),
+        _ => None,
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn coverage_percent((covered, total): (u64, u64)) -> f64 {
+    covered as f64 * 100.0 / total as f64
+}
+
+fn coverage_json(coverage: Option<(u64, u64)>) -> Value {
+    coverage.map_or(Value::Null, |(covered, total)| {
+        json!({
+            "covered": covered,
+            "total": total,
+            "percent": coverage_percent((covered, total)),
+        })
+    })
+}
+
+/// The share of the time of a client spent in the scheduler, the manager, each stage, and each feedback
+#[cfg(feature = "introspection")]
+#[allow(clippy::cast_precision_loss)]
+fn introspection_json(perf: &ClientPerfMonitor) -> Value {
+    let elapsed = perf.elapsed_cycles() as f64;
+    if elapsed == 0.0 {
+        return Value::Null;
+    }
+    let scheduler = perf.scheduler_cycles() as f64 / elapsed;
+    let manager = perf.manager_cycles() as f64 / elapsed;
+    let mut unmeasured = 1.0 - scheduler - manager;
+
+    let mut stages = vec![];
+    for (stage_index, features) in perf.used_stages() {
+        let mut stage = Map::new();
+        for (feature_index, feature) in features.iter().enumerate() {
+            if *feature == 0 {
+                continue;
+            }
+            let share = *feature as f64 / elapsed;
+            unmeasured -= share;
+            let feature: PerfFeature = feature_index.into();
+            stage.insert(format!("{feature:?}"), json!(share));
+        }
+        stages.push(json!({ "stage": stage_index, "features": stage }));
+    }
+
+    let mut feedbacks = Map::new();
+    for (name, time) in perf.feedbacks() {
+        if *time == 0 {
+            continue;
+        }
+        let share = *time as f64 / elapsed;
+        unmeasured -= share;
+        feedbacks.insert(name.clone(), json!(share));
+    }
+
+    json!({
+        "scheduler": scheduler,
+        "manager": manager,
+        "stages": stages,
+        "feedbacks": feedbacks,
+        "unmeasured": unmeasured,
+    })
+}
+
+impl<M> DashboardMonitor<M>
+where
+    M: Monitor,
+{
+    /// Create a new [`DashboardMonitor`], serving the dashboard on `listener`, such as `127.0.0.1:8080`
+    #[must_use]
+    pub fn new(listener: String, base: M) -> Self {
+        let context = Arc::new(RwLock::new(DashboardContext::default()));
+        let context_clone = context.clone();
+        thread::spawn(move || {
+            if let Err(e) = block_on(serve_dashboard(listener, context_clone)) {
+                log::error!("The dashboard server failed: {e}");
+            }
+        });
+        Self {
+            base,
+            context,
+            history_interval: Duration::from_secs(10),
+            coverage_stat: "edges".into(),
+            last_update: Duration::ZERO,
+            last_sample: Duration::ZERO,
+        }
+    }
+
+    /// Sets the initial interval between two points of the history
+    #[must_use]
+    pub fn with_history_interval(mut self, history_interval: Duration) -> Self {
+        self.history_interval = history_interval;
+        self
+    }
+
+    /// Sets the name of the user stat holding the coverage map ratio, `edges` by default
+    #[must_use]
+    pub fn with_coverage_stat<S>(mut self, coverage_stat: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.coverage_stat = coverage_stat.into();
+        self
+    }
+
+    /// Updates the stats served, and the history if it is time for a new point
+    fn update_stats(&mut self, cur_time: Duration) {
+        let start_time = self.base.start_time();
+        let run_time = cur_time.saturating_sub(start_time);
+
+        let mut clients = vec![];
+        let mut exec_sec = 0.0;
+        // The clients share their finds, so the best client tells the coverage of the campaign
+        let mut coverage: Option<(u64, u64)> = None;
+        for (client_id, client) in self.base.client_stats_mut().iter_mut().enumerate() {
+            // The broker, and clients that did not start yet
+            if client.executions == 0 {
+                continue;
+            }
+            let client_coverage = ratio_stat(client.user_monitor.get(&self.coverage_stat));
+            if let Some((covered, total)) = client_coverage {
+                if !matches!(coverage, Some((best, _)) if best >= covered) {
+                    coverage = Some((covered, total));
+                }
+            }
+            let client_exec_sec = client.execs_per_sec(cur_time);
+            exec_sec += client_exec_sec;
+
+            let user_stats = client
+                .user_monitor
+                .iter()
+                .map(|(key, value)| (key.clone(), Value::String(value.to_string())))
+                .collect::<Map<_, _>>();
+            #[allow(unused_mut)]
+            let mut client_json = json!({
+                "id": client_id,
+                "corpus": client.corpus_size,
+                "objectives": client.objective_size,
+                "executions": client.executions,
+                "exec_sec": client_exec_sec,
+                "coverage": coverage_json(client_coverage),
+                "user_stats": user_stats,
+            });
+            #[cfg(feature = "introspection")]
+            {
+                client_json["introspection"] = introspection_json(&client.introspection_monitor);
+            }
+            clients.push(client_json);
+        }
+
+        let point = HistoryPoint {
+            run_time: run_time.as_secs(),
+            corpus: self.base.corpus_size(),
+            objectives: self.base.objective_size(),
+            executions: self.base.client_stats().iter().map(|c| c.executions).sum(),
+            exec_sec,
+            coverage: coverage.map(coverage_percent),
+        };
+        let stats = json!({
+            "start_time": start_time.as_secs(),
+            "run_time": point.run_time,
+            "clients": clients.len(),
+            "corpus": point.corpus,
+            "objectives": point.objectives,
+            "executions": point.executions,
+            "exec_sec": exec_sec,
+            "coverage": coverage_json(coverage),
+            "introspection": cfg!(feature = "introspection"),
+            "client_stats": clients,
+        });
+
+        let mut context = self.context.write().unwrap();
+        context.stats = stats;
+        if cur_time.saturating_sub(self.last_sample) >= self.history_interval {
+            self.last_sample = cur_time;
+            context.history.push(point);
+            if context.history.len() >= MAX_HISTORY_POINTS {
+                // Keep every other point, and sample at half the rate from now on
+                let mut keep = false;
+                context.history.retain(|_| {
+                    keep = !keep;
+                    keep
+                });
+                self.history_interval *= 2;
+            }
+        }
+    }
+}
+
+impl DashboardMonitor<NopMonitor> {
+    /// Create a new [`DashboardMonitor`] without a base
+    #[must_use]
+    pub fn nop(listener: String) -> Self {
+        Self::new(listener, NopMonitor::new())
+    }
+}
+
+type DashboardState = Arc<RwLock<DashboardContext>>;
+
+fn json_response(value: &Value) -> tide::Result {
+    Ok(Response::builder(StatusCode::Ok)
+        .body(Body::from_json(value)?)
+        .build())
+}
+
+/// Serves the dashboard page and the JSON API
+async fn serve_dashboard(listener: String, context: DashboardState) -> Result<(), std::io::Error> {
+    let mut app = tide::with_state(context);
+
+    app.at("/").get(|_| async {
+        Ok(Response::builder(StatusCode::Ok)
+            .body(DASHBOARD_HTML)
+            .content_type(mime::HTML)
+            .build())
+    });
+    app.at("/api/stats")
+        .get(|req: Request<DashboardState>| async move {
+            let stats = req.state().read().unwrap().stats.clone();
+            json_response(&stats)
+        });
+    app.at("/api/history")
+        .get(|req: Request<DashboardState>| async move {
+            let history = json!(req.state().read().unwrap().history);
+            json_response(&history)
+        });
+    app.at("/api/objectives")
+        .get(|req: Request<DashboardState>| async move {
+            let objectives = req.state().read().unwrap().objectives_json();
+            json_response(&objectives)
+        });
+    app.at("/api/objectives/:id")
+        .get(|req: Request<DashboardState>| async move {
+            let Some(id) = req.param("id").ok().and_then(|id| id.parse::<usize>().ok()) else {
+                return Ok(Response::new(StatusCode::NotFound));
+            };
+            let context = req.state().read().unwrap();
+            let Some((_, input)) = context.objectives.iter().find(|(other, _)| *other == id) else {
+                return Ok(Response::new(StatusCode::NotFound));
+            };
+            // Names are generated from the input, keep them safe for the header
+            let filename: String = input
+                .name
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || "._-".contains(c) {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            Ok(Response::builder(StatusCode::Ok)
+                .body(input.bytes.clone())
+                .content_type(mime::BYTE_STREAM)
+                .header(
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{filename}\""),
+                )
+                .build())
+        });
+
+    app.listen(listener).await
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+        thread,
+        time::Duration,
+    };
+
+    use super::DashboardMonitor;
+    use crate::{
+        bolts::current_time,
+        monitors::{ClientInput, Monitor, UserStats},
+    };
+
+    const LISTENER: &str = "127.0.0.1:28915";
+
+    fn get(path: &str) -> Option<String> {
+        let mut stream = TcpStream::connect(LISTENER).ok()?;
+        write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nHost: {LISTENER}\r\nConnection: close\r\n\r\n"
+        )
+        .ok()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+        Some(response)
+    }
+
+    #[test]
+    fn test_dashboard_monitor() {
+        let mut monitor = DashboardMonitor::nop(LISTENER.into());
+        let client = monitor.client_stats_mut_for(1);
+        client.update_executions(100, current_time());
+        client.update_corpus_size(10);
+        client.update_user_stats("edges".into(), UserStats::Ratio(5, 10));
+        monitor.add_input(ClientInput {
+            client_id: 1,
+            objective: true,
+            idx: Some(0),
+            time: current_time(),
+            name: "crash \"1\"".into(),
+            bytes: b"boom".to_vec(),
+        });
+        monitor.display("Test".into(), 1);
+
+        // The server starts in the background
+        let stats = (0..50)
+            .find_map(|_| {
+                get("/api/stats").or_else(|| {
+                    thread::sleep(Duration::from_millis(100));
+                    None
+                })
+            })
+            .expect("The dashboard server did not start");
+        assert!(stats.contains("\"corpus\":10"));
+        assert!(stats.contains("\"percent\":50.0"));
+
+        assert!(get("/").unwrap().contains("LibAFL Dashboard"));
+        assert!(get("/api/history").unwrap().contains("\"corpus\":10"));
+        assert!(get("/api/objectives")
+            .unwrap()
+            .contains("\"url\":\"/api/objectives/0\""));
+        let download = get("/api/objectives/0").unwrap();
+        assert!(download.contains("filename=\"crash__1_\""));
+        assert!(download.ends_with("boom"));
+        assert!(get("/api/objectives/1")
+            .unwrap()
+            .starts_with("HTTP/1.1 404"));
+    }
+}
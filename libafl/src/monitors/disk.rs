@@ -1,18 +1,22 @@
 //! Monitors that wrap a base one and log on disk
 
 use alloc::{string::String, vec::Vec};
-use core::time::Duration;
+use core::{fmt::Write as _, time::Duration};
 use std::{
-    fs::{File, OpenOptions},
+    env,
+    fs::{self, File, OpenOptions},
     io::Write,
     path::PathBuf,
+    process,
 };
 
 use serde_json::json;
 
 use crate::{
-    bolts::{current_time, format_duration_hms},
-    monitors::{ClientStats, Monitor, NopMonitor},
+    bolts::{current_time, format_duration_hms, fs::write_file_atomic},
+    events::{ClientCommand, LogSeverity},
+    monitors::{ClientInput, ClientStats, Monitor, NopMonitor, UserStats},
+    Error,
 };
 
 /// Wrap a monitor and log the current state of the monitor into a TOML file.
@@ -109,6 +113,26 @@ exec_sec = {}
 
         self.base.display(event_msg, sender_id);
     }
+
+    fn wants_inputs(&self) -> bool {
+        self.base.wants_inputs()
+    }
+
+    fn add_input(&mut self, input: ClientInput) {
+        self.base.add_input(input);
+    }
+
+    fn add_log(&mut self, sender_id: u32, severity_level: LogSeverity, message: &str) {
+        self.base.add_log(sender_id, severity_level, message);
+    }
+
+    fn issues_client_commands(&self) -> bool {
+        self.base.issues_client_commands()
+    }
+
+    fn take_client_commands(&mut self) -> Vec<(u32, ClientCommand)> {
+        self.base.take_client_commands()
+    }
 }
 
 impl<M> OnDiskTOMLMonitor<M>
@@ -211,4 +235,358 @@ where
         }
         self.base.display(event_msg, sender_id);
     }
+
+    fn wants_inputs(&self) -> bool {
+        self.base.wants_inputs()
+    }
+
+    fn add_input(&mut self, input: ClientInput) {
+        self.base.add_input(input);
+    }
+
+    fn add_log(&mut self, sender_id: u32, severity_level: LogSeverity, message: &str) {
+        self.base.add_log(sender_id, severity_level, message);
+    }
+
+    fn issues_client_commands(&self) -> bool {
+        self.base.issues_client_commands()
+    }
+
+    fn take_client_commands(&mut self) -> Vec<(u32, ClientCommand)> {
+        self.base.take_client_commands()
+    }
+}
+
+/// The header of AFL's `plot_data` files
+const AFL_PLOT_DATA_HEADER: &str = "# relative_time, cycles_done, cur_item, corpus_count, pending_total, pending_favs, map_size, saved_crashes, saved_hangs, max_depth, execs_per_sec, total_execs, edges_found";
+
+/// What the [`AflStatsMonitor`] remembers about each client, between two updates
+#[derive(Debug, Clone, Copy, Default)]
+struct AflClientTracker {
+    corpus_size: u64,
+    objective_size: u64,
+    last_find: Duration,
+    last_crash: Duration,
+    execs_at_last_crash: u64,
+}
+
+/// Wraps a base monitor and maintains AFL-compatible `fuzzer_stats` and `plot_data` files,
+/// in one directory per client, `client_<id>`, in the output directory,
+/// for tools such as `afl-whatsup` and `afl-plot`, and AFL-based statistics collectors.
+///
+/// The fields are computed from the [`ClientStats`] and the user stats of each client:
+/// `stability` from the `stability` stat of the calibration stage,
+/// and `bitmap_cvg`, `edges_found`, and `total_edges` from the coverage stat of the map feedback, `edges` by default.
+/// Fields without a `LibAFL` counterpart, such as `cycles_done` or `pending_total`, are `0`.
+/// All other user stats are appended to `fuzzer_stats`.
+#[derive(Debug, Clone)]
+pub struct AflStatsMonitor<M>
+where
+    M: Monitor,
+{
+    base: M,
+    out_dir: PathBuf,
+    interval: Duration,
+    coverage_stat: String,
+    banner: String,
+    command_line: String,
+    last_update: Duration,
+    trackers: Vec<AflClientTracker>,
+}
+
+impl<M> Monitor for AflStatsMonitor<M>
+where
+    M: Monitor,
+{
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        self.base.client_stats_mut()
+    }
+
+    fn client_stats(&self) -> &[ClientStats] {
+        self.base.client_stats()
+    }
+
+    fn start_time(&mut self) -> Duration {
+        self.base.start_time()
+    }
+
+    fn display(&mut self, event_msg: String, sender_id: u32) {
+        let cur_time = current_time();
+        self.track_finds(cur_time);
+
+        if cur_time.saturating_sub(self.last_update) >= self.interval {
+            self.last_update = cur_time;
+            if let Err(e) = self.write_stats(cur_time) {
+                log::error!("Failed to write the AFL stats: {e}");
+            }
+        }
+
+        self.base.display(event_msg, sender_id);
+    }
+
+    fn wants_inputs(&self) -> bool {
+        self.base.wants_inputs()
+    }
+
+    fn add_input(&mut self, input: ClientInput) {
+        self.base.add_input(input);
+    }
+
+    fn add_log(&mut self, sender_id: u32, severity_level: LogSeverity, message: &str) {
+        self.base.add_log(sender_id, severity_level, message);
+    }
+
+    fn issues_client_commands(&self) -> bool {
+        self.base.issues_client_commands()
+    }
+
+    fn take_client_commands(&mut self) -> Vec<(u32, ClientCommand)> {
+        self.base.take_client_commands()
+    }
+}
+
+/// The value and total of a ratio stat, and its percentage
+#[allow(clippy::cast_precision_loss)]
+fn ratio_stat(stat: Option<&UserStats>) -> (u64, u64, f64) {
+    match stat {
+        Some(UserStats::Ratio(value, total)) if *total > 0 => {
+            (*value, *total, *value as f64 * 100.0 / *total as f64)
+        }
+        _ => (0, 0, 0.0),
+    }
+}
+
+impl<M> AflStatsMonitor<M>
+where
+    M: Monitor,
+{
+    /// Create a new [`AflStatsMonitor`], writing to the client directories in `out_dir` every 5 seconds
+    #[must_use]
+    pub fn new<P>(out_dir: P, base: M) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            base,
+            out_dir: out_dir.into(),
+            interval: Duration::from_secs(5),
+            coverage_stat: "edges".into(),
+            banner: "libafl".into(),
+            command_line: env::args().collect::<Vec<_>>().join(" "),
+            last_update: current_time(),
+            trackers: vec![],
+        }
+    }
+
+    /// Sets the interval between two updates of the files
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the name of the user stat holding the coverage map ratio, `edges` by default
+    #[must_use]
+    pub fn with_coverage_stat<S>(mut self, coverage_stat: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.coverage_stat = coverage_stat.into();
+        self
+    }
+
+    /// Sets the `afl_banner`, usually the name of the target
+    #[must_use]
+    pub fn with_banner<S>(mut self, banner: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.banner = banner.into();
+        self
+    }
+
+    /// Remembers when the corpus or the objectives of each client last grew
+    fn track_finds(&mut self, cur_time: Duration) {
+        let client_stats = self.base.client_stats();
+        if self.trackers.len() < client_stats.len() {
+            self.trackers
+                .resize(client_stats.len(), AflClientTracker::default());
+        }
+        for (client, tracker) in client_stats.iter().zip(self.trackers.iter_mut()) {
+            if client.corpus_size > tracker.corpus_size {
+                tracker.corpus_size = client.corpus_size;
+                tracker.last_find = cur_time;
+            }
+            if client.objective_size > tracker.objective_size {
+                tracker.objective_size = client.objective_size;
+                tracker.last_crash = cur_time;
+                tracker.execs_at_last_crash = client.executions;
+            }
+        }
+    }
+
+    /// Writes `fuzzer_stats` and appends to `plot_data` of each client that reported executions
+    fn write_stats(&mut self, cur_time: Duration) -> Result<(), Error> {
+        let start_time = self.base.start_time();
+        let run_time = cur_time.saturating_sub(start_time);
+        // The monitor runs in the broker, which lives as long as the clients.
+        let pid = process::id();
+
+        for (client_id, client) in self.base.client_stats_mut().iter_mut().enumerate().skip(1) {
+            if client.executions == 0 {
+                continue;
+            }
+            let tracker = self.trackers[client_id];
+            let execs_per_sec = client.execs_per_sec(cur_time);
+            let (_, _, stability) = ratio_stat(client.user_monitor.get("stability"));
+            let (edges_found, total_edges, bitmap_cvg) =
+                ratio_stat(client.user_monitor.get(&self.coverage_stat));
+
+            let mut stats = String::new();
+            let mut stat = |key: &str, value: &dyn core::fmt::Display| {
+                writeln!(stats, "{key:<18}: {value}").unwrap();
+            };
+            stat("start_time", &start_time.as_secs());
+            stat("last_update", &cur_time.as_secs());
+            stat("run_time", &run_time.as_secs());
+            stat("fuzzer_pid", &pid);
+            stat("cycles_done", &0);
+            stat("cycles_wo_finds", &0);
+            stat("execs_done", &client.executions);
+            stat("execs_per_sec", &format!("{execs_per_sec:.2}"));
+            stat("corpus_count", &client.corpus_size);
+            stat("corpus_found", &client.corpus_size);
+            stat("pending_favs", &0);
+            stat("pending_total", &0);
+            stat("stability", &format!("{stability:.2}%"));
+            stat("bitmap_cvg", &format!("{bitmap_cvg:.2}%"));
+            stat("saved_crashes", &client.objective_size);
+            stat("saved_hangs", &0);
+            stat("last_find", &tracker.last_find.as_secs());
+            stat("last_crash", &tracker.last_crash.as_secs());
+            stat(
+                "execs_since_crash",
+                &client
+                    .executions
+                    .saturating_sub(tracker.execs_at_last_crash),
+            );
+            stat("edges_found", &edges_found);
+            stat("total_edges", &total_edges);
+            stat("afl_banner", &self.banner);
+            stat(
+                "afl_version",
+                &concat!("libafl-", env!("CARGO_PKG_VERSION")),
+            );
+            stat("target_mode", &"default");
+            stat("command_line", &self.command_line);
+
+            let mut user_stats = client
+                .user_monitor
+                .iter()
+                .filter(|(key, _)| *key != "stability" && **key != self.coverage_stat)
+                .collect::<Vec<_>>();
+            user_stats.sort_by_key(|(key, _)| *key);
+            for (key, value) in user_stats {
+                let key: String = key
+                    .chars()
+                    .map(|c| if c.is_whitespace() { '_' } else { c })
+                    .filter(|c| c.is_alphanumeric() || *c == '_')
+                    .collect();
+                stat(&key, value);
+            }
+
+            let client_dir = self.out_dir.join(format!("client_{client_id}"));
+            fs::create_dir_all(&client_dir)?;
+            // Readers, e.g. `afl-whatsup`, never see a partially written file
+            write_file_atomic(client_dir.join("fuzzer_stats"), stats.as_bytes())?;
+
+            let mut plot_data = OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(client_dir.join("plot_data"))?;
+            if plot_data.metadata()?.len() == 0 {
+                writeln!(plot_data, "{AFL_PLOT_DATA_HEADER}")?;
+            }
+            writeln!(
+                plot_data,
+                "{}, 0, 0, {}, 0, 0, {bitmap_cvg:.2}%, {}, 0, 0, {execs_per_sec:.2}, {}, {edges_found}",
+                run_time.as_secs(),
+                client.corpus_size,
+                client.objective_size,
+                client.executions,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl AflStatsMonitor<NopMonitor> {
+    /// Create a new [`AflStatsMonitor`] without a base
+    #[must_use]
+    pub fn nop<P>(out_dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self::new(out_dir, NopMonitor::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+    use std::{env::temp_dir, fs};
+
+    use super::AflStatsMonitor;
+    use crate::{
+        bolts::current_time,
+        monitors::{Monitor, UserStats},
+    };
+
+    #[test]
+    fn test_afl_stats_monitor() {
+        let out_dir = temp_dir().join(format!("libafl_test_afl_stats_{}", std::process::id()));
+        let mut monitor = AflStatsMonitor::nop(&out_dir).with_interval(Duration::ZERO);
+
+        let client = monitor.client_stats_mut_for(1);
+        client.update_executions(1000, current_time());
+        client.update_corpus_size(12);
+        client.update_objective_size(1);
+        client
+            .user_monitor
+            .insert("edges".into(), UserStats::Ratio(128, 256));
+        client
+            .user_monitor
+            .insert("stability".into(), UserStats::Ratio(99, 100));
+        client
+            .user_monitor
+            .insert("crash buckets".into(), UserStats::Number(1));
+        monitor.display("Testcase".into(), 1);
+        monitor.display("Testcase".into(), 1);
+
+        let stats = fs::read_to_string(out_dir.join("client_1").join("fuzzer_stats")).unwrap();
+        for line in [
+            "execs_done        : 1000",
+            "corpus_count      : 12",
+            "saved_crashes     : 1",
+            "bitmap_cvg        : 50.00%",
+            "stability         : 99.00%",
+            "edges_found       : 128",
+            "total_edges       : 256",
+            "crash_buckets     : 1",
+        ] {
+            assert!(
+                stats.lines().any(|l| l == line),
+                "{line} missing in {stats}"
+            );
+        }
+
+        let plot_data = fs::read_to_string(out_dir.join("client_1").join("plot_data")).unwrap();
+        let lines = plot_data.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("# relative_time"));
+        assert!(lines[1].ends_with(", 1000, 128"));
+
+        fs::remove_dir_all(out_dir).unwrap();
+    }
 }
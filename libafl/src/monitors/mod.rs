@@ -13,17 +13,26 @@ pub mod prometheus;
 #[cfg(all(feature = "prometheus_monitor", feature = "std"))]
 pub use prometheus::PrometheusMonitor;
 
+#[cfg(all(feature = "dashboard_monitor", feature = "std"))]
+pub mod dashboard;
+#[cfg(all(feature = "dashboard_monitor", feature = "std"))]
+pub use dashboard::DashboardMonitor;
+
 #[cfg(feature = "std")]
 pub mod disk;
 use alloc::{fmt::Debug, string::String, vec::Vec};
 use core::{fmt, fmt::Write, time::Duration};
 
 #[cfg(feature = "std")]
-pub use disk::{OnDiskJSONMonitor, OnDiskTOMLMonitor};
+pub use disk::{AflStatsMonitor, OnDiskJSONMonitor, OnDiskTOMLMonitor};
 use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
 
-use crate::bolts::{current_time, format_duration_hms};
+use crate::{
+    bolts::{current_time, format_duration_hms},
+    events::{ClientCommand, LogSeverity},
+    inputs::Input,
+};
 
 #[cfg(feature = "afl_exec_sec")]
 const CLIENT_STATS_TIME_WINDOW_SECS: u64 = 5; // 5 seconds
@@ -59,6 +68,79 @@ impl fmt::Display for UserStats {
     }
 }
 
+/// The input of a new testcase, or objective, a client found, see [`Monitor::add_input`]
+#[derive(Debug, Clone)]
+pub struct ClientInput {
+    /// The client that found the input
+    pub client_id: u32,
+    /// If the input is an objective, and not a new corpus entry
+    pub objective: bool,
+    /// The index of the input in the corpus, or the solutions, of the client, if known
+    pub idx: Option<usize>,
+    /// The time the input was reported
+    pub time: Duration,
+    /// The name of the input, see [`crate::inputs::Input::generate_name`]
+    pub name: String,
+    /// The bytes of the input, see [`crate::inputs::Input::to_bytes`]
+    pub bytes: Vec<u8>,
+}
+
+/// Passes the input a client reported on to the monitor, if it [`Monitor::wants_inputs`].
+/// Inputs are only shown to the user, so failing to serialize one is logged, not fatal.
+pub(crate) fn add_client_input<MT, I>(
+    monitor: &mut MT,
+    client_id: u32,
+    objective: bool,
+    idx: Option<usize>,
+    time: Duration,
+    input: &I,
+) where
+    MT: Monitor,
+    I: Input,
+{
+    if !monitor.wants_inputs() {
+        return;
+    }
+    let bytes = match input.to_bytes() {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::warn!("Could not pass an input of client {client_id} to the monitor: {e}");
+            return;
+        }
+    };
+    monitor.add_input(ClientInput {
+        client_id,
+        objective,
+        idx,
+        time,
+        name: input.generate_name(idx.unwrap_or_default()),
+        bytes,
+    });
+}
+
+/// Passes the input of an [`crate::events::OBJECTIVE_INPUT_TAG`] event on to the monitor,
+/// if it [`Monitor::wants_inputs`]
+pub(crate) fn add_objective_input<MT, I>(monitor: &mut MT, client_id: u32, buf: &[u8])
+where
+    MT: Monitor,
+    I: Input,
+{
+    if !monitor.wants_inputs() {
+        return;
+    }
+    match postcard::from_bytes::<(usize, I)>(buf) {
+        Ok((objective_size, input)) => add_client_input(
+            monitor,
+            client_id,
+            true,
+            Some(objective_size.saturating_sub(1)),
+            current_time(),
+            &input,
+        ),
+        Err(e) => log::warn!("Could not read an objective input of client {client_id}: {e}"),
+    }
+}
+
 /// Prettifies float values for human-readable output
 fn prettify_float(value: f64) -> String {
     let (value, suffix) = match value {
@@ -223,6 +305,32 @@ pub trait Monitor {
     /// Show the monitor to the user
     fn display(&mut self, event_msg: String, sender_id: u32);
 
+    /// If this monitor shows the inputs the clients find, to get them passed to [`Monitor::add_input`].
+    /// Keeping inputs costs the broker time and memory, so only monitors that show them ask for them.
+    fn wants_inputs(&self) -> bool {
+        false
+    }
+
+    /// Called with the input of each new testcase, and objective, reported by a client,
+    /// if this monitor [`Monitor::wants_inputs`].
+    fn add_input(&mut self, _input: ClientInput) {}
+
+    /// Called with each log message a client sends with [`crate::events::EventFirer::log`]
+    fn add_log(&mut self, _sender_id: u32, _severity_level: LogSeverity, _message: &str) {}
+
+    /// If the user can issue commands to clients in this monitor, see [`Monitor::take_client_commands`].
+    /// Only then the broker sets up the means to pass them on.
+    fn issues_client_commands(&self) -> bool {
+        false
+    }
+
+    /// Takes the commands to clients the user issued in this monitor since the last call,
+    /// for the broker to pass them on, see [`crate::events::CLIENT_COMMAND_TAG`].
+    /// Only interactive monitors issue commands.
+    fn take_client_commands(&mut self) -> Vec<(u32, ClientCommand)> {
+        Vec::new()
+    }
+
     /// Amount of elements in the corpus (combined for all children)
     fn corpus_size(&self) -> u64 {
         self.client_stats()
@@ -5,7 +5,7 @@ use std::{
     collections::VecDeque,
     fmt::Write,
     io::{self, BufRead},
-    panic,
+    mem, panic,
     string::String,
     sync::{Arc, RwLock},
     thread,
@@ -26,7 +26,8 @@ use tui::{backend::CrosstermBackend, Terminal};
 use super::{ClientPerfMonitor, PerfFeature};
 use crate::{
     bolts::{current_time, format_duration_hms},
-    monitors::{ClientStats, Monitor, UserStats},
+    events::{ClientCommand, LogSeverity},
+    monitors::{ClientInput, ClientStats, Monitor, UserStats},
 };
 
 mod ui;
@@ -34,6 +35,7 @@ use ui::TuiUI;
 
 const DEFAULT_TIME_WINDOW: u64 = 60 * 10; // 10 min
 const DEFAULT_LOGS_NUMBER: usize = 128;
+const DEFAULT_INPUTS_NUMBER: usize = 64;
 
 #[derive(Debug, Copy, Clone)]
 pub struct TimedStat {
@@ -173,6 +175,9 @@ pub struct ClientTuiContext {
     pub exec_sec: String,
 
     pub user_stats: HashMap<String, UserStats>,
+
+    /// If the user paused this client
+    pub paused: bool,
 }
 
 impl ClientTuiContext {
@@ -188,6 +193,14 @@ impl ClientTuiContext {
     }
 }
 
+/// A line of the log stream: a status update, or a log message of a client
+#[derive(Debug, Clone)]
+pub struct TuiLog {
+    /// The severity of a log message, `None` for status updates
+    pub severity: Option<LogSeverity>,
+    pub message: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct TuiContext {
     pub graphs: Vec<String>,
@@ -202,7 +215,15 @@ pub struct TuiContext {
 
     pub clients: HashMap<usize, ClientTuiContext>,
 
-    pub client_logs: VecDeque<String>,
+    pub client_logs: VecDeque<TuiLog>,
+
+    /// The most recent new corpus entries of all clients
+    pub testcases: VecDeque<ClientInput>,
+    /// The most recent objectives of all clients
+    pub objectives: VecDeque<ClientInput>,
+
+    /// The commands issued by the user, not yet sent to the clients
+    pub client_commands: Vec<(u32, ClientCommand)>,
 
     pub clients_num: usize,
     pub total_execs: u64,
@@ -224,15 +245,41 @@ impl TuiContext {
             clients: HashMap::default(),
 
             client_logs: VecDeque::with_capacity(DEFAULT_LOGS_NUMBER),
+            testcases: VecDeque::with_capacity(DEFAULT_INPUTS_NUMBER),
+            objectives: VecDeque::with_capacity(DEFAULT_INPUTS_NUMBER),
+            client_commands: vec![],
 
             clients_num: 0,
             total_execs: 0,
             start_time,
         }
     }
+
+    /// Adds a line to the log stream, dropping the oldest one if it is full
+    pub fn add_log(&mut self, severity: Option<LogSeverity>, message: String) {
+        while self.client_logs.len() >= DEFAULT_LOGS_NUMBER {
+            self.client_logs.pop_front();
+        }
+        self.client_logs.push_back(TuiLog { severity, message });
+    }
+}
+
+/// The head of a line of the log stream
+fn log_head(event_msg: &str, sender_id: u32) -> String {
+    let sender = format!("#{sender_id}");
+    let pad = if event_msg.len() + sender.len() < 13 {
+        " ".repeat(13 - event_msg.len() - sender.len())
+    } else {
+        String::new()
+    };
+    format!("{event_msg}{pad} {sender}")
 }
 
 /// Tracking monitor during fuzzing and display with tui-rs.
+///
+/// Select a client with the left/right arrows, show its details with `d`, and pause or resume it with `p`.
+/// `b` switches between the log stream, filtered by [`LogSeverity`] with `f`,
+/// and the recent corpus entries and objectives, with a hex view of the one selected with the up/down arrows.
 #[derive(Debug, Clone)]
 pub struct TuiMonitor {
     pub(crate) context: Arc<RwLock<TuiContext>>,
@@ -279,13 +326,7 @@ impl Monitor for TuiMonitor {
         let client = self.client_stats_mut_for(sender_id);
         let exec_sec = client.execs_per_sec_pretty(cur_time);
 
-        let sender = format!("#{sender_id}");
-        let pad = if event_msg.len() + sender.len() < 13 {
-            " ".repeat(13 - event_msg.len() - sender.len())
-        } else {
-            String::new()
-        };
-        let head = format!("{event_msg}{pad} {sender}");
+        let head = log_head(&event_msg, sender_id);
         let mut fmt = format!(
             "[{}] corpus: {}, objectives: {}, executions: {}, exec/sec: {}",
             head, client.corpus_size, client.objective_size, client.executions, exec_sec
@@ -301,10 +342,7 @@ impl Monitor for TuiMonitor {
                 .entry(sender_id as usize)
                 .or_default()
                 .grab_data(client, exec_sec);
-            while ctx.client_logs.len() >= DEFAULT_LOGS_NUMBER {
-                ctx.client_logs.pop_front();
-            }
-            ctx.client_logs.push_back(fmt);
+            ctx.add_log(None, fmt);
         }
 
         #[cfg(feature = "introspection")]
@@ -321,6 +359,39 @@ impl Monitor for TuiMonitor {
             }
         }
     }
+
+    fn wants_inputs(&self) -> bool {
+        true
+    }
+
+    fn add_input(&mut self, input: ClientInput) {
+        let mut ctx = self.context.write().unwrap();
+        let inputs = if input.objective {
+            &mut ctx.objectives
+        } else {
+            &mut ctx.testcases
+        };
+        while inputs.len() >= DEFAULT_INPUTS_NUMBER {
+            inputs.pop_front();
+        }
+        inputs.push_back(input);
+    }
+
+    fn add_log(&mut self, sender_id: u32, severity_level: LogSeverity, message: &str) {
+        let head = log_head(&format!("{severity_level}"), sender_id);
+        self.context
+            .write()
+            .unwrap()
+            .add_log(Some(severity_level), format!("[{head}] {message}"));
+    }
+
+    fn issues_client_commands(&self) -> bool {
+        true
+    }
+
+    fn take_client_commands(&mut self) -> Vec<(u32, ClientCommand)> {
+        mem::take(&mut self.context.write().unwrap().client_commands)
+    }
 }
 
 impl TuiMonitor {
@@ -396,11 +467,13 @@ fn run_tui_thread(
             if crossterm::event::poll(timeout)? {
                 if let Event::Key(key) = event::read()? {
                     match key.code {
-                        KeyCode::Char(c) => ui.on_key(c),
+                        KeyCode::Char(c) => ui.on_key(c, &context),
                         KeyCode::Left => ui.on_left(),
-                        //KeyCode::Up => ui.on_up(),
+                        KeyCode::Up => ui.on_up(),
                         KeyCode::Right => ui.on_right(),
-                        //KeyCode::Down => ui.on_down(),
+                        KeyCode::Down => ui.on_down(),
+                        KeyCode::PageUp => ui.on_page_up(),
+                        KeyCode::PageDown => ui.on_page_down(),
                         _ => {}
                     }
                 }
@@ -1,6 +1,7 @@
 use alloc::vec::Vec;
 use std::{
     cmp::{max, min},
+    fmt::Write,
     sync::{Arc, RwLock},
 };
 
@@ -11,14 +12,19 @@ use tui::{
     symbols,
     text::{Span, Spans},
     widgets::{
-        Axis, Block, Borders, Cell, Chart, Dataset, List, ListItem, Paragraph, Row, Table, Tabs,
+        Axis, Block, Borders, Cell, Chart, Dataset, List, ListItem, ListState, Paragraph, Row,
+        Table, Tabs,
     },
     Frame,
 };
 
-use super::{current_time, format_duration_hms, Duration, String, TimedStats, TuiContext};
+use super::{
+    current_time, format_duration_hms, ClientCommand, ClientInput, Duration, LogSeverity, String,
+    TimedStats, TuiContext, VecDeque,
+};
 
 #[derive(Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct TuiUI {
     title: String,
     enhanced_graphics: bool,
@@ -27,6 +33,16 @@ pub struct TuiUI {
     clients: usize,
     charts_tab_idx: usize,
     graph_data: Vec<(f64, f64)>,
+    /// Show the details of the selected client, instead of the overview
+    show_details: bool,
+    /// Show the logs, the recent corpus entries, or the recent objectives
+    bottom_tab_idx: usize,
+    /// Only show the log messages at least this severe, or all lines
+    log_filter: Option<LogSeverity>,
+    /// The selected input, starting from the most recent one
+    inputs_idx: usize,
+    /// The first line shown in the hex view of the selected input
+    hex_scroll: u16,
 
     pub should_quit: bool,
 }
@@ -42,7 +58,7 @@ impl TuiUI {
         }
     }
 
-    pub fn on_key(&mut self, c: char) {
+    pub fn on_key(&mut self, c: char, app: &Arc<RwLock<TuiContext>>) {
         match c {
             'q' => {
                 self.should_quit = true;
@@ -53,13 +69,63 @@ impl TuiUI {
             't' => {
                 self.show_logs = !self.show_logs;
             }
+            'd' => {
+                self.show_details = !self.show_details;
+            }
+            'b' => {
+                self.bottom_tab_idx = (self.bottom_tab_idx + 1) % 3;
+                self.inputs_idx = 0;
+                self.hex_scroll = 0;
+            }
+            'f' => {
+                self.log_filter = match self.log_filter {
+                    None => Some(LogSeverity::Debug),
+                    Some(LogSeverity::Debug) => Some(LogSeverity::Info),
+                    Some(LogSeverity::Info) => Some(LogSeverity::Warn),
+                    Some(LogSeverity::Warn) => Some(LogSeverity::Error),
+                    Some(LogSeverity::Error) => None,
+                };
+            }
+            'p' => {
+                self.toggle_pause(app);
+            }
             _ => {}
         }
     }
 
-    //pub fn on_up(&mut self) {}
+    /// Pauses the selected client, or resumes it if it is paused
+    fn toggle_pause(&mut self, app: &Arc<RwLock<TuiContext>>) {
+        let mut guard = app.write().unwrap();
+        let ctx = &mut *guard;
+        if let Some(client) = ctx.clients.get_mut(&self.clients_idx) {
+            client.paused = !client.paused;
+            let command = if client.paused {
+                ClientCommand::Pause
+            } else {
+                ClientCommand::Resume
+            };
+            ctx.client_commands.push((self.clients_idx as u32, command));
+        }
+    }
 
-    //pub fn on_down(&mut self) {}
+    pub fn on_up(&mut self) {
+        self.inputs_idx = self.inputs_idx.saturating_sub(1);
+        self.hex_scroll = 0;
+    }
+
+    pub fn on_down(&mut self) {
+        // Clamped to the number of inputs when drawing
+        self.inputs_idx += 1;
+        self.hex_scroll = 0;
+    }
+
+    pub fn on_page_up(&mut self) {
+        self.hex_scroll = self.hex_scroll.saturating_sub(8);
+    }
+
+    pub fn on_page_down(&mut self) {
+        self.hex_scroll = self.hex_scroll.saturating_add(8);
+    }
 
     pub fn on_right(&mut self) {
         if self.clients != 0 {
@@ -97,10 +163,29 @@ impl TuiUI {
             })
             .split(f.size());
 
+        if self.show_details {
+            self.draw_client_details(f, app, body[0]);
+        } else {
+            self.draw_overview(f, app, body[0]);
+        }
+
+        if self.show_logs {
+            match self.bottom_tab_idx {
+                1 => self.draw_inputs(f, app, body[1], false),
+                2 => self.draw_inputs(f, app, body[1], true),
+                _ => self.draw_logs(f, app, body[1]),
+            }
+        }
+    }
+
+    fn draw_overview<B>(&mut self, f: &mut Frame<B>, app: &Arc<RwLock<TuiContext>>, area: Rect)
+    where
+        B: Backend,
+    {
         let top_layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-            .split(body[0]);
+            .split(area);
 
         let left_layout = Layout::default()
             .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
@@ -185,10 +270,6 @@ impl TuiUI {
             }
             _ => {}
         }
-
-        if self.show_logs {
-            self.draw_logs(f, app, body[1]);
-        }
     }
 
     #[allow(clippy::too_many_lines, clippy::cast_precision_loss)]
@@ -376,9 +457,19 @@ impl TuiUI {
             .widths(&[Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)]);
         f.render_widget(table, chunks[0]);
 
+        let paused = app
+            .read()
+            .unwrap()
+            .clients
+            .get(&self.clients_idx)
+            .map_or(false, |client| client.paused);
         let client_block = Block::default()
             .title(Span::styled(
-                format!("client #{} (l/r arrows to switch)", self.clients_idx),
+                format!(
+                    "client #{}{} (l/r arrows to switch, `d` details)",
+                    self.clients_idx,
+                    if paused { " [paused]" } else { "" }
+                ),
                 Style::default()
                     .fg(Color::LightCyan)
                     .add_modifier(Modifier::BOLD),
@@ -387,34 +478,7 @@ impl TuiUI {
         let client_area = client_block.inner(chunks[1]);
         f.render_widget(client_block, chunks[1]);
 
-        let mut client_items = vec![];
-        {
-            let ctx = app.read().unwrap();
-            if let Some(client) = ctx.clients.get(&self.clients_idx) {
-                client_items.push(Row::new(vec![
-                    Cell::from(Span::raw("executions")),
-                    Cell::from(Span::raw(format!("{}", client.executions))),
-                ]));
-                client_items.push(Row::new(vec![
-                    Cell::from(Span::raw("exec/sec")),
-                    Cell::from(Span::raw(client.exec_sec.clone())),
-                ]));
-                client_items.push(Row::new(vec![
-                    Cell::from(Span::raw("corpus")),
-                    Cell::from(Span::raw(format!("{}", client.corpus))),
-                ]));
-                client_items.push(Row::new(vec![
-                    Cell::from(Span::raw("objectives")),
-                    Cell::from(Span::raw(format!("{}", client.objectives))),
-                ]));
-                for (key, val) in &client.user_stats {
-                    client_items.push(Row::new(vec![
-                        Cell::from(Span::raw(key.clone())),
-                        Cell::from(Span::raw(format!("{}", val.clone()))),
-                    ]));
-                }
-            };
-        }
+        let client_items = self.client_rows(&app.read().unwrap());
 
         #[cfg(feature = "introspection")]
         let client_chunks = Layout::default()
@@ -438,45 +502,167 @@ impl TuiUI {
 
         #[cfg(feature = "introspection")]
         {
-            let mut items = vec![];
-            {
-                let ctx = app.read().unwrap();
-                if let Some(client) = ctx.introspection.get(&self.clients_idx) {
-                    items.push(Row::new(vec![
-                        Cell::from(Span::raw("scheduler")),
-                        Cell::from(Span::raw(format!("{:.2}%", client.scheduler * 100.0))),
-                    ]));
-                    items.push(Row::new(vec![
-                        Cell::from(Span::raw("manager")),
-                        Cell::from(Span::raw(format!("{:.2}%", client.manager * 100.0))),
-                    ]));
-                    for i in 0..client.stages.len() {
-                        items.push(Row::new(vec![
-                            Cell::from(Span::raw(format!("stage {i}"))),
-                            Cell::from(Span::raw("")),
-                        ]));
-
-                        for (key, val) in &client.stages[i] {
-                            items.push(Row::new(vec![
-                                Cell::from(Span::raw(key.clone())),
-                                Cell::from(Span::raw(format!("{:.2}%", val * 100.0))),
-                            ]));
-                        }
-                    }
-                    for (key, val) in &client.feedbacks {
-                        items.push(Row::new(vec![
-                            Cell::from(Span::raw(key.clone())),
-                            Cell::from(Span::raw(format!("{:.2}%", val * 100.0))),
-                        ]));
-                    }
+            let items = self.introspection_rows(&app.read().unwrap());
+            let table = Table::new(items)
+                .block(
+                    Block::default()
+                        .title(Span::styled(
+                            "introspection",
+                            Style::default()
+                                .fg(Color::LightCyan)
+                                .add_modifier(Modifier::BOLD),
+                        ))
+                        .borders(Borders::ALL),
+                )
+                .widths(&[Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)]);
+            f.render_widget(table, client_chunks[1]);
+        }
+    }
+
+    /// The stats of the selected client
+    fn client_rows(&self, ctx: &TuiContext) -> Vec<Row<'static>> {
+        let mut client_items = vec![];
+        if let Some(client) = ctx.clients.get(&self.clients_idx) {
+            client_items.push(Row::new(vec![
+                Cell::from(Span::raw("executions")),
+                Cell::from(Span::raw(format!("{}", client.executions))),
+            ]));
+            client_items.push(Row::new(vec![
+                Cell::from(Span::raw("exec/sec")),
+                Cell::from(Span::raw(client.exec_sec.clone())),
+            ]));
+            client_items.push(Row::new(vec![
+                Cell::from(Span::raw("corpus")),
+                Cell::from(Span::raw(format!("{}", client.corpus))),
+            ]));
+            client_items.push(Row::new(vec![
+                Cell::from(Span::raw("objectives")),
+                Cell::from(Span::raw(format!("{}", client.objectives))),
+            ]));
+            let mut user_stats: Vec<_> = client.user_stats.iter().collect();
+            user_stats.sort_by_key(|(key, _)| *key);
+            for (key, val) in user_stats {
+                client_items.push(Row::new(vec![
+                    Cell::from(Span::raw(key.clone())),
+                    Cell::from(Span::raw(format!("{}", val.clone()))),
+                ]));
+            }
+        }
+        client_items
+    }
+
+    /// The introspection stats of the selected client
+    #[cfg(feature = "introspection")]
+    fn introspection_rows(&self, ctx: &TuiContext) -> Vec<Row<'static>> {
+        let mut items = vec![];
+        if let Some(client) = ctx.introspection.get(&self.clients_idx) {
+            items.push(Row::new(vec![
+                Cell::from(Span::raw("scheduler")),
+                Cell::from(Span::raw(format!("{:.2}%", client.scheduler * 100.0))),
+            ]));
+            items.push(Row::new(vec![
+                Cell::from(Span::raw("manager")),
+                Cell::from(Span::raw(format!("{:.2}%", client.manager * 100.0))),
+            ]));
+            for i in 0..client.stages.len() {
+                items.push(Row::new(vec![
+                    Cell::from(Span::raw(format!("stage {i}"))),
+                    Cell::from(Span::raw("")),
+                ]));
+
+                for (key, val) in &client.stages[i] {
                     items.push(Row::new(vec![
-                        Cell::from(Span::raw("not measured")),
-                        Cell::from(Span::raw(format!("{:.2}%", client.unmeasured * 100.0))),
+                        Cell::from(Span::raw(key.clone())),
+                        Cell::from(Span::raw(format!("{:.2}%", val * 100.0))),
                     ]));
-                };
+                }
+            }
+            for (key, val) in &client.feedbacks {
+                items.push(Row::new(vec![
+                    Cell::from(Span::raw(key.clone())),
+                    Cell::from(Span::raw(format!("{:.2}%", val * 100.0))),
+                ]));
             }
+            items.push(Row::new(vec![
+                Cell::from(Span::raw("not measured")),
+                Cell::from(Span::raw(format!("{:.2}%", client.unmeasured * 100.0))),
+            ]));
+        }
+        items
+    }
 
-            let table = Table::new(items)
+    /// The selected client on the whole area: its stats, and the introspection breakdown
+    fn draw_client_details<B>(
+        &mut self,
+        f: &mut Frame<B>,
+        app: &Arc<RwLock<TuiContext>>,
+        area: Rect,
+    ) where
+        B: Backend,
+    {
+        let ctx = app.read().unwrap();
+        let paused = ctx
+            .clients
+            .get(&self.clients_idx)
+            .map_or(false, |client| client.paused);
+
+        #[cfg(feature = "introspection")]
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(area);
+        #[cfg(not(feature = "introspection"))]
+        let chunks = Layout::default()
+            .constraints([Constraint::Percentage(100)].as_ref())
+            .split(area);
+
+        let mut items = vec![Row::new(vec![
+            Cell::from(Span::raw("status")),
+            Cell::from(Span::styled(
+                if paused { "paused" } else { "running" },
+                Style::default().fg(if paused {
+                    Color::LightRed
+                } else {
+                    Color::LightGreen
+                }),
+            )),
+        ])];
+        items.extend(self.client_rows(&ctx));
+        let inputs = |inputs: &VecDeque<ClientInput>| {
+            inputs
+                .iter()
+                .filter(|input| input.client_id as usize == self.clients_idx)
+                .count()
+        };
+        items.push(Row::new(vec![
+            Cell::from(Span::raw("recent corpus entries")),
+            Cell::from(Span::raw(format!("{}", inputs(&ctx.testcases)))),
+        ]));
+        items.push(Row::new(vec![
+            Cell::from(Span::raw("recent objectives")),
+            Cell::from(Span::raw(format!("{}", inputs(&ctx.objectives)))),
+        ]));
+
+        let table = Table::new(items)
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        format!(
+                            "client #{} (l/r arrows to switch, `p` pause/resume, `d` back)",
+                            self.clients_idx
+                        ),
+                        Style::default()
+                            .fg(Color::LightCyan)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                    .borders(Borders::ALL),
+            )
+            .widths(&[Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)]);
+        f.render_widget(table, chunks[0]);
+
+        #[cfg(feature = "introspection")]
+        {
+            let table = Table::new(self.introspection_rows(&ctx))
                 .block(
                     Block::default()
                         .title(Span::styled(
@@ -488,11 +674,91 @@ impl TuiUI {
                         .borders(Borders::ALL),
                 )
                 .widths(&[Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)]);
-            f.render_widget(table, client_chunks[1]);
+            f.render_widget(table, chunks[1]);
         }
     }
 
-    #[allow(clippy::unused_self)]
+    /// The recent corpus entries, or objectives, of all clients, and a hex view of the selected one
+    fn draw_inputs<B>(
+        &mut self,
+        f: &mut Frame<B>,
+        app: &Arc<RwLock<TuiContext>>,
+        area: Rect,
+        objectives: bool,
+    ) where
+        B: Backend,
+    {
+        let ctx = app.read().unwrap();
+        let inputs = if objectives {
+            &ctx.objectives
+        } else {
+            &ctx.testcases
+        };
+        self.inputs_idx = min(self.inputs_idx, inputs.len().saturating_sub(1));
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+            .split(area);
+
+        let items: Vec<ListItem> = inputs
+            .iter()
+            .rev()
+            .map(|input| {
+                ListItem::new(Span::raw(format!(
+                    "{} #{:<3} {:>6} {:>8}B  {}",
+                    format_duration_hms(&input.time.saturating_sub(ctx.start_time)),
+                    input.client_id,
+                    input.idx.map_or_else(|| "-".into(), |idx| format!("{idx}")),
+                    input.bytes.len(),
+                    input.name
+                )))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default().borders(Borders::ALL).title(Span::styled(
+                    if objectives {
+                        "recent objectives (`b` switch, up/down arrows to select)"
+                    } else {
+                        "recent corpus entries (`b` switch, up/down arrows to select)"
+                    },
+                    Style::default()
+                        .fg(Color::LightCyan)
+                        .add_modifier(Modifier::BOLD),
+                )),
+            )
+            .highlight_style(
+                Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ");
+        let mut state = ListState::default();
+        if !inputs.is_empty() {
+            state.select(Some(self.inputs_idx));
+        }
+        f.render_stateful_widget(list, chunks[0], &mut state);
+
+        let lines: Vec<Spans> = inputs
+            .iter()
+            .rev()
+            .nth(self.inputs_idx)
+            .map(|input| hexdump(&input.bytes).into_iter().map(Spans::from).collect())
+            .unwrap_or_default();
+        let hex = Paragraph::new(lines)
+            .block(
+                Block::default().borders(Borders::ALL).title(Span::styled(
+                    "hex view (page up/down to scroll)",
+                    Style::default()
+                        .fg(Color::LightCyan)
+                        .add_modifier(Modifier::BOLD),
+                )),
+            )
+            .scroll((self.hex_scroll, 0));
+        f.render_widget(hex, chunks[1]);
+    }
+
     fn draw_logs<B>(&mut self, f: &mut Frame<B>, app: &Arc<RwLock<TuiContext>>, area: Rect)
     where
         B: Backend,
@@ -501,11 +767,26 @@ impl TuiUI {
         let logs: Vec<ListItem> = app
             .client_logs
             .iter()
-            .map(|msg| ListItem::new(Span::raw(msg)))
+            .filter(|log| {
+                self.log_filter
+                    .map_or(true, |min| log.severity.map_or(false, |s| s >= min))
+            })
+            .map(|log| {
+                let style = match log.severity {
+                    Some(LogSeverity::Error) => Style::default().fg(Color::LightRed),
+                    Some(LogSeverity::Warn) => Style::default().fg(Color::LightYellow),
+                    _ => Style::default(),
+                };
+                ListItem::new(Span::styled(&log.message, style))
+            })
             .collect();
         let logs = List::new(logs).block(
             Block::default().borders(Borders::ALL).title(Span::styled(
-                "clients logs (`t` to show/hide)",
+                format!(
+                    "clients logs (`t` to show/hide, `b` switch, `f` filter: {})",
+                    self.log_filter
+                        .map_or_else(|| "all".into(), |min| format!("{min} and above"))
+                ),
                 Style::default()
                     .fg(Color::LightCyan)
                     .add_modifier(Modifier::BOLD),
@@ -514,3 +795,50 @@ impl TuiUI {
         f.render_widget(logs, area);
     }
 }
+
+/// Formats `bytes` like `hexdump -C`, 16 bytes per line
+fn hexdump(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut line = format!("{:08x} ", i * 16);
+            for j in 0..16 {
+                if j % 8 == 0 {
+                    line.push(' ');
+                }
+                match chunk.get(j) {
+                    Some(b) => write!(line, "{b:02x} ").unwrap(),
+                    None => line.push_str("   "),
+                }
+            }
+            line.push_str(" |");
+            line.extend(chunk.iter().map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            }));
+            line.push('|');
+            line
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hexdump;
+
+    #[test]
+    fn test_hexdump() {
+        assert!(hexdump(&[]).is_empty());
+        assert_eq!(
+            hexdump(b"LibAFL hexdump\x00\xffx"),
+            vec![
+                "00000000  4c 69 62 41 46 4c 20 68  65 78 64 75 6d 70 00 ff  |LibAFL hexdump..|",
+                "00000010  78                                                |x|",
+            ]
+        );
+    }
+}
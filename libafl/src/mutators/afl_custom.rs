@@ -0,0 +1,406 @@
+//! Run the custom mutators of AFL++, shared libraries exporting `afl_custom_*` hooks, as `LibAFL` mutators.
+//! See <https://github.com/AFLplusplus/AFLplusplus/blob/stable/docs/custom_mutators.md>.
+//!
+//! The [`AflCustomMutator`] calls `afl_custom_fuzz` with the input and a splice candidate from the corpus.
+//! The [`crate::executors::AflCustomPostProcessExecutor`] applies `afl_custom_post_process` to each input
+//! right before it is executed, while the corpus keeps the inputs as mutated.
+//! The `afl_custom_*trim` hooks are used by the [`crate::stages::AflCustomTrimStage`].
+
+use alloc::{rc::Rc, string::String, vec::Vec};
+use core::{
+    ffi::{c_uint, c_void, CStr},
+    fmt::{self, Debug},
+    marker::PhantomData,
+    ptr, slice,
+};
+use std::{ffi::CString, path::Path};
+
+use crate::{
+    bolts::{rands::Rand, tuples::Named},
+    corpus::Corpus,
+    inputs::{HasBytesVec, Input, UsesInput},
+    mutators::{MutationResult, Mutator},
+    random_corpus_id,
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error,
+};
+
+type AflCustomInitFn = unsafe extern "C" fn(afl: *mut c_void, seed: c_uint) -> *mut c_void;
+type AflCustomFuzzFn = unsafe extern "C" fn(
+    data: *mut c_void,
+    buf: *mut u8,
+    buf_size: usize,
+    out_buf: *mut *mut u8,
+    add_buf: *mut u8,
+    add_buf_size: usize,
+    max_size: usize,
+) -> usize;
+pub(crate) type AflCustomPostProcessFn = unsafe extern "C" fn(
+    data: *mut c_void,
+    buf: *mut u8,
+    buf_size: usize,
+    out_buf: *mut *mut u8,
+) -> usize;
+type AflCustomInitTrimFn =
+    unsafe extern "C" fn(data: *mut c_void, buf: *mut u8, buf_size: usize) -> i32;
+type AflCustomTrimFn = unsafe extern "C" fn(data: *mut c_void, out_buf: *mut *mut u8) -> usize;
+type AflCustomPostTrimFn = unsafe extern "C" fn(data: *mut c_void, success: u8) -> i32;
+type AflCustomDeinitFn = unsafe extern "C" fn(data: *mut c_void);
+
+/// The trimming hooks of a custom mutator, all exported or none
+#[derive(Clone, Copy)]
+struct AflCustomTrimFns {
+    init_trim: AflCustomInitTrimFn,
+    trim: AflCustomTrimFn,
+    post_trim: AflCustomPostTrimFn,
+}
+
+/// A custom mutator library of AFL++, loaded with `dlopen` and initialized with `afl_custom_init`.
+///
+/// Custom mutators get a `NULL` AFL++ state: the ones reading it, e.g. for the AFL++ options, are not supported.
+pub struct AflCustomMutatorLibrary {
+    name: String,
+    handle: *mut c_void,
+    data: *mut c_void,
+    fuzz: Option<AflCustomFuzzFn>,
+    post_process: Option<AflCustomPostProcessFn>,
+    trim: Option<AflCustomTrimFns>,
+    deinit: Option<AflCustomDeinitFn>,
+    splice_optout: bool,
+}
+
+impl Debug for AflCustomMutatorLibrary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AflCustomMutatorLibrary")
+            .field("name", &self.name)
+            .field("fuzz", &self.fuzz.is_some())
+            .field("post_process", &self.post_process.is_some())
+            .field("trim", &self.trim.is_some())
+            .field("splice_optout", &self.splice_optout)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The last `dlerror`, for error messages
+fn dlerror() -> String {
+    let err = unsafe { libc::dlerror() };
+    if err.is_null() {
+        String::from("unknown error")
+    } else {
+        unsafe { CStr::from_ptr(err) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Copy the buffer returned by a hook, owned by the custom mutator
+unsafe fn hook_output(out_buf: *mut u8, size: usize) -> Vec<u8> {
+    if out_buf.is_null() || size == 0 {
+        vec![]
+    } else {
+        slice::from_raw_parts(out_buf, size).to_vec()
+    }
+}
+
+impl AflCustomMutatorLibrary {
+    /// Load the custom mutator at the given path, and initialize it with the given seed
+    pub fn load<P>(path: P, seed: u32) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let name = path.to_string_lossy().into_owned();
+        let c_path = CString::new(name.clone())
+            .map_err(|_| Error::illegal_argument(format!("Invalid path {name}")))?;
+        let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW) };
+        if handle.is_null() {
+            return Err(Error::illegal_argument(format!(
+                "Could not load custom mutator {name}: {}",
+                dlerror()
+            )));
+        }
+        let symbol = |symbol: &str| {
+            let symbol = CString::new(symbol).unwrap();
+            let sym = unsafe { libc::dlsym(handle, symbol.as_ptr()) };
+            (!sym.is_null()).then_some(sym)
+        };
+
+        let Some(init) = symbol("afl_custom_init") else {
+            unsafe { libc::dlclose(handle) };
+            return Err(Error::illegal_argument(format!(
+                "Custom mutator {name} does not export afl_custom_init"
+            )));
+        };
+        // The hooks have the signatures documented by AFL++
+        let fuzz = symbol("afl_custom_fuzz")
+            .map(|f| unsafe { core::mem::transmute::<*mut c_void, AflCustomFuzzFn>(f) });
+        let post_process = symbol("afl_custom_post_process")
+            .map(|f| unsafe { core::mem::transmute::<*mut c_void, AflCustomPostProcessFn>(f) });
+        let trim = match (
+            symbol("afl_custom_init_trim"),
+            symbol("afl_custom_trim"),
+            symbol("afl_custom_post_trim"),
+        ) {
+            (Some(init_trim), Some(trim), Some(post_trim)) => Some(unsafe {
+                AflCustomTrimFns {
+                    init_trim: core::mem::transmute::<*mut c_void, AflCustomInitTrimFn>(init_trim),
+                    trim: core::mem::transmute::<*mut c_void, AflCustomTrimFn>(trim),
+                    post_trim: core::mem::transmute::<*mut c_void, AflCustomPostTrimFn>(post_trim),
+                }
+            }),
+            _ => None,
+        };
+        let deinit = symbol("afl_custom_deinit")
+            .map(|f| unsafe { core::mem::transmute::<*mut c_void, AflCustomDeinitFn>(f) });
+        let splice_optout = symbol("afl_custom_splice_optout").is_some();
+
+        let init = unsafe { core::mem::transmute::<*mut c_void, AflCustomInitFn>(init) };
+        let data = unsafe { init(ptr::null_mut(), seed) };
+        if data.is_null() {
+            unsafe { libc::dlclose(handle) };
+            return Err(Error::illegal_state(format!(
+                "afl_custom_init of custom mutator {name} failed"
+            )));
+        }
+
+        Ok(Self {
+            name,
+            handle,
+            data,
+            fuzz,
+            post_process,
+            trim,
+            deinit,
+            splice_optout,
+        })
+    }
+
+    /// A custom mutator with only the given `afl_custom_post_process` hook, not loaded from a library
+    #[cfg(test)]
+    pub(crate) fn with_post_process(post_process: AflCustomPostProcessFn) -> Self {
+        Self {
+            name: String::from("post_process"),
+            handle: ptr::null_mut(),
+            data: ptr::null_mut(),
+            fuzz: None,
+            post_process: Some(post_process),
+            trim: None,
+            deinit: None,
+            splice_optout: true,
+        }
+    }
+
+    /// The path of this custom mutator
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// If this custom mutator exports `afl_custom_fuzz`
+    #[must_use]
+    pub fn has_fuzz(&self) -> bool {
+        self.fuzz.is_some()
+    }
+
+    /// If this custom mutator exports `afl_custom_post_process`
+    #[must_use]
+    pub fn has_post_process(&self) -> bool {
+        self.post_process.is_some()
+    }
+
+    /// If this custom mutator exports `afl_custom_init_trim`, `afl_custom_trim` and `afl_custom_post_trim`
+    #[must_use]
+    pub fn has_trim(&self) -> bool {
+        self.trim.is_some()
+    }
+
+    /// If this custom mutator exports `afl_custom_splice_optout`, i.e., does not want splice candidates
+    #[must_use]
+    pub fn splice_optout(&self) -> bool {
+        self.splice_optout
+    }
+
+    /// Mutate `buf` with `afl_custom_fuzz`, returning the mutant, at most `max_size` bytes long.
+    /// Returns `None` if the custom mutator does not export `afl_custom_fuzz`, or gave up on this input.
+    pub fn fuzz(&self, buf: &mut [u8], add_buf: Option<&[u8]>, max_size: usize) -> Option<Vec<u8>> {
+        let fuzz = self.fuzz?;
+        let (add_buf, add_buf_size) = add_buf.map_or((ptr::null_mut(), 0), |add| {
+            (add.as_ptr().cast_mut(), add.len())
+        });
+        let mut out_buf = ptr::null_mut();
+        let size = unsafe {
+            fuzz(
+                self.data,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut out_buf,
+                add_buf,
+                add_buf_size,
+                max_size,
+            )
+        };
+        (size > 0).then(|| unsafe { hook_output(out_buf, size.min(max_size)) })
+    }
+
+    /// Transform `buf` with `afl_custom_post_process`, if exported, returning the bytes to execute.
+    /// As in AFL++, the input is executed unchanged if the hook returns an empty buffer.
+    #[must_use]
+    pub fn post_process(&self, buf: &[u8]) -> Vec<u8> {
+        let Some(post_process) = self.post_process else {
+            return buf.to_vec();
+        };
+        // Some custom mutators post process in place
+        let mut buf = buf.to_vec();
+        let mut out_buf = ptr::null_mut();
+        let size = unsafe { post_process(self.data, buf.as_mut_ptr(), buf.len(), &mut out_buf) };
+        if size == 0 || out_buf.is_null() {
+            buf
+        } else {
+            unsafe { hook_output(out_buf, size) }
+        }
+    }
+
+    /// Trim `buf` with the trimming hooks, calling `is_kept` on each candidate to check it still has the same behavior.
+    /// Returns the trimmed input, or `None` if the custom mutator does not export the trimming hooks.
+    pub fn trim<F>(&self, buf: &[u8], mut is_kept: F) -> Result<Option<Vec<u8>>, Error>
+    where
+        F: FnMut(&[u8]) -> Result<bool, Error>,
+    {
+        let Some(fns) = self.trim else {
+            return Ok(None);
+        };
+        let mut current = buf.to_vec();
+        let steps = unsafe { (fns.init_trim)(self.data, current.as_mut_ptr(), current.len()) };
+        if steps < 0 {
+            return Err(Error::illegal_state(format!(
+                "afl_custom_init_trim of custom mutator {} failed",
+                self.name
+            )));
+        }
+        let mut step = 0;
+        while step < steps {
+            let mut out_buf = ptr::null_mut();
+            let size = unsafe { (fns.trim)(self.data, &mut out_buf) };
+            let candidate = unsafe { hook_output(out_buf, size) };
+            let kept = !candidate.is_empty() && is_kept(&candidate)?;
+            if kept {
+                // The custom mutator may still point into the buffer given to `afl_custom_init_trim`, keep it
+                if candidate.len() <= current.len() {
+                    current.truncate(candidate.len());
+                    current.copy_from_slice(&candidate);
+                } else {
+                    current = candidate;
+                }
+            }
+            step = unsafe { (fns.post_trim)(self.data, u8::from(kept)) };
+            if step < 0 {
+                return Err(Error::illegal_state(format!(
+                    "afl_custom_post_trim of custom mutator {} failed",
+                    self.name
+                )));
+            }
+        }
+        Ok(Some(current))
+    }
+}
+
+impl Drop for AflCustomMutatorLibrary {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(deinit) = self.deinit {
+                deinit(self.data);
+            }
+            if !self.handle.is_null() {
+                libc::dlclose(self.handle);
+            }
+        }
+    }
+}
+
+/// A [`Mutator`] calling the `afl_custom_fuzz` hook of an AFL++ custom mutator
+#[derive(Debug, Clone)]
+pub struct AflCustomMutator<I, S> {
+    library: Rc<AflCustomMutatorLibrary>,
+    phantom: PhantomData<(I, S)>,
+}
+
+impl<I, S> AflCustomMutator<I, S> {
+    /// Load the custom mutator at the given path, initialized with the given seed
+    pub fn new<P>(path: P, seed: u32) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::with_library(Rc::new(AflCustomMutatorLibrary::load(
+            path, seed,
+        )?)))
+    }
+
+    /// Creates a new [`AflCustomMutator`] for a custom mutator already loaded
+    #[must_use]
+    pub fn with_library(library: Rc<AflCustomMutatorLibrary>) -> Self {
+        Self {
+            library,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The custom mutator library
+    #[must_use]
+    pub fn library(&self) -> &Rc<AflCustomMutatorLibrary> {
+        &self.library
+    }
+}
+
+impl<I, S> AflCustomMutator<I, S>
+where
+    I: Input + HasBytesVec,
+    S: HasRand + HasMaxSize + HasCorpus + UsesInput<Input = I>,
+{
+    /// Mutate the input with `afl_custom_fuzz`, splicing with another corpus entry unless the custom mutator opts out
+    fn fuzz(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        if !self.library.has_fuzz() {
+            return Ok(MutationResult::Skipped);
+        }
+        let mut splice = None;
+        if !self.library.splice_optout() && state.corpus().count() > 1 {
+            let idx = random_corpus_id!(state.corpus(), state.rand_mut());
+            if *state.corpus().current() != Some(idx) {
+                let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+                splice = Some(other_testcase.load_input()?.bytes().to_vec());
+            }
+        }
+
+        let max_size = state.max_size();
+        match self
+            .library
+            .fuzz(input.bytes_mut(), splice.as_deref(), max_size)
+        {
+            Some(mutant) => {
+                *input.bytes_mut() = mutant;
+                Ok(MutationResult::Mutated)
+            }
+            None => Ok(MutationResult::Skipped),
+        }
+    }
+}
+
+impl<I, S> Mutator<I, S> for AflCustomMutator<I, S>
+where
+    I: Input + HasBytesVec,
+    S: HasRand + HasMaxSize + HasCorpus + UsesInput<Input = I>,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        self.fuzz(state, input)
+    }
+}
+
+impl<I, S> Named for AflCustomMutator<I, S> {
+    fn name(&self) -> &str {
+        "AflCustomMutator"
+    }
+}
@@ -0,0 +1,444 @@
+//! The [`BanditScheduledMutator`] learns which mutations, and how many stacked mutations, find new corpus entries,
+//! with a discounted Thompson sampling bandit. Unlike `MOpt`, it needs no pilot phases, and forgets old
+//! rewards to adapt as the fuzzing campaign progresses.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    fmt::{self, Debug},
+    marker::PhantomData,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bolts::{rands::Rand, tuples::NamedTuple},
+    corpus::{Corpus, CorpusId},
+    mutators::{
+        ComposedByMutations, MutationId, MutationResult, Mutator, MutatorsTuple, ScheduledMutator,
+    },
+    schedulers::bandit::sample_beta,
+    state::{HasCorpus, HasMetadata, HasRand, HasSolutions},
+    Error,
+};
+
+/// The default discount of past rewards, applied at each execution
+pub const DEFAULT_BANDIT_DISCOUNT: f64 = 0.999;
+
+/// The default `max_stack_pow` of the [`BanditScheduledMutator`], stacking up to 64 mutations
+pub const DEFAULT_BANDIT_MAX_STACK_POW: u64 = 6;
+
+/// The largest `max_stack_pow` of the [`BanditScheduledMutator`], stacking up to 128 mutations
+pub const MAX_BANDIT_STACK_POW: u64 = 7;
+
+/// An arm of a discounted Thompson sampling bandit
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct DiscountedArm {
+    /// The discounted number of rewarded pulls
+    pub successes: f64,
+    /// The discounted number of pulls not rewarded
+    pub failures: f64,
+    /// The total number of pulls
+    pub pulls: u64,
+    /// The total number of rewarded pulls
+    pub finds: u64,
+}
+
+impl DiscountedArm {
+    /// Sample the reward probability of this arm, from its beta distribution
+    pub fn sample<R>(&self, rand: &mut R) -> f64
+    where
+        R: Rand,
+    {
+        sample_beta(rand, self.successes + 1.0, self.failures + 1.0)
+    }
+
+    /// Discount the past rewards of this arm
+    pub fn discount(&mut self, discount: f64) {
+        self.successes *= discount;
+        self.failures *= discount;
+    }
+
+    /// Count a pull of this arm
+    pub fn update(&mut self, rewarded: bool) {
+        self.pulls += 1;
+        if rewarded {
+            self.successes += 1.0;
+            self.finds += 1;
+        } else {
+            self.failures += 1.0;
+        }
+    }
+
+    /// The ratio of pulls rewarded so far
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn success_rate(&self) -> f64 {
+        if self.pulls == 0 {
+            0.0
+        } else {
+            self.finds as f64 / self.pulls as f64
+        }
+    }
+}
+
+/// The state of the [`BanditScheduledMutator`]: the arms of each mutation and of each stacking depth
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BanditMutatorMetadata {
+    /// The names of the mutations
+    names: Vec<String>,
+    /// An arm for each mutation
+    mutations: Vec<DiscountedArm>,
+    /// An arm for each stacking depth, `1 << (1 + i)` stacked mutations for the `i`th one
+    stacks: Vec<DiscountedArm>,
+}
+
+crate::impl_serdeany!(BanditMutatorMetadata);
+
+impl BanditMutatorMetadata {
+    /// Creates a new [`BanditMutatorMetadata`] for the given mutations and `max_stack_pow` stacking depths
+    #[must_use]
+    pub fn new(names: Vec<String>, max_stack_pow: usize) -> Self {
+        Self {
+            mutations: vec![DiscountedArm::default(); names.len()],
+            names,
+            stacks: vec![DiscountedArm::default(); max_stack_pow],
+        }
+    }
+
+    /// The name and the arm of each mutation
+    pub fn mutations(&self) -> impl Iterator<Item = (&str, &DiscountedArm)> {
+        self.names.iter().map(String::as_str).zip(&self.mutations)
+    }
+
+    /// The number of stacked mutations and the arm of each stacking depth
+    pub fn stacks(&self) -> impl Iterator<Item = (u64, &DiscountedArm)> {
+        self.stacks
+            .iter()
+            .enumerate()
+            .map(|(i, arm)| (1 << (1 + i), arm))
+    }
+}
+
+/// Choose the arm with the best sampled reward probability among the arms of the metadata
+fn select_arm<S, F>(state: &mut S, len: usize, arms: F) -> usize
+where
+    S: HasMetadata + HasRand,
+    F: Fn(&BanditMutatorMetadata) -> &[DiscountedArm],
+{
+    let mut best = 0;
+    let mut best_score = f64::NEG_INFINITY;
+    for i in 0..len {
+        let arm = arms(state.metadata().get::<BanditMutatorMetadata>().unwrap())[i];
+        let score = arm.sample(state.rand_mut());
+        if score > best_score {
+            best = i;
+            best_score = score;
+        }
+    }
+    best
+}
+
+/// A [`ScheduledMutator`] choosing the mutations, and the number of stacked mutations,
+/// with a discounted Thompson sampling bandit rewarded when the mutated input is added to the corpus or the solutions.
+///
+/// The success rate of each mutation can be reported to the monitors with a [`crate::stages::BanditStatsStage`].
+pub struct BanditScheduledMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+{
+    mutations: MT,
+    max_stack_pow: usize,
+    discount: f64,
+    /// The mutations applied to the current input
+    scheduled: Vec<MutationId>,
+    /// The stacking depth used for the current input
+    stack: usize,
+    /// The number of corpus entries and solutions before the current input
+    finds_before: usize,
+    phantom: PhantomData<(I, S)>,
+}
+
+impl<I, MT, S> Debug for BanditScheduledMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "BanditScheduledMutator with {} mutations for Input type {}",
+            self.mutations.len(),
+            core::any::type_name::<I>()
+        )
+    }
+}
+
+impl<I, MT, S> Mutator<I, S> for BanditScheduledMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand + HasMetadata + HasCorpus + HasSolutions,
+{
+    #[inline]
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        self.finds_before = state.corpus().count() + state.solutions().count();
+        self.scheduled_mutate(state, input, stage_idx)
+    }
+
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        _stage_idx: i32,
+        _corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        if self.scheduled.is_empty() {
+            return Ok(());
+        }
+        let rewarded = state.corpus().count() + state.solutions().count() > self.finds_before;
+        let meta = state
+            .metadata_mut()
+            .get_mut::<BanditMutatorMetadata>()
+            .unwrap();
+
+        for arm in meta.mutations.iter_mut().chain(meta.stacks.iter_mut()) {
+            arm.discount(self.discount);
+        }
+        // A mutation stacked several times is counted once
+        self.scheduled.sort_unstable();
+        self.scheduled.dedup();
+        for idx in &self.scheduled {
+            meta.mutations[idx.0].update(rewarded);
+        }
+        meta.stacks[self.stack].update(rewarded);
+        self.scheduled.clear();
+        Ok(())
+    }
+}
+
+impl<I, MT, S> ComposedByMutations<I, MT, S> for BanditScheduledMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+{
+    /// Get the mutations
+    #[inline]
+    fn mutations(&self) -> &MT {
+        &self.mutations
+    }
+
+    // Get the mutations (mutable)
+    #[inline]
+    fn mutations_mut(&mut self) -> &mut MT {
+        &mut self.mutations
+    }
+}
+
+impl<I, MT, S> ScheduledMutator<I, MT, S> for BanditScheduledMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand + HasMetadata + HasCorpus + HasSolutions,
+{
+    /// Compute the number of iterations used to apply stacked mutations
+    fn iterations(&self, state: &mut S, _: &I) -> u64 {
+        let stack = select_arm(state, self.max_stack_pow, |meta| &meta.stacks);
+        1 << (1 + stack)
+    }
+
+    /// Get the next mutation to apply
+    fn schedule(&self, state: &mut S, _: &I) -> MutationId {
+        select_arm(state, self.mutations.len(), |meta| &meta.mutations).into()
+    }
+
+    fn scheduled_mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let mut r = MutationResult::Skipped;
+        // Remember what is applied, to reward it in `post_exec`
+        let stack = select_arm(state, self.max_stack_pow, |meta| &meta.stacks);
+        self.stack = stack;
+        let num = 1_u64 << (1 + stack);
+        self.scheduled.clear();
+        for _ in 0..num {
+            let idx = self.schedule(state, input);
+            self.scheduled.push(idx);
+            let outcome = self
+                .mutations_mut()
+                .get_and_mutate(idx, state, input, stage_idx)?;
+            if outcome == MutationResult::Mutated {
+                r = MutationResult::Mutated;
+            }
+        }
+        Ok(r)
+    }
+}
+
+impl<I, MT, S> BanditScheduledMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S> + NamedTuple,
+    S: HasRand + HasMetadata,
+{
+    /// Create a new [`BanditScheduledMutator`] instance specifying mutations
+    pub fn new(state: &mut S, mutations: MT) -> Self {
+        Self::with_params(
+            state,
+            mutations,
+            DEFAULT_BANDIT_MAX_STACK_POW,
+            DEFAULT_BANDIT_DISCOUNT,
+        )
+    }
+
+    /// Create a new [`BanditScheduledMutator`] instance, stacking up to `1 << max_stack_pow` mutations,
+    /// and multiplying past rewards by `discount` at each execution.
+    /// `max_stack_pow` is clamped to `1..=`[`MAX_BANDIT_STACK_POW`], stacking at least two mutations.
+    pub fn with_params(state: &mut S, mutations: MT, max_stack_pow: u64, discount: f64) -> Self {
+        let max_stack_pow = usize::try_from(max_stack_pow.clamp(1, MAX_BANDIT_STACK_POW)).unwrap();
+        // Keep the learned arms across restarts, unless the mutations changed
+        let names: Vec<String> = (0..mutations.len())
+            .map(|i| mutations.name(i).unwrap().to_string())
+            .collect();
+        let outdated = state
+            .metadata()
+            .get::<BanditMutatorMetadata>()
+            .map_or(true, |meta| {
+                meta.names != names || meta.stacks.len() != max_stack_pow
+            });
+        if outdated {
+            state.add_metadata(BanditMutatorMetadata::new(names, max_stack_pow));
+        }
+        Self {
+            mutations,
+            max_stack_pow,
+            discount,
+            scheduled: vec![],
+            stack: 0,
+            finds_before: 0,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::{
+        bolts::{
+            rands::{Rand, StdRand},
+            tuples::tuple_list,
+        },
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        feedbacks::ConstFeedback,
+        inputs::BytesInput,
+        mutators::{
+            bandit::{BanditMutatorMetadata, BanditScheduledMutator, DiscountedArm},
+            BitFlipMutator, ByteFlipMutator, MutationResult, Mutator,
+        },
+        state::{HasCorpus, HasMetadata, StdState},
+    };
+
+    #[test]
+    fn test_discounted_arm() {
+        let mut rand = StdRand::with_seed(0);
+        let mut good = DiscountedArm::default();
+        let mut bad = DiscountedArm::default();
+        for _ in 0..100 {
+            good.discount(0.9);
+            bad.discount(0.9);
+            good.update(rand.below(2) == 0);
+            bad.update(false);
+        }
+        assert_eq!(good.pulls, 100);
+        assert!(good.successes + good.failures < 10.0);
+        let wins = (0..100)
+            .filter(|_| good.sample(&mut rand) > bad.sample(&mut rand))
+            .count();
+        assert!(wins > 80, "{wins}");
+    }
+
+    #[test]
+    fn test_bandit_mutator_rewards() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        // A stacking depth of 0 is clamped to 1
+        let mut mutator = BanditScheduledMutator::with_params(
+            &mut state,
+            tuple_list!(BitFlipMutator::new(), ByteFlipMutator::new()),
+            0,
+            0.5,
+        );
+        let arms = |state: &StdState<_, _, _, _>| {
+            let meta = state.metadata().get::<BanditMutatorMetadata>().unwrap();
+            (
+                meta.mutations().map(|(_, arm)| *arm).collect::<Vec<_>>(),
+                meta.stacks().map(|(_, arm)| *arm).collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(arms(&state).1.len(), 1);
+
+        // No new corpus entry: the applied mutations and the stacking depth are not rewarded
+        let mut input = BytesInput::new(vec![0; 16]);
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Mutated
+        );
+        mutator.post_exec(&mut state, 0, None).unwrap();
+        let (mutations, stacks) = arms(&state);
+        assert_eq!(stacks[0].pulls, 1);
+        assert_eq!(stacks[0].finds, 0);
+        assert_eq!(stacks[0].failures, 1.0);
+        let pulls: u64 = mutations.iter().map(|arm| arm.pulls).sum();
+        assert!((1..=2).contains(&pulls));
+        assert!(mutations.iter().all(|arm| arm.finds == 0));
+
+        // A new corpus entry rewards them, and discounts the past rewards
+        mutator.mutate(&mut state, &mut input, 0).unwrap();
+        state
+            .corpus_mut()
+            .add(Testcase::new(input.clone()))
+            .unwrap();
+        mutator.post_exec(&mut state, 0, None).unwrap();
+        let (mutations, stacks) = arms(&state);
+        assert_eq!(stacks[0].pulls, 2);
+        assert_eq!(stacks[0].finds, 1);
+        assert_eq!(stacks[0].successes, 1.0);
+        assert_eq!(stacks[0].failures, 0.5);
+        assert!(mutations.iter().any(|arm| arm.finds == 1));
+
+        // Nothing is rewarded without a mutation
+        mutator.post_exec(&mut state, 0, None).unwrap();
+        assert_eq!(arms(&state).1[0].pulls, 2);
+
+        // Deeper stacks are clamped too, and replace the arms
+        let _: BanditScheduledMutator<BytesInput, _, _> = BanditScheduledMutator::with_params(
+            &mut state,
+            tuple_list!(BitFlipMutator::new(), ByteFlipMutator::new()),
+            63,
+            0.5,
+        );
+        assert_eq!(arms(&state).1.len(), 7);
+        let _: BanditScheduledMutator<BytesInput, _, _> = BanditScheduledMutator::new(
+            &mut state,
+            tuple_list!(BitFlipMutator::new(), ByteFlipMutator::new()),
+        );
+        assert_eq!(arms(&state).1.len(), 6);
+    }
+}
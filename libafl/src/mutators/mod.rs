@@ -19,6 +19,14 @@ pub mod grimoire;
 pub use grimoire::*;
 pub mod tuneable;
 pub use tuneable::*;
+pub mod bandit;
+pub use bandit::{BanditMutatorMetadata, BanditScheduledMutator};
+#[cfg(all(unix, feature = "std"))]
+pub mod afl_custom;
+#[cfg(all(unix, feature = "std"))]
+pub use afl_custom::{AflCustomMutator, AflCustomMutatorLibrary};
+pub mod multi;
+pub use multi::*;
 
 #[cfg(feature = "nautilus")]
 pub mod nautilus;
@@ -0,0 +1,357 @@
+//! Mutators for the [`MultipartInput`], selecting a part and mutating it.
+
+use alloc::vec::Vec;
+use core::{
+    cmp::{max, min},
+    fmt::{self, Debug},
+    marker::PhantomData,
+};
+
+use crate::{
+    bolts::{rands::Rand, tuples::Named},
+    corpus::{Corpus, CorpusId},
+    inputs::{HasBytesVec, Input, MultipartInput, UsesInput},
+    mutators::{
+        mutations::{buffer_copy, buffer_self_copy},
+        MutationResult, Mutator, MutatorsTuple,
+    },
+    random_corpus_id,
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error,
+};
+
+/// A [`Mutator`] for [`MultipartInput`]s, selecting a random part and mutating it
+/// with a random mutation of the inner [`MutatorsTuple`].
+/// Wrap it in a `StdScheduledMutator` to stack mutations, possibly across parts.
+pub struct MultipartMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+{
+    mutations: MT,
+    phantom: PhantomData<(I, S)>,
+}
+
+impl<I, MT, S> Debug for MultipartMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MultipartMutator with {} mutations for Input type {}",
+            self.mutations.len(),
+            core::any::type_name::<I>()
+        )
+    }
+}
+
+impl<I, MT, S> Mutator<MultipartInput<I>, S> for MultipartMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput<I>,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.is_empty() || self.mutations.len() == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let part_idx = state.rand_mut().below(input.len() as u64) as usize;
+        let mutation = state.rand_mut().below(self.mutations.len() as u64);
+        let part = input.part_mut(part_idx).unwrap();
+        self.mutations
+            .get_and_mutate(mutation.into(), state, part, stage_idx)
+    }
+
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        stage_idx: i32,
+        corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.mutations.post_exec_all(state, stage_idx, corpus_idx)
+    }
+}
+
+impl<I, MT, S> Named for MultipartMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+{
+    fn name(&self) -> &str {
+        "MultipartMutator"
+    }
+}
+
+impl<I, MT, S> MultipartMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+{
+    /// Creates a new [`MultipartMutator`] delegating to the given mutations.
+    pub fn new(mutations: MT) -> Self {
+        Self {
+            mutations,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The inner mutations
+    pub fn mutations(&self) -> &MT {
+        &self.mutations
+    }
+
+    /// The inner mutations (mutable)
+    pub fn mutations_mut(&mut self) -> &mut MT {
+        &mut self.mutations
+    }
+}
+
+/// Select a random part of `input`, and a random part with the same name in another [`crate::corpus::Testcase`].
+/// Returns the index of the part in `input`, the other testcase, the index of the part in it, and its size.
+fn select_crossover_parts<I, S>(
+    state: &mut S,
+    input: &MultipartInput<I>,
+) -> Result<Option<(usize, CorpusId, usize, usize)>, Error>
+where
+    S: HasCorpus + HasRand + UsesInput<Input = MultipartInput<I>>,
+    I: Input + HasBytesVec,
+{
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let part_idx = state.rand_mut().below(input.len() as u64) as usize;
+
+    // We don't want to use the testcase we're already using for splicing
+    let idx = random_corpus_id!(state.corpus(), state.rand_mut());
+    if let Some(cur) = state.corpus().current() {
+        if idx == *cur {
+            return Ok(None);
+        }
+    }
+
+    let candidates = {
+        let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+        let other = other_testcase.load_input()?;
+        other
+            .part_indexes_by_name(&input.names()[part_idx])
+            .map(|other_part_idx| (other_part_idx, other.parts()[other_part_idx].bytes().len()))
+            .filter(|(_, other_size)| *other_size >= 2)
+            .collect::<Vec<_>>()
+    };
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+    let (other_part_idx, other_size) = *state.rand_mut().choose(&candidates);
+    Ok(Some((part_idx, idx, other_part_idx, other_size)))
+}
+
+/// Crossover insert mutation for [`MultipartInput`]s, inserting bytes from an equally named part of another testcase
+#[derive(Debug, Default)]
+pub struct MultipartCrossoverInsertMutator;
+
+impl<I, S> Mutator<MultipartInput<I>, S> for MultipartCrossoverInsertMutator
+where
+    S: HasCorpus + HasRand + HasMaxSize + UsesInput<Input = MultipartInput<I>>,
+    I: Input + HasBytesVec,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput<I>,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let Some((part_idx, idx, other_part_idx, other_size)) =
+            select_crossover_parts(state, input)?
+        else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let part = input.part_mut(part_idx).unwrap();
+        let size = part.bytes().len();
+        let max_size = state.max_size();
+
+        let from = state.rand_mut().below(other_size as u64) as usize;
+        let to = state.rand_mut().below(max(size, 1) as u64) as usize;
+        let mut len = 1 + state.rand_mut().below((other_size - from) as u64) as usize;
+
+        let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+        let other = &other_testcase.load_input()?.parts()[other_part_idx];
+
+        if size + len > max_size {
+            if max_size > size {
+                len = max_size - size;
+            } else {
+                return Ok(MutationResult::Skipped);
+            }
+        }
+
+        part.bytes_mut().resize(size + len, 0);
+        buffer_self_copy(part.bytes_mut(), to, to + len, size - to);
+        buffer_copy(part.bytes_mut(), other.bytes(), from, to, len);
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for MultipartCrossoverInsertMutator {
+    fn name(&self) -> &str {
+        "MultipartCrossoverInsertMutator"
+    }
+}
+
+impl MultipartCrossoverInsertMutator {
+    /// Creates a new [`MultipartCrossoverInsertMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Crossover replace mutation for [`MultipartInput`]s, copying bytes from an equally named part of another testcase
+#[derive(Debug, Default)]
+pub struct MultipartCrossoverReplaceMutator;
+
+impl<I, S> Mutator<MultipartInput<I>, S> for MultipartCrossoverReplaceMutator
+where
+    S: HasCorpus + HasRand + UsesInput<Input = MultipartInput<I>>,
+    I: Input + HasBytesVec,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput<I>,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let Some((part_idx, idx, other_part_idx, other_size)) =
+            select_crossover_parts(state, input)?
+        else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let part = input.part_mut(part_idx).unwrap();
+        let size = part.bytes().len();
+        if size == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let from = state.rand_mut().below(other_size as u64) as usize;
+        let len = state.rand_mut().below(min(other_size - from, size) as u64) as usize;
+        let to = state.rand_mut().below((size - len) as u64) as usize;
+
+        let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+        let other = &other_testcase.load_input()?.parts()[other_part_idx];
+
+        buffer_copy(part.bytes_mut(), other.bytes(), from, to, len);
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for MultipartCrossoverReplaceMutator {
+    fn name(&self) -> &str {
+        "MultipartCrossoverReplaceMutator"
+    }
+}
+
+impl MultipartCrossoverReplaceMutator {
+    /// Creates a new [`MultipartCrossoverReplaceMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        MultipartCrossoverInsertMutator, MultipartCrossoverReplaceMutator, MultipartMutator,
+    };
+    use crate::{
+        bolts::{rands::StdRand, tuples::tuple_list},
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        feedbacks::ConstFeedback,
+        inputs::{BytesInput, HasBytesVec, MultipartInput},
+        mutators::{BitFlipMutator, MutationResult, Mutator},
+        state::{HasCorpus, StdState},
+    };
+
+    #[test]
+    fn test_multipart_mutators() {
+        let input = MultipartInput::with_parts([
+            ("config", BytesInput::new(b"AAAA".to_vec())),
+            ("payload", BytesInput::new(b"BBBB".to_vec())),
+        ]);
+        let other = MultipartInput::with_parts([
+            ("payload", BytesInput::new(b"CCCC".to_vec())),
+            ("unrelated", BytesInput::new(b"DDDD".to_vec())),
+        ]);
+
+        let mut corpus = InMemoryCorpus::new();
+        corpus.add(Testcase::new(other)).unwrap();
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            corpus,
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+
+        let mut mutator = MultipartMutator::new(tuple_list!(BitFlipMutator::new()));
+        let mut mutated = input.clone();
+        assert_eq!(
+            mutator.mutate(&mut state, &mut mutated, 0).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_ne!(mutated, input);
+        assert_eq!(mutated.names(), input.names());
+
+        // The only testcase of the corpus is not the current one, and has a payload to cross over with
+        let payload = MultipartInput::with_parts([("payload", BytesInput::new(b"BBBB".to_vec()))]);
+        for _ in 0..16 {
+            let mut mutated = payload.clone();
+            assert_eq!(
+                MultipartCrossoverInsertMutator::new()
+                    .mutate(&mut state, &mut mutated, 0)
+                    .unwrap(),
+                MutationResult::Mutated
+            );
+            assert_eq!(mutated.names(), payload.names());
+            assert!(mutated.parts()[0].bytes().len() > 4);
+            assert!(mutated.parts()[0].bytes().contains(&b'C'));
+
+            let mut mutated = payload.clone();
+            assert_eq!(
+                MultipartCrossoverReplaceMutator::new()
+                    .mutate(&mut state, &mut mutated, 0)
+                    .unwrap(),
+                MutationResult::Mutated
+            );
+            assert_eq!(mutated.parts()[0].bytes().len(), 4);
+            assert!(mutated.parts()[0]
+                .bytes()
+                .iter()
+                .all(|byte| *byte == b'B' || *byte == b'C'));
+        }
+
+        // Only equally named parts are crossed over
+        let config = MultipartInput::with_parts([("config", BytesInput::new(b"AAAA".to_vec()))]);
+        let mut mutated = config.clone();
+        assert_eq!(
+            MultipartCrossoverInsertMutator::new()
+                .mutate(&mut state, &mut mutated, 0)
+                .unwrap(),
+            MutationResult::Skipped
+        );
+        assert_eq!(mutated, config);
+        assert_eq!(state.corpus().count(), 1);
+    }
+}
@@ -16,7 +16,7 @@ use crate::{
         tuples::{tuple_list, tuple_list_type, NamedTuple},
         AsMutSlice, AsSlice,
     },
-    corpus::{Corpus, CorpusId},
+    corpus::{Corpus, CorpusId, LineageContextMetadata},
     mutators::{MutationResult, Mutator, MutatorsTuple},
     state::{HasCorpus, HasMetadata, HasRand},
     Error,
@@ -303,7 +303,7 @@ where
 impl<I, MT, S, SM> Mutator<I, S> for LoggerScheduledMutator<I, MT, S, SM>
 where
     MT: MutatorsTuple<I, S> + NamedTuple,
-    S: HasRand + HasCorpus,
+    S: HasRand + HasCorpus + HasMetadata,
     SM: ScheduledMutator<I, MT, S>,
 {
     fn mutate(
@@ -312,7 +312,16 @@ where
         input: &mut I,
         stage_idx: i32,
     ) -> Result<MutationResult, Error> {
-        self.scheduled_mutate(state, input, stage_idx)
+        let ret = self.scheduled_mutate(state, input, stage_idx);
+        // Make the mutations known to the lineage of a new testcase, before it is sent to other clients
+        let mutations = self
+            .mutation_log
+            .iter()
+            .filter_map(|idx| self.scheduled.mutations().name(idx.0))
+            .map(String::from)
+            .collect();
+        LineageContextMetadata::set_mutations(state, mutations);
+        ret
     }
 
     fn post_exec(
@@ -357,7 +366,7 @@ where
 impl<I, MT, S, SM> ScheduledMutator<I, MT, S> for LoggerScheduledMutator<I, MT, S, SM>
 where
     MT: MutatorsTuple<I, S> + NamedTuple,
-    S: HasRand + HasCorpus,
+    S: HasRand + HasCorpus + HasMetadata,
     SM: ScheduledMutator<I, MT, S>,
 {
     /// Compute the number of iterations used to apply stacked mutations
@@ -150,6 +150,17 @@ impl Tokens {
         true
     }
 
+    /// Removes a token from a dictionary
+    /// Returns `false` if the token was not present.
+    #[allow(clippy::ptr_arg)]
+    pub fn remove_token(&mut self, token: &Vec<u8>) -> bool {
+        if !self.tokens_set.remove(token) {
+            return false;
+        }
+        self.tokens_vec.retain(|t| t != token);
+        true
+    }
+
     /// Reads a tokens file, returning the count of new entries read
     #[cfg(feature = "std")]
     pub fn add_from_file<P>(&mut self, file: P) -> Result<&mut Self, Error>
@@ -10,7 +10,7 @@ use c2rust_bitfields::BitfieldStruct;
 use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{
-    bolts::{ownedref::OwnedRefMut, tuples::Named, AsMutSlice, AsSlice},
+    bolts::{ownedref::OwnedRefMut, shmem::ShMem, tuples::Named, AsMutSlice, AsSlice},
     executors::ExitKind,
     inputs::UsesInput,
     observers::Observer,
@@ -19,7 +19,7 @@ use crate::{
 };
 
 /// Compare values collected during a run
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CmpValues {
     /// Two u8 values
     U8((u8, u8)),
@@ -198,6 +198,7 @@ where
 }
 
 /// A standard [`CmpObserver`] observer
+#[allow(clippy::unsafe_derive_deserialize)]
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound = "CM: serde::de::DeserializeOwned")]
 pub struct StdCmpObserver<'a, CM, S>
@@ -284,6 +285,20 @@ where
         }
     }
 
+    /// Creates a new [`StdCmpObserver`] with the given name, observing the map in the given shared memory,
+    /// for example an [`AFLCmpMap`] filled by the target of a forkserver.
+    ///
+    /// # Safety
+    /// The shared memory must be large enough, and hold a valid `CM`.
+    #[must_use]
+    pub unsafe fn from_shmem<SHM>(name: &'static str, shmem: &'a mut SHM, add_meta: bool) -> Self
+    where
+        SHM: ShMem,
+        CM: 'static,
+    {
+        Self::new(name, shmem.as_object_mut::<CM>(), add_meta)
+    }
+
     /// Creates a new [`StdCmpObserver`] with the given name, map and reference to variable size.
     #[must_use]
     pub fn with_size(
@@ -394,12 +409,14 @@ pub struct AFLCmpFnOperands {
     v1_len: u8,
 }
 
-/// A proxy union to avoid casting operands as in AFL++
+/// A proxy union to avoid casting operands as in AFL++.
+/// AFL++ casts each row of `log` to `cmpfn_operands`, so a row holds twice as many of them,
+/// of which only the first `CMP_MAP_RTN_H` are used.
 #[derive(Clone, Copy)]
 #[repr(C, packed)]
 pub union AFLCmpVals {
     operands: [[AFLCmpOperands; AFL_CMP_MAP_H]; AFL_CMP_MAP_W],
-    fn_operands: [[AFLCmpFnOperands; AFL_CMP_MAP_RTN_H]; AFL_CMP_MAP_W],
+    fn_operands: [[AFLCmpFnOperands; AFL_CMP_MAP_H / 2]; AFL_CMP_MAP_W],
 }
 
 impl Debug for AFLCmpVals {
@@ -511,3 +528,108 @@ impl CmpMap for AFLCmpMap {
         Ok(())
     }
 }
+
+/// A [`CmpObserver`] for the [`AFLCmpMap`] filled by a binary compiled with AFL++ `cmplog` instrumentation.
+/// The map is usually placed in a shared memory passed to the target with
+/// [`crate::executors::forkserver::ForkserverExecutorBuilder::cmplog_shmem`],
+/// and observed with [`StdCmpObserver::from_shmem`].
+pub type AFLCmpLogObserver<'a, S> = StdCmpObserver<'a, AFLCmpMap, S>;
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use core::mem::size_of;
+
+    use super::{
+        AFLCmpLogObserver, AFLCmpMap, CmpMap, CmpObserver, CmpValues, CmpValuesMetadata,
+        AFL_CMP_MAP_H, AFL_CMP_MAP_W, AFL_CMP_TYPE_INS, AFL_CMP_TYPE_RTN,
+    };
+    use crate::{
+        bolts::{
+            rands::StdRand,
+            shmem::{ShMemProvider, StdShMemProvider},
+            AsMutSlice,
+        },
+        corpus::InMemoryCorpus,
+        executors::ExitKind,
+        feedbacks::ConstFeedback,
+        inputs::BytesInput,
+        observers::Observer,
+        state::{HasMetadata, StdState},
+    };
+
+    /// Writes the `cmp_header` of the cmp `idx`, as the AFL++ runtime does
+    fn write_header(map: &mut [u8], idx: usize, hits: u64, shape: u64, ty: u32) {
+        let header = hits | (idx as u64) << 24 | shape << 48 | u64::from(ty) << 53;
+        map[idx * 8..idx * 8 + 8].copy_from_slice(&header.to_le_bytes());
+    }
+
+    /// The offset of the `cmp_operands` of the cmp `idx` at execution `hit` in `log`
+    fn operands_offset(idx: usize, hit: usize) -> usize {
+        AFL_CMP_MAP_W * 8 + (idx * AFL_CMP_MAP_H + hit) * 32
+    }
+
+    fn write_operands(map: &mut [u8], idx: usize, hit: usize, v0: u64, v1: u64) {
+        let offset = operands_offset(idx, hit);
+        map[offset..offset + 8].copy_from_slice(&v0.to_le_bytes());
+        map[offset + 8..offset + 16].copy_from_slice(&v1.to_le_bytes());
+    }
+
+    /// AFL++ writes the `cmpfn_operands` of a routine to the row of `log` of its cmp
+    fn write_fn_operands(map: &mut [u8], idx: usize, hit: usize, v0: &[u8], v1: &[u8]) {
+        let offset = operands_offset(idx, 0) + hit * 64;
+        map[offset..offset + v0.len()].copy_from_slice(v0);
+        map[offset + 31] = v0.len() as u8;
+        map[offset + 32..offset + 32 + v1.len()].copy_from_slice(v1);
+        map[offset + 63] = v1.len() as u8;
+    }
+
+    #[test]
+    fn test_afl_cmplog_observer() {
+        let mut shmem_provider = StdShMemProvider::new().unwrap();
+        let mut shmem = shmem_provider.new_shmem(size_of::<AFLCmpMap>()).unwrap();
+        let map = shmem.as_mut_slice();
+        assert_eq!(map.len(), AFL_CMP_MAP_W * (8 + AFL_CMP_MAP_H * 32));
+
+        // A 32 bits comparison, executed twice
+        write_header(map, 1, 2, 3, AFL_CMP_TYPE_INS);
+        write_operands(map, 1, 0, 0x1122_3344, 0x5566_7788);
+        write_operands(map, 1, 1, 5, 6);
+        // Two routine calls comparing 5 bytes
+        write_header(map, 2, 2, 4, AFL_CMP_TYPE_RTN);
+        write_fn_operands(map, 2, 0, b"hello", b"world");
+        write_fn_operands(map, 2, 1, b"fuzz!", b"bytes");
+        // A loop counter, filtered out
+        write_header(map, 3, 8, 0, AFL_CMP_TYPE_INS);
+        for i in 0..8 {
+            write_operands(map, 3, i as usize, i, 100);
+        }
+
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut observer = unsafe { AFLCmpLogObserver::from_shmem("cmplog", &mut shmem, true) };
+        assert_eq!(observer.cmp_map().executions_for(1), 2);
+        observer
+            .post_exec(&mut state, &BytesInput::new(vec![]), &ExitKind::Ok)
+            .unwrap();
+
+        let meta = state.metadata().get::<CmpValuesMetadata>().unwrap();
+        assert_eq!(
+            meta.list,
+            vec![
+                CmpValues::U32((0x1122_3344, 0x5566_7788)),
+                CmpValues::U32((5, 6)),
+                CmpValues::Bytes((b"hello".to_vec(), b"world".to_vec())),
+                CmpValues::Bytes((b"fuzz!".to_vec(), b"bytes".to_vec())),
+            ]
+        );
+    }
+}
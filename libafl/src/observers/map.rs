@@ -5,7 +5,7 @@ use alloc::{
     vec::Vec,
 };
 use core::{
-    fmt::Debug,
+    fmt::{Debug, Write},
     hash::{BuildHasher, Hasher},
     iter::Flatten,
     marker::PhantomData,
@@ -14,7 +14,7 @@ use core::{
 
 use ahash::RandomState;
 use intervaltree::IntervalTree;
-use num_traits::Bounded;
+use num_traits::{Bounded, ToPrimitive};
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -124,6 +124,47 @@ pub trait MapObserver: HasLen + Named + Serialize + serde::de::DeserializeOwned
     }
 }
 
+/// Dump the entries of a [`MapObserver`] not set to its initial value, in the format of `afl-showmap`.
+///
+/// Each entry is written on its own line as `index:value`, with the index padded to six digits,
+/// e.g. `001337:4`, in increasing order of index. For a [`HitcountsMapObserver`], the values are the
+/// classified hitcounts (`1`, `2`, `4`, `8`, ..., `128`), as with `afl-showmap` without `-r`.
+pub fn showmap_dump<O>(observer: &O) -> String
+where
+    O: MapObserver,
+    O::Entry: ToPrimitive,
+{
+    let initial = observer.initial();
+    let mut dump = String::new();
+    for idx in 0..observer.usable_count() {
+        let value = *observer.get(idx);
+        if value != initial {
+            writeln!(dump, "{idx:06}:{}", value.to_u64().unwrap_or(u64::MAX)).unwrap();
+        }
+    }
+    dump
+}
+
+/// Parse a map dump written by [`showmap_dump`] or by `afl-showmap` into `(index, value)` pairs.
+/// Empty lines are skipped.
+pub fn parse_showmap_dump(dump: &str) -> Result<Vec<(usize, u64)>, Error> {
+    let mut entries = vec![];
+    for line in dump.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let entry = line
+            .split_once(':')
+            .and_then(|(idx, value)| Some((idx.parse().ok()?, value.parse().ok()?)));
+        match entry {
+            Some(entry) => entries.push(entry),
+            None => {
+                return Err(Error::illegal_argument(format!(
+                    "Invalid map dump line: {line}"
+                )))
+            }
+        }
+    }
+    Ok(entries)
+}
+
 /// A Simple iterator calling `MapObserver::get`
 #[derive(Debug)]
 pub struct MapObserverSimpleIterator<'a, O>
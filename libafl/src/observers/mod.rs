@@ -281,6 +281,16 @@ where
 pub trait ObserverWithHashField {
     /// get the value of the hash field
     fn hash(&self) -> Option<u64>;
+
+    /// get the type of the bug, if the observer knows it (e.g. from sanitizer output)
+    fn bug_type(&self) -> Option<&str> {
+        None
+    }
+
+    /// get the signal of the crash, if the observer knows it
+    fn signal(&self) -> Option<i32> {
+        None
+    }
 }
 
 /// A trait for [`Observer`]`s` which observe over differential execution.
@@ -1325,11 +1335,13 @@ mod tests {
 
     use crate::{
         bolts::tuples::{tuple_list, tuple_list_type, Named},
-        observers::{StdMapObserver, TimeObserver},
+        observers::{parse_showmap_dump, showmap_dump, StdMapObserver, TimeObserver},
     };
 
     static mut MAP: [u32; 4] = [0; 4];
 
+    static mut SHOWMAP: [u8; 8] = [0, 1, 0, 0, 4, 0, 0, 128];
+
     #[test]
     fn test_observer_serde() {
         let obv = tuple_list!(TimeObserver::new("time"), unsafe {
@@ -1341,4 +1353,16 @@ mod tests {
             postcard::from_bytes(&vec).unwrap();
         assert_eq!(obv.0.name(), obv2.0.name());
     }
+
+    #[test]
+    fn test_showmap_dump() {
+        let observer = unsafe { StdMapObserver::new("map", &mut SHOWMAP) };
+        let dump = showmap_dump(&observer);
+        assert_eq!(dump, "000001:1\n000004:4\n000007:128\n");
+        assert_eq!(
+            parse_showmap_dump(&dump).unwrap(),
+            [(1, 1), (4, 4), (7, 128)]
+        );
+        assert!(parse_showmap_dump("1:1\nfoo").is_err());
+    }
 }
@@ -9,11 +9,13 @@ use std::{
     process::ChildStderr,
 };
 
-use backtrace::Backtrace;
+use backtrace::{Backtrace, BacktraceFrame};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 use super::ObserverWithHashField;
+#[cfg(unix)]
+use crate::executors::inprocess::CRASH_SIGNAL;
 use crate::{
     bolts::{ownedref::OwnedRefMut, tuples::Named},
     executors::ExitKind,
@@ -45,6 +47,58 @@ pub fn collect_backtrace() -> u64 {
     hash
 }
 
+/// The symbols of the frames between a crash handler and the crashing frames:
+/// the signal trampolines, and the panic machinery
+const CRASH_ENTRY_SYMBOLS: [&str; 7] = [
+    "__restore_rt",
+    "_sigtramp",
+    "rust_begin_unwind",
+    "__rust_end_short_backtrace",
+    "std::panicking::begin_panic",
+    "std::panicking::rust_panic_with_hook",
+    "core::panicking::",
+];
+
+/// Collects the backtrace like [`collect_backtrace`], but only hashes the `top_frames` frames the closest to the crash.
+/// The frames of the crash handler, up to the signal trampoline or the panic machinery, are skipped,
+/// so the symbols of the frames are resolved, which is slow.
+#[must_use]
+pub fn collect_backtrace_top(top_frames: usize) -> u64 {
+    let mut b = Backtrace::new_unresolved();
+    b.resolve();
+    let frames = b.frames();
+    let matches = |frame: &BacktraceFrame, patterns: &[&str]| {
+        frame.symbols().iter().any(|symbol| {
+            symbol.name().is_some_and(|name| {
+                let name = name.to_string();
+                patterns.iter().any(|pattern| name.contains(pattern))
+            })
+        })
+    };
+    // Without a crash handler, skip up to this function
+    let entry = frames
+        .iter()
+        .position(|frame| matches(frame, &CRASH_ENTRY_SYMBOLS))
+        .map_or_else(
+            || {
+                frames
+                    .iter()
+                    .position(|frame| matches(frame, &["collect_backtrace_top"]))
+                    .map_or(0, |pos| pos + 1)
+            },
+            |pos| {
+                pos + frames[pos..]
+                    .iter()
+                    .take_while(|frame| matches(frame, &CRASH_ENTRY_SYMBOLS))
+                    .count()
+            },
+        );
+    frames[entry..]
+        .iter()
+        .take(top_frames)
+        .fold(0, |hash, frame| hash ^ frame.ip() as u64)
+}
+
 /// An enum encoding the types of harnesses
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum HarnessType {
@@ -61,6 +115,10 @@ pub struct BacktraceObserver<'a> {
     observer_name: String,
     hash: OwnedRefMut<'a, Option<u64>>,
     harness_type: HarnessType,
+    /// The number of frames of the crashing stack to hash, all frames if `None`
+    top_frames: Option<usize>,
+    /// The signal of the last crash
+    signal: Option<i32>,
 }
 
 impl<'a> BacktraceObserver<'a> {
@@ -75,6 +133,23 @@ impl<'a> BacktraceObserver<'a> {
             observer_name: observer_name.to_string(),
             hash: OwnedRefMut::Ref(backtrace_hash),
             harness_type,
+            top_frames: None,
+            signal: None,
+        }
+    }
+
+    /// Creates a new [`BacktraceObserver`] with the given name, only hashing the top `top_frames` frames
+    /// of the crashing stack, see [`collect_backtrace_top`].
+    #[must_use]
+    pub fn with_top_frames(
+        observer_name: &str,
+        backtrace_hash: &'a mut Option<u64>,
+        harness_type: HarnessType,
+        top_frames: usize,
+    ) -> Self {
+        Self {
+            top_frames: Some(top_frames),
+            ..Self::new(observer_name, backtrace_hash, harness_type)
         }
     }
 
@@ -86,6 +161,28 @@ impl<'a> BacktraceObserver<'a> {
     /// Clears the current hash value (sets it to `None`)
     fn clear_hash(&mut self) {
         *self.hash.as_mut() = None;
+        self.signal = None;
+    }
+
+    /// The hash of the backtrace of the current crash
+    fn crash_hash(&self) -> u64 {
+        match self.top_frames {
+            Some(top_frames) => collect_backtrace_top(top_frames),
+            None => collect_backtrace(),
+        }
+    }
+
+    /// Takes the signal of the current crash, as stored by the crash handlers of the executor
+    fn take_crash_signal() -> Option<i32> {
+        #[cfg(unix)]
+        {
+            let signal = CRASH_SIGNAL.swap(0, core::sync::atomic::Ordering::Relaxed);
+            (signal != 0).then_some(signal)
+        }
+        #[cfg(not(unix))]
+        {
+            None
+        }
     }
 }
 
@@ -95,6 +192,12 @@ impl<'a> ObserverWithHashField for BacktraceObserver<'a> {
     fn hash(&self) -> Option<u64> {
         *self.hash.as_ref()
     }
+
+    /// Gets the signal of the crash, for an in-process harness.
+    /// Only the hash is shared with the parent of a child harness, so the signal is mixed into the hash instead.
+    fn signal(&self) -> Option<i32> {
+        self.signal
+    }
 }
 
 impl<'a, S> Observer<S> for BacktraceObserver<'a>
@@ -109,7 +212,8 @@ where
     ) -> Result<(), Error> {
         if self.harness_type == HarnessType::InProcess {
             if exit_kind == &ExitKind::Crash {
-                self.update_hash(collect_backtrace());
+                self.update_hash(self.crash_hash());
+                self.signal = Self::take_crash_signal();
             } else {
                 self.clear_hash();
             }
@@ -125,7 +229,8 @@ where
     ) -> Result<(), Error> {
         if self.harness_type == HarnessType::Child {
             if exit_kind == &ExitKind::Crash {
-                self.update_hash(collect_backtrace());
+                let signal = Self::take_crash_signal().unwrap_or_default();
+                self.update_hash(self.crash_hash() ^ u64::from(signal.unsigned_abs()) << 56);
             } else {
                 self.clear_hash();
             }
@@ -173,6 +278,10 @@ pub fn get_asan_runtime_flags() -> String {
 pub struct AsanBacktraceObserver {
     observer_name: String,
     hash: Option<u64>,
+    /// The number of frames of the crashing stack to hash, all frames if `None`
+    top_frames: Option<usize>,
+    /// The bug type reported by the sanitizer, such as `heap-buffer-overflow`
+    bug_type: Option<String>,
 }
 
 impl AsanBacktraceObserver {
@@ -182,6 +291,18 @@ impl AsanBacktraceObserver {
         Self {
             observer_name: observer_name.to_string(),
             hash: None,
+            top_frames: None,
+            bug_type: None,
+        }
+    }
+
+    /// Creates a new [`AsanBacktraceObserver`] with the given name, only hashing the top `top_frames` frames
+    /// of the crashing stack, so that crashes reaching the same bug through different paths get the same hash.
+    #[must_use]
+    pub fn with_top_frames(observer_name: &str, top_frames: usize) -> Self {
+        Self {
+            top_frames: Some(top_frames),
+            ..Self::new(observer_name)
         }
     }
 
@@ -212,12 +333,24 @@ impl AsanBacktraceObserver {
     /// parse ASAN error output emited by the target command and compute the hash
     pub fn parse_asan_output(&mut self, output: &str) {
         let mut hash = 0;
-        let matcher = Regex::new("\\s*#[0-9]*\\s0x([0-9a-f]*)\\s.*").unwrap();
-        matcher.captures_iter(output).for_each(|m| {
-            let g = m.get(1).unwrap();
+        let matcher = Regex::new("\\s*#([0-9]*)\\s0x([0-9a-f]*)\\s.*").unwrap();
+        for (i, m) in matcher.captures_iter(output).enumerate() {
+            if let Some(top_frames) = self.top_frames {
+                // The crashing stack comes first, the numbering restarts with the allocation stacks
+                let frame: usize = m.get(1).unwrap().as_str().parse().unwrap_or(0);
+                if frame >= top_frames || (i > 0 && frame == 0) {
+                    break;
+                }
+            }
+            let g = m.get(2).unwrap();
             hash ^= u64::from_str_radix(g.as_str(), 16).unwrap();
-        });
+        }
         self.update_hash(hash);
+
+        let matcher = Regex::new("ERROR: \\w*Sanitizer: ([\\w-]+)").unwrap();
+        self.bug_type = matcher
+            .captures(output)
+            .map(|m| m.get(1).unwrap().as_str().to_string());
     }
 
     /// Updates the hash value of this observer.
@@ -232,6 +365,11 @@ impl ObserverWithHashField for AsanBacktraceObserver {
     fn hash(&self) -> Option<u64> {
         self.hash
     }
+
+    /// Gets the bug type reported by the sanitizer, such as `heap-buffer-overflow` or `SEGV`.
+    fn bug_type(&self) -> Option<&str> {
+        self.bug_type.as_deref()
+    }
 }
 
 impl Default for AsanBacktraceObserver {
@@ -274,3 +412,29 @@ impl Named for AsanBacktraceObserver {
         &self.observer_name
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::collect_backtrace_top;
+
+    #[inline(never)]
+    fn leaf(top_frames: usize) -> u64 {
+        collect_backtrace_top(top_frames)
+    }
+
+    #[inline(never)]
+    fn first_caller(top_frames: usize) -> u64 {
+        core::hint::black_box(leaf(core::hint::black_box(top_frames)))
+    }
+
+    #[inline(never)]
+    fn second_caller(top_frames: usize) -> u64 {
+        core::hint::black_box(leaf(core::hint::black_box(top_frames)))
+    }
+
+    #[test]
+    fn test_collect_backtrace_top() {
+        assert_eq!(first_caller(1), second_caller(1));
+        assert_ne!(first_caller(2), second_caller(2));
+    }
+}
@@ -0,0 +1,645 @@
+//! The [`BanditScheduler`] is a meta-scheduler, choosing among several [`Scheduler`]s with a multi-armed bandit.
+//! Each sub-scheduler is an arm, rewarded when the testcase it picked leads to new corpus entries,
+//! so the fuzzer learns which scheduler works best for the target.
+//! The [`BanditPowerScheduler`] likewise chooses the [`PowerSchedule`] of a single power scheduler.
+
+use alloc::{string::ToString, vec::Vec};
+use core::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bolts::rands::Rand,
+    corpus::{Corpus, CorpusId, LineageMetadata, Testcase},
+    inputs::UsesInput,
+    schedulers::{
+        powersched::{PowerSchedule, SchedulerMetadata},
+        Scheduler, SchedulersTuple,
+    },
+    state::{HasCorpus, HasMetadata, HasRand, UsesState},
+    Error,
+};
+
+/// The algorithm choosing the arm of a multi-armed bandit
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BanditAlgorithm {
+    /// The upper confidence bound algorithm `UCB1`: the arm with the best mean reward plus an exploration bonus
+    #[default]
+    Ucb1,
+    /// Thompson sampling: the arm with the best reward probability sampled from its beta distribution
+    Thompson,
+}
+
+/// The statistics of an arm of a multi-armed bandit
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BanditArm {
+    /// The number of times this arm was chosen
+    pub pulls: u64,
+    /// The number of times this arm was rewarded, at most once per pull
+    pub rewards: u64,
+}
+
+impl BanditArm {
+    /// The mean reward of this arm, in `[0, 1]`
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn mean(&self) -> f64 {
+        if self.pulls == 0 {
+            0.0
+        } else {
+            self.rewards as f64 / self.pulls as f64
+        }
+    }
+}
+
+/// The arms of a multi-armed bandit, with binary rewards
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BanditArms {
+    arms: Vec<BanditArm>,
+}
+
+impl BanditArms {
+    /// Creates `len` new arms, never pulled
+    #[must_use]
+    pub fn new(len: usize) -> Self {
+        Self {
+            arms: vec![BanditArm::default(); len],
+        }
+    }
+
+    /// The statistics of the arms
+    #[must_use]
+    pub fn arms(&self) -> &[BanditArm] {
+        &self.arms
+    }
+
+    /// The number of arms
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.arms.len()
+    }
+
+    /// If there are no arms
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.arms.is_empty()
+    }
+
+    /// Resize to `len` arms, keeping the statistics of the existing ones
+    pub fn resize(&mut self, len: usize) {
+        self.arms.resize(len, BanditArm::default());
+    }
+
+    /// Count a pull of the given arm
+    pub fn pull(&mut self, arm: usize) {
+        self.arms[arm].pulls += 1;
+    }
+
+    /// Count a reward of the given arm
+    pub fn reward(&mut self, arm: usize) {
+        self.arms[arm].rewards += 1;
+    }
+
+    /// Choose the next arm to pull with the given algorithm
+    #[allow(clippy::cast_precision_loss)]
+    pub fn select<R>(&self, algorithm: BanditAlgorithm, rand: &mut R) -> usize
+    where
+        R: Rand,
+    {
+        // Try each arm once first
+        if let Some(arm) = self.arms.iter().position(|arm| arm.pulls == 0) {
+            return arm;
+        }
+        let scores = self.arms.iter().map(|arm| match algorithm {
+            BanditAlgorithm::Ucb1 => {
+                let total: u64 = self.arms.iter().map(|arm| arm.pulls).sum();
+                arm.mean() + libm::sqrt(2.0 * libm::log(total as f64) / arm.pulls as f64)
+            }
+            BanditAlgorithm::Thompson => sample_beta(
+                rand,
+                arm.rewards as f64 + 1.0,
+                (arm.pulls - arm.rewards) as f64 + 1.0,
+            ),
+        });
+        let mut best = 0;
+        let mut best_score = f64::NEG_INFINITY;
+        for (arm, score) in scores.enumerate() {
+            if score > best_score {
+                best = arm;
+                best_score = score;
+            }
+        }
+        best
+    }
+}
+
+/// A uniform sample in `(0, 1)`
+#[allow(clippy::cast_precision_loss)]
+fn sample_uniform<R>(rand: &mut R) -> f64
+where
+    R: Rand,
+{
+    ((rand.next() >> 11) as f64 + 0.5) / (1_u64 << 53) as f64
+}
+
+/// A sample of the standard normal distribution, with the Box-Muller transform
+fn sample_normal<R>(rand: &mut R) -> f64
+where
+    R: Rand,
+{
+    let r = libm::sqrt(-2.0 * libm::log(sample_uniform(rand)));
+    r * libm::cos(2.0 * core::f64::consts::PI * sample_uniform(rand))
+}
+
+/// A sample of the gamma distribution of the given shape, at least 1, with the method of Marsaglia and Tsang
+fn sample_gamma<R>(rand: &mut R, shape: f64) -> f64
+where
+    R: Rand,
+{
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / libm::sqrt(9.0 * d);
+    loop {
+        let x = sample_normal(rand);
+        let v = 1.0 + c * x;
+        if v <= 0.0 {
+            continue;
+        }
+        let v = v * v * v;
+        if libm::log(sample_uniform(rand)) < 0.5 * x * x + d - d * v + d * libm::log(v) {
+            return d * v;
+        }
+    }
+}
+
+/// A sample of the beta distribution of the given parameters, both at least 1
+pub(crate) fn sample_beta<R>(rand: &mut R, alpha: f64, beta: f64) -> f64
+where
+    R: Rand,
+{
+    let x = sample_gamma(rand, alpha);
+    let y = sample_gamma(rand, beta);
+    x / (x + y)
+}
+
+/// The state of the [`BanditScheduler`]: the statistics of each sub-scheduler, kept across restarts
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BanditSchedulerMetadata {
+    /// The statistics of each sub-scheduler
+    arms: BanditArms,
+    /// The sub-scheduler which picked the current testcase
+    current_arm: Option<usize>,
+    /// If the current pull was already rewarded
+    rewarded: bool,
+}
+
+crate::impl_serdeany!(BanditSchedulerMetadata);
+
+impl BanditSchedulerMetadata {
+    /// Creates a new [`BanditSchedulerMetadata`] for `len` sub-schedulers
+    #[must_use]
+    pub fn new(len: usize) -> Self {
+        Self {
+            arms: BanditArms::new(len),
+            current_arm: None,
+            rewarded: false,
+        }
+    }
+
+    /// The statistics of each sub-scheduler
+    #[must_use]
+    pub fn arms(&self) -> &BanditArms {
+        &self.arms
+    }
+
+    /// The sub-scheduler which picked the current testcase
+    #[must_use]
+    pub fn current_arm(&self) -> Option<usize> {
+        self.current_arm
+    }
+
+    /// Count a pull of the given arm, which picked the current testcase
+    fn pull(&mut self, arm: usize) {
+        self.arms.pull(arm);
+        self.current_arm = Some(arm);
+        self.rewarded = false;
+    }
+
+    /// Reward the current arm, at most once per pull
+    fn reward_current(&mut self) {
+        if let (Some(arm), false) = (self.current_arm, self.rewarded) {
+            self.arms.reward(arm);
+            self.rewarded = true;
+        }
+    }
+}
+
+/// If the testcase was derived from the current testcase by a local fuzzing stage,
+/// as recorded in its [`LineageMetadata`] when it was added to the corpus.
+/// Testcases received from other clients, or imported, e.g. from disk, were not.
+fn found_by_fuzzing_current<S>(state: &S, idx: CorpusId) -> Result<bool, Error>
+where
+    S: HasCorpus,
+{
+    let Some(current) = *state.corpus().current() else {
+        return Ok(false);
+    };
+    let testcase = state.corpus().get(idx)?.borrow();
+    Ok(testcase
+        .metadata()
+        .get::<LineageMetadata>()
+        .is_some_and(|lineage| {
+            lineage.client.is_none()
+                && lineage.stage.is_some()
+                && lineage.parents.contains(&current)
+        }))
+}
+
+/// A meta-scheduler, choosing the sub-scheduler picking the next testcase with a multi-armed bandit.
+///
+/// A sub-scheduler is rewarded when the testcase it picked leads to at least a new corpus entry,
+/// i.e., new coverage, found by the fuzzing stages: testcases received from other clients earn no reward.
+/// All sub-schedulers are notified of the corpus changes, to keep their own metadata up to date.
+/// Sub-schedulers keeping their state in the same metadata, such as two [`crate::schedulers::PowerQueueScheduler`]s,
+/// would overwrite each other: to choose among power schedules, use the [`BanditPowerScheduler`].
+///
+/// The statistics are kept in the [`BanditSchedulerMetadata`] of the state, so they survive restarts.
+#[derive(Debug, Clone)]
+pub struct BanditScheduler<ST, S> {
+    schedulers: ST,
+    algorithm: BanditAlgorithm,
+    phantom: PhantomData<S>,
+}
+
+impl<ST, S> BanditScheduler<ST, S>
+where
+    ST: SchedulersTuple<S>,
+    S: HasCorpus + HasMetadata + HasRand,
+{
+    /// Creates a new [`BanditScheduler`] choosing among the given schedulers with `UCB1`
+    #[must_use]
+    pub fn new(schedulers: ST) -> Self {
+        Self::with_algorithm(schedulers, BanditAlgorithm::Ucb1)
+    }
+
+    /// Creates a new [`BanditScheduler`] choosing among the given schedulers with the given algorithm
+    #[must_use]
+    pub fn with_algorithm(schedulers: ST, algorithm: BanditAlgorithm) -> Self {
+        Self {
+            schedulers,
+            algorithm,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The sub-schedulers
+    #[must_use]
+    pub fn schedulers(&self) -> &ST {
+        &self.schedulers
+    }
+
+    /// The metadata of this scheduler, created if missing
+    fn metadata_mut<'a>(&self, state: &'a mut S) -> &'a mut BanditSchedulerMetadata {
+        if !state.has_metadata::<BanditSchedulerMetadata>() {
+            state.add_metadata(BanditSchedulerMetadata::new(self.schedulers.len()));
+        }
+        let meta = state
+            .metadata_mut()
+            .get_mut::<BanditSchedulerMetadata>()
+            .unwrap();
+        // The number of sub-schedulers may change across restarts
+        meta.arms.resize(self.schedulers.len());
+        meta
+    }
+}
+
+impl<ST, S> UsesState for BanditScheduler<ST, S>
+where
+    S: UsesInput,
+{
+    type State = S;
+}
+
+impl<ST, S> Scheduler for BanditScheduler<ST, S>
+where
+    ST: SchedulersTuple<S>,
+    S: HasCorpus + HasMetadata + HasRand,
+{
+    fn on_add(&self, state: &mut S, idx: CorpusId) -> Result<(), Error> {
+        // The testcase being fuzzed, picked by the current arm, led to a new entry
+        if found_by_fuzzing_current(state, idx)? {
+            self.metadata_mut(state).reward_current();
+        }
+        self.schedulers.on_add_all(state, idx)
+    }
+
+    fn on_replace(
+        &self,
+        state: &mut S,
+        idx: CorpusId,
+        prev: &Testcase<S::Input>,
+    ) -> Result<(), Error> {
+        self.schedulers.on_replace_all(state, idx, prev)
+    }
+
+    fn on_remove(
+        &self,
+        state: &mut S,
+        idx: CorpusId,
+        testcase: &Option<Testcase<S::Input>>,
+    ) -> Result<(), Error> {
+        self.schedulers.on_remove_all(state, idx, testcase)
+    }
+
+    fn next(&self, state: &mut S) -> Result<CorpusId, Error> {
+        if state.corpus().count() == 0 {
+            return Err(Error::empty("No entries in corpus".to_string()));
+        }
+        let arms = self.metadata_mut(state).arms.clone();
+        let arm = arms.select(self.algorithm, state.rand_mut());
+
+        self.metadata_mut(state).pull(arm);
+
+        self.schedulers.get_and_next(arm, state)
+    }
+}
+
+/// The state of the [`BanditPowerScheduler`]: the statistics of each power schedule, kept across restarts
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BanditPowerScheduleMetadata(BanditSchedulerMetadata);
+
+crate::impl_serdeany!(BanditPowerScheduleMetadata);
+
+impl Deref for BanditPowerScheduleMetadata {
+    type Target = BanditSchedulerMetadata;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for BanditPowerScheduleMetadata {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Wraps a scheduler using power schedules, such as the [`crate::schedulers::PowerQueueScheduler`]
+/// or the [`crate::schedulers::WeightedScheduler`], and chooses the [`PowerSchedule`] of each pick with a multi-armed bandit.
+///
+/// Before each pick, the chosen power schedule is set in the [`SchedulerMetadata`] of the state,
+/// so the scheduler, and the power stage fuzzing the testcase, both use it.
+/// A power schedule is rewarded as the arms of the [`BanditScheduler`] are.
+/// The statistics are kept in the [`BanditPowerScheduleMetadata`] of the state, so they survive restarts.
+#[derive(Debug, Clone)]
+pub struct BanditPowerScheduler<CS> {
+    scheduler: CS,
+    schedules: Vec<PowerSchedule>,
+    algorithm: BanditAlgorithm,
+}
+
+impl<CS> BanditPowerScheduler<CS>
+where
+    CS: Scheduler,
+    CS::State: HasCorpus + HasMetadata + HasRand,
+{
+    /// Creates a new [`BanditPowerScheduler`] choosing among the given power schedules with `UCB1`
+    #[must_use]
+    pub fn new(scheduler: CS, power_schedules: Vec<PowerSchedule>) -> Self {
+        Self::with_algorithm(scheduler, power_schedules, BanditAlgorithm::Ucb1)
+    }
+
+    /// Creates a new [`BanditPowerScheduler`] choosing among the given power schedules with the given algorithm
+    #[must_use]
+    pub fn with_algorithm(
+        scheduler: CS,
+        power_schedules: Vec<PowerSchedule>,
+        algorithm: BanditAlgorithm,
+    ) -> Self {
+        assert!(
+            !power_schedules.is_empty(),
+            "The BanditPowerScheduler needs at least one power schedule"
+        );
+        Self {
+            scheduler,
+            schedules: power_schedules,
+            algorithm,
+        }
+    }
+
+    /// The wrapped scheduler
+    #[must_use]
+    pub fn scheduler(&self) -> &CS {
+        &self.scheduler
+    }
+
+    /// The power schedules to choose from
+    #[must_use]
+    pub fn schedules(&self) -> &[PowerSchedule] {
+        &self.schedules
+    }
+
+    /// The metadata of this scheduler, created if missing
+    fn metadata_mut<'a>(&self, state: &'a mut CS::State) -> &'a mut BanditPowerScheduleMetadata {
+        if !state.has_metadata::<BanditPowerScheduleMetadata>() {
+            state.add_metadata(BanditPowerScheduleMetadata(BanditSchedulerMetadata::new(
+                self.schedules.len(),
+            )));
+        }
+        let meta = state
+            .metadata_mut()
+            .get_mut::<BanditPowerScheduleMetadata>()
+            .unwrap();
+        // The power schedules may change across restarts
+        meta.arms.resize(self.schedules.len());
+        meta
+    }
+}
+
+impl<CS> UsesState for BanditPowerScheduler<CS>
+where
+    CS: UsesState,
+{
+    type State = CS::State;
+}
+
+impl<CS> Scheduler for BanditPowerScheduler<CS>
+where
+    CS: Scheduler,
+    CS::State: HasCorpus + HasMetadata + HasRand,
+{
+    fn on_add(&self, state: &mut CS::State, idx: CorpusId) -> Result<(), Error> {
+        if found_by_fuzzing_current(state, idx)? {
+            self.metadata_mut(state).reward_current();
+        }
+        self.scheduler.on_add(state, idx)
+    }
+
+    fn on_replace(
+        &self,
+        state: &mut CS::State,
+        idx: CorpusId,
+        prev: &Testcase<<CS::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        self.scheduler.on_replace(state, idx, prev)
+    }
+
+    fn on_remove(
+        &self,
+        state: &mut CS::State,
+        idx: CorpusId,
+        testcase: &Option<Testcase<<CS::State as UsesInput>::Input>>,
+    ) -> Result<(), Error> {
+        self.scheduler.on_remove(state, idx, testcase)
+    }
+
+    fn next(&self, state: &mut CS::State) -> Result<CorpusId, Error> {
+        if state.corpus().count() == 0 {
+            return Err(Error::empty("No entries in corpus".to_string()));
+        }
+        let arms = self.metadata_mut(state).arms.clone();
+        let arm = arms.select(self.algorithm, state.rand_mut());
+        self.metadata_mut(state).pull(arm);
+
+        let schedule = self.schedules[arm];
+        match state.metadata_mut().get_mut::<SchedulerMetadata>() {
+            Some(meta) => meta.set_strat(Some(schedule)),
+            None => state.add_metadata(SchedulerMetadata::new(Some(schedule))),
+        }
+        self.scheduler.next(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::{rands::StdRand, tuples::tuple_list},
+        corpus::{Corpus, CorpusId, InMemoryCorpus, LineageMetadata, Testcase},
+        feedbacks::ConstFeedback,
+        inputs::BytesInput,
+        schedulers::{
+            bandit::{
+                BanditAlgorithm, BanditArms, BanditPowerScheduleMetadata, BanditPowerScheduler,
+                BanditScheduler, BanditSchedulerMetadata,
+            },
+            powersched::{PowerSchedule, SchedulerMetadata},
+            PowerQueueScheduler, QueueScheduler, RandScheduler, Scheduler,
+        },
+        state::{HasCorpus, HasMetadata, StdState},
+    };
+
+    #[test]
+    fn test_bandit_arms() {
+        let mut rand = StdRand::with_seed(0);
+        for algorithm in [BanditAlgorithm::Ucb1, BanditAlgorithm::Thompson] {
+            let mut arms = BanditArms::new(2);
+            let mut chosen = [0; 2];
+            for _ in 0..1000 {
+                let arm = arms.select(algorithm, &mut rand);
+                chosen[arm] += 1;
+                arms.pull(arm);
+                // Only the second arm is ever rewarded
+                if arm == 1 {
+                    arms.reward(arm);
+                }
+            }
+            assert!(chosen[1] > chosen[0] * 4, "{algorithm:?}: {chosen:?}");
+        }
+    }
+
+    /// A testcase found by a fuzzing stage from the given parent, or received from another client
+    fn testcase(byte: u8, parent: Option<CorpusId>) -> Testcase<BytesInput> {
+        let mut testcase = Testcase::new(BytesInput::new(vec![byte]));
+        let lineage = match parent {
+            Some(parent) => LineageMetadata {
+                parents: vec![parent],
+                stage: Some("StdMutationalStage".into()),
+                ..LineageMetadata::new()
+            },
+            None => LineageMetadata::new(),
+        };
+        testcase.add_metadata(lineage);
+        testcase
+    }
+
+    #[test]
+    fn test_bandit_scheduler() {
+        let scheduler =
+            BanditScheduler::new(tuple_list!(QueueScheduler::new(), RandScheduler::new()));
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut ConstFeedback::new(false),
+            &mut ConstFeedback::new(false),
+        )
+        .unwrap();
+
+        let idx = state.corpus_mut().add(testcase(0, None)).unwrap();
+        scheduler.on_add(&mut state, idx).unwrap();
+
+        // Both schedulers are tried, the one picking the current testcase gets the reward of the new entry
+        scheduler.next(&mut state).unwrap();
+        let current = scheduler.next(&mut state).unwrap();
+        // A testcase received from another client, meanwhile, earns nothing
+        let idx = state.corpus_mut().add(testcase(1, None)).unwrap();
+        scheduler.on_add(&mut state, idx).unwrap();
+        assert_eq!(
+            state
+                .metadata()
+                .get::<BanditSchedulerMetadata>()
+                .unwrap()
+                .arms()
+                .arms()[1]
+                .rewards,
+            0
+        );
+        let idx = state.corpus_mut().add(testcase(2, Some(current))).unwrap();
+        scheduler.on_add(&mut state, idx).unwrap();
+
+        let meta = state.metadata().get::<BanditSchedulerMetadata>().unwrap();
+        assert_eq!(meta.current_arm(), Some(1));
+        assert_eq!(meta.arms().arms()[0].pulls, 1);
+        assert_eq!(meta.arms().arms()[0].rewards, 0);
+        assert_eq!(meta.arms().arms()[1].rewards, 1);
+    }
+
+    #[test]
+    fn test_bandit_power_scheduler() {
+        let schedules = vec![PowerSchedule::EXPLORE, PowerSchedule::FAST];
+        let scheduler = BanditPowerScheduler::new(
+            PowerQueueScheduler::new(PowerSchedule::EXPLORE),
+            schedules.clone(),
+        );
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut ConstFeedback::new(false),
+            &mut ConstFeedback::new(false),
+        )
+        .unwrap();
+        let idx = state.corpus_mut().add(testcase(0, None)).unwrap();
+        scheduler.on_add(&mut state, idx).unwrap();
+
+        // Each pick uses the power schedule of the chosen arm
+        for arm in 0..2 {
+            let current = scheduler.next(&mut state).unwrap();
+            let strat = state.metadata().get::<SchedulerMetadata>().unwrap().strat();
+            assert_eq!(strat, Some(schedules[arm]));
+            if arm == 1 {
+                let idx = state.corpus_mut().add(testcase(1, Some(current))).unwrap();
+                scheduler.on_add(&mut state, idx).unwrap();
+            }
+        }
+
+        let meta = state
+            .metadata()
+            .get::<BanditPowerScheduleMetadata>()
+            .unwrap();
+        assert_eq!(meta.arms().arms()[0].rewards, 0);
+        assert_eq!(meta.arms().arms()[1].rewards, 1);
+    }
+}
@@ -0,0 +1,406 @@
+//! The directed scheduler computes the distance of each [`Testcase`] to a set of target sites, as in `AFLGo`.
+//! The distances of the edges to the targets are computed ahead of time, e.g. using the `ControlFlowGraph` of `libafl_cc`.
+//! It is meant to be used with the [`crate::schedulers::testcase_score::AnnealingTestcaseScore`] power schedule.
+
+use alloc::string::ToString;
+use core::time::Duration;
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bolts::current_time,
+    corpus::{Corpus, CorpusId, Testcase},
+    feedbacks::MapIndexesMetadata,
+    inputs::UsesInput,
+    schedulers::Scheduler,
+    state::{HasCorpus, HasMetadata, UsesState},
+    Error,
+};
+
+/// The default time after which the annealing schedule is fully in exploitation mode
+pub const DEFAULT_TIME_TO_EXPLOIT: Duration = Duration::from_secs(60 * 60 * 10);
+
+/// The cooling schedule used to compute the temperature of the simulated annealing
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoolingSchedule {
+    /// Exponential cooling, the default of `AFLGo`
+    Exp,
+    /// Logarithmic cooling
+    Log,
+    /// Linear cooling
+    Lin,
+    /// Quadratic cooling
+    Quad,
+}
+
+impl CoolingSchedule {
+    /// Compute the temperature, going from `1.0` to around `0.05`, given the progress (from `0.0` to `1.0`) towards the exploitation time.
+    #[must_use]
+    pub fn temperature(&self, progress: f64) -> f64 {
+        let progress = progress.clamp(0.0, 1.0);
+        match self {
+            CoolingSchedule::Exp => libm::pow(20.0, -progress),
+            CoolingSchedule::Log => 1.0 - 0.95 * libm::log(1.0 + 19.0 * progress) / libm::log(20.0),
+            CoolingSchedule::Lin => 1.0 / (1.0 + 19.0 * progress),
+            CoolingSchedule::Quad => 1.0 / (1.0 + 19.0 * progress * progress),
+        }
+    }
+}
+
+/// The state metadata holding the distance of each edge to the targets, used by the [`DirectedScheduler`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DistanceMetadata {
+    /// The distance of each edge of the coverage map to the targets
+    edge_distances: HashMap<usize, f64>,
+    /// The minimum distance of a [`Testcase`] in the corpus.
+    /// It only grows closer as testcases are added, and is recomputed when testcases are removed or replaced.
+    min_distance: f64,
+    /// The maximum distance of a [`Testcase`] in the corpus, updated like `min_distance`
+    max_distance: f64,
+    /// The cooling schedule of the annealing
+    cooling: CoolingSchedule,
+    /// The time after which only the closest testcases get fuzzed
+    time_to_exploit: Duration,
+    /// The time the directed fuzzing started
+    start_time: Duration,
+}
+
+crate::impl_serdeany!(DistanceMetadata);
+
+impl DistanceMetadata {
+    /// Creates a new [`struct@DistanceMetadata`] given the distance of each edge to the targets
+    #[must_use]
+    pub fn new(edge_distances: HashMap<usize, f64>) -> Self {
+        Self::with_cooling(
+            edge_distances,
+            CoolingSchedule::Exp,
+            DEFAULT_TIME_TO_EXPLOIT,
+        )
+    }
+
+    /// Creates a new [`struct@DistanceMetadata`] with a given cooling schedule and time to exploitation
+    #[must_use]
+    pub fn with_cooling(
+        edge_distances: HashMap<usize, f64>,
+        cooling: CoolingSchedule,
+        time_to_exploit: Duration,
+    ) -> Self {
+        Self {
+            edge_distances,
+            min_distance: f64::MAX,
+            max_distance: 0.0,
+            cooling,
+            time_to_exploit,
+            start_time: current_time(),
+        }
+    }
+
+    /// The distance of each edge to the targets
+    #[must_use]
+    pub fn edge_distances(&self) -> &HashMap<usize, f64> {
+        &self.edge_distances
+    }
+
+    /// The minimum distance of a [`Testcase`] in the corpus
+    #[must_use]
+    pub fn min_distance(&self) -> f64 {
+        self.min_distance
+    }
+
+    /// The maximum distance of a [`Testcase`] in the corpus
+    #[must_use]
+    pub fn max_distance(&self) -> f64 {
+        self.max_distance
+    }
+
+    /// The cooling schedule
+    #[must_use]
+    pub fn cooling(&self) -> CoolingSchedule {
+        self.cooling
+    }
+
+    /// The time after which only the closest testcases get fuzzed
+    #[must_use]
+    pub fn time_to_exploit(&self) -> Duration {
+        self.time_to_exploit
+    }
+
+    /// The time the directed fuzzing started
+    #[must_use]
+    pub fn start_time(&self) -> Duration {
+        self.start_time
+    }
+
+    /// Compute the distance of a [`Testcase`] from its covered edges, or `None` if no covered edge reaches a target
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn testcase_distance(&self, covered: &[usize]) -> Option<f64> {
+        let (sum, count) = covered
+            .iter()
+            .filter_map(|idx| self.edge_distances.get(idx))
+            .fold((0.0, 0_usize), |(sum, count), d| (sum + d, count + 1));
+        (count > 0).then(|| sum / count as f64)
+    }
+
+    /// The current temperature of the simulated annealing
+    #[must_use]
+    pub fn temperature(&self) -> f64 {
+        let elapsed = current_time().saturating_sub(self.start_time);
+        self.cooling
+            .temperature(elapsed.as_secs_f64() / self.time_to_exploit.as_secs_f64())
+    }
+
+    /// The power factor to apply to a [`Testcase`] with the given distance.
+    /// Close testcases get up to `max_factor` times more energy, far away testcases down to `1 / max_factor`.
+    #[must_use]
+    pub fn power_factor(&self, distance: f64, max_factor: f64) -> f64 {
+        let normalized = if self.max_distance > self.min_distance {
+            (distance - self.min_distance) / (self.max_distance - self.min_distance)
+        } else {
+            // All testcases are at the same distance, don't favor any
+            0.5
+        };
+        annealing_power_factor(normalized, self.temperature(), max_factor)
+    }
+
+    fn update_bounds(&mut self, distance: f64) {
+        self.min_distance = self.min_distance.min(distance);
+        self.max_distance = self.max_distance.max(distance);
+    }
+
+    fn reset_bounds(&mut self) {
+        self.min_distance = f64::MAX;
+        self.max_distance = 0.0;
+    }
+}
+
+/// The `AFLGo` power factor given the normalized distance of a testcase (from `0.0` to `1.0`) and the temperature.
+/// At high temperatures every testcase gets a factor close to `1.0`, as it cools down the closer testcases are favored.
+#[must_use]
+pub fn annealing_power_factor(normalized_distance: f64, temperature: f64, max_factor: f64) -> f64 {
+    let power = (1.0 - normalized_distance) * (1.0 - temperature) + 0.5 * temperature;
+    libm::pow(2.0, 2.0 * libm::log2(max_factor) * (power - 0.5))
+}
+
+/// The distance of a [`Testcase`] to the targets, computed by the [`DirectedScheduler`]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct TestcaseDistanceMetadata {
+    /// The average distance of the covered edges to the targets
+    pub distance: f64,
+}
+
+crate::impl_serdeany!(TestcaseDistanceMetadata);
+
+/// A [`Scheduler`] computing the distance to the targets of each new [`Testcase`], then delegating to a base scheduler.
+/// The testcases need the [`MapIndexesMetadata`] of all their covered edges, i.e. use a `MaxMapFeedback` with indexes tracking.
+/// It must wrap the `MinimizerScheduler`s, as these may remove the [`MapIndexesMetadata`] from testcases.
+#[derive(Debug, Clone)]
+pub struct DirectedScheduler<CS> {
+    base: CS,
+}
+
+impl<CS> UsesState for DirectedScheduler<CS>
+where
+    CS: UsesState,
+{
+    type State = CS::State;
+}
+
+impl<CS> Scheduler for DirectedScheduler<CS>
+where
+    CS: Scheduler,
+    CS::State: HasCorpus + HasMetadata,
+{
+    fn on_add(&self, state: &mut Self::State, idx: CorpusId) -> Result<(), Error> {
+        self.update_distance(state, idx)?;
+        self.base.on_add(state, idx)
+    }
+
+    fn on_replace(
+        &self,
+        state: &mut Self::State,
+        idx: CorpusId,
+        prev: &Testcase<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        self.update_distance(state, idx)?;
+        // The replaced testcase may have been the closest or the farthest one
+        self.update_bounds(state)?;
+        self.base.on_replace(state, idx, prev)
+    }
+
+    fn on_remove(
+        &self,
+        state: &mut Self::State,
+        idx: CorpusId,
+        testcase: &Option<Testcase<<Self::State as UsesInput>::Input>>,
+    ) -> Result<(), Error> {
+        self.update_bounds(state)?;
+        self.base.on_remove(state, idx, testcase)
+    }
+
+    fn next(&self, state: &mut Self::State) -> Result<CorpusId, Error> {
+        self.base.next(state)
+    }
+}
+
+impl<CS> DirectedScheduler<CS>
+where
+    CS: Scheduler,
+    CS::State: HasCorpus + HasMetadata,
+{
+    /// Create a new [`DirectedScheduler`] wrapping the given scheduler.
+    /// The state needs a [`struct@DistanceMetadata`].
+    #[must_use]
+    pub fn new(base: CS) -> Self {
+        Self { base }
+    }
+
+    /// The wrapped scheduler
+    pub fn base(&self) -> &CS {
+        &self.base
+    }
+
+    /// Compute the distance of the [`Testcase`] at the given index and add it as [`TestcaseDistanceMetadata`]
+    #[allow(clippy::unused_self)]
+    pub fn update_distance(&self, state: &mut CS::State, idx: CorpusId) -> Result<(), Error> {
+        let distance = {
+            let meta = state
+                .metadata()
+                .get::<DistanceMetadata>()
+                .ok_or_else(|| Error::key_not_found("DistanceMetadata not found".to_string()))?;
+            let testcase = state.corpus().get(idx)?.borrow();
+            testcase
+                .metadata()
+                .get::<MapIndexesMetadata>()
+                .and_then(|indexes| meta.testcase_distance(&indexes.list))
+        };
+
+        if let Some(distance) = distance {
+            state
+                .metadata_mut()
+                .get_mut::<DistanceMetadata>()
+                .unwrap()
+                .update_bounds(distance);
+            state
+                .corpus()
+                .get(idx)?
+                .borrow_mut()
+                .add_metadata(TestcaseDistanceMetadata { distance });
+        }
+        Ok(())
+    }
+
+    /// Recompute the minimum and maximum distances of the [`struct@DistanceMetadata`] from the testcases in the corpus
+    #[allow(clippy::unused_self)]
+    pub fn update_bounds(&self, state: &mut CS::State) -> Result<(), Error> {
+        let mut distances = vec![];
+        for idx in state.corpus().ids() {
+            if let Some(meta) = state
+                .corpus()
+                .get(idx)?
+                .borrow()
+                .metadata()
+                .get::<TestcaseDistanceMetadata>()
+            {
+                distances.push(meta.distance);
+            }
+        }
+
+        let meta = state
+            .metadata_mut()
+            .get_mut::<DistanceMetadata>()
+            .ok_or_else(|| Error::key_not_found("DistanceMetadata not found".to_string()))?;
+        meta.reset_bounds();
+        for distance in distances {
+            meta.update_bounds(distance);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use hashbrown::HashMap;
+
+    use super::{annealing_power_factor, CoolingSchedule, DirectedScheduler, DistanceMetadata};
+    use crate::{
+        bolts::rands::StdRand,
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        feedbacks::{ConstFeedback, MapIndexesMetadata},
+        inputs::BytesInput,
+        schedulers::{QueueScheduler, Scheduler},
+        state::{HasCorpus, HasMetadata, StdState},
+    };
+
+    #[test]
+    fn test_annealing_power_factor() {
+        // Hot: everyone gets the same energy
+        assert!((annealing_power_factor(0.0, 1.0, 32.0) - 1.0).abs() < f64::EPSILON);
+        assert!((annealing_power_factor(1.0, 1.0, 32.0) - 1.0).abs() < f64::EPSILON);
+        // Cold: the closest testcases get the most energy
+        assert!((annealing_power_factor(0.0, 0.0, 32.0) - 32.0).abs() < 1e-9);
+        assert!((annealing_power_factor(1.0, 0.0, 32.0) - 1.0 / 32.0).abs() < 1e-9);
+
+        for cooling in [
+            CoolingSchedule::Exp,
+            CoolingSchedule::Log,
+            CoolingSchedule::Lin,
+            CoolingSchedule::Quad,
+        ] {
+            assert!((cooling.temperature(0.0) - 1.0).abs() < f64::EPSILON);
+            assert!(cooling.temperature(1.0) < 0.1);
+            assert!(cooling.temperature(0.5) > cooling.temperature(0.6));
+        }
+    }
+
+    #[test]
+    fn test_directed_scheduler_bounds() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        state.add_metadata(DistanceMetadata::new(HashMap::from([
+            (0, 1.0),
+            (1, 3.0),
+            (2, 5.0),
+        ])));
+        let scheduler = DirectedScheduler::new(QueueScheduler::new());
+
+        let mut ids = vec![];
+        for edge in 0..3 {
+            let mut testcase = Testcase::new(BytesInput::new(vec![]));
+            testcase.add_metadata(MapIndexesMetadata::new(vec![edge]));
+            let id = state.corpus_mut().add(testcase).unwrap();
+            scheduler.on_add(&mut state, id).unwrap();
+            ids.push(id);
+        }
+        let bounds = |state: &StdState<_, _, _, _>| {
+            let meta = state.metadata().get::<DistanceMetadata>().unwrap();
+            (meta.min_distance(), meta.max_distance())
+        };
+        assert_eq!(bounds(&state), (1.0, 5.0));
+
+        // The farthest testcase is gone
+        let testcase = state.corpus_mut().remove(ids[2]).unwrap();
+        scheduler
+            .on_remove(&mut state, ids[2], &Some(testcase))
+            .unwrap();
+        assert_eq!(bounds(&state), (1.0, 3.0));
+
+        // The closest testcase now covers the middle edge
+        let mut testcase = Testcase::new(BytesInput::new(vec![]));
+        testcase.add_metadata(MapIndexesMetadata::new(vec![1]));
+        let prev = state.corpus_mut().replace(ids[0], testcase).unwrap();
+        scheduler.on_replace(&mut state, ids[0], &prev).unwrap();
+        assert_eq!(bounds(&state), (3.0, 3.0));
+    }
+}
@@ -0,0 +1,399 @@
+//! The entropic power schedule of `libFuzzer`, see <https://mboehme.github.io/paper/FSE20.Entropy.pdf>.
+//! Each [`crate::corpus::Testcase`] gets an energy equal to the entropy of the rare features hit by its mutants:
+//! testcases whose mutants keep discovering rare features get more energy than those only hitting abundant ones.
+//!
+//! The frequencies of the features are collected by the [`crate::feedbacks::EntropicFeedback`],
+//! the energy is turned into a score by the [`crate::schedulers::testcase_score::EntropicTestcaseScore`],
+//! to be used in a [`crate::schedulers::WeightedScheduler`] and a [`crate::stages::PowerMutationalStage`].
+
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::Corpus,
+    schedulers::{testcase_score::EntropicTestcaseScore, WeightedScheduler},
+    state::HasMetadata,
+    Error,
+};
+
+/// The default number of rarest features to keep, as in `libFuzzer`
+pub const DEFAULT_NUMBER_OF_RAREST_FEATURES: usize = 100;
+
+/// The default frequency above which a feature is no longer considered rare, as in `libFuzzer`
+pub const DEFAULT_FEATURE_FREQUENCY_THRESHOLD: u16 = 0xFF;
+
+/// The default maximum number of features whose frequency is tracked, the feature set size of `libFuzzer`
+pub const DEFAULT_MAX_TRACKED_FEATURES: usize = 1 << 21;
+
+/// The state metadata of the entropic power schedule: the global frequency of each feature and the rare features
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EntropicMetadata {
+    /// The number of times each feature seen so far was hit
+    global_freqs: HashMap<usize, u16>,
+    /// The features currently considered rare
+    rare_features: Vec<usize>,
+    /// The frequency of the most abundant rare feature
+    most_abundant_rare_freq: u16,
+    /// The minimum number of rare features kept
+    number_of_rarest: usize,
+    /// The frequency above which rare features are dropped, once there are more than `number_of_rarest`
+    threshold: u16,
+    /// If the energy of a testcase should be scaled by its execution time
+    scale_per_exec_time: bool,
+    /// The number of tracked features above which the frequencies decay
+    max_tracked_features: usize,
+}
+
+crate::impl_serdeany!(EntropicMetadata);
+
+impl Default for EntropicMetadata {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_NUMBER_OF_RAREST_FEATURES,
+            DEFAULT_FEATURE_FREQUENCY_THRESHOLD,
+            false,
+        )
+    }
+}
+
+impl EntropicMetadata {
+    /// Creates a new [`struct@EntropicMetadata`], keeping at least `number_of_rarest` rare features,
+    /// and all features hit less than `threshold` times.
+    #[must_use]
+    pub fn new(number_of_rarest: usize, threshold: u16, scale_per_exec_time: bool) -> Self {
+        Self {
+            global_freqs: HashMap::new(),
+            rare_features: vec![],
+            most_abundant_rare_freq: 0,
+            number_of_rarest,
+            threshold,
+            scale_per_exec_time,
+            max_tracked_features: DEFAULT_MAX_TRACKED_FEATURES,
+        }
+    }
+
+    /// Sets the number of tracked features above which the frequencies decay, see [`EntropicMetadata::decay`]
+    pub fn set_max_tracked_features(&mut self, max_tracked_features: usize) {
+        self.max_tracked_features = max_tracked_features;
+    }
+
+    /// The number of features whose frequency is tracked
+    #[must_use]
+    pub fn tracked_features(&self) -> usize {
+        self.global_freqs.len()
+    }
+
+    /// The features currently considered rare
+    #[must_use]
+    pub fn rare_features(&self) -> &[usize] {
+        &self.rare_features
+    }
+
+    /// The number of times the given feature was hit, `None` if it was never seen
+    #[must_use]
+    pub fn global_freq(&self, feature: usize) -> Option<u16> {
+        self.global_freqs.get(&feature).copied()
+    }
+
+    /// If the energy of a testcase should be scaled by its execution time
+    #[must_use]
+    pub fn scale_per_exec_time(&self) -> bool {
+        self.scale_per_exec_time
+    }
+
+    /// Add a newly discovered feature to the rare features.
+    /// Returns the features no longer rare, which must be removed from the [`EntropicTestcaseMetadata`] of all testcases.
+    pub fn add_rare_feature(&mut self, feature: usize) -> Vec<usize> {
+        let mut removed = vec![];
+        while self.rare_features.len() > self.number_of_rarest
+            && self.most_abundant_rare_freq > self.threshold
+        {
+            // Drop the most abundant rare feature
+            let (pos, _) = self
+                .rare_features
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, f)| self.global_freqs[*f])
+                .unwrap();
+            removed.push(self.rare_features.swap_remove(pos));
+            self.most_abundant_rare_freq = self
+                .rare_features
+                .iter()
+                .map(|f| self.global_freqs[f])
+                .max()
+                .unwrap_or(0);
+        }
+        self.rare_features.push(feature);
+        self.global_freqs.insert(feature, 0);
+        // All frequencies are zero after as many halvings as they have bits
+        for _ in 0..u16::BITS {
+            if self.global_freqs.len() <= self.max_tracked_features {
+                break;
+            }
+            self.decay();
+        }
+        removed
+    }
+
+    /// Halves the frequencies of all features, and forgets the features not rare that are no longer hit.
+    /// A forgotten feature is added to the rare features again, the next time it is hit.
+    pub fn decay(&mut self) {
+        let rare_features = &self.rare_features;
+        self.global_freqs.retain(|feature, freq| {
+            *freq /= 2;
+            *freq > 0 || rare_features.contains(feature)
+        });
+        self.most_abundant_rare_freq = self
+            .rare_features
+            .iter()
+            .map(|f| self.global_freqs[f])
+            .max()
+            .unwrap_or(0);
+    }
+
+    /// Count a hit of the given feature.
+    /// Returns `true` if the feature is rare, so its frequency must also be counted for the current testcase.
+    pub fn update_feature_frequency(&mut self, feature: usize) -> bool {
+        let freq = self.global_freqs.entry(feature).or_default();
+        // Saturated increment
+        if *freq == u16::MAX {
+            return false;
+        }
+        let prev = *freq;
+        *freq += 1;
+        if prev > self.most_abundant_rare_freq || !self.rare_features.contains(&feature) {
+            return false;
+        }
+        if prev == self.most_abundant_rare_freq {
+            self.most_abundant_rare_freq += 1;
+        }
+        true
+    }
+}
+
+/// The frequencies of the rare features hit by the mutants of a [`crate::corpus::Testcase`], for the entropic power schedule
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct EntropicTestcaseMetadata {
+    /// The number of times the mutants of this testcase hit each rare feature
+    feature_freqs: HashMap<usize, u16>,
+    /// The number of mutants of this testcase executed so far
+    executed_mutations: u64,
+}
+
+crate::impl_serdeany!(EntropicTestcaseMetadata);
+
+impl EntropicTestcaseMetadata {
+    /// Creates a new, empty, [`struct@EntropicTestcaseMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of times the mutants of this testcase hit each rare feature
+    #[must_use]
+    pub fn feature_freqs(&self) -> &HashMap<usize, u16> {
+        &self.feature_freqs
+    }
+
+    /// The number of mutants of this testcase executed so far
+    #[must_use]
+    pub fn executed_mutations(&self) -> u64 {
+        self.executed_mutations
+    }
+
+    /// Count the execution of a mutant of this testcase, hitting the given rare features
+    pub fn update(&mut self, rare_features: &[usize]) {
+        self.executed_mutations += 1;
+        for feature in rare_features {
+            let freq = self.feature_freqs.entry(*feature).or_default();
+            *freq = freq.saturating_add(1);
+        }
+    }
+
+    /// Forget a feature no longer rare
+    pub fn remove_feature(&mut self, feature: usize) {
+        self.feature_freqs.remove(&feature);
+    }
+
+    /// The energy of this testcase: the entropy of the rare features hit by its mutants, given the number of rare features.
+    /// Add-one smoothing is applied to all rare features, and the features not rare are counted as a single abundant feature.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn energy(&self, number_of_rare_features: usize) -> f64 {
+        let mut energy = 0.0;
+        let mut sum_incidence = 0.0;
+        for freq in self.feature_freqs.values() {
+            let incidence = f64::from(*freq) + 1.0;
+            energy -= incidence * libm::log(incidence);
+            sum_incidence += incidence;
+        }
+        // The rare features never hit have an incidence of 1, which adds nothing to the energy
+        sum_incidence += number_of_rare_features.saturating_sub(self.feature_freqs.len()) as f64;
+        // A single locally abundant feature
+        let abundant_incidence = self.executed_mutations as f64 + 1.0;
+        energy -= abundant_incidence * libm::log(abundant_incidence);
+        sum_incidence += abundant_incidence;
+
+        energy / sum_incidence + libm::log(sum_incidence)
+    }
+}
+
+/// Remove features no longer rare from all testcases of the corpus
+pub(crate) fn remove_rare_features<C>(corpus: &C, removed: &[usize]) -> Result<(), Error>
+where
+    C: Corpus,
+{
+    if removed.is_empty() {
+        return Ok(());
+    }
+    for idx in corpus.ids() {
+        let mut testcase = corpus.get(idx)?.borrow_mut();
+        if let Some(meta) = testcase
+            .metadata_mut()
+            .get_mut::<EntropicTestcaseMetadata>()
+        {
+            for feature in removed {
+                meta.remove_feature(*feature);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A [`WeightedScheduler`] selecting testcases by their entropic energy, as `libFuzzer` does with `-entropic=1`
+pub type EntropicScheduler<S> = WeightedScheduler<EntropicTestcaseScore<S>, S>;
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use super::{EntropicMetadata, EntropicScheduler, EntropicTestcaseMetadata};
+    use crate::{
+        bolts::{rands::StdRand, tuples::tuple_list},
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::{ConstFeedback, EntropicFeedback, Feedback},
+        inputs::BytesInput,
+        observers::{MapObserver, StdMapObserver},
+        schedulers::{
+            testcase_score::{EntropicTestcaseScore, TestcaseScore},
+            weighted::WeightedScheduleMetadata,
+            Scheduler,
+        },
+        state::{HasCorpus, HasMetadata, StdState},
+    };
+
+    #[test]
+    fn test_entropic_energy() {
+        let mut meta = EntropicMetadata::new(2, 2, false);
+        for feature in 0..3 {
+            assert!(meta.add_rare_feature(feature).is_empty());
+        }
+
+        // A testcase whose mutants keep hitting different rare features
+        let mut diverse = EntropicTestcaseMetadata::new();
+        // A testcase whose mutants always hit the same rare feature
+        let mut repetitive = EntropicTestcaseMetadata::new();
+        for i in 0..3 {
+            assert!(meta.update_feature_frequency(i));
+            diverse.update(&[i]);
+            assert!(meta.update_feature_frequency(0));
+            repetitive.update(&[0]);
+        }
+        assert!(diverse.energy(3) > repetitive.energy(3));
+        // Unfuzzed testcases get the maximum energy
+        assert!(EntropicTestcaseMetadata::new().energy(3) > diverse.energy(3));
+
+        // Feature 0 is now above the threshold, and the most abundant: adding a feature drops it
+        assert_eq!(meta.add_rare_feature(3), [0]);
+        assert_eq!(meta.rare_features().len(), 3);
+        assert!(!meta.update_feature_frequency(0));
+    }
+
+    #[test]
+    fn test_entropic_decay() {
+        let mut meta = EntropicMetadata::new(2, 2, false);
+        meta.set_max_tracked_features(4);
+        for feature in 0..4 {
+            meta.add_rare_feature(feature);
+            for _ in 0..=feature {
+                meta.update_feature_frequency(feature);
+            }
+        }
+        assert_eq!(meta.tracked_features(), 4);
+
+        // Five features are tracked: the frequencies are halved twice, until feature 2,
+        // no longer rare, is forgotten, while the rare features are kept
+        assert_eq!(meta.add_rare_feature(4), [3]);
+        assert_eq!(meta.tracked_features(), 4);
+        assert_eq!(meta.global_freq(2), None);
+        assert_eq!(meta.global_freq(3), Some(1));
+        assert_eq!(meta.global_freq(0), Some(0));
+        assert_eq!(meta.rare_features(), [0, 1, 4]);
+    }
+
+    #[test]
+    fn test_entropic_scheduler() {
+        let mut observers = tuple_list!(StdMapObserver::new_owned("edges", vec![0_u8; 16]));
+        let mut feedback = EntropicFeedback::new(&observers.0);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let scheduler = EntropicScheduler::new();
+        let mut mgr = NopEventManager::new();
+        let input = BytesInput::new(vec![]);
+
+        let mut ids = vec![];
+        for _ in 0..2 {
+            let id = state
+                .corpus_mut()
+                .add(Testcase::new(input.clone()))
+                .unwrap();
+            scheduler.on_add(&mut state, id).unwrap();
+            ids.push(id);
+        }
+
+        // The mutants of the first testcase always hit the same feature, those of the second new ones
+        for (id, features) in [(ids[0], [1; 8]), (ids[1], [2, 3, 4, 5, 6, 7, 8, 9])] {
+            *state.corpus_mut().current_mut() = Some(id);
+            for feature in features {
+                observers.0.reset_map().unwrap();
+                *observers.0.get_mut(feature) = 1;
+                assert!(!feedback
+                    .is_interesting(&mut state, &mut mgr, &input, &observers, &ExitKind::Ok)
+                    .unwrap());
+            }
+        }
+        assert!(state
+            .metadata()
+            .get::<WeightedScheduleMetadata>()
+            .unwrap()
+            .is_stale());
+
+        let scores: Vec<f64> = ids
+            .iter()
+            .map(|id| {
+                let mut testcase = state.corpus().get(*id).unwrap().borrow_mut();
+                EntropicTestcaseScore::compute(&mut testcase, &state).unwrap()
+            })
+            .collect();
+        assert!(scores[1] > scores[0]);
+
+        // The scheduler picks up the new energies
+        scheduler.next(&mut state).unwrap();
+        assert!(!state
+            .metadata()
+            .get::<WeightedScheduleMetadata>()
+            .unwrap()
+            .is_stale());
+    }
+}
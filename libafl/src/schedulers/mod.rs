@@ -29,8 +29,20 @@ pub use weighted::{StdWeightedScheduler, WeightedScheduler};
 pub mod tuneable;
 pub use tuneable::*;
 
+pub mod directed;
+pub use directed::{DirectedScheduler, DistanceMetadata};
+
+pub mod bandit;
+pub use bandit::{
+    BanditAlgorithm, BanditPowerScheduleMetadata, BanditPowerScheduler, BanditScheduler,
+    BanditSchedulerMetadata,
+};
+
+pub mod entropic;
+pub use entropic::{EntropicMetadata, EntropicScheduler, EntropicTestcaseMetadata};
+
 use crate::{
-    bolts::rands::Rand,
+    bolts::{rands::Rand, tuples::HasConstLen},
     corpus::{Corpus, CorpusId, Testcase},
     inputs::UsesInput,
     random_corpus_id,
@@ -70,6 +82,107 @@ pub trait Scheduler: UsesState {
     fn next(&self, state: &mut Self::State) -> Result<CorpusId, Error>;
 }
 
+/// A tuple of [`Scheduler`]s, all notified of the corpus changes, such as the arms of a [`BanditScheduler`]
+pub trait SchedulersTuple<S>: HasConstLen
+where
+    S: UsesInput,
+{
+    /// Runs `on_add` on all the schedulers of this tuple
+    fn on_add_all(&self, state: &mut S, idx: CorpusId) -> Result<(), Error>;
+
+    /// Runs `on_replace` on all the schedulers of this tuple
+    fn on_replace_all(
+        &self,
+        state: &mut S,
+        idx: CorpusId,
+        prev: &Testcase<S::Input>,
+    ) -> Result<(), Error>;
+
+    /// Runs `on_remove` on all the schedulers of this tuple
+    fn on_remove_all(
+        &self,
+        state: &mut S,
+        idx: CorpusId,
+        testcase: &Option<Testcase<S::Input>>,
+    ) -> Result<(), Error>;
+
+    /// Gets the [`Scheduler`] at the given index and runs `next` on it
+    fn get_and_next(&self, index: usize, state: &mut S) -> Result<CorpusId, Error>;
+}
+
+impl<S> SchedulersTuple<S> for ()
+where
+    S: UsesInput,
+{
+    fn on_add_all(&self, _state: &mut S, _idx: CorpusId) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn on_replace_all(
+        &self,
+        _state: &mut S,
+        _idx: CorpusId,
+        _prev: &Testcase<S::Input>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn on_remove_all(
+        &self,
+        _state: &mut S,
+        _idx: CorpusId,
+        _testcase: &Option<Testcase<S::Input>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn get_and_next(&self, index: usize, _state: &mut S) -> Result<CorpusId, Error> {
+        Err(Error::key_not_found(format!(
+            "No scheduler at index {index}"
+        )))
+    }
+}
+
+impl<Head, Tail, S> SchedulersTuple<S> for (Head, Tail)
+where
+    Head: Scheduler<State = S>,
+    Tail: SchedulersTuple<S>,
+    S: UsesInput,
+{
+    fn on_add_all(&self, state: &mut S, idx: CorpusId) -> Result<(), Error> {
+        self.0.on_add(state, idx)?;
+        self.1.on_add_all(state, idx)
+    }
+
+    fn on_replace_all(
+        &self,
+        state: &mut S,
+        idx: CorpusId,
+        prev: &Testcase<S::Input>,
+    ) -> Result<(), Error> {
+        self.0.on_replace(state, idx, prev)?;
+        self.1.on_replace_all(state, idx, prev)
+    }
+
+    fn on_remove_all(
+        &self,
+        state: &mut S,
+        idx: CorpusId,
+        testcase: &Option<Testcase<S::Input>>,
+    ) -> Result<(), Error> {
+        self.0.on_remove(state, idx, testcase)?;
+        self.1.on_remove_all(state, idx, testcase)
+    }
+
+    fn get_and_next(&self, index: usize, state: &mut S) -> Result<CorpusId, Error> {
+        if index == 0 {
+            self.0.next(state)
+        } else {
+            self.1.get_and_next(index - 1, state)
+        }
+    }
+}
+
 /// Feed the fuzzer simply with a random testcase on request
 #[derive(Debug, Clone)]
 pub struct RandScheduler<S> {
@@ -65,6 +65,11 @@ impl SchedulerMetadata {
         self.strat
     }
 
+    /// Set the powerschedule strategy
+    pub fn set_strat(&mut self, strat: Option<PowerSchedule>) {
+        self.strat = strat;
+    }
+
     /// The measured exec time during calibration
     #[must_use]
     pub fn exec_time(&self) -> Duration {
@@ -201,13 +206,13 @@ where
             None => 0,
         };
 
-        // Attach a `SchedulerTestcaseMetaData` to the queue entry.
+        // Attach a `SchedulerTestcaseMetaData` to the queue entry,
+        // unless it already got one, e.g. from the client it was imported from.
         depth += 1;
-        state
-            .corpus()
-            .get(idx)?
-            .borrow_mut()
-            .add_metadata(SchedulerTestcaseMetaData::new(depth));
+        let mut testcase = state.corpus().get(idx)?.borrow_mut();
+        if !testcase.has_metadata::<SchedulerTestcaseMetaData>() {
+            testcase.add_metadata(SchedulerTestcaseMetaData::new(depth));
+        }
         Ok(())
     }
 
@@ -7,6 +7,8 @@ use crate::{
     corpus::{Corpus, SchedulerTestcaseMetaData, Testcase},
     feedbacks::MapIndexesMetadata,
     schedulers::{
+        directed::{DistanceMetadata, TestcaseDistanceMetadata},
+        entropic::{EntropicMetadata, EntropicTestcaseMetadata},
         minimizer::{IsFavoredMetadata, TopRatedsMetadata},
         powersched::{PowerSchedule, SchedulerMetadata},
     },
@@ -371,3 +373,101 @@ where
         Ok(weight)
     }
 }
+
+/// The power assigned to each corpus entry in directed fuzzing, as in `AFLGo`.
+/// It scales the [`CorpusPowerTestcaseScore`] with a simulated annealing on the distance to the targets,
+/// computed by the [`crate::schedulers::DirectedScheduler`].
+#[derive(Debug, Clone)]
+pub struct AnnealingTestcaseScore<S> {
+    phantom: PhantomData<S>,
+}
+
+impl<S> TestcaseScore<S> for AnnealingTestcaseScore<S>
+where
+    S: HasCorpus + HasMetadata,
+{
+    /// Compute the `power` we assign to each corpus entry
+    fn compute(entry: &mut Testcase<S::Input>, state: &S) -> Result<f64, Error> {
+        let perf_score = CorpusPowerTestcaseScore::compute(entry, state)?;
+
+        let dmeta = state
+            .metadata()
+            .get::<DistanceMetadata>()
+            .ok_or_else(|| Error::key_not_found("DistanceMetadata not found".to_string()))?;
+
+        // Testcases not reaching any target keep their default power
+        let factor = entry
+            .metadata()
+            .get::<TestcaseDistanceMetadata>()
+            .map_or(1.0, |tcmeta| {
+                dmeta.power_factor(tcmeta.distance, MAX_FACTOR)
+            });
+
+        Ok((perf_score * factor).min(HAVOC_MAX_MULT * 100.0))
+    }
+}
+
+/// The power assigned to each corpus entry by the entropic schedule of `libFuzzer`: the entropy of
+/// the rare features hit by its mutants, collected by the [`crate::feedbacks::EntropicFeedback`].
+/// If enabled in the [`EntropicMetadata`], it is scaled to favor the testcases faster than the average.
+#[derive(Debug, Clone)]
+pub struct EntropicTestcaseScore<S> {
+    phantom: PhantomData<S>,
+}
+
+impl<S> TestcaseScore<S> for EntropicTestcaseScore<S>
+where
+    S: HasCorpus + HasMetadata,
+{
+    /// Compute the `power` we assign to each corpus entry
+    #[allow(clippy::cast_precision_loss)]
+    fn compute(entry: &mut Testcase<S::Input>, state: &S) -> Result<f64, Error> {
+        let emeta = state
+            .metadata()
+            .get::<EntropicMetadata>()
+            .ok_or_else(|| Error::key_not_found("EntropicMetadata not found".to_string()))?;
+
+        // Testcases never fuzzed get the maximum energy
+        let energy = entry
+            .metadata()
+            .get::<EntropicTestcaseMetadata>()
+            .map_or_else(
+                || EntropicTestcaseMetadata::new().energy(emeta.rare_features().len()),
+                |tcmeta| tcmeta.energy(emeta.rare_features().len()),
+            );
+
+        let mut perf_score = 100.0;
+        if emeta.scale_per_exec_time() {
+            if let (Some(psmeta), Some(exec_time)) = (
+                state.metadata().get::<SchedulerMetadata>(),
+                entry.exec_time(),
+            ) {
+                if psmeta.cycles() > 0 {
+                    let avg_exec_time =
+                        psmeta.exec_time().as_nanos() as f64 / psmeta.cycles() as f64;
+                    let exec_time = exec_time.as_nanos() as f64;
+                    // The same thresholds as libFuzzer
+                    perf_score = if exec_time > avg_exec_time * 10.0 {
+                        10.0
+                    } else if exec_time > avg_exec_time * 4.0 {
+                        25.0
+                    } else if exec_time > avg_exec_time * 2.0 {
+                        50.0
+                    } else if exec_time * 3.0 > avg_exec_time * 4.0 {
+                        75.0
+                    } else if exec_time * 4.0 < avg_exec_time {
+                        300.0
+                    } else if exec_time * 3.0 < avg_exec_time {
+                        200.0
+                    } else if exec_time * 2.0 < avg_exec_time {
+                        150.0
+                    } else {
+                        100.0
+                    };
+                }
+            }
+        }
+
+        Ok((energy * perf_score).max(1.0))
+    }
+}
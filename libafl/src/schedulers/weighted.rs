@@ -31,6 +31,9 @@ pub struct WeightedScheduleMetadata {
     alias_table: HashMap<CorpusId, CorpusId>,
     /// Probability for which queue entry is selected
     alias_probability: HashMap<CorpusId, f64>,
+    /// If the weights changed since the alias table was created, for scores not only updated on corpus changes
+    #[serde(default)]
+    stale: bool,
 }
 
 impl Default for WeightedScheduleMetadata {
@@ -47,6 +50,7 @@ impl WeightedScheduleMetadata {
             runs_in_current_cycle: 0,
             alias_table: HashMap::default(),
             alias_probability: HashMap::default(),
+            stale: false,
         }
     }
 
@@ -82,6 +86,17 @@ impl WeightedScheduleMetadata {
     pub fn set_alias_probability(&mut self, probability: HashMap<CorpusId, f64>) {
         self.alias_probability = probability;
     }
+
+    /// Mark the alias table as outdated, to recreate it at the next scheduling
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// If the alias table is outdated
+    #[must_use]
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
 }
 
 crate::impl_serdeany!(WeightedScheduleMetadata);
@@ -212,6 +227,7 @@ where
         // Update metadata
         wsmeta.set_alias_probability(alias_probability);
         wsmeta.set_alias_table(alias_table);
+        wsmeta.stale = false;
         Ok(())
     }
 }
@@ -254,13 +270,14 @@ where
             None => 0,
         };
 
-        // Attach a `SchedulerTestcaseMetaData` to the queue entry.
+        // Attach a `SchedulerTestcaseMetaData` to the queue entry,
+        // unless it already got one, e.g. from the client it was imported from.
         depth += 1;
-        state
-            .corpus()
-            .get(idx)?
-            .borrow_mut()
-            .add_metadata(SchedulerTestcaseMetaData::new(depth));
+        let mut testcase = state.corpus().get(idx)?.borrow_mut();
+        if !testcase.has_metadata::<SchedulerTestcaseMetaData>() {
+            testcase.add_metadata(SchedulerTestcaseMetaData::new(depth));
+        }
+        drop(testcase);
 
         // Recreate the alias table
         self.create_alias_table(state)?;
@@ -294,6 +311,15 @@ where
         if corpus_counts == 0 {
             Err(Error::empty(String::from("No entries in corpus")))
         } else {
+            // The weights changed, e.g. with the entropic schedule
+            if state
+                .metadata()
+                .get::<WeightedScheduleMetadata>()
+                .map_or(false, WeightedScheduleMetadata::is_stale)
+            {
+                self.create_alias_table(state)?;
+            }
+
             let s = random_corpus_id!(state.corpus(), state.rand_mut());
 
             // Choose a random value between 0.000000000 and 1.000000000
@@ -0,0 +1,139 @@
+//! The [`AflCustomTrimStage`] trims the corpus entries with the trimming hooks of an AFL++ custom mutator,
+//! see [`crate::mutators::afl_custom`].
+
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+};
+use core::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bolts::{tuples::MatchName, HasLen},
+    corpus::{Corpus, CorpusId, Testcase},
+    executors::{Executor, ExitKind, HasObservers},
+    fuzzer::{ExecutesInput, HasScheduler},
+    inputs::HasBytesVec,
+    mutators::AflCustomMutatorLibrary,
+    observers::MapObserver,
+    schedulers::Scheduler,
+    stages::Stage,
+    state::{HasCorpus, HasExecutions, HasMetadata, UsesState},
+    Error,
+};
+
+/// Marks the testcases already trimmed by the [`AflCustomTrimStage`]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AflCustomTrimmedMetadata;
+
+crate::impl_serdeany!(AflCustomTrimmedMetadata);
+
+/// A stage trimming each corpus entry once, the first time it is fuzzed, with the `afl_custom_init_trim`,
+/// `afl_custom_trim` and `afl_custom_post_trim` hooks of an AFL++ custom mutator.
+///
+/// As in AFL++, a trimmed candidate is kept if it runs without crashing and yields the same coverage map.
+#[derive(Debug, Clone)]
+pub struct AflCustomTrimStage<E, EM, O, Z> {
+    library: Rc<AflCustomMutatorLibrary>,
+    map_observer_name: String,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(E, EM, O, Z)>,
+}
+
+impl<E, EM, O, Z> UsesState for AflCustomTrimStage<E, EM, O, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, O, Z> Stage<E, EM, Z> for AflCustomTrimStage<E, EM, O, Z>
+where
+    E: Executor<EM, Z> + HasObservers,
+    EM: UsesState<State = E::State>,
+    O: MapObserver,
+    Z: ExecutesInput<E, EM, State = E::State> + HasScheduler,
+    E::State: HasCorpus + HasExecutions + HasMetadata,
+    E::Input: HasBytesVec + HasLen + Clone,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        let base = {
+            let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+            if testcase.has_metadata::<AflCustomTrimmedMetadata>() {
+                return Ok(());
+            }
+            testcase.add_metadata(AflCustomTrimmedMetadata);
+            testcase.load_input()?.clone()
+        };
+        if !self.library.has_trim() {
+            return Ok(());
+        }
+
+        fuzzer.execute_input(state, executor, manager, &base)?;
+        let base_hash = self.map_hash(executor);
+
+        let trimmed = self.library.trim(base.bytes(), |candidate| {
+            let mut input = base.clone();
+            *input.bytes_mut() = candidate.to_vec();
+            let exit_kind = fuzzer.execute_input(state, executor, manager, &input)?;
+            Ok(exit_kind == ExitKind::Ok && self.map_hash(executor) == base_hash)
+        })?;
+
+        let Some(trimmed) = trimmed else {
+            return Ok(());
+        };
+        if trimmed.len() >= base.len() {
+            return Ok(());
+        }
+        let mut input = base;
+        *input.bytes_mut() = trimmed;
+
+        // Replace the testcase, keeping its metadata
+        let mut testcase = Testcase::with_executions(input, *state.executions());
+        {
+            let prev = state.corpus().get(corpus_idx)?.borrow();
+            *testcase.metadata_mut() = prev.metadata().clone();
+            if let Some(exec_time) = prev.exec_time() {
+                testcase.set_exec_time(*exec_time);
+            }
+        }
+        let prev = state.corpus_mut().replace(corpus_idx, testcase)?;
+        fuzzer
+            .scheduler_mut()
+            .on_replace(state, corpus_idx, &prev)?;
+        Ok(())
+    }
+}
+
+impl<E, EM, O, Z> AflCustomTrimStage<E, EM, O, Z>
+where
+    E: HasObservers,
+    O: MapObserver,
+{
+    /// Creates a new [`AflCustomTrimStage`] with the given custom mutator, comparing the coverage of the given map observer
+    #[must_use]
+    pub fn new(library: Rc<AflCustomMutatorLibrary>, map_observer: &O) -> Self {
+        Self {
+            library,
+            map_observer_name: map_observer.name().to_string(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// The hash of the coverage map of the last execution
+    fn map_hash(&self, executor: &E) -> u64 {
+        executor
+            .observers()
+            .match_name::<O>(&self.map_observer_name)
+            .expect("AflCustomTrimStage needs a MapObserver")
+            .hash()
+    }
+}
@@ -0,0 +1,478 @@
+//! The [`AutoDictStage`] learns dictionary entries from the comparison operands logged by `CmpLog`,
+//! promoting the ones seen frequently into the [`Tokens`] of the state.
+
+use alloc::{boxed::Box, string::ToString, vec::Vec};
+use core::{cmp::Reverse, marker::PhantomData};
+
+use ahash::RandomState;
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::CorpusId,
+    events::{CustomBufEventResult, Event, EventFirer, HasCustomBufHandlers},
+    mutators::Tokens,
+    observers::cmp::{CmpValues, CmpValuesMetadata},
+    stages::Stage,
+    state::{HasMetadata, UsesState},
+    Error,
+};
+
+/// The tag of the [`Event::CustomBuf`] events sharing learned tokens between clients
+pub const AUTODICT_TAG: &str = "autodict";
+
+/// The default number of traces a value has to be seen in before becoming a token
+pub const DEFAULT_MIN_HITS: u64 = 3;
+/// The default maximum number of learned tokens
+pub const DEFAULT_MAX_TOKENS: usize = 256;
+/// The default number of rounds after which learned values not seen anymore are forgotten
+pub const DEFAULT_MAX_AGE: u64 = 4096;
+
+/// The shortest byte string worth becoming a token
+const MIN_TOKEN_LEN: usize = 2;
+/// The longest byte string worth becoming a token
+const MAX_TOKEN_LEN: usize = 32;
+
+/// The stats of a value seen in comparisons
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AutoDictEntry {
+    /// The number of traces in which the value was seen
+    pub hits: u64,
+    /// The round in which the value was last seen
+    pub last_seen: u64,
+}
+
+/// Metadata holding the values seen in comparisons, and the tokens learned from them
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AutoDictMetadata {
+    /// The candidate values, not yet promoted to tokens
+    candidates: HashMap<Vec<u8>, AutoDictEntry>,
+    /// The values promoted to [`Tokens`] by this stage
+    learned: HashMap<Vec<u8>, AutoDictEntry>,
+    /// The number of traces processed
+    round: u64,
+    /// The hash of the last trace processed, not to process the same trace again
+    last_trace: Option<u64>,
+}
+
+crate::impl_serdeany!(AutoDictMetadata);
+
+impl AutoDictMetadata {
+    /// Creates a new [`struct@AutoDictMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The values promoted to [`Tokens`] so far
+    pub fn learned(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.learned.keys()
+    }
+
+    /// The number of traces processed
+    #[must_use]
+    pub fn round(&self) -> u64 {
+        self.round
+    }
+
+    /// Adds the tokens learned by another client, as long as less than `max_tokens` tokens are learned.
+    /// They are forgotten like the tokens learned locally, unless seen in the traces of this client.
+    pub fn add_shared(&mut self, tokens: &mut Tokens, values: Vec<Vec<u8>>, max_tokens: usize) {
+        for value in values {
+            if self.learned.contains_key(&value) {
+                continue;
+            }
+            if self.learned.len() >= max_tokens {
+                break;
+            }
+            let entry = self.candidates.remove(&value).unwrap_or(AutoDictEntry {
+                hits: 0,
+                last_seen: self.round,
+            });
+            if tokens.add_token(&value) {
+                self.learned.insert(value, entry);
+            }
+        }
+    }
+}
+
+/// Returns if a wide integer constant is worth becoming a token
+fn is_interesting_int(v: u64, max: u64) -> bool {
+    v > 0xff && v != max && v.count_ones() > 1
+}
+
+/// Extract the token candidates out of the operands of a comparison
+fn cmp_tokens(cmp: &CmpValues, tokens: &mut HashSet<Vec<u8>>) {
+    match cmp {
+        CmpValues::U32((a, b)) => {
+            for v in [*a, *b] {
+                if is_interesting_int(u64::from(v), u64::from(u32::MAX)) {
+                    tokens.insert(v.to_le_bytes().to_vec());
+                }
+            }
+        }
+        CmpValues::U64((a, b)) => {
+            for v in [*a, *b] {
+                if is_interesting_int(v, u64::MAX) {
+                    tokens.insert(v.to_le_bytes().to_vec());
+                }
+            }
+        }
+        CmpValues::Bytes((a, b)) => {
+            for v in [a, b] {
+                // Strings compared with strcmp and friends may be logged with trailing garbage
+                let v = v
+                    .iter()
+                    .position(|c| *c == 0)
+                    .map_or(&v[..], |nul| &v[..nul]);
+                let v = &v[..v.len().min(MAX_TOKEN_LEN)];
+                if v.len() >= MIN_TOKEN_LEN && v.iter().any(|c| *c != v[0]) {
+                    tokens.insert(v.to_vec());
+                }
+            }
+        }
+        CmpValues::U8(_) | CmpValues::U16(_) => {}
+    }
+}
+
+/// A stage learning dictionary entries from comparison operands, to be placed after a
+/// [`crate::stages::TracingStage`] adding the [`struct@CmpValuesMetadata`] to the state.
+/// A trace is only learned from once: if no execution updated the [`struct@CmpValuesMetadata`]
+/// since the last time, the stage does nothing.
+///
+/// Byte strings and wide integer constants seen in at least `min_hits` traces become [`Tokens`],
+/// up to `max_tokens` learned tokens. Learned tokens not seen for `max_age` rounds are removed again.
+/// With [`AutoDictStage::share_tokens`], the tokens learned are sent to the other clients,
+/// in [`Event::CustomBuf`] events tagged [`AUTODICT_TAG`].
+#[derive(Debug, Clone)]
+pub struct AutoDictStage<E, EM, Z> {
+    min_hits: u64,
+    max_tokens: usize,
+    max_age: u64,
+    share: bool,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> UsesState for AutoDictStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for AutoDictStage<E, EM, Z>
+where
+    E: UsesState,
+    EM: EventFirer<State = E::State>,
+    Z: UsesState<State = E::State>,
+    E::State: HasMetadata,
+{
+    #[inline]
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        _corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        let Some(cmps) = state.metadata().get::<CmpValuesMetadata>() else {
+            return Ok(());
+        };
+        let trace =
+            RandomState::with_seeds(0, 0, 0, 0).hash_one(postcard::to_allocvec(&cmps.list)?);
+        let mut seen = HashSet::new();
+        for cmp in &cmps.list {
+            cmp_tokens(cmp, &mut seen);
+        }
+
+        if !state.has_metadata::<Tokens>() {
+            state.add_metadata(Tokens::new());
+        }
+        if !state.has_metadata::<AutoDictMetadata>() {
+            state.add_metadata(AutoDictMetadata::new());
+        }
+        let mut meta = state.metadata_mut().remove::<AutoDictMetadata>().unwrap();
+        // The cmp values are left over from a trace we already learned from
+        if meta.last_trace == Some(trace) {
+            state.add_metadata(*meta);
+            return Ok(());
+        }
+        meta.last_trace = Some(trace);
+        let tokens = state.metadata_mut().get_mut::<Tokens>().unwrap();
+        let learned = self.learn(&mut meta, tokens, seen);
+        state.add_metadata(*meta);
+
+        if self.share && !learned.is_empty() {
+            manager.fire(
+                state,
+                Event::CustomBuf {
+                    buf: postcard::to_allocvec(&learned)?,
+                    tag: AUTODICT_TAG.to_string(),
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<E, EM, Z> AutoDictStage<E, EM, Z> {
+    /// Creates a new [`AutoDictStage`] with the default thresholds
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_limits(DEFAULT_MIN_HITS, DEFAULT_MAX_TOKENS, DEFAULT_MAX_AGE)
+    }
+
+    /// Creates a new [`AutoDictStage`], promoting values seen in `min_hits` traces,
+    /// learning at most `max_tokens` tokens and forgetting them after `max_age` rounds without being seen.
+    #[must_use]
+    pub fn with_limits(min_hits: u64, max_tokens: usize, max_age: u64) -> Self {
+        Self {
+            min_hits,
+            max_tokens,
+            max_age,
+            share: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Shares the tokens learned by this stage with the other clients, and learns the tokens they share,
+    /// registering a handler for their [`Event::CustomBuf`] events to the given event manager.
+    pub fn share_tokens(&mut self, manager: &mut EM)
+    where
+        EM: HasCustomBufHandlers,
+        EM::State: HasMetadata,
+    {
+        self.share = true;
+        let max_tokens = self.max_tokens;
+        manager.add_custom_buf_handler(Box::new(move |state, tag, buf| {
+            if tag != AUTODICT_TAG {
+                return Ok(CustomBufEventResult::Next);
+            }
+            let values: Vec<Vec<u8>> = postcard::from_bytes(buf)?;
+            if !state.has_metadata::<Tokens>() {
+                state.add_metadata(Tokens::new());
+            }
+            if !state.has_metadata::<AutoDictMetadata>() {
+                state.add_metadata(AutoDictMetadata::new());
+            }
+            let mut meta = state.metadata_mut().remove::<AutoDictMetadata>().unwrap();
+            let tokens = state.metadata_mut().get_mut::<Tokens>().unwrap();
+            meta.add_shared(tokens, values, max_tokens);
+            state.add_metadata(*meta);
+            Ok(CustomBufEventResult::Handled)
+        }));
+    }
+
+    /// Update the learned tokens with the values seen in a trace, returning the newly learned tokens
+    fn learn(
+        &self,
+        meta: &mut AutoDictMetadata,
+        tokens: &mut Tokens,
+        seen: HashSet<Vec<u8>>,
+    ) -> Vec<Vec<u8>> {
+        meta.round += 1;
+        let round = meta.round;
+
+        for value in seen {
+            if let Some(entry) = meta.learned.get_mut(&value) {
+                entry.hits += 1;
+                entry.last_seen = round;
+                continue;
+            }
+            let entry = meta.candidates.entry(value).or_insert(AutoDictEntry {
+                hits: 0,
+                last_seen: round,
+            });
+            entry.hits += 1;
+            entry.last_seen = round;
+        }
+
+        // Age out stale values
+        let max_age = self.max_age;
+        meta.candidates
+            .retain(|_, entry| round - entry.last_seen <= max_age);
+        meta.learned.retain(|token, entry| {
+            let keep = round - entry.last_seen <= max_age;
+            if !keep {
+                tokens.remove_token(token);
+            }
+            keep
+        });
+
+        // Promote the frequent values, most frequent first
+        let mut promotable: Vec<_> = meta
+            .candidates
+            .iter()
+            .filter(|(_, entry)| entry.hits >= self.min_hits)
+            .map(|(value, entry)| (value.clone(), *entry))
+            .collect();
+        promotable.sort_unstable_by_key(|(_, entry)| Reverse(entry.hits));
+        let mut learned = vec![];
+        for (value, entry) in promotable {
+            if meta.learned.len() >= self.max_tokens {
+                break;
+            }
+            meta.candidates.remove(&value);
+            // Tokens already present, e.g. from a dictionary file, are never removed by this stage
+            if tokens.add_token(&value) {
+                learned.push(value.clone());
+                meta.learned.insert(value, entry);
+            }
+        }
+
+        // Don't let the candidates grow unbounded
+        let max_candidates = self.max_tokens * 16;
+        if meta.candidates.len() > max_candidates {
+            let mut last_seen: Vec<_> = meta
+                .candidates
+                .values()
+                .map(|entry| entry.last_seen)
+                .collect();
+            last_seen.sort_unstable();
+            let oldest = last_seen[meta.candidates.len() - max_candidates];
+            meta.candidates.retain(|_, entry| entry.last_seen > oldest);
+        }
+        learned
+    }
+}
+
+impl<E, EM, Z> Default for AutoDictStage<E, EM, Z> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use hashbrown::HashSet;
+
+    use super::{cmp_tokens, AutoDictMetadata, AutoDictStage};
+    use crate::{
+        bolts::rands::StdRand,
+        corpus::{CorpusId, InMemoryCorpus},
+        events::NopEventManager,
+        executors::{ExitKind, InProcessExecutor},
+        feedbacks::ConstFeedback,
+        inputs::BytesInput,
+        mutators::Tokens,
+        observers::cmp::{CmpValues, CmpValuesMetadata},
+        schedulers::QueueScheduler,
+        stages::Stage,
+        state::{HasMetadata, StdState},
+        StdFuzzer,
+    };
+
+    #[test]
+    fn test_autodict_learning() {
+        let stage = AutoDictStage::<(), (), ()>::with_limits(2, 1, 2);
+        let mut meta = AutoDictMetadata::new();
+        let mut tokens = Tokens::new();
+        tokens.add_token(&b"user".to_vec());
+
+        let trace = |cmps: &[CmpValues]| {
+            let mut seen = HashSet::new();
+            for cmp in cmps {
+                cmp_tokens(cmp, &mut seen);
+            }
+            seen
+        };
+        let magic = CmpValues::Bytes((b"MAGIC\0garbage".to_vec(), b"xxxxx".to_vec()));
+        let int = CmpValues::U32((0xdead_beef, 3));
+
+        stage.learn(&mut meta, &mut tokens, trace(&[magic.clone(), int.clone()]));
+        assert_eq!(tokens.len(), 1);
+        stage.learn(&mut meta, &mut tokens, trace(&[magic.clone(), int]));
+        // Only one token can be learned, the loop counter and constant strings are ignored
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(meta.learned().count(), 1);
+
+        // Unseen learned tokens get forgotten, tokens from the user never
+        for _ in 0..3 {
+            stage.learn(&mut meta, &mut tokens, HashSet::new());
+        }
+        assert_eq!(meta.learned().count(), 0);
+        assert_eq!(tokens.tokens(), [b"user".to_vec()]);
+
+        assert_eq!(
+            trace(&[magic]).into_iter().collect::<Vec<_>>(),
+            [b"MAGIC".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_autodict_stage() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::<BytesInput>::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut fuzzer: StdFuzzer<_, _, _, ()> =
+            StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut mgr = NopEventManager::new();
+        let mut harness = |_: &BytesInput| ExitKind::Ok;
+        let mut executor =
+            InProcessExecutor::new(&mut harness, (), &mut fuzzer, &mut state, &mut mgr).unwrap();
+
+        let mut stage = AutoDictStage::with_limits(2, 4, 16);
+        let mut perform = |state: &mut StdState<_, _, _, _>, cmps: Vec<CmpValues>| {
+            state.add_metadata(CmpValuesMetadata { list: cmps });
+            stage
+                .perform(
+                    &mut fuzzer,
+                    &mut executor,
+                    state,
+                    &mut mgr,
+                    CorpusId::from(0_usize),
+                )
+                .unwrap();
+        };
+        let magic = CmpValues::Bytes((b"MAGIC".to_vec(), b"xxxxx".to_vec()));
+
+        // A trace left over from an earlier execution is not counted twice
+        perform(&mut state, vec![magic.clone()]);
+        let meta = state.metadata().get::<CmpValuesMetadata>().unwrap();
+        let list = meta.list.clone();
+        perform(&mut state, list);
+        assert_eq!(
+            state.metadata().get::<AutoDictMetadata>().unwrap().round(),
+            1
+        );
+        assert!(state.metadata().get::<Tokens>().unwrap().is_empty());
+
+        // A new trace seeing the value again is
+        perform(&mut state, vec![CmpValues::U8((1, 2)), magic]);
+        assert_eq!(
+            state.metadata().get::<AutoDictMetadata>().unwrap().round(),
+            2
+        );
+        assert_eq!(
+            state.metadata().get::<Tokens>().unwrap().tokens(),
+            [b"MAGIC".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_autodict_shared() {
+        let mut meta = AutoDictMetadata::new();
+        let mut tokens = Tokens::new();
+        tokens.add_token(&b"user".to_vec());
+
+        meta.add_shared(
+            &mut tokens,
+            vec![b"user".to_vec(), b"MAGIC".to_vec(), b"other".to_vec()],
+            1,
+        );
+        // Tokens from the user are not learned, and no more than `max_tokens` are
+        assert_eq!(meta.learned().collect::<Vec<_>>(), [&b"MAGIC".to_vec()]);
+        assert_eq!(tokens.len(), 2);
+    }
+}
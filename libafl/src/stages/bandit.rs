@@ -0,0 +1,113 @@
+//! The [`BanditStatsStage`] reports the success rates learned by the [`crate::mutators::BanditScheduledMutator`]
+//! to the monitors.
+
+use alloc::format;
+use core::{marker::PhantomData, time::Duration};
+
+use crate::{
+    bolts::current_time,
+    corpus::CorpusId,
+    events::{Event, EventFirer},
+    monitors::UserStats,
+    mutators::BanditMutatorMetadata,
+    stages::Stage,
+    state::{HasMetadata, UsesState},
+    Error,
+};
+
+/// The default interval between two reports
+pub const DEFAULT_BANDIT_STATS_INTERVAL: Duration = Duration::from_secs(15);
+
+/// A stage reporting, as user stats, the ratio of executions finding new corpus entries or solutions
+/// for each mutation of the [`crate::mutators::BanditScheduledMutator`], and for each number of stacked mutations.
+#[derive(Clone, Debug)]
+pub struct BanditStatsStage<E, EM, Z> {
+    interval: Duration,
+    last_report: Duration,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> UsesState for BanditStatsStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for BanditStatsStage<E, EM, Z>
+where
+    E: UsesState,
+    EM: EventFirer<State = E::State>,
+    Z: UsesState<State = E::State>,
+    E::State: HasMetadata,
+{
+    #[inline]
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        _corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        let now = current_time();
+        if now.saturating_sub(self.last_report) < self.interval {
+            return Ok(());
+        }
+        self.last_report = now;
+
+        let Some(meta) = state.metadata().get::<BanditMutatorMetadata>() else {
+            return Ok(());
+        };
+        let mut reports = vec![];
+        for (name, arm) in meta.mutations() {
+            reports.push((
+                format!("mutation {name}"),
+                UserStats::Ratio(arm.finds, arm.pulls),
+            ));
+        }
+        for (depth, arm) in meta.stacks() {
+            reports.push((
+                format!("stacking {depth}"),
+                UserStats::Ratio(arm.finds, arm.pulls),
+            ));
+        }
+
+        for (name, value) in reports {
+            manager.fire(
+                state,
+                Event::UpdateUserStats {
+                    name,
+                    value,
+                    phantom: PhantomData,
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<E, EM, Z> BanditStatsStage<E, EM, Z> {
+    /// Creates a new [`BanditStatsStage`], reporting every [`DEFAULT_BANDIT_STATS_INTERVAL`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_interval(DEFAULT_BANDIT_STATS_INTERVAL)
+    }
+
+    /// Creates a new [`BanditStatsStage`], reporting at most once per `interval`
+    #[must_use]
+    pub fn with_interval(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_report: Duration::ZERO,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, EM, Z> Default for BanditStatsStage<E, EM, Z> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -6,6 +6,7 @@ use alloc::{
 };
 use core::{cmp::Ordering, fmt::Debug, marker::PhantomData, ops::Range};
 
+use ahash::RandomState;
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -106,13 +107,20 @@ where
 pub struct TaintMetadata {
     input_vec: Vec<u8>,
     ranges: Vec<Range<usize>>,
+    /// The hash of the original input the taint was computed for
+    #[serde(default)]
+    input_hash: Option<u64>,
 }
 
 impl TaintMetadata {
     #[must_use]
     /// Constructor for taint metadata
     pub fn new(input_vec: Vec<u8>, ranges: Vec<Range<usize>>) -> Self {
-        Self { input_vec, ranges }
+        Self {
+            input_vec,
+            ranges,
+            input_hash: None,
+        }
     }
 
     /// Set input and ranges
@@ -132,6 +140,17 @@ impl TaintMetadata {
     pub fn ranges(&self) -> &Vec<Range<usize>> {
         &self.ranges
     }
+
+    /// Set the original input the taint was computed for
+    pub fn set_orig_input(&mut self, orig_input: &[u8]) {
+        self.input_hash = Some(RandomState::with_seeds(0, 0, 0, 0).hash_one(orig_input));
+    }
+
+    #[must_use]
+    /// If the taint was computed for the given original input
+    pub fn is_for(&self, orig_input: &[u8]) -> bool {
+        self.input_hash == Some(RandomState::with_seeds(0, 0, 0, 0).hash_one(orig_input))
+    }
 }
 
 crate::impl_serdeany!(TaintMetadata);
@@ -271,10 +290,12 @@ where
 
         if let Some(meta) = state.metadata_mut().get_mut::<TaintMetadata>() {
             meta.update(input.bytes().to_vec(), res);
+            meta.set_orig_input(backup.bytes());
 
             // println!("meta: {:#?}", meta);
         } else {
-            let meta = TaintMetadata::new(input.bytes().to_vec(), res);
+            let mut meta = TaintMetadata::new(input.bytes().to_vec(), res);
+            meta.set_orig_input(backup.bytes());
             state.add_metadata::<TaintMetadata>(meta);
         }
 
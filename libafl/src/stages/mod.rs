@@ -10,7 +10,8 @@ pub use mutational::{MutationalStage, StdMutationalStage};
 
 pub mod tmin;
 pub use tmin::{
-    MapEqualityFactory, MapEqualityFeedback, StdTMinMutationalStage, TMinMutationalStage,
+    MapEqualityFactory, MapEqualityFeedback, MinimizedObjectiveMetadata,
+    MinimizedReproducerMetadata, ObjectiveTMinStage, StdTMinMutationalStage, TMinMutationalStage,
 };
 
 pub mod push;
@@ -36,6 +37,23 @@ pub use tuneable::*;
 pub mod colorization;
 pub use colorization::*;
 
+pub mod redqueen;
+pub use redqueen::*;
+
+pub mod autodict;
+pub use autodict::{AutoDictMetadata, AutoDictStage};
+
+pub mod bandit;
+pub use bandit::BanditStatsStage;
+
+pub mod share;
+pub use share::{ShareMetadataStage, SharedTestcasesMetadata};
+
+#[cfg(all(unix, feature = "std"))]
+pub mod afl_custom;
+#[cfg(all(unix, feature = "std"))]
+pub use afl_custom::AflCustomTrimStage;
+
 #[cfg(feature = "std")]
 pub mod concolic;
 #[cfg(feature = "std")]
@@ -48,6 +66,11 @@ pub mod sync;
 #[cfg(feature = "std")]
 pub use sync::*;
 
+#[cfg(feature = "std")]
+pub mod pause;
+#[cfg(feature = "std")]
+pub use pause::{PauseMetadata, PauseStage};
+
 #[cfg(feature = "std")]
 pub mod dump;
 use core::{convert::From, marker::PhantomData};
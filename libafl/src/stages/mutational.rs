@@ -7,14 +7,14 @@ use core::marker::PhantomData;
 use crate::monitors::PerfFeature;
 use crate::{
     bolts::rands::Rand,
-    corpus::{Corpus, CorpusId, Testcase},
+    corpus::{Corpus, CorpusId, LineageContextMetadata, Testcase},
     fuzzer::Evaluator,
     inputs::Input,
     mark_feature_time,
     mutators::Mutator,
     stages::Stage,
     start_timer,
-    state::{HasClientPerfMonitor, HasCorpus, HasRand, UsesState},
+    state::{HasClientPerfMonitor, HasCorpus, HasMetadata, HasRand, UsesState},
     Error,
 };
 
@@ -159,7 +159,7 @@ where
     EM: UsesState<State = Z::State>,
     M: Mutator<I, Z::State>,
     Z: Evaluator<E, EM>,
-    Z::State: HasClientPerfMonitor + HasCorpus + HasRand,
+    Z::State: HasClientPerfMonitor + HasCorpus + HasRand + HasMetadata,
     I: MutatedTransform<Self::Input, Self::State> + Clone,
 {
     /// The mutator, added to this stage
@@ -197,7 +197,7 @@ where
     EM: UsesState<State = Z::State>,
     M: Mutator<I, Z::State>,
     Z: Evaluator<E, EM>,
-    Z::State: HasClientPerfMonitor + HasCorpus + HasRand,
+    Z::State: HasClientPerfMonitor + HasCorpus + HasRand + HasMetadata,
     I: MutatedTransform<Self::Input, Self::State> + Clone,
 {
     #[inline]
@@ -210,7 +210,9 @@ where
         manager: &mut EM,
         corpus_idx: CorpusId,
     ) -> Result<(), Error> {
+        LineageContextMetadata::enter_stage(state, "StdMutationalStage");
         let ret = self.perform_mutational(fuzzer, executor, state, manager, corpus_idx);
+        LineageContextMetadata::leave_stage(state);
 
         #[cfg(feature = "introspection")]
         state.introspection_monitor_mut().finish_stage();
@@ -0,0 +1,247 @@
+//! The [`PauseStage`] lets the user pause and resume this client in the monitor of the broker,
+//! see [`crate::monitors::Monitor::take_client_commands`].
+
+use alloc::{boxed::Box, string::ToString};
+use core::{marker::PhantomData, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bolts::current_time,
+    corpus::CorpusId,
+    events::{
+        ClientCommand, CustomBufEventResult, Event, EventFirer, EventProcessor,
+        HasCustomBufHandlers, HasEventManagerId, CLIENT_COMMAND_HELLO_TAG, CLIENT_COMMAND_TAG,
+    },
+    stages::Stage,
+    state::{HasMetadata, UsesState},
+    Error,
+};
+
+/// How long a paused client sleeps between handling events
+const PAUSED_SLEEP: Duration = Duration::from_millis(10);
+/// How often a paused client announces itself to the broker again.
+/// The broker only passes on commands while handling events, so this keeps the resume command coming,
+/// even if all clients are paused.
+const PAUSED_HELLO_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Metadata telling if the monitor paused this client
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct PauseMetadata {
+    /// If the client is paused
+    pub paused: bool,
+}
+
+crate::impl_serdeany!(PauseMetadata);
+
+/// A stage waiting, as long as the user paused this client in the monitor of the broker.
+///
+/// The stage announces the client to the broker, in an [`Event::CustomBuf`] tagged [`CLIENT_COMMAND_HELLO_TAG`],
+/// and handles the [`ClientCommand`]s the broker passes on, tagged [`CLIENT_COMMAND_TAG`].
+/// While paused, the client keeps handling the events of the other clients.
+#[derive(Debug, Clone)]
+pub struct PauseStage<E, EM, Z> {
+    mgr_id: usize,
+    announced: bool,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> UsesState for PauseStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for PauseStage<E, EM, Z>
+where
+    E: UsesState,
+    EM: EventFirer<State = E::State> + EventProcessor<E, Z>,
+    Z: UsesState<State = E::State>,
+    E::State: HasMetadata,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        _corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        if !self.announced {
+            self.announce(state, manager)?;
+            self.announced = true;
+        }
+
+        let mut last_hello = current_time();
+        while state
+            .metadata()
+            .get::<PauseMetadata>()
+            .is_some_and(|meta| meta.paused)
+        {
+            if current_time().saturating_sub(last_hello) >= PAUSED_HELLO_INTERVAL {
+                self.announce(state, manager)?;
+                last_hello = current_time();
+            }
+            std::thread::sleep(PAUSED_SLEEP);
+            manager.process(fuzzer, state, executor)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E, EM, Z> PauseStage<E, EM, Z>
+where
+    E: UsesState,
+    EM: HasCustomBufHandlers<State = E::State> + HasEventManagerId,
+    E::State: HasMetadata,
+{
+    /// Creates a new [`PauseStage`], registering a handler for the commands of the monitor to the given event manager
+    pub fn new(manager: &mut EM) -> Self {
+        let mgr_id = manager.mgr_id().id;
+        manager.add_custom_buf_handler(Box::new(move |state, tag, buf| {
+            if tag != CLIENT_COMMAND_TAG {
+                return Ok(CustomBufEventResult::Next);
+            }
+            let (target, command): (usize, ClientCommand) = postcard::from_bytes(buf)?;
+            if target == mgr_id {
+                log::info!("Received the {command} command of the monitor");
+                state.add_metadata(PauseMetadata {
+                    paused: command == ClientCommand::Pause,
+                });
+            }
+            Ok(CustomBufEventResult::Handled)
+        }));
+        Self {
+            mgr_id,
+            announced: false,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, EM, Z> PauseStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    /// Tells the broker which commands are for this client
+    fn announce(&self, state: &mut E::State, manager: &mut EM) -> Result<(), Error>
+    where
+        EM: EventFirer<State = E::State>,
+    {
+        manager.fire(
+            state,
+            Event::CustomBuf {
+                buf: postcard::to_allocvec(&self.mgr_id)?,
+                tag: CLIENT_COMMAND_HELLO_TAG.to_string(),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec, vec::Vec};
+    use core::time::Duration;
+
+    use super::{PauseMetadata, PauseStage};
+    use crate::{
+        bolts::{current_time, rands::StdRand},
+        corpus::{CorpusId, InMemoryCorpus},
+        events::{ClientCommand, EventProcessor, SimpleEventManager},
+        executors::{ExitKind, InProcessExecutor},
+        feedbacks::ConstFeedback,
+        inputs::BytesInput,
+        monitors::{ClientStats, Monitor},
+        schedulers::QueueScheduler,
+        stages::Stage,
+        state::{HasMetadata, StdState},
+        StdFuzzer,
+    };
+
+    /// Pauses the client, then resumes it on the next call
+    #[derive(Debug)]
+    struct PausingMonitor {
+        client_stats: Vec<ClientStats>,
+        commands: Vec<Vec<(u32, ClientCommand)>>,
+    }
+
+    impl Monitor for PausingMonitor {
+        fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+            &mut self.client_stats
+        }
+
+        fn client_stats(&self) -> &[ClientStats] {
+            &self.client_stats
+        }
+
+        fn start_time(&mut self) -> Duration {
+            current_time()
+        }
+
+        fn display(&mut self, _event_msg: String, _sender_id: u32) {}
+
+        fn issues_client_commands(&self) -> bool {
+            true
+        }
+
+        fn take_client_commands(&mut self) -> Vec<(u32, ClientCommand)> {
+            self.commands.pop().unwrap_or_default()
+        }
+    }
+
+    #[test]
+    fn test_pause_stage() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::<BytesInput>::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut fuzzer: StdFuzzer<_, _, _, ()> =
+            StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut mgr = SimpleEventManager::new(PausingMonitor {
+            client_stats: vec![],
+            commands: vec![
+                vec![(0, ClientCommand::Resume)],
+                vec![(0, ClientCommand::Pause)],
+            ],
+        });
+        let mut harness = |_: &BytesInput| ExitKind::Ok;
+        let mut executor =
+            InProcessExecutor::new(&mut harness, (), &mut fuzzer, &mut state, &mut mgr).unwrap();
+        let mut stage = PauseStage::new(&mut mgr);
+
+        // The hello is handled in the broker, not passed on to the client
+        stage
+            .perform(
+                &mut fuzzer,
+                &mut executor,
+                &mut state,
+                &mut mgr,
+                CorpusId::from(0_usize),
+            )
+            .unwrap();
+        assert!(!state.has_metadata::<PauseMetadata>());
+
+        mgr.process(&mut fuzzer, &mut state, &mut executor).unwrap();
+        assert!(state.metadata().get::<PauseMetadata>().unwrap().paused);
+
+        // Waits until the monitor resumes the client
+        stage
+            .perform(
+                &mut fuzzer,
+                &mut executor,
+                &mut state,
+                &mut mgr,
+                CorpusId::from(0_usize),
+            )
+            .unwrap();
+        assert!(!state.metadata().get::<PauseMetadata>().unwrap().paused);
+    }
+}
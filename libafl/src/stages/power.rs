@@ -5,7 +5,7 @@ use core::{fmt::Debug, marker::PhantomData};
 
 use crate::{
     bolts::tuples::MatchName,
-    corpus::{Corpus, CorpusId, SchedulerTestcaseMetaData},
+    corpus::{Corpus, CorpusId, LineageContextMetadata, SchedulerTestcaseMetaData},
     executors::{Executor, HasObservers},
     fuzzer::Evaluator,
     mutators::Mutator,
@@ -152,7 +152,9 @@ where
         manager: &mut EM,
         corpus_idx: CorpusId,
     ) -> Result<(), Error> {
+        LineageContextMetadata::enter_stage(state, "PowerMutationalStage");
         let ret = self.perform_mutational(fuzzer, executor, state, manager, corpus_idx);
+        LineageContextMetadata::leave_stage(state);
         ret
     }
 }
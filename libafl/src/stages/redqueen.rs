@@ -0,0 +1,589 @@
+//! The input-to-state stage from `cmplog` in afl++, also known as `RedQueen`.
+//! It uses the [`TaintMetadata`] of the [`crate::stages::ColorizationStage`] to find the bytes of the input
+//! that reach the comparisons logged in the [`CmpValuesMetadata`] and replaces them with the other operand.
+
+use alloc::{string::ToString, vec::Vec};
+use core::{fmt::Debug, marker::PhantomData, ops::Range};
+
+use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "introspection")]
+use crate::monitors::PerfFeature;
+use crate::{
+    corpus::{Corpus, CorpusId},
+    executors::{Executor, HasObservers},
+    inputs::HasBytesVec,
+    mark_feature_time,
+    observers::{
+        cmp::{CmpValues, CmpValuesMetadata},
+        ObserversTuple,
+    },
+    stages::{colorization::TaintMetadata, Stage},
+    start_timer,
+    state::{HasClientPerfMonitor, HasCorpus, HasExecutions, HasMetadata, UsesState},
+    Error, Evaluator, ExecuteInputResult,
+};
+
+/// The encoding in which a comparison operand was found in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct I2STransform {
+    /// The amount of bytes of the operand found in the input
+    pub width: usize,
+    /// If the operand was found with its bytes swapped
+    pub swapped: bool,
+    /// If the operand is the sign- or zero-extension of a narrower value found in the input
+    pub extended: bool,
+    /// The value added to the other operand, to pass `<` and `>` comparisons
+    pub delta: i8,
+}
+
+/// A replacement done by the [`RedQueenStage`] which produced a new corpus entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct I2SSubstitution {
+    /// The offset in the input
+    pub offset: usize,
+    /// The bytes found in the input
+    pub pattern: Vec<u8>,
+    /// The bytes written to the input
+    pub replacement: Vec<u8>,
+    /// How the operands were encoded
+    pub transform: I2STransform,
+    /// The new corpus entry
+    pub corpus_idx: CorpusId,
+}
+
+/// Testcase metadata holding the substitutions of the [`RedQueenStage`] that produced new coverage.
+/// Its presence also marks the testcase as already processed.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RedQueenMetadata {
+    substitutions: Vec<I2SSubstitution>,
+}
+
+crate::impl_serdeany!(RedQueenMetadata);
+
+impl RedQueenMetadata {
+    /// Creates a new [`RedQueenMetadata`]
+    #[must_use]
+    pub fn new(substitutions: Vec<I2SSubstitution>) -> Self {
+        Self { substitutions }
+    }
+
+    /// The substitutions that produced new corpus entries
+    #[must_use]
+    pub fn substitutions(&self) -> &[I2SSubstitution] {
+        &self.substitutions
+    }
+}
+
+/// A candidate substitution for one comparison operand
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct I2SCandidate {
+    pattern: Vec<u8>,
+    replacement: Vec<u8>,
+    transform: I2STransform,
+}
+
+/// Returns the mask for the given width, in bytes
+fn width_mask(width: usize) -> u64 {
+    if width >= 8 {
+        u64::MAX
+    } else {
+        (1_u64 << (width * 8)) - 1
+    }
+}
+
+/// Returns if the value (of width `width`) is the sign- or zero-extension of a value of width `narrow`
+fn is_extension(value: u64, width: usize, narrow: usize) -> bool {
+    let bits = narrow * 8;
+    let high = (value & width_mask(width)) >> bits;
+    high == 0 || (high == width_mask(width) >> bits && value & (1 << (bits - 1)) != 0)
+}
+
+/// The bytes of the value truncated to `width`, in native or swapped byte order
+fn int_bytes(value: u64, width: usize, swapped: bool) -> Vec<u8> {
+    let mut bytes = value.to_le_bytes()[..width].to_vec();
+    if cfg!(target_endian = "big") != swapped {
+        bytes.reverse();
+    }
+    bytes
+}
+
+fn push_int_candidates(
+    pattern: u64,
+    replacement: u64,
+    width: usize,
+    candidates: &mut Vec<I2SCandidate>,
+) {
+    let mask = width_mask(width);
+    for delta in [0_i8, 1, -1] {
+        let replacement = replacement.wrapping_add_signed(i64::from(delta)) & mask;
+        if replacement == pattern & mask {
+            continue;
+        }
+        for narrow in [8, 4, 2, 1] {
+            if narrow > width
+                || (narrow < width
+                    && !(is_extension(pattern, width, narrow)
+                        && is_extension(replacement, width, narrow)))
+            {
+                continue;
+            }
+            for swapped in [false, true] {
+                if swapped && narrow == 1 {
+                    continue;
+                }
+                candidates.push(I2SCandidate {
+                    pattern: int_bytes(pattern, narrow, swapped),
+                    replacement: int_bytes(replacement, narrow, swapped),
+                    transform: I2STransform {
+                        width: narrow,
+                        swapped,
+                        extended: narrow < width,
+                        delta,
+                    },
+                });
+            }
+        }
+    }
+}
+
+fn push_bytes_candidates(pattern: &[u8], replacement: &[u8], candidates: &mut Vec<I2SCandidate>) {
+    let transform = I2STransform {
+        width: pattern.len(),
+        swapped: false,
+        extended: false,
+        delta: 0,
+    };
+    if !pattern.is_empty() && pattern != replacement {
+        candidates.push(I2SCandidate {
+            pattern: pattern.to_vec(),
+            replacement: replacement.to_vec(),
+            transform,
+        });
+    }
+    // Routines such as `strcmp` log a fixed amount of bytes, also try the C strings
+    let pattern_str = pattern.split(|&b| b == 0).next().unwrap_or_default();
+    let replacement_str = replacement.split(|&b| b == 0).next().unwrap_or_default();
+    if !pattern_str.is_empty()
+        && pattern_str != replacement_str
+        && (pattern_str.len() != pattern.len() || replacement_str.len() != replacement.len())
+    {
+        candidates.push(I2SCandidate {
+            pattern: pattern_str.to_vec(),
+            replacement: replacement_str.to_vec(),
+            transform: I2STransform {
+                width: pattern_str.len(),
+                ..transform
+            },
+        });
+    }
+}
+
+/// Computes the substitutions for a logged comparison.
+/// If the same comparison of the colorized input is known, only the operands that changed
+/// with the colorization, hence the ones derived from the input, are replaced.
+fn i2s_candidates(orig: &CmpValues, colorized: Option<&CmpValues>) -> Vec<I2SCandidate> {
+    let mut candidates = vec![];
+    let width = match orig {
+        CmpValues::U8(_) => 1,
+        CmpValues::U16(_) => 2,
+        CmpValues::U32(_) => 4,
+        CmpValues::U64(_) => 8,
+        CmpValues::Bytes((v0, v1)) => {
+            let (from_v0, from_v1) = if let Some(CmpValues::Bytes((c0, c1))) = colorized {
+                (v0 != c0, v1 != c1)
+            } else {
+                (true, true)
+            };
+            if from_v0 {
+                push_bytes_candidates(v0, v1, &mut candidates);
+            }
+            if from_v1 {
+                push_bytes_candidates(v1, v0, &mut candidates);
+            }
+            return candidates;
+        }
+    };
+    let (v0, v1) = orig.to_u64_tuple().unwrap();
+    let (from_v0, from_v1) = match colorized.and_then(CmpValues::to_u64_tuple) {
+        Some((c0, c1)) => (v0 != c0, v1 != c1),
+        None => (true, true),
+    };
+    if from_v0 {
+        push_int_candidates(v0, v1, width, &mut candidates);
+    }
+    if from_v1 {
+        push_int_candidates(v1, v0, width, &mut candidates);
+    }
+    candidates
+}
+
+/// The input-to-state replacement stage (`RedQueen`).
+/// It runs the tracer executor, which should fill the [`CmpValuesMetadata`] (i.e. it has a
+/// [`crate::observers::cmp::CmpObserver`] with `add_meta` set), on the testcase and on the colorized input
+/// of the [`TaintMetadata`]. Add it right after a [`crate::stages::ColorizationStage`].
+#[derive(Clone, Debug)]
+pub struct RedQueenStage<EM, TE, Z> {
+    tracer_executor: TE,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(EM, TE, Z)>,
+}
+
+impl<EM, TE, Z> UsesState for RedQueenStage<EM, TE, Z>
+where
+    TE: UsesState,
+{
+    type State = TE::State;
+}
+
+impl<E, EM, TE, Z> Stage<E, EM, Z> for RedQueenStage<EM, TE, Z>
+where
+    E: UsesState<State = TE::State>,
+    TE: Executor<EM, Z> + HasObservers,
+    TE::State: HasClientPerfMonitor + HasExecutions + HasCorpus + HasMetadata,
+    TE::Input: HasBytesVec,
+    EM: UsesState<State = TE::State>,
+    Z: UsesState<State = TE::State> + Evaluator<E, EM>,
+{
+    #[inline]
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut TE::State,
+        manager: &mut EM,
+        corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        start_timer!(state);
+        let input = {
+            let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+            if testcase.metadata().contains::<RedQueenMetadata>() {
+                return Ok(());
+            }
+            testcase.load_input()?.clone()
+        };
+        mark_feature_time!(state, PerfFeature::GetInputFromCorpus);
+
+        let input_len = input.bytes().len();
+        let orig_cmps = self.trace(fuzzer, state, manager, &input)?;
+
+        // The taint is valid only if it was computed for this testcase
+        let taint = state
+            .metadata()
+            .get::<TaintMetadata>()
+            .filter(|meta| meta.is_for(input.bytes()))
+            .map(|meta| (meta.input_vec().clone(), meta.ranges().clone()));
+        let (colorized_cmps, ranges) = if let Some((colorized_bytes, ranges)) = taint {
+            let mut colorized = input.clone();
+            *colorized.bytes_mut() = colorized_bytes;
+            let colorized_cmps = self.trace(fuzzer, state, manager, &colorized)?;
+            // If the colorized input took another path, the logs do not correspond
+            (
+                Some(colorized_cmps).filter(|cmps| cmps.len() == orig_cmps.len()),
+                ranges,
+            )
+        } else {
+            (
+                None,
+                vec![Range {
+                    start: 0,
+                    end: input_len,
+                }],
+            )
+        };
+
+        let mut tried = HashSet::new();
+        let mut substitutions = vec![];
+        for (i, cmp) in orig_cmps.iter().enumerate() {
+            let colorized = colorized_cmps.as_ref().map(|cmps| &cmps[i]);
+            for candidate in i2s_candidates(cmp, colorized) {
+                for offset in ranges.iter().flat_map(Range::clone) {
+                    if !input.bytes()[offset..].starts_with(&candidate.pattern)
+                        || !tried.insert((offset, candidate.replacement.clone()))
+                    {
+                        continue;
+                    }
+
+                    let mut mutated = input.clone();
+                    let end = core::cmp::min(offset + candidate.replacement.len(), input_len);
+                    mutated.bytes_mut()[offset..end]
+                        .copy_from_slice(&candidate.replacement[..end - offset]);
+
+                    // Time is measured directly the `evaluate_input` function
+                    let (res, new_idx) =
+                        fuzzer.evaluate_input(state, executor, manager, mutated)?;
+                    if let (ExecuteInputResult::Corpus, Some(new_idx)) = (res, new_idx) {
+                        substitutions.push(I2SSubstitution {
+                            offset,
+                            pattern: candidate.pattern.clone(),
+                            replacement: candidate.replacement.clone(),
+                            transform: candidate.transform,
+                            corpus_idx: new_idx,
+                        });
+                    }
+                }
+            }
+        }
+
+        // Restore the comparisons of the original input for the following stages
+        if let Some(meta) = state.metadata_mut().get_mut::<CmpValuesMetadata>() {
+            meta.list = orig_cmps;
+        }
+
+        state
+            .corpus()
+            .get(corpus_idx)?
+            .borrow_mut()
+            .add_metadata(RedQueenMetadata::new(substitutions));
+
+        Ok(())
+    }
+}
+
+impl<EM, TE, Z> RedQueenStage<EM, TE, Z>
+where
+    TE: Executor<EM, Z> + HasObservers,
+    TE::State: HasClientPerfMonitor + HasExecutions + HasMetadata,
+    EM: UsesState<State = TE::State>,
+    Z: UsesState<State = TE::State>,
+{
+    /// Creates a new [`RedQueenStage`]
+    pub fn new(tracer_executor: TE) -> Self {
+        Self {
+            tracer_executor,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Gets the underlying tracer executor
+    pub fn executor(&self) -> &TE {
+        &self.tracer_executor
+    }
+
+    /// Gets the underlying tracer executor (mut)
+    pub fn executor_mut(&mut self) -> &mut TE {
+        &mut self.tracer_executor
+    }
+
+    /// Runs the tracer and returns the logged comparisons
+    fn trace(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut TE::State,
+        manager: &mut EM,
+        input: &TE::Input,
+    ) -> Result<Vec<CmpValues>, Error> {
+        start_timer!(state);
+        self.tracer_executor
+            .observers_mut()
+            .pre_exec_all(state, input)?;
+        mark_feature_time!(state, PerfFeature::PreExecObservers);
+
+        start_timer!(state);
+        let exit_kind = self
+            .tracer_executor
+            .run_target(fuzzer, state, manager, input)?;
+        mark_feature_time!(state, PerfFeature::TargetExecution);
+
+        *state.executions_mut() += 1;
+
+        start_timer!(state);
+        self.tracer_executor
+            .observers_mut()
+            .post_exec_all(state, input, &exit_kind)?;
+        mark_feature_time!(state, PerfFeature::PostExecObservers);
+
+        state
+            .metadata()
+            .get::<CmpValuesMetadata>()
+            .map(|meta| meta.list.clone())
+            .ok_or_else(|| {
+                Error::key_not_found(
+                    "CmpValuesMetadata not found, the tracer needs a CmpObserver adding metadata"
+                        .to_string(),
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use super::{i2s_candidates, I2STransform, RedQueenMetadata, RedQueenStage};
+    use crate::{
+        bolts::{
+            rands::StdRand,
+            tuples::{tuple_list, Named},
+        },
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        executors::{ExitKind, InProcessExecutor},
+        feedbacks::ConstFeedback,
+        inputs::{BytesInput, HasBytesVec, UsesInput},
+        observers::{
+            cmp::{CmpValues, CmpValuesMetadata},
+            Observer,
+        },
+        schedulers::QueueScheduler,
+        stages::{colorization::TaintMetadata, Stage},
+        state::{HasCorpus, HasMetadata, StdState},
+        Error, StdFuzzer,
+    };
+
+    /// Logs the comparison of the first 4 bytes of the input with a magic value
+    #[derive(Debug)]
+    struct MagicCmpObserver;
+
+    impl<S> Observer<S> for MagicCmpObserver
+    where
+        S: UsesInput<Input = BytesInput> + HasMetadata,
+    {
+        fn post_exec(
+            &mut self,
+            state: &mut S,
+            input: &BytesInput,
+            _exit_kind: &ExitKind,
+        ) -> Result<(), Error> {
+            let value = u32::from_ne_bytes(input.bytes()[..4].try_into().unwrap());
+            state.add_metadata(CmpValuesMetadata {
+                list: vec![CmpValues::U32((value, 0xdead_beef))],
+            });
+            Ok(())
+        }
+    }
+
+    impl Named for MagicCmpObserver {
+        fn name(&self) -> &str {
+            "magic_cmp"
+        }
+    }
+
+    /// Runs the [`RedQueenStage`] on `input`, with the given taint, returning the new corpus entries
+    fn red_queen(input: &[u8], taint: Option<TaintMetadata>) -> Vec<Vec<u8>> {
+        let mut feedback = ConstFeedback::new(true);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::<BytesInput>::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut mgr = NopEventManager::new();
+        if let Some(taint) = taint {
+            state.add_metadata(taint);
+        }
+        let corpus_idx = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(input.to_vec())))
+            .unwrap();
+
+        let mut harness = |_: &BytesInput| ExitKind::Ok;
+        let mut executor =
+            InProcessExecutor::new(&mut harness, (), &mut fuzzer, &mut state, &mut mgr).unwrap();
+        let mut tracer_harness = |_: &BytesInput| ExitKind::Ok;
+        let tracer = InProcessExecutor::new(
+            &mut tracer_harness,
+            tuple_list!(MagicCmpObserver),
+            &mut fuzzer,
+            &mut state,
+            &mut mgr,
+        )
+        .unwrap();
+        RedQueenStage::new(tracer)
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr, corpus_idx)
+            .unwrap();
+
+        let substitutions = state
+            .corpus()
+            .get(corpus_idx)
+            .unwrap()
+            .borrow()
+            .metadata()
+            .get::<RedQueenMetadata>()
+            .unwrap()
+            .substitutions()
+            .to_vec();
+        substitutions
+            .iter()
+            .map(|substitution| {
+                state
+                    .corpus()
+                    .get(substitution.corpus_idx)
+                    .unwrap()
+                    .borrow()
+                    .input()
+                    .as_ref()
+                    .unwrap()
+                    .bytes()
+                    .to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_red_queen_stage() {
+        let input = b"abcdabcd";
+        let solved = |entries: &[Vec<u8>]| {
+            entries
+                .iter()
+                .any(|entry| entry[..4] == 0xdead_beef_u32.to_ne_bytes())
+        };
+
+        // Without taint, the whole input is searched
+        assert!(solved(&red_queen(input, None)));
+
+        // The taint of this input only lets the stage search the tainted bytes
+        let mut taint = TaintMetadata::new(b"wxyzabcd".to_vec(), vec![0..4]);
+        taint.set_orig_input(input);
+        let entries = red_queen(input, Some(taint));
+        assert!(solved(&entries));
+        assert!(entries.iter().all(|entry| entry[4..] == input[4..]));
+
+        // The taint of another input of the same length is ignored
+        let mut stale = TaintMetadata::new(b"abcdwxyz".to_vec(), vec![4..8]);
+        stale.set_orig_input(b"ABCDABCD");
+        assert!(solved(&red_queen(input, Some(stale))));
+    }
+
+    #[test]
+    fn test_i2s_candidates() {
+        // Only the first operand changed with colorization
+        let candidates = i2s_candidates(
+            &CmpValues::U32((0x10, 0xdead_beef)),
+            Some(&CmpValues::U32((0x42, 0xdead_beef))),
+        );
+        let direct = I2STransform {
+            width: 4,
+            swapped: false,
+            extended: false,
+            delta: 0,
+        };
+        assert!(candidates
+            .iter()
+            .any(|c| c.transform == direct && c.replacement == 0xdead_beef_u32.to_ne_bytes()));
+        assert!(candidates
+            .iter()
+            .all(|c| c.pattern != 0xdead_beef_u32.to_ne_bytes()));
+
+        // A negative value compared as u64 is also searched as a narrower value
+        let candidates = i2s_candidates(&CmpValues::U64((u64::MAX, 7)), None);
+        assert!(candidates.iter().any(|c| c.transform.extended
+            && c.transform.width == 1
+            && c.pattern == [0xff]
+            && c.replacement == [7]));
+
+        // Strings are also tried up to the terminator
+        let candidates = i2s_candidates(
+            &CmpValues::Bytes((b"abc\0\0\0".to_vec(), b"magic\0".to_vec())),
+            None,
+        );
+        assert!(candidates
+            .iter()
+            .any(|c| c.pattern == b"abc" && c.replacement == b"magic"));
+    }
+}
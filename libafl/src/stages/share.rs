@@ -0,0 +1,108 @@
+//! The [`ShareMetadataStage`] sends the testcase metadata computed by the stages before it,
+//! such as the [`crate::stages::GeneralizationStage`], to the other clients.
+
+use core::marker::PhantomData;
+
+use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, LineageMetadata},
+    events::{Event, EventFirer, TestcaseMetadataSelection},
+    stages::Stage,
+    state::{HasCorpus, HasMetadata, UsesState},
+    Error,
+};
+
+/// The testcases whose metadata was already sent by a [`ShareMetadataStage`], kept in the state
+/// not to send it again after a restart
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SharedTestcasesMetadata {
+    ids: HashSet<CorpusId>,
+}
+
+crate::impl_serdeany!(SharedTestcasesMetadata);
+
+/// A stage sending the metadata of the fuzzed testcases, as selected by the [`TestcaseMetadataSelection`] of the state,
+/// to the other clients in [`Event::UpdateTestcaseMetadata`] events, once per testcase.
+/// Only the testcases found by this client are shared, the imported ones are shared by the clients that found them.
+#[derive(Clone, Debug)]
+pub struct ShareMetadataStage<E, EM, Z> {
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> UsesState for ShareMetadataStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for ShareMetadataStage<E, EM, Z>
+where
+    E: UsesState,
+    EM: EventFirer<State = E::State>,
+    Z: UsesState<State = E::State>,
+    E::State: HasCorpus + HasMetadata,
+{
+    #[inline]
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        if !state.has_metadata::<SharedTestcasesMetadata>() {
+            state.add_metadata(SharedTestcasesMetadata::default());
+        }
+        let shared = state
+            .metadata_mut()
+            .get_mut::<SharedTestcasesMetadata>()
+            .unwrap();
+        if !shared.ids.insert(corpus_idx) {
+            return Ok(());
+        }
+
+        let imported = state
+            .corpus()
+            .get(corpus_idx)?
+            .borrow()
+            .metadata()
+            .get::<LineageMetadata>()
+            .map_or(false, |lineage| lineage.client.is_some());
+        if imported {
+            return Ok(());
+        }
+
+        if let Some(metadata) = TestcaseMetadataSelection::metadata_to_send(state, corpus_idx)? {
+            manager.fire(
+                state,
+                Event::UpdateTestcaseMetadata {
+                    corpus_id: corpus_idx,
+                    metadata,
+                    phantom: PhantomData,
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<E, EM, Z> ShareMetadataStage<E, EM, Z> {
+    /// Creates a new [`ShareMetadataStage`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, EM, Z> Default for ShareMetadataStage<E, EM, Z> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -11,8 +11,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     bolts::{current_time, shmem::ShMemProvider},
-    corpus::{Corpus, CorpusId},
-    events::{llmp::LlmpEventConverter, Event, EventConfig, EventFirer},
+    corpus::{Corpus, CorpusId, LineageContextMetadata, LineageMetadata},
+    events::{llmp::LlmpEventConverter, Event, EventConfig, EventFirer, TestcaseMetadataSelection},
     executors::{Executor, ExitKind, HasObservers},
     fuzzer::{Evaluator, EvaluatorObservers, ExecutionProcessor},
     inputs::{Input, InputConverter, UsesInput},
@@ -75,9 +75,10 @@ where
             .get::<SyncFromDiskMetadata>()
             .map(|m| m.last_time);
         let path = self.sync_dir.clone();
-        if let Some(max_time) =
-            self.load_from_directory(&path, &last, fuzzer, executor, state, manager)?
-        {
+        LineageContextMetadata::enter_import_stage(state, "SyncFromDiskStage");
+        let max_time = self.load_from_directory(&path, &last, fuzzer, executor, state, manager);
+        LineageContextMetadata::leave_stage(state);
+        if let Some(max_time) = max_time? {
             if last.is_none() {
                 state
                     .metadata_mut()
@@ -262,7 +263,14 @@ where
                 last_id.map_or_else(|| state.corpus().first(), |id| state.corpus().next(id));
 
             while let Some(id) = cur_id {
-                let input = state.corpus().get(id)?.borrow_mut().load_input()?.clone();
+                let mut testcase = state.corpus().get(id)?.borrow_mut();
+                let input = testcase.load_input()?.clone();
+                let lineage = testcase
+                    .metadata()
+                    .get::<LineageMetadata>()
+                    .map(|lineage| lineage.to_send(id));
+                drop(testcase);
+                let metadata = TestcaseMetadataSelection::metadata_to_send(state, id)?;
 
                 self.client.fire(
                     state,
@@ -274,6 +282,8 @@ where
                         client_config: EventConfig::AlwaysUnique,
                         time: current_time(),
                         executions: 0,
+                        lineage,
+                        metadata,
                     },
                 )?;
 
@@ -1,4 +1,5 @@
 //! The [`TMinMutationalStage`] is a stage which will attempt to minimize corpus entries.
+//! The [`ObjectiveTMinStage`] minimizes the objectives, preserving their crash.
 
 use alloc::string::{String, ToString};
 use core::{
@@ -8,23 +9,30 @@ use core::{
 };
 
 use ahash::RandomState;
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "introspection")]
 use crate::monitors::PerfFeature;
 use crate::{
-    bolts::{tuples::Named, HasLen},
+    bolts::{
+        tuples::{MatchName, Named},
+        HasLen,
+    },
     corpus::{Corpus, CorpusId, Testcase},
     events::EventFirer,
-    executors::{Executor, ExitKind, HasObservers},
+    executors::{Executor, ExitKind, HasObservers, SurvivesCrashes},
     feedbacks::{Feedback, FeedbackFactory, HasObserverName},
     inputs::UsesInput,
     mark_feature_time,
     mutators::Mutator,
-    observers::{MapObserver, ObserversTuple},
+    observers::{MapObserver, ObserverWithHashField, ObserversTuple},
     schedulers::Scheduler,
     stages::Stage,
     start_timer,
-    state::{HasClientPerfMonitor, HasCorpus, HasExecutions, HasMaxSize, UsesState},
+    state::{
+        HasClientPerfMonitor, HasCorpus, HasExecutions, HasMaxSize, HasMetadata, HasSolutions,
+        UsesState,
+    },
     Error, ExecutesInput, ExecutionProcessor, HasFeedback, HasScheduler,
 };
 
@@ -387,3 +395,374 @@ where
         }
     }
 }
+
+/// The metadata of the [`ObjectiveTMinStage`] in the state, remembering the last processed objective
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ObjectiveTMinMetadata {
+    /// The last objective processed by the stage
+    pub last_id: Option<CorpusId>,
+}
+
+crate::impl_serdeany!(ObjectiveTMinMetadata);
+
+/// The metadata added to an objective minimized by the [`ObjectiveTMinStage`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MinimizedObjectiveMetadata {
+    /// The id of the original objective in the solutions
+    pub original: CorpusId,
+}
+
+crate::impl_serdeany!(MinimizedObjectiveMetadata);
+
+/// The metadata added to an objective when the [`ObjectiveTMinStage`] found a smaller reproducer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MinimizedReproducerMetadata {
+    /// The id of the minimized objective in the solutions
+    pub minimized: CorpusId,
+}
+
+crate::impl_serdeany!(MinimizedReproducerMetadata);
+
+/// A stage minimizing the objectives found, in the background of the fuzzing loop.
+///
+/// Each time it's performed, it takes the next new objective from the solutions and shrinks it with the
+/// given mutator, keeping only the inputs crashing with the same [`ExitKind`], the same stacktrace hash and
+/// the same bug type, as given by an [`ObserverWithHashField`] such as the `AsanBacktraceObserver`.
+/// The minimized reproducer is added to the solutions alongside the original, with a [`MinimizedObjectiveMetadata`].
+///
+/// The executor has to survive the crashes of the target, see [`SurvivesCrashes`]: the crash handlers of an
+/// [`crate::executors::InProcessExecutor`] would store each reproducing input as a new objective and restart
+/// the fuzzer, instead of returning the [`ExitKind`] to the stage.
+///
+/// You must provide at least one mutator that actually reduces size.
+#[derive(Clone, Debug)]
+pub struct ObjectiveTMinStage<E, EM, M, O, Z> {
+    mutator: M,
+    observer_name: String,
+    runs: usize,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(E, EM, O, Z)>,
+}
+
+impl<E, EM, M, O, Z> UsesState for ObjectiveTMinStage<E, EM, M, O, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, M, O, Z> Stage<E, EM, Z> for ObjectiveTMinStage<E, EM, M, O, Z>
+where
+    E: Executor<EM, Z> + HasObservers + SurvivesCrashes,
+    EM: UsesState<State = E::State>,
+    M: Mutator<E::Input, E::State>,
+    O: ObserverWithHashField + Named,
+    E::State: HasSolutions + HasMetadata + HasExecutions + HasMaxSize + HasClientPerfMonitor,
+    E::Input: HasLen,
+    Z: ExecutesInput<E, EM, State = E::State>,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        _corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        let res = self.minimize_next(fuzzer, executor, state, manager);
+
+        #[cfg(feature = "introspection")]
+        state.introspection_monitor_mut().finish_stage();
+
+        res
+    }
+}
+
+impl<E, EM, M, O, Z> ObjectiveTMinStage<E, EM, M, O, Z>
+where
+    E: Executor<EM, Z> + HasObservers + SurvivesCrashes,
+    EM: UsesState<State = E::State>,
+    M: Mutator<E::Input, E::State>,
+    O: ObserverWithHashField + Named,
+    E::State: HasSolutions + HasMetadata + HasExecutions + HasMaxSize + HasClientPerfMonitor,
+    E::Input: HasLen,
+    Z: ExecutesInput<E, EM, State = E::State>,
+{
+    /// Minimize the next new objective, if any
+    #[allow(clippy::cast_possible_wrap)] // more than i32 stages on 32 bit system - highly unlikely...
+    fn minimize_next(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let last_id = state
+            .metadata()
+            .get::<ObjectiveTMinMetadata>()
+            .and_then(|meta| meta.last_id);
+        let next_id = last_id.map_or_else(
+            || state.solutions().first(),
+            |id| state.solutions().next(id),
+        );
+        let Some(original_idx) = next_id else {
+            return Ok(());
+        };
+        if state.has_metadata::<ObjectiveTMinMetadata>() {
+            state
+                .metadata_mut()
+                .get_mut::<ObjectiveTMinMetadata>()
+                .unwrap()
+                .last_id = Some(original_idx);
+        } else {
+            state.add_metadata(ObjectiveTMinMetadata {
+                last_id: Some(original_idx),
+            });
+        }
+
+        let mut original = state.solutions().get(original_idx)?.borrow_mut();
+        if original.has_metadata::<MinimizedObjectiveMetadata>() {
+            // Already minimized by us
+            return Ok(());
+        }
+        let mut base = original.load_input()?.clone();
+        drop(original);
+        let original_len = base.len();
+
+        // The original has to reproduce, for non-deterministic crashes there is nothing to do
+        let exit_kind = fuzzer.execute_input(state, executor, manager, &base)?;
+        if exit_kind == ExitKind::Ok {
+            return Ok(());
+        }
+        let Some(bucket) = self.observed_bucket(executor) else {
+            return Ok(());
+        };
+
+        let orig_max_size = state.max_size();
+        let mut i = 0;
+        while i < self.runs {
+            let mut next_i = i + 1;
+            let mut input = base.clone();
+            let before_len = input.len();
+            state.set_max_size(before_len);
+
+            start_timer!(state);
+            self.mutator.mutate(state, &mut input, i as i32)?;
+            mark_feature_time!(state, PerfFeature::Mutate);
+
+            // skip any mutations that don't reduce size, they would waste eval time
+            if input.len() < before_len
+                && fuzzer.execute_input(state, executor, manager, &input)? == exit_kind
+                && self.observed_bucket(executor).as_ref() == Some(&bucket)
+            {
+                // same crash, use the smaller base and try to minimize further
+                base = input;
+                next_i = 0;
+            }
+
+            start_timer!(state);
+            self.mutator.post_exec(state, i as i32, None)?;
+            mark_feature_time!(state, PerfFeature::MutatePostExec);
+
+            i = next_i;
+        }
+        state.set_max_size(orig_max_size);
+
+        if base.len() < original_len {
+            let mut testcase = Testcase::with_executions(base, *state.executions());
+            testcase.add_metadata(MinimizedObjectiveMetadata {
+                original: original_idx,
+            });
+            let minimized = state.solutions_mut().add(testcase)?;
+            state
+                .solutions()
+                .get(original_idx)?
+                .borrow_mut()
+                .add_metadata(MinimizedReproducerMetadata { minimized });
+        }
+        Ok(())
+    }
+}
+
+impl<E, EM, M, O, Z> ObjectiveTMinStage<E, EM, M, O, Z>
+where
+    E: HasObservers + SurvivesCrashes,
+    O: ObserverWithHashField + Named,
+{
+    /// Creates a new objective minimizing stage, running the mutator `runs` times without progress
+    /// before giving up, and comparing the hashes of the given observer.
+    pub fn new(mutator: M, observer: &O, runs: usize) -> Self {
+        Self {
+            mutator,
+            observer_name: observer.name().to_string(),
+            runs,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The hash of the stacktrace and the bug type of the last execution, if it crashed
+    fn observed_bucket(&self, executor: &E) -> Option<(u64, Option<String>)> {
+        let observer = executor.observers().match_name::<O>(&self.observer_name)?;
+        Some((
+            observer.hash()?,
+            observer.bug_type().map(ToString::to_string),
+        ))
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "fork", unix))]
+mod tests {
+    use alloc::string::{String, ToString};
+
+    use serde::{Deserialize, Serialize};
+    use serial_test::serial;
+
+    use super::{MinimizedObjectiveMetadata, MinimizedReproducerMetadata, ObjectiveTMinStage};
+    use crate::{
+        bolts::{
+            rands::StdRand,
+            shmem::{ShMemProvider, StdShMemProvider},
+            tuples::{tuple_list, Named},
+        },
+        corpus::{Corpus, CorpusId, InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        executors::{ExitKind, InProcessForkExecutor},
+        feedbacks::ConstFeedback,
+        inputs::{BytesInput, HasBytesVec, UsesInput},
+        mutators::BytesDeleteMutator,
+        observers::{Observer, ObserverWithHashField},
+        schedulers::QueueScheduler,
+        stages::Stage,
+        state::{HasMetadata, HasSolutions, StdState},
+        Error, StdFuzzer,
+    };
+
+    /// Crashes on inputs containing an `X`, like a sanitizer would: with a `heap-buffer-overflow`
+    /// from 4 bytes on, with a `SEGV` with the same stacktrace below
+    #[derive(Debug, Serialize, Deserialize)]
+    struct BugObserver {
+        hash: Option<u64>,
+        bug_type: Option<String>,
+    }
+
+    impl<S> Observer<S> for BugObserver
+    where
+        S: UsesInput<Input = BytesInput>,
+    {
+        fn post_exec(
+            &mut self,
+            _state: &mut S,
+            input: &BytesInput,
+            _exit_kind: &ExitKind,
+        ) -> Result<(), Error> {
+            let crashes = input.bytes().contains(&b'X');
+            self.hash = crashes.then_some(1);
+            self.bug_type = crashes.then(|| {
+                if input.bytes().len() >= 4 {
+                    "heap-buffer-overflow".to_string()
+                } else {
+                    "SEGV".to_string()
+                }
+            });
+            Ok(())
+        }
+    }
+
+    impl Named for BugObserver {
+        fn name(&self) -> &str {
+            "bug"
+        }
+    }
+
+    impl ObserverWithHashField for BugObserver {
+        fn hash(&self) -> Option<u64> {
+            self.hash
+        }
+
+        fn bug_type(&self) -> Option<&str> {
+            self.bug_type.as_deref()
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_objective_tmin() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::<BytesInput>::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut mgr = NopEventManager::new();
+
+        let original = BytesInput::new(b"aaaaaaXaaaaaa".to_vec());
+        let original_idx = state
+            .solutions_mut()
+            .add(Testcase::new(original.clone()))
+            .unwrap();
+
+        let observer = BugObserver {
+            hash: None,
+            bug_type: None,
+        };
+        let mut stage = ObjectiveTMinStage::new(BytesDeleteMutator::new(), &observer, 256);
+        // The target really crashes, in the forked child
+        let mut harness = |input: &BytesInput| {
+            if input.bytes().contains(&b'X') {
+                std::process::abort();
+            }
+            ExitKind::Ok
+        };
+        let mut executor = InProcessForkExecutor::new(
+            &mut harness,
+            tuple_list!(observer),
+            &mut fuzzer,
+            &mut state,
+            &mut mgr,
+            StdShMemProvider::new().unwrap(),
+        )
+        .unwrap();
+
+        stage
+            .perform(
+                &mut fuzzer,
+                &mut executor,
+                &mut state,
+                &mut mgr,
+                CorpusId::from(0_usize),
+            )
+            .unwrap();
+
+        // The minimized reproducer is stored alongside the original
+        assert_eq!(state.solutions().count(), 2);
+        let minimized_idx = state
+            .solutions()
+            .get(original_idx)
+            .unwrap()
+            .borrow()
+            .metadata()
+            .get::<MinimizedReproducerMetadata>()
+            .unwrap()
+            .minimized;
+        let minimized = state.solutions().get(minimized_idx).unwrap().borrow();
+        assert_eq!(
+            minimized
+                .metadata()
+                .get::<MinimizedObjectiveMetadata>()
+                .unwrap()
+                .original,
+            original_idx
+        );
+
+        // Shrunk, but still the same heap-buffer-overflow, not the SEGV of shorter inputs
+        let bytes = minimized.input().as_ref().unwrap().bytes();
+        assert!(bytes.len() < original.bytes().len());
+        assert!(bytes.len() >= 4);
+        assert!(bytes.contains(&b'X'));
+    }
+}
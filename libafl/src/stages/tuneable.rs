@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     bolts::rands::Rand,
-    corpus::CorpusId,
+    corpus::{CorpusId, LineageContextMetadata},
     impl_serdeany,
     mutators::Mutator,
     stages::{
@@ -124,7 +124,9 @@ where
         manager: &mut EM,
         corpus_idx: CorpusId,
     ) -> Result<(), Error> {
+        LineageContextMetadata::enter_stage(state, "TuneableMutationalStage");
         let ret = self.perform_mutational(fuzzer, executor, state, manager, corpus_idx);
+        LineageContextMetadata::leave_stage(state);
 
         #[cfg(feature = "introspection")]
         state.introspection_monitor_mut().finish_stage();
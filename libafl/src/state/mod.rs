@@ -8,10 +8,14 @@ use std::{
     vec::Vec,
 };
 
+#[cfg(feature = "std")]
+use hashbrown::HashMap;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 #[cfg(test)]
 use crate::bolts::rands::StdRand;
+#[cfg(feature = "std")]
+use crate::corpus::{afl::read_afl_dir, CorpusId, LineageMetadata, Testcase};
 use crate::{
     bolts::{
         rands::Rand,
@@ -567,6 +571,72 @@ where
         )
     }
 
+    /// Imports an `AFL++` output directory, i.e. `out/<fuzzer>`, to continue its campaign.
+    ///
+    /// All the testcases in its `queue` are executed and added to the corpus, no matter if they are interesting,
+    /// the ones in its `crashes` are added to the solutions as they are.
+    /// The information `AFL++` encoded in their filenames is added as [`crate::corpus::AflTestcaseMetadata`],
+    /// with the parents mapped to the ids of the imported testcases in the corpus, also added as their [`LineageMetadata`].
+    pub fn import_afl_output<E, EM, Z>(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        manager: &mut EM,
+        out_dir: &Path,
+    ) -> Result<(), Error>
+    where
+        E: UsesState<State = Self>,
+        EM: EventFirer<State = Self>,
+        Z: Evaluator<E, EM, State = Self>,
+    {
+        let queue = read_afl_dir(out_dir.join("queue"))?;
+        let queue_len = queue.len();
+        // The ids of the imported testcases in the corpus, by their `AFL++` id
+        let mut ids = HashMap::new();
+        for (path, mut meta) in queue {
+            log::info!("Importing AFL++ testcase {:?} ...", &path);
+            let afl_id = meta.id;
+            // Parents come first in the queue, so they were already imported
+            meta.src = meta
+                .src
+                .iter()
+                .filter_map(|src| ids.get(src).map(|id: &CorpusId| id.0))
+                .collect();
+            let lineage = LineageMetadata {
+                parents: meta.src.iter().copied().map(CorpusId::from).collect(),
+                stage: meta.op.clone(),
+                ..LineageMetadata::new()
+            };
+
+            let mut testcase = Testcase::new(I::from_file(&path)?);
+            testcase.add_metadata(meta);
+            testcase.add_metadata(lineage);
+            let idx = fuzzer.add_testcase(self, executor, manager, testcase)?;
+            ids.insert(afl_id, idx);
+        }
+
+        let crashes = read_afl_dir(out_dir.join("crashes"))?;
+        let crashes_len = crashes.len();
+        for (path, meta) in crashes {
+            let mut testcase = Testcase::new(I::from_file(&path)?);
+            testcase.add_metadata(meta);
+            self.solutions_mut().add(testcase)?;
+        }
+
+        manager.fire(
+            self,
+            Event::Log {
+                severity_level: LogSeverity::Debug,
+                message: format!(
+                    "Imported {queue_len} testcases and {crashes_len} crashes from {}.",
+                    out_dir.display()
+                ),
+                phantom: PhantomData::<I>,
+            },
+        )?;
+        Ok(())
+    }
+
     /// Loads initial inputs from the passed-in `in_dirs`.
     pub fn load_initial_inputs<E, EM, Z>(
         &mut self,
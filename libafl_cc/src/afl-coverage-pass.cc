@@ -43,6 +43,7 @@
 #include <ctype.h>
 
 #include <list>
+#include <set>
 #include <string>
 #include <fstream>
 
@@ -811,6 +812,18 @@ bool AFLCoverage::runOnModule(Module &M) {
         cfg += "%%__";
       auto current_cur_loc = record->getSecond();
       cfg += formatv("+{0}\n", current_cur_loc);
+      // Dump the source locations of the BB, if compiled with debug info
+      std::set<std::string> locations;
+      for (auto &I : *current_bb) {
+        if (DILocation *Loc = I.getDebugLoc()) {
+          if (Loc->getLine() == 0) { continue; }
+          locations.insert(
+              formatv("{0}:{1}", Loc->getFilename(), Loc->getLine()).str());
+        }
+      }
+      for (auto &location : locations) {
+        cfg += formatv("##{0}\n", location).str();
+      }
       for (auto bb_successor = succ_begin(current_bb);
            bb_successor != succ_end(current_bb); bb_successor++) {
         cfg += formatv("->{0}\n", bb_to_cur_loc[*bb_successor]).str();
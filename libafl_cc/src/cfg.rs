@@ -1,9 +1,10 @@
 //! LLVM style control flow graph with information of AFL-style index of the each
 //! edges, use together with ``AFLCoverage`` pass having --dump-afl-cfg flag enabled.
-use core::borrow::Borrow;
+use core::{borrow::Borrow, cmp::Reverse, str::FromStr};
 use std::{
     collections::{BinaryHeap, HashMap, HashSet},
     marker::PhantomData,
+    path::Path,
 };
 
 use serde::{Deserialize, Serialize};
@@ -74,6 +75,67 @@ impl EntryBasicBlockInfo {
     }
 }
 
+/// A target site for directed fuzzing, parsed from `file:line` or a function name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TargetSite {
+    /// A source location. Only the file name is compared, not its directory.
+    Location {
+        /// The source file
+        file: String,
+        /// The line in the source file
+        line: u32,
+    },
+    /// The entry of a function.
+    Function(String),
+}
+
+impl FromStr for TargetSite {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("Empty target site".to_string());
+        }
+        if let Some((file, line)) = s.rsplit_once(':') {
+            if let Ok(line) = line.parse() {
+                return Ok(TargetSite::Location {
+                    file: file.to_string(),
+                    line,
+                });
+            }
+        }
+        Ok(TargetSite::Function(s.to_string()))
+    }
+}
+
+impl TargetSite {
+    /// Read target sites from a file, one per line, like the `BBtargets.txt` file of `AFLGo`.
+    /// Empty lines and lines starting with `#` are skipped.
+    ///
+    /// # Errors
+    /// If the file cannot be read.
+    pub fn from_file<P: AsRef<Path>>(file_name: P) -> Result<Vec<TargetSite>, String> {
+        std::fs::read_to_string(file_name)
+            .map_err(|err| format!("Cannot read the targets file: {err}"))?
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(str::parse)
+            .collect()
+    }
+
+    /// Returns if the given `file:line` source location matches this target.
+    fn matches_location(&self, location_file: &str, location_line: u32) -> bool {
+        match self {
+            TargetSite::Location { file, line } => {
+                *line == location_line
+                    && Path::new(file).file_name() == Path::new(location_file).file_name()
+            }
+            TargetSite::Function(_) => false,
+        }
+    }
+}
+
 /// An LLVM style control flow graph.
 /// Note: This does not track across functions.
 #[derive(Debug)]
@@ -87,6 +149,8 @@ where
     edges: Vec<Option<CfgEdge<T>>>,
     /// Mapping each function's name to its corresponding entry basic block information.
     func_to_entry_bb: HashMap<String, EntryBasicBlockInfo>,
+    /// The `file:line` source locations of each basic block, if the target was compiled with debug info.
+    bb_to_locations: HashMap<usize, Vec<(String, u32)>>,
 }
 
 impl<T> ControlFlowGraph<T>
@@ -102,6 +166,7 @@ where
         Self {
             edges: (0..map_size).map(|_| None).collect(),
             func_to_entry_bb: HashMap::default(),
+            bb_to_locations: HashMap::default(),
         }
     }
 
@@ -126,6 +191,7 @@ where
     current_bb: usize,
     bb_to_func: HashMap<usize, String>,
     bb_to_successors: HashMap<usize, Vec<usize>>,
+    bb_to_locations: HashMap<usize, Vec<(String, u32)>>,
     func_to_entry_bb: HashMap<String, usize>,
     phantom: PhantomData<T>,
 }
@@ -139,6 +205,7 @@ where
             current_bb: 0,
             bb_to_func: HashMap::default(),
             bb_to_successors: HashMap::default(),
+            bb_to_locations: HashMap::default(),
             func_to_entry_bb: HashMap::default(),
             phantom: PhantomData,
         }
@@ -173,6 +240,14 @@ where
                 self.current_bb = splitter.next().expect(FAILED_TO_PARSE).parse().expect("");
                 self.bb_to_func.insert(self.current_bb, func_name);
             }
+            "##" => {
+                // "##{file}:{line}": The current basic block contains code from {file}:{line}.
+                let (file, line) = line_content.rsplit_once(':').expect(FAILED_TO_PARSE);
+                self.bb_to_locations
+                    .entry(self.current_bb)
+                    .or_default()
+                    .push((file.to_string(), line.parse().expect(FAILED_TO_PARSE)));
+            }
             "$$" => {
                 // "$${function name}+{index}": Function {function name}'s entry block is {index}.
                 let mut splitter = line_content.split('+');
@@ -238,6 +313,7 @@ where
                 cfg.insert_edge(xored_loc, edge);
             }
         }
+        cfg.bb_to_locations.clone_from(&self.bb_to_locations);
         cfg
     }
 }
@@ -333,6 +409,107 @@ where
     }
 }
 
+impl<T> ControlFlowGraph<T>
+where
+    T: HasWeight<T>,
+{
+    /// Get the edges leading to a [`TargetSite`], i.e. the edges whose bottom node is the target basic block.
+    #[must_use]
+    pub fn target_edges(&self, target: &TargetSite) -> Vec<usize> {
+        let target_bbs: HashSet<usize> = match target {
+            TargetSite::Function(func_name) => self
+                .get_entry(func_name)
+                .map(|entry| entry.node_loc)
+                .into_iter()
+                .collect(),
+            TargetSite::Location { .. } => self
+                .bb_to_locations
+                .iter()
+                .filter(|(_, locations)| {
+                    locations
+                        .iter()
+                        .any(|(file, line)| target.matches_location(file, *line))
+                })
+                .map(|(bb, _)| *bb)
+                .collect(),
+        };
+        self.edges
+            .iter()
+            .flatten()
+            .filter(|edge| target_bbs.contains(&edge.bottom_node_loc))
+            .map(|edge| edge.xored_loc)
+            .collect()
+    }
+
+    /// Calculate the shortest distance from every edge to the given target edge.
+    /// Unreachable edges are not inserted in the returned hash map.
+    fn calculate_distances_to_edge(
+        &self,
+        target: usize,
+        predecessors: &HashMap<usize, Vec<usize>>,
+    ) -> HashMap<usize, u32> {
+        let mut distances: HashMap<usize, u32> = HashMap::new();
+        let mut to_visit = BinaryHeap::new(); // BinaryHeap<Reverse<(distance, loc)>>
+        distances.insert(target, 0);
+        to_visit.push(Reverse((0, target)));
+
+        while let Some(Reverse((distance, edge))) = to_visit.pop() {
+            if distances
+                .get(&edge)
+                .map_or(false, |&current| current < distance)
+            {
+                continue;
+            }
+            // Going from a predecessor to this edge costs the weight of this edge
+            let new_distance = distance + self.get_edge(edge).map_or(1, CfgEdge::get_weight);
+            for predecessor in predecessors.get(&edge).into_iter().flatten() {
+                let is_shorter = distances
+                    .get(predecessor)
+                    .map_or(true, |&current| new_distance < current);
+                if is_shorter {
+                    distances.insert(*predecessor, new_distance);
+                    to_visit.push(Reverse((new_distance, *predecessor)));
+                }
+            }
+        }
+        distances
+    }
+
+    /// Calculate the distance of every edge to a set of target edges, as done by `AFLGo`:
+    /// the harmonic mean of the shortest distances to every reachable target.
+    /// The result is indexed by the edges' index in the coverage map.
+    /// Edges that reach no target are not inserted in the returned hash map.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn calculate_distances_to_targets(&self, targets: &[usize]) -> HashMap<usize, f64> {
+        let mut predecessors: HashMap<usize, Vec<usize>> = HashMap::new();
+        for edge in self.edges.iter().flatten() {
+            for successor in &edge.successor_edges {
+                predecessors
+                    .entry(*successor)
+                    .or_default()
+                    .push(edge.xored_loc);
+            }
+        }
+
+        // Sum of the inverse distances and number of reachable targets
+        let mut sums: HashMap<usize, (f64, usize)> = HashMap::new();
+        let unique_targets: HashSet<usize> = targets.iter().copied().collect();
+        for target in unique_targets {
+            for (edge, distance) in self.calculate_distances_to_edge(target, &predecessors) {
+                let entry = sums.entry(edge).or_insert((0.0, 0));
+                // The targets themselves have distance 0
+                entry.0 += 1.0 / (f64::from(distance) + 1.0);
+                entry.1 += 1;
+            }
+        }
+
+        sums.into_iter()
+            .map(|(edge, (sum, count))| (edge, count as f64 / sum - 1.0))
+            .collect()
+    }
+}
+
 impl<T> Default for ControlFlowGraph<T>
 where
     T: HasWeight<T>,
@@ -344,7 +521,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::cfg::{ControlFlowGraph, HasWeight};
+    use crate::cfg::{ControlFlowGraph, HasWeight, TargetSite};
 
     struct TestMetaData {}
 
@@ -390,6 +567,44 @@ mod tests {
         assert!(cfg.get_edge(41864).is_some());
     }
 
+    #[test]
+    fn test_target_sites() {
+        assert_eq!(
+            "src/png.c:42".parse::<TargetSite>().unwrap(),
+            TargetSite::Location {
+                file: "src/png.c".to_string(),
+                line: 42
+            }
+        );
+        assert_eq!(
+            "png_read_info".parse::<TargetSite>().unwrap(),
+            TargetSite::Function("png_read_info".to_string())
+        );
+
+        let graph = format!("{TEST_GRAPH_STR}%%main+41925\n##/home/user/main.c:13\n");
+        let cfg: ControlFlowGraph<TestMetaData> = ControlFlowGraph::from_content(&graph);
+        assert_eq!(
+            cfg.target_edges(&"main.c:13".parse().unwrap()),
+            vec![(26911 >> 1) ^ 41925]
+        );
+        assert!(cfg.target_edges(&"main.c:14".parse().unwrap()).is_empty());
+        assert_eq!(
+            cfg.target_edges(&TargetSite::Function("main".to_string())),
+            vec![41864]
+        );
+    }
+
+    #[test]
+    fn test_distances_to_targets() {
+        let cfg: ControlFlowGraph<TestMetaData> = ControlFlowGraph::from_content(TEST_GRAPH_STR);
+        let target = (26911 >> 1) ^ 41925;
+        let distances = cfg.calculate_distances_to_targets(&[target]);
+        assert!(distances[&target].abs() < f64::EPSILON);
+        assert!((distances[&((41864 >> 1) ^ 26911)] - 1.0).abs() < f64::EPSILON);
+        assert!((distances[&41864] - 2.0).abs() < f64::EPSILON);
+        assert!(distances.get(&((41864 >> 1) ^ 52706)).is_none());
+    }
+
     #[test]
     fn test_shortest_path() {
         let cfg: ControlFlowGraph<TestMetaData> = ControlFlowGraph::from_content(TEST_GRAPH_STR);
@@ -61,7 +61,7 @@
 use std::{convert::Into, path::Path, process::Command, string::String, vec::Vec};
 
 pub mod cfg;
-pub use cfg::{CfgEdge, ControlFlowGraph, EntryBasicBlockInfo, HasWeight};
+pub use cfg::{CfgEdge, ControlFlowGraph, EntryBasicBlockInfo, HasWeight, TargetSite};
 pub mod clang;
 pub use clang::{ClangWrapper, LLVMPasses};
 
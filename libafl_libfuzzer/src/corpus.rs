@@ -0,0 +1,259 @@
+//! The [`LibfuzzerCorpus`] stores the corpus entries, and the artifacts, with libFuzzer's file names
+
+use core::cell::RefCell;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use libafl::{
+    bolts::AsSlice,
+    corpus::{Corpus, CorpusId, InMemoryCorpus, Testcase},
+    inputs::{HasTargetBytes, Input, UsesInput},
+    state::HasMetadata,
+    Error,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::feedbacks::ArtifactMetadata;
+
+/// The hex encoded `SHA-1` of `bytes`, that libFuzzer names its files after
+#[must_use]
+pub fn sha1_hex(bytes: &[u8]) -> String {
+    sha1_smol::Sha1::from(bytes).digest().to_string()
+}
+
+/// An in-memory corpus writing each new entry to disk, as libFuzzer does.
+///
+/// Corpus entries are written to the first corpus directory as `<sha1>`,
+/// artifacts as `<artifact_prefix><kind>-<sha1>`, e.g. `crash-<sha1>`, or to the exact artifact path.
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[serde(bound = "I: serde::de::DeserializeOwned")]
+pub struct LibfuzzerCorpus<I>
+where
+    I: Input,
+{
+    inner: InMemoryCorpus<I>,
+    /// The prefix of the written files, `None` to keep the entries in memory only
+    prefix: Option<String>,
+    /// Write all entries to this path, instead
+    exact_path: Option<PathBuf>,
+    /// Name the files after the kind of the artifacts
+    artifacts: bool,
+    /// Write the new entries to disk
+    write_to_disk: bool,
+}
+
+impl<I> UsesInput for LibfuzzerCorpus<I>
+where
+    I: Input,
+{
+    type Input = I;
+}
+
+impl<I> Corpus for LibfuzzerCorpus<I>
+where
+    I: Input + HasTargetBytes,
+{
+    #[inline]
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    #[inline]
+    fn add(&mut self, testcase: Testcase<I>) -> Result<CorpusId, Error> {
+        let idx = self.inner.add(testcase)?;
+        self.save_testcase(&mut self.get(idx)?.borrow_mut())?;
+        Ok(idx)
+    }
+
+    #[inline]
+    fn replace(&mut self, idx: CorpusId, testcase: Testcase<I>) -> Result<Testcase<I>, Error> {
+        let entry = self.inner.replace(idx, testcase)?;
+        self.save_testcase(&mut self.get(idx)?.borrow_mut())?;
+        Ok(entry)
+    }
+
+    /// Removes an entry from the corpus, keeping its file, as libFuzzer never deletes corpus files
+    #[inline]
+    fn remove(&mut self, idx: CorpusId) -> Result<Testcase<I>, Error> {
+        self.inner.remove(idx)
+    }
+
+    #[inline]
+    fn get(&self, idx: CorpusId) -> Result<&RefCell<Testcase<I>>, Error> {
+        self.inner.get(idx)
+    }
+
+    #[inline]
+    fn current(&self) -> &Option<CorpusId> {
+        self.inner.current()
+    }
+
+    #[inline]
+    fn current_mut(&mut self) -> &mut Option<CorpusId> {
+        self.inner.current_mut()
+    }
+
+    #[inline]
+    fn next(&self, idx: CorpusId) -> Option<CorpusId> {
+        self.inner.next(idx)
+    }
+
+    #[inline]
+    fn prev(&self, idx: CorpusId) -> Option<CorpusId> {
+        self.inner.prev(idx)
+    }
+
+    #[inline]
+    fn first(&self) -> Option<CorpusId> {
+        self.inner.first()
+    }
+
+    #[inline]
+    fn last(&self) -> Option<CorpusId> {
+        self.inner.last()
+    }
+
+    #[inline]
+    fn nth(&self, nth: usize) -> CorpusId {
+        self.inner.nth(nth)
+    }
+}
+
+impl<I> LibfuzzerCorpus<I>
+where
+    I: Input + HasTargetBytes,
+{
+    /// Creates a new [`LibfuzzerCorpus`] for the corpus entries, writing them to `dir`, if any
+    pub fn new(dir: Option<&Path>) -> Result<Self, Error> {
+        let prefix = match dir {
+            Some(dir) => {
+                fs::create_dir_all(dir)?;
+                Some(format!("{}/", dir.display()))
+            }
+            None => None,
+        };
+        Ok(Self {
+            inner: InMemoryCorpus::new(),
+            prefix,
+            exact_path: None,
+            artifacts: false,
+            write_to_disk: true,
+        })
+    }
+
+    /// Creates a new [`LibfuzzerCorpus`] for the artifacts, writing them as `<artifact_prefix><kind>-<sha1>`,
+    /// or to `exact_path`, if set
+    #[must_use]
+    pub fn artifacts(artifact_prefix: &str, exact_path: Option<PathBuf>) -> Self {
+        Self {
+            inner: InMemoryCorpus::new(),
+            prefix: Some(artifact_prefix.to_string()),
+            exact_path,
+            artifacts: true,
+            write_to_disk: true,
+        }
+    }
+
+    /// Enable or disable writing the new entries to disk, e.g. while loading the initial corpus
+    pub fn set_write_to_disk(&mut self, write_to_disk: bool) {
+        self.write_to_disk = write_to_disk;
+    }
+
+    fn save_testcase(&self, testcase: &mut Testcase<I>) -> Result<(), Error> {
+        if !self.write_to_disk {
+            return Ok(());
+        }
+        let Some(prefix) = &self.prefix else {
+            return Ok(());
+        };
+        let bytes = testcase.load_input()?.target_bytes().as_slice().to_vec();
+        let path = if let Some(exact_path) = &self.exact_path {
+            exact_path.clone()
+        } else if self.artifacts {
+            let kind = testcase
+                .metadata()
+                .get::<ArtifactMetadata>()
+                .map_or("crash", ArtifactMetadata::kind);
+            PathBuf::from(format!("{prefix}{kind}-{}", sha1_hex(&bytes)))
+        } else {
+            PathBuf::from(format!("{prefix}{}", sha1_hex(&bytes)))
+        };
+
+        // The same input may be found by several workers
+        if self.artifacts || !path.exists() {
+            fs::write(&path, &bytes)?;
+        }
+        if self.artifacts {
+            eprintln!(
+                "artifact_prefix='{prefix}'; Test unit written to {}",
+                path.display()
+            );
+        }
+        testcase.set_filename(path.to_string_lossy().into_owned());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs};
+
+    use libafl::{
+        corpus::{Corpus, Testcase},
+        inputs::BytesInput,
+        state::HasMetadata,
+    };
+
+    use super::{sha1_hex, LibfuzzerCorpus};
+    use crate::feedbacks::ArtifactMetadata;
+
+    #[test]
+    fn test_libfuzzer_corpus_names() {
+        let dir = temp_dir().join(format!("libafl_libfuzzer_corpus_{}", std::process::id()));
+        let sha1 = sha1_hex(b"abc");
+        assert_eq!(sha1, "a9993e364706816aba3e25717850c26c9cd0d89d");
+
+        // Corpus entries are named after their hash, in the corpus directory
+        let mut corpus = LibfuzzerCorpus::new(Some(&dir.join("corpus"))).unwrap();
+        let idx = corpus
+            .add(Testcase::new(BytesInput::new(b"abc".to_vec())))
+            .unwrap();
+        let path = dir.join("corpus").join(&sha1);
+        assert_eq!(fs::read(&path).unwrap(), b"abc");
+        assert_eq!(
+            corpus.get(idx).unwrap().borrow().filename().as_deref(),
+            Some(path.to_string_lossy().as_ref())
+        );
+
+        // Nothing is written while loading the initial inputs
+        corpus.set_write_to_disk(false);
+        corpus
+            .add(Testcase::new(BytesInput::new(b"def".to_vec())))
+            .unwrap();
+        assert!(!dir.join("corpus").join(sha1_hex(b"def")).exists());
+
+        // Artifacts are named after their kind and hash, after the prefix
+        let mut artifacts =
+            LibfuzzerCorpus::artifacts(&format!("{}/artifact-", dir.display()), None);
+        let mut testcase = Testcase::new(BytesInput::new(b"abc".to_vec()));
+        testcase.add_metadata(ArtifactMetadata::new("timeout"));
+        artifacts.add(testcase).unwrap();
+        artifacts
+            .add(Testcase::new(BytesInput::new(b"abc".to_vec())))
+            .unwrap();
+        assert!(dir.join(format!("artifact-timeout-{sha1}")).exists());
+        assert!(dir.join(format!("artifact-crash-{sha1}")).exists());
+
+        // The exact artifact path overrides the names
+        let exact_path = dir.join("exact");
+        let mut artifacts = LibfuzzerCorpus::artifacts("ignored-", Some(exact_path.clone()));
+        artifacts
+            .add(Testcase::new(BytesInput::new(b"abc".to_vec())))
+            .unwrap();
+        assert_eq!(fs::read(exact_path).unwrap(), b"abc");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
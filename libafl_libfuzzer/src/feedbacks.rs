@@ -0,0 +1,110 @@
+//! The objective of the libFuzzer runtime, classifying the artifacts as crashes, timeouts and out-of-memory runs
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use libafl::{
+    bolts::tuples::Named,
+    corpus::Testcase,
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::Feedback,
+    inputs::UsesInput,
+    observers::ObserversTuple,
+    state::{HasClientPerfMonitor, HasMetadata},
+    Error,
+};
+use serde::{Deserialize, Serialize};
+
+/// Set when the run is aborted for exceeding the rss limit, so that it is reported as out-of-memory
+pub static OOM_DETECTED: AtomicBool = AtomicBool::new(false);
+
+/// The kind of an artifact, that names its file: `crash`, `timeout` or `oom`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactMetadata {
+    kind: String,
+}
+
+libafl::impl_serdeany!(ArtifactMetadata);
+
+impl ArtifactMetadata {
+    /// Creates a new [`ArtifactMetadata`]
+    #[must_use]
+    pub fn new(kind: &str) -> Self {
+        Self {
+            kind: kind.to_string(),
+        }
+    }
+
+    /// The kind of the artifact
+    #[must_use]
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+}
+
+/// A feedback reporting crashes, timeouts and out-of-memory runs as objectives,
+/// and tagging them with an [`ArtifactMetadata`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArtifactFeedback {
+    kind: Option<String>,
+}
+
+impl<S> Feedback<S> for ArtifactFeedback
+where
+    S: UsesInput + HasClientPerfMonitor,
+{
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        _observers: &OT,
+        exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let kind = match exit_kind {
+            ExitKind::Crash if OOM_DETECTED.load(Ordering::SeqCst) => "oom",
+            ExitKind::Crash => "crash",
+            ExitKind::Oom => "oom",
+            ExitKind::Timeout => "timeout",
+            _ => return Ok(false),
+        };
+        self.kind = Some(kind.to_string());
+        Ok(true)
+    }
+
+    fn append_metadata(
+        &mut self,
+        _state: &mut S,
+        testcase: &mut Testcase<S::Input>,
+    ) -> Result<(), Error> {
+        if let Some(kind) = self.kind.take() {
+            testcase.add_metadata(ArtifactMetadata { kind });
+        }
+        Ok(())
+    }
+
+    fn discard_metadata(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.kind = None;
+        Ok(())
+    }
+}
+
+impl Named for ArtifactFeedback {
+    #[inline]
+    fn name(&self) -> &str {
+        "ArtifactFeedback"
+    }
+}
+
+impl ArtifactFeedback {
+    /// Creates a new [`ArtifactFeedback`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
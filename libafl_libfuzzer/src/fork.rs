@@ -0,0 +1,144 @@
+//! Running single inputs in a forked child, for the modes that must survive crashing inputs
+
+use core::{slice, time::Duration};
+
+#[cfg(target_os = "linux")]
+use libafl::executors::inprocess::TimeoutInProcessForkExecutor;
+#[cfg(not(target_os = "linux"))]
+use libafl::executors::InProcessForkExecutor;
+use libafl::{
+    bolts::{
+        rands::StdRand,
+        shmem::{ShMem, ShMemProvider, StdShMemProvider},
+        AsMutSlice, AsSlice,
+    },
+    corpus::InMemoryCorpus,
+    events::NopEventManager,
+    executors::{Executor, ExitKind},
+    feedbacks::ConstFeedback,
+    inputs::{BytesInput, HasTargetBytes},
+    schedulers::QueueScheduler,
+    state::StdState,
+    Error, StdFuzzer,
+};
+use libafl_targets::{edges_map_mut_ptr, edges_max_num, libfuzzer_test_one_input};
+
+type ForkedState =
+    StdState<BytesInput, InMemoryCorpus<BytesInput>, StdRand, InMemoryCorpus<BytesInput>>;
+type ForkedFuzzer = StdFuzzer<QueueScheduler<ForkedState>, ConstFeedback, ConstFeedback, ()>;
+
+/// The outcome of an input run in a forked child
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForkedRun {
+    /// The run finished, with this edges map
+    Ok(Vec<u8>),
+    /// The child crashed, or exited with an error
+    Crash,
+    /// The child timed out
+    Timeout,
+}
+
+/// Runs inputs in forked children, with the fork executors of `LibAFL`.
+/// The child copies its edges map to a shared map, for this process to read it after the run.
+/// Timeouts are only detected on Linux.
+#[derive(Debug)]
+pub struct ForkedRunner {
+    shmem_provider: StdShMemProvider,
+    map: <StdShMemProvider as ShMemProvider>::ShMem,
+    timeout: Duration,
+    fuzzer: ForkedFuzzer,
+    state: ForkedState,
+    mgr: NopEventManager<ForkedState>,
+}
+
+impl ForkedRunner {
+    /// Creates a new [`ForkedRunner`], running each input for at most `timeout`, zero for no timeout
+    pub fn new(timeout: Duration) -> Result<Self, Error> {
+        let mut shmem_provider = StdShMemProvider::new()?;
+        let map = shmem_provider.new_shmem(edges_max_num())?;
+
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )?;
+        Ok(Self {
+            shmem_provider,
+            map,
+            timeout,
+            fuzzer: StdFuzzer::new(QueueScheduler::new(), feedback, objective),
+            state,
+            mgr: NopEventManager::new(),
+        })
+    }
+
+    /// Run `input` in a forked child, returning its edges map unless it crashed or timed out
+    pub fn run(&mut self, input: &[u8]) -> Result<ForkedRun, Error> {
+        self.map.as_mut_slice().fill(0);
+        let (map_ptr, map_len) = (self.map.as_mut_slice().as_mut_ptr(), self.map.len());
+        let mut harness = |input: &BytesInput| {
+            let edges = unsafe { slice::from_raw_parts_mut(edges_map_mut_ptr(), map_len) };
+            edges.fill(0);
+            libfuzzer_test_one_input(input.target_bytes().as_slice());
+            // Runs in the child, the only one writing to the shared map
+            unsafe { slice::from_raw_parts_mut(map_ptr, map_len) }.copy_from_slice(edges);
+            ExitKind::Ok
+        };
+
+        #[cfg(target_os = "linux")]
+        let mut executor = TimeoutInProcessForkExecutor::new(
+            &mut harness,
+            (),
+            &mut self.fuzzer,
+            &mut self.state,
+            &mut self.mgr,
+            self.timeout,
+            self.shmem_provider.clone(),
+        )?;
+        #[cfg(not(target_os = "linux"))]
+        let mut executor = InProcessForkExecutor::new(
+            &mut harness,
+            (),
+            &mut self.fuzzer,
+            &mut self.state,
+            &mut self.mgr,
+            self.shmem_provider.clone(),
+        )?;
+
+        let exit_kind = executor.run_target(
+            &mut self.fuzzer,
+            &mut self.state,
+            &mut self.mgr,
+            &BytesInput::new(input.to_vec()),
+        )?;
+        Ok(match exit_kind {
+            ExitKind::Ok => ForkedRun::Ok(self.map.as_slice().to_vec()),
+            ExitKind::Timeout => ForkedRun::Timeout,
+            _ => ForkedRun::Crash,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::{ForkedRun, ForkedRunner};
+
+    #[test]
+    fn test_forked_runner() {
+        let mut runner = ForkedRunner::new(Duration::from_secs(5)).unwrap();
+        let ForkedRun::Ok(map) = runner.run(b"ab").unwrap() else {
+            panic!("The input did not run");
+        };
+        assert_eq!(map[usize::from(b'a')], 1);
+        assert_eq!(map[usize::from(b'b')], 1);
+        assert_eq!(map.iter().filter(|count| **count > 0).count(), 2);
+
+        assert_eq!(runner.run(b"crash").unwrap(), ForkedRun::Crash);
+    }
+}
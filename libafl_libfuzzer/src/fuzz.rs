@@ -0,0 +1,278 @@
+//! The fuzzing mode of the libFuzzer runtime, in this process or in a [`Launcher`] for `-jobs`
+
+use core::{ptr, sync::atomic::Ordering, time::Duration};
+use std::{path::PathBuf, process, thread};
+
+use libafl::{
+    bolts::{
+        core_affinity::Cores,
+        current_nanos, current_time,
+        launcher::Launcher,
+        rands::StdRand,
+        shmem::{ShMemProvider, StdShMemProvider},
+        tuples::{tuple_list, Merge},
+        AsSlice,
+    },
+    corpus::Corpus,
+    events::{EventConfig, EventManager, SimpleEventManager},
+    executors::{inprocess::InProcessExecutor, ExitKind, TimeoutExecutor},
+    feedback_or,
+    feedbacks::{EagerOrFeedback, MaxMapFeedback, TimeFeedback},
+    fuzzer::{Evaluator, Fuzzer, StdFuzzer},
+    inputs::{BytesInput, HasTargetBytes},
+    monitors::{MultiMonitor, SimpleMonitor},
+    mutators::{
+        scheduled::{havoc_mutations, tokens_mutations, StdScheduledMutator},
+        token_mutations::Tokens,
+    },
+    observers::{HitcountsMapObserver, StdMapObserver, TimeObserver},
+    schedulers::{IndexesLenTimeMinimizerScheduler, QueueScheduler},
+    stages::StdMutationalStage,
+    state::{HasCorpus, HasExecutions, HasMaxSize, HasMetadata, HasStartTime, StdState, UsesState},
+    Error,
+};
+use libafl_targets::{
+    libfuzzer_has_custom_mutator, libfuzzer_test_one_input, std_edges_map_observer,
+    LLVMCustomMutator,
+};
+
+use crate::{
+    corpus::LibfuzzerCorpus,
+    feedbacks::{ArtifactFeedback, OOM_DETECTED},
+    len_control::LenControlStage,
+    options::LibfuzzerOptions,
+};
+
+/// The default maximum length, if the corpus has no larger input
+pub const DEFAULT_MAX_LEN: usize = 4096;
+
+/// The interval between two progress reports
+const STATS_INTERVAL: Duration = Duration::from_secs(15);
+
+type LibfuzzerState =
+    StdState<BytesInput, LibfuzzerCorpus<BytesInput>, StdRand, LibfuzzerCorpus<BytesInput>>;
+type EdgesObserver = HitcountsMapObserver<StdMapObserver<'static, u8, false>>;
+type LibfuzzerObservers = (EdgesObserver, (TimeObserver, ()));
+type LibfuzzerFeedback = EagerOrFeedback<
+    MaxMapFeedback<EdgesObserver, LibfuzzerState, u8>,
+    TimeFeedback,
+    LibfuzzerState,
+>;
+type LibfuzzerScheduler = IndexesLenTimeMinimizerScheduler<QueueScheduler<LibfuzzerState>>;
+type LibfuzzerFuzzer =
+    StdFuzzer<LibfuzzerScheduler, LibfuzzerFeedback, ArtifactFeedback, LibfuzzerObservers>;
+type Harness = fn(&BytesInput) -> ExitKind;
+type LibfuzzerExecutor<'a> =
+    TimeoutExecutor<InProcessExecutor<'a, Harness, LibfuzzerObservers, LibfuzzerState>>;
+
+/// The harness, calling `LLVMFuzzerTestOneInput`
+fn harness(input: &BytesInput) -> ExitKind {
+    libfuzzer_test_one_input(input.target_bytes().as_slice());
+    ExitKind::Ok
+}
+
+/// The peak rss of this process, in megabytes
+fn peak_rss_mb() -> usize {
+    let mut usage: libc::rusage = unsafe { core::mem::zeroed() };
+    unsafe { libc::getrusage(libc::RUSAGE_SELF, ptr::addr_of_mut!(usage)) };
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    let max_rss = usage.ru_maxrss as usize;
+    if cfg!(target_vendor = "apple") {
+        max_rss >> 20
+    } else {
+        max_rss >> 10
+    }
+}
+
+/// The thread running the target, signalled by the rss watcher
+struct FuzzThread(libc::pthread_t);
+
+// The id is only passed to `pthread_kill`, the fuzzing thread runs until the process exits
+unsafe impl Send for FuzzThread {}
+
+impl FuzzThread {
+    /// Abort the current run of the target, in the crash handler of the executor on this thread
+    fn abort(&self) {
+        unsafe { libc::pthread_kill(self.0, libc::SIGABRT) };
+    }
+}
+
+/// Watch the rss of this process, aborting the current run as out-of-memory when it exceeds the limit.
+///
+/// Must be called on the thread running the target.
+fn spawn_rss_watcher(rss_limit_mb: usize) {
+    if rss_limit_mb == 0 {
+        return;
+    }
+    let fuzz_thread = FuzzThread(unsafe { libc::pthread_self() });
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(1));
+        let rss = peak_rss_mb();
+        if rss > rss_limit_mb {
+            eprintln!(
+                "==={}== ERROR: libFuzzer: out-of-memory (used: {rss}Mb; exceeds: {rss_limit_mb}Mb)",
+                process::id()
+            );
+            // The crash handler of the executor reports the current input, as `oom`
+            OOM_DETECTED.store(true, Ordering::SeqCst);
+            fuzz_thread.abort();
+        }
+    });
+}
+
+/// Fuzz in a client, until `-runs` executions are reached
+fn fuzz_client<EM>(
+    options: &LibfuzzerOptions,
+    state: Option<LibfuzzerState>,
+    mut mgr: EM,
+    seed: u64,
+) -> Result<(), Error>
+where
+    EM: UsesState<State = LibfuzzerState>
+        + for<'a> EventManager<LibfuzzerExecutor<'a>, LibfuzzerFuzzer>,
+{
+    let dirs: Vec<PathBuf> = options.dirs().cloned().collect();
+
+    let edges_observer = HitcountsMapObserver::new(unsafe { std_edges_map_observer("edges") });
+    let time_observer = TimeObserver::new("time");
+
+    let mut feedback = feedback_or!(
+        MaxMapFeedback::new_tracking(&edges_observer, true, false),
+        TimeFeedback::with_observer(&time_observer)
+    );
+    let mut objective = ArtifactFeedback::new();
+
+    let mut state = match state {
+        Some(state) => state,
+        None => StdState::new(
+            StdRand::with_seed(seed),
+            LibfuzzerCorpus::new(dirs.first().map(PathBuf::as_path))?,
+            LibfuzzerCorpus::artifacts(
+                &options.artifact_prefix,
+                options.exact_artifact_path.clone(),
+            ),
+            &mut feedback,
+            &mut objective,
+        )?,
+    };
+
+    if !options.dicts.is_empty() && state.metadata().get::<Tokens>().is_none() {
+        let mut tokens = Tokens::new();
+        for dict in &options.dicts {
+            tokens.add_from_file(dict)?;
+        }
+        state.add_metadata(tokens);
+    }
+
+    let scheduler = IndexesLenTimeMinimizerScheduler::new(QueueScheduler::new());
+    let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
+
+    let mut harness: Harness = harness;
+    let mut executor = TimeoutExecutor::new(
+        InProcessExecutor::new(
+            &mut harness,
+            tuple_list!(edges_observer, time_observer),
+            &mut fuzzer,
+            &mut state,
+            &mut mgr,
+        )?,
+        options.timeout,
+    );
+    spawn_rss_watcher(options.rss_limit_mb);
+
+    if state.must_load_initial_inputs() {
+        // The initial inputs are already on disk
+        state.corpus_mut().set_write_to_disk(false);
+        state.load_initial_inputs(&mut fuzzer, &mut executor, &mut mgr, &dirs)?;
+        state.corpus_mut().set_write_to_disk(true);
+        if state.corpus().count() == 0 {
+            // As libFuzzer, start from the empty input
+            fuzzer.add_input(&mut state, &mut executor, &mut mgr, BytesInput::new(vec![]))?;
+        }
+        eprintln!("INFO: loaded {} inputs from disk", state.corpus().count());
+
+        let max_len = if let Some(max_len) = options.max_len {
+            max_len
+        } else {
+            let mut largest = DEFAULT_MAX_LEN;
+            let mut idx = state.corpus().first();
+            while let Some(cur) = idx {
+                largest = largest.max(state.corpus().get(cur)?.borrow_mut().cached_len()?);
+                idx = state.corpus().next(cur);
+            }
+            largest
+        };
+        state.set_max_size(max_len);
+        eprintln!("INFO: -max_len is {max_len}");
+    }
+
+    let len_control = usize::try_from(options.len_control).unwrap_or(usize::MAX);
+    let len_control = LenControlStage::new(len_control, state.max_size());
+    let havoc = StdScheduledMutator::new(havoc_mutations().merge(tokens_mutations()));
+
+    let mut last = current_time();
+    let runs = options.runs.unwrap_or(u64::MAX);
+    if libfuzzer_has_custom_mutator() {
+        // As libFuzzer, only use the custom mutator, that may call the default mutations with `LLVMFuzzerMutate`
+        let mutator = LLVMCustomMutator::new(havoc)?;
+        let mut stages = tuple_list!(len_control, StdMutationalStage::new(mutator));
+        while (*state.executions() as u64) < runs {
+            fuzzer.fuzz_one(&mut stages, &mut executor, &mut state, &mut mgr)?;
+            last = mgr.maybe_report_progress(&mut state, last, STATS_INTERVAL)?;
+        }
+    } else {
+        let mut stages = tuple_list!(len_control, StdMutationalStage::new(havoc));
+        while (*state.executions() as u64) < runs {
+            fuzzer.fuzz_one(&mut stages, &mut executor, &mut state, &mut mgr)?;
+            last = mgr.maybe_report_progress(&mut state, last, STATS_INTERVAL)?;
+        }
+    }
+
+    eprintln!(
+        "Done {} runs in {} second(s)",
+        state.executions(),
+        current_time().saturating_sub(*state.start_time()).as_secs()
+    );
+    mgr.on_restart(&mut state)?;
+    Ok(())
+}
+
+/// Fuzz, in this process, or in `-workers` processes connected by a [`Launcher`] if `-jobs` is set.
+///
+/// As libFuzzer, fuzzing in this process stops at the first crash.
+/// The workers keep fuzzing after crashes, as with libFuzzer's `-fork` and `-ignore_crashes`, until `-runs` is reached.
+pub fn fuzz(options: &LibfuzzerOptions) -> Result<(), Error> {
+    let seed = options.seed.unwrap_or_else(current_nanos);
+    eprintln!("INFO: Seed: {seed}");
+
+    if options.jobs == 0 {
+        let monitor = SimpleMonitor::new(|s| eprintln!("{s}"));
+        let mgr = SimpleEventManager::new(monitor);
+        return fuzz_client(options, None, mgr, seed);
+    }
+
+    let workers = options.worker_count();
+    eprintln!("INFO: running {} jobs in {workers} workers", options.jobs);
+    let cores = Cores::from((0..workers).collect::<Vec<_>>());
+    let shmem_provider = StdShMemProvider::new()?;
+    let monitor = MultiMonitor::new(|s| eprintln!("{s}"));
+
+    let mut run_client = |state: Option<_>, mgr, core_id: usize| {
+        fuzz_client(options, state, mgr, seed.wrapping_add(core_id as u64))?;
+        // All runs are done, do not respawn
+        process::exit(0);
+    };
+
+    match Launcher::builder()
+        .shmem_provider(shmem_provider)
+        .configuration(EventConfig::from_build_id())
+        .monitor(monitor)
+        .run_client(&mut run_client)
+        .cores(&cores)
+        .build()
+        .launch()
+    {
+        Ok(()) | Err(Error::ShuttingDown) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
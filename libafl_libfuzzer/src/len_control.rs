@@ -0,0 +1,184 @@
+//! The [`LenControlStage`] grows the maximum input length over time, as libFuzzer's `-len_control` does
+
+use core::marker::PhantomData;
+
+use libafl::{
+    bolts::HasLen,
+    corpus::{Corpus, CorpusId},
+    inputs::UsesInput,
+    stages::Stage,
+    state::{HasCorpus, HasExecutions, HasMaxSize, UsesState},
+    Error,
+};
+
+/// `floor(log2(x))`, as libFuzzer computes it
+fn log2(x: usize) -> usize {
+    if x == 0 {
+        0
+    } else {
+        (usize::BITS - 1 - x.leading_zeros()) as usize
+    }
+}
+
+/// A stage starting with small inputs, and increasing the maximum length of the mutated inputs
+/// each time no new corpus entry was found for `len_control * log2(length)` executions, up to `max_len`.
+#[derive(Debug, Clone)]
+pub struct LenControlStage<E, EM, Z> {
+    len_control: usize,
+    max_len: usize,
+    /// The current maximum length, 0 until the first run
+    cur_len: usize,
+    corpus_count: usize,
+    last_corpus_update: usize,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> UsesState for LenControlStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for LenControlStage<E, EM, Z>
+where
+    E: UsesState,
+    EM: UsesState<State = E::State>,
+    Z: UsesState<State = E::State>,
+    E::State: HasCorpus + HasExecutions + HasMaxSize,
+    <E::State as UsesInput>::Input: HasLen,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut E::State,
+        _manager: &mut EM,
+        _corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        if self.len_control == 0 {
+            return Ok(());
+        }
+        let executions = *state.executions();
+        if self.cur_len == 0 {
+            // Start from the largest corpus entry
+            let mut largest = 0;
+            let mut idx = state.corpus().first();
+            while let Some(cur) = idx {
+                largest = largest.max(state.corpus().get(cur)?.borrow_mut().cached_len()?);
+                idx = state.corpus().next(cur);
+            }
+            self.cur_len = largest.max(4).min(self.max_len);
+            self.last_corpus_update = executions;
+            state.set_max_size(self.cur_len);
+        }
+
+        let count = state.corpus().count();
+        if count != self.corpus_count {
+            self.corpus_count = count;
+            self.last_corpus_update = executions;
+        }
+        if self.cur_len < self.max_len
+            && executions - self.last_corpus_update > self.len_control * log2(self.cur_len)
+        {
+            self.cur_len = (self.cur_len + log2(self.cur_len)).min(self.max_len);
+            self.last_corpus_update = executions;
+            state.set_max_size(self.cur_len);
+        }
+        Ok(())
+    }
+}
+
+impl<E, EM, Z> LenControlStage<E, EM, Z> {
+    /// Creates a new [`LenControlStage`], growing the length up to `max_len`.
+    /// A `len_control` of 0 disables it.
+    #[must_use]
+    pub fn new(len_control: usize, max_len: usize) -> Self {
+        Self {
+            len_control,
+            max_len,
+            cur_len: 0,
+            corpus_count: 0,
+            last_corpus_update: 0,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::{
+        bolts::rands::StdRand,
+        corpus::{Corpus, CorpusId, InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        executors::{ExitKind, InProcessExecutor},
+        feedbacks::ConstFeedback,
+        inputs::BytesInput,
+        schedulers::QueueScheduler,
+        stages::Stage,
+        state::{HasCorpus, HasExecutions, HasMaxSize, StdState},
+        StdFuzzer,
+    };
+
+    use super::LenControlStage;
+
+    #[test]
+    fn test_len_control_stage() {
+        let mut corpus = InMemoryCorpus::new();
+        corpus
+            .add(Testcase::new(BytesInput::new(vec![0; 8])))
+            .unwrap();
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            corpus,
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut fuzzer: StdFuzzer<_, _, _, ()> =
+            StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut mgr = NopEventManager::new();
+        let mut harness = |_: &BytesInput| ExitKind::Ok;
+        let mut executor =
+            InProcessExecutor::new(&mut harness, (), &mut fuzzer, &mut state, &mut mgr).unwrap();
+
+        let mut len_control = LenControlStage::new(1, 12);
+        let mut perform = |state: &mut StdState<_, _, _, _>| {
+            len_control
+                .perform(
+                    &mut fuzzer,
+                    &mut executor,
+                    state,
+                    &mut mgr,
+                    CorpusId::from(0_usize),
+                )
+                .unwrap();
+            state.max_size()
+        };
+
+        // Starts from the largest corpus entry
+        assert_eq!(perform(&mut state), 8);
+        // Grows by log2(8) after len_control * log2(8) executions without a new corpus entry
+        *state.executions_mut() += 3;
+        assert_eq!(perform(&mut state), 8);
+        *state.executions_mut() += 1;
+        assert_eq!(perform(&mut state), 11);
+
+        // A new corpus entry resets the count
+        state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(vec![1; 4])))
+            .unwrap();
+        *state.executions_mut() += 4;
+        assert_eq!(perform(&mut state), 11);
+        // Up to the maximum length
+        *state.executions_mut() += 4;
+        assert_eq!(perform(&mut state), 12);
+        *state.executions_mut() += 100;
+        assert_eq!(perform(&mut state), 12);
+    }
+}
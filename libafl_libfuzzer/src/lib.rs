@@ -0,0 +1,154 @@
+//! A drop-in [`libFuzzer`](https://www.llvm.org/docs/LibFuzzer.html)-compatible runtime, built on `LibAFL`.
+//!
+//! Link an existing `LLVMFuzzerTestOneInput` harness, compiled with `-fsanitize-coverage=trace-pc-guard`,
+//! against the static library of this crate, instead of `-fsanitize=fuzzer`, and run it with the usual libFuzzer flags:
+//! `-runs`, `-max_len`, `-len_control`, `-dict`, `-jobs`/`-workers`, `-merge=1`, `-minimize_crash=1`, `-timeout`,
+//! `-rss_limit_mb`, `-artifact_prefix`, and corpus directories or files as positional arguments.
+//! Crashes, timeouts and out-of-memory inputs are written as `crash-<sha1>`, `timeout-<sha1>` and `oom-<sha1>`.
+//!
+//! The `main` of `libafl_targets` is weak, so link the library as a whole, for the linker to pick up [`libafl_main`]:
+//! `cc harness.o -Wl,--whole-archive liblibafl_libfuzzer.a -Wl,--no-whole-archive -lpthread -ldl -lm -lrt`.
+
+#![deny(rustdoc::broken_intra_doc_links)]
+#![deny(clippy::all)]
+#![deny(clippy::pedantic)]
+#![allow(
+    clippy::unreadable_literal,
+    clippy::type_repetition_in_bounds,
+    clippy::missing_errors_doc,
+    clippy::cast_possible_truncation,
+    clippy::used_underscore_binding,
+    clippy::ptr_as_ptr,
+    clippy::missing_panics_doc,
+    clippy::missing_docs_in_private_items,
+    clippy::module_name_repetitions
+)]
+#![cfg_attr(not(test), warn(
+    missing_debug_implementations,
+    missing_docs,
+    //trivial_casts,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    //unused_results
+))]
+#![cfg_attr(test, deny(
+    missing_debug_implementations,
+    missing_docs,
+    //trivial_casts,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_must_use,
+    //unused_results
+))]
+#![cfg_attr(
+    test,
+    deny(
+        bad_style,
+        dead_code,
+        improper_ctypes,
+        non_shorthand_field_patterns,
+        no_mangle_generic_items,
+        overflowing_literals,
+        path_statements,
+        patterns_in_fns_without_body,
+        private_in_public,
+        unconditional_recursion,
+        unused,
+        unused_allocation,
+        unused_comparisons,
+        unused_parens,
+        while_true
+    )
+)]
+
+pub mod corpus;
+pub use corpus::LibfuzzerCorpus;
+
+pub mod feedbacks;
+pub use feedbacks::{ArtifactFeedback, ArtifactMetadata};
+
+pub mod len_control;
+pub use len_control::LenControlStage;
+
+pub mod options;
+pub use options::{LibfuzzerMode, LibfuzzerOptions};
+
+pub mod run;
+
+#[cfg(unix)]
+pub mod fork;
+#[cfg(unix)]
+pub mod fuzz;
+#[cfg(unix)]
+pub mod merge;
+#[cfg(unix)]
+pub mod tmin;
+
+#[cfg(unix)]
+use std::{env, process};
+
+#[cfg(unix)]
+use libafl_targets::libfuzzer_initialize;
+
+/// Run the libFuzzer-compatible runtime with the given command line
+#[cfg(unix)]
+pub fn libfuzzer_main(args: &[String]) -> Result<(), libafl::Error> {
+    let options = LibfuzzerOptions::parse(args)?;
+    let mode = options.mode();
+    if mode == LibfuzzerMode::Help {
+        eprintln!("{}", options::USAGE);
+        return Ok(());
+    }
+
+    // Call LLVMFuzzerInitialize() if present.
+    if libfuzzer_initialize(args) == -1 {
+        eprintln!("Warning: LLVMFuzzerInitialize failed with -1");
+    }
+
+    match mode {
+        LibfuzzerMode::Fuzz => fuzz::fuzz(&options),
+        LibfuzzerMode::Merge => merge::merge(&options),
+        LibfuzzerMode::MinimizeCrash => tmin::minimize_crash(&options),
+        LibfuzzerMode::RunInputs => run::run_inputs(&options),
+        LibfuzzerMode::Help => unreachable!(),
+    }
+}
+
+/// The main fn, `no_mangle` as it is a C symbol, called by the `main` of `libafl_targets`
+#[cfg(unix)]
+#[no_mangle]
+pub extern "C" fn libafl_main() {
+    let args: Vec<String> = env::args().collect();
+    if let Err(err) = libfuzzer_main(&args) {
+        eprintln!("ERROR: {err}");
+        process::exit(1);
+    }
+}
+
+/// The harness the tests run, as `LLVMFuzzerTestOneInput` is weak in `libafl_targets`:
+/// it hits the edge of each byte of the input, and crashes on inputs containing `crash`.
+#[cfg(test)]
+mod tests {
+    use core::slice;
+
+    use libafl_targets::edges_map_mut_ptr;
+
+    #[no_mangle]
+    extern "C" fn LLVMFuzzerTestOneInput(data: *const u8, size: usize) -> i32 {
+        let input = unsafe { slice::from_raw_parts(data, size) };
+        for byte in input {
+            unsafe {
+                let edge = edges_map_mut_ptr().add(usize::from(*byte));
+                *edge = (*edge).wrapping_add(1);
+            }
+        }
+        if input.windows(5).any(|window| window == b"crash") {
+            std::process::abort();
+        }
+        0
+    }
+}
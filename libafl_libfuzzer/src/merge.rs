@@ -0,0 +1,186 @@
+//! `-merge=1`: add the inputs of the other corpus directories that increase the coverage to the first one
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use libafl::{corpus::hitcount_bucket, Error};
+
+use crate::{
+    corpus::sha1_hex,
+    fork::{ForkedRun, ForkedRunner},
+    options::LibfuzzerOptions,
+};
+
+/// The coverage features of an input: the edges map indexes and their hitcount buckets
+type Features = Vec<(usize, u8)>;
+
+/// List the files in `dir`, recursively, skipping hidden files
+pub fn list_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .file_name()
+            .map_or(true, |name| name.to_string_lossy().starts_with('.'))
+        {
+            continue;
+        }
+        if path.is_dir() {
+            list_files(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The coverage features of `input`, or `None` if it crashed or timed out
+fn run_features(
+    runner: &mut ForkedRunner,
+    options: &LibfuzzerOptions,
+    path: &Path,
+) -> Result<Option<(Vec<u8>, Features)>, Error> {
+    let mut input = fs::read(path)?;
+    if let Some(max_len) = options.max_len {
+        input.truncate(max_len);
+    }
+    match runner.run(&input)? {
+        ForkedRun::Ok(map) => {
+            let features = map
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(idx, count)| (idx, hitcount_bucket(u64::from(*count))))
+                .collect();
+            Ok(Some((input, features)))
+        }
+        ForkedRun::Crash => {
+            eprintln!("MERGE-OUTER: skipping {}, it crashes", path.display());
+            Ok(None)
+        }
+        ForkedRun::Timeout => {
+            eprintln!("MERGE-OUTER: skipping {}, it times out", path.display());
+            Ok(None)
+        }
+    }
+}
+
+/// Merge the other corpus directories into the first one, as libFuzzer's `-merge=1` does:
+/// the inputs of the first directory are kept, and the other ones, from the smallest,
+/// are copied to it if they add coverage features.
+/// Each input runs in a forked child, so that crashing inputs are skipped.
+pub fn merge(options: &LibfuzzerOptions) -> Result<(), Error> {
+    let dirs: Vec<&PathBuf> = options.dirs().collect();
+    let Some((output, others)) = dirs.split_first() else {
+        return Err(Error::illegal_argument(
+            "-merge=1 needs at least two corpus directories",
+        ));
+    };
+    if others.is_empty() {
+        return Err(Error::illegal_argument(
+            "-merge=1 needs at least two corpus directories",
+        ));
+    }
+
+    let mut initial = vec![];
+    list_files(output, &mut initial)?;
+    let mut candidates = vec![];
+    for dir in others {
+        list_files(dir, &mut candidates)?;
+    }
+    candidates.sort_by_cached_key(|path| (fs::metadata(path).map_or(0, |m| m.len()), path.clone()));
+    eprintln!(
+        "MERGE-OUTER: {} files, {} in the initial corpus",
+        initial.len() + candidates.len(),
+        initial.len()
+    );
+
+    let mut runner = ForkedRunner::new(options.timeout)?;
+    let mut features = HashSet::new();
+    let mut edges = HashSet::new();
+    for path in &initial {
+        if let Some((_, found)) = run_features(&mut runner, options, path)? {
+            for (idx, bucket) in found {
+                edges.insert(idx);
+                features.insert((idx, bucket));
+            }
+        }
+    }
+
+    let (initial_features, initial_edges) = (features.len(), edges.len());
+    let mut added = 0;
+    for path in &candidates {
+        let Some((input, found)) = run_features(&mut runner, options, path)? else {
+            continue;
+        };
+        let mut new = false;
+        for (idx, bucket) in found {
+            edges.insert(idx);
+            new |= features.insert((idx, bucket));
+        }
+        if new {
+            let dest = output.join(sha1_hex(&input));
+            if !dest.exists() {
+                fs::write(dest, &input)?;
+            }
+            added += 1;
+        }
+    }
+    eprintln!(
+        "MERGE-OUTER: {added} new files with {} new features added; {} new coverage edges",
+        features.len() - initial_features,
+        edges.len() - initial_edges
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs};
+
+    use super::{list_files, merge};
+    use crate::{corpus::sha1_hex, options::LibfuzzerOptions};
+
+    #[test]
+    fn test_merge() {
+        let dir = temp_dir().join(format!("libafl_libfuzzer_merge_{}", std::process::id()));
+        let (output, other) = (dir.join("output"), dir.join("other"));
+        fs::create_dir_all(&output).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        fs::write(output.join("initial"), b"ab").unwrap();
+        // The test harness hits the edge of each byte
+        for (name, input) in [
+            ("same", &b"ba"[..]),
+            ("new_edge", b"abc"),
+            ("new_hitcount", b"aab"),
+            ("crash", b"crash"),
+            (".hidden", b"xyz"),
+        ] {
+            fs::write(other.join(name), input).unwrap();
+        }
+
+        let options = LibfuzzerOptions::parse([
+            "./fuzzer",
+            "-merge=1",
+            &output.to_string_lossy(),
+            &other.to_string_lossy(),
+        ])
+        .unwrap();
+        merge(&options).unwrap();
+
+        let mut merged = vec![];
+        list_files(&output, &mut merged).unwrap();
+        merged.sort();
+        let mut expected = vec![
+            output.join("initial"),
+            output.join(sha1_hex(b"abc")),
+            output.join(sha1_hex(b"aab")),
+        ];
+        expected.sort();
+        assert_eq!(merged, expected);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
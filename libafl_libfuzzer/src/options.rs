@@ -0,0 +1,268 @@
+//! Parsing of the libFuzzer command line, e.g. `./fuzzer -runs=1000 -max_len=128 -dict=foo.dict corpus/`
+
+use core::time::Duration;
+use std::path::PathBuf;
+
+use libafl::Error;
+
+/// The usage, printed for `-help=1`
+pub const USAGE: &str = "Usage: fuzzer [-flag1=val1 [-flag2=val2 ...] ] [dir1 [dir2 ...] ]
+       fuzzer [-flag1=val1 [-flag2=val2 ...] ] file1 [file2 ...]
+
+Flags:
+ runs                 -1  Number of individual test runs (-1 for infinite runs).
+ max_len               0  Maximum length of the test input. If 0, guessed from the corpus.
+ len_control         100  Try generating small inputs first, then try larger inputs over time.
+                          Specifies the rate at which the length limit is increased. 0 disables it.
+ dict                     Use the dictionary file, in the AFL format.
+ jobs                  0  Number of jobs to run. If jobs >= 1, the jobs are spread over the workers.
+ workers               0  Number of simultaneous worker processes. If 0, min(jobs, number_of_cpu_cores/2).
+ merge                 0  If 1, the 2nd, 3rd, etc corpora will be merged into the 1st corpus.
+                          Only interesting units will be taken.
+ minimize_crash        0  If 1, minimizes the provided crash input.
+ timeout            1200  Timeout in seconds. If an input takes longer, it is reported as a timeout.
+                          0 disables it.
+ rss_limit_mb       2048  If non-zero, the fuzzer exits upon reaching this limit of RSS memory usage.
+ artifact_prefix          Write fuzzing artifacts (crash, timeout, or slow inputs) as $(artifact_prefix)file
+ exact_artifact_path      Write the single artifact on failure (crash, timeout) as $(exact_artifact_path).
+ seed                  0  Random seed. If 0, the seed is generated.
+ help                  0  Print this help.
+
+Flags starting with '--' are ignored.";
+
+/// The default timeout of each run
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1200);
+/// The default rss limit, in megabytes
+pub const DEFAULT_RSS_LIMIT_MB: usize = 2048;
+/// The default rate at which the length limit is increased
+pub const DEFAULT_LEN_CONTROL: u64 = 100;
+
+/// What the runtime should do, depending on the flags and the positional arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibfuzzerMode {
+    /// Fuzz, with the positional directories as corpus
+    Fuzz,
+    /// Merge the interesting inputs of the other directories into the first one
+    Merge,
+    /// Minimize a crashing input
+    MinimizeCrash,
+    /// Run each positional file once
+    RunInputs,
+    /// Print the usage
+    Help,
+}
+
+/// The options of the libFuzzer command line
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct LibfuzzerOptions {
+    /// The number of runs, `None` to fuzz forever
+    pub runs: Option<u64>,
+    /// The maximum length of the inputs, `None` to guess it from the corpus
+    pub max_len: Option<usize>,
+    /// The rate at which the length limit is increased, 0 to start with the maximum length
+    pub len_control: u64,
+    /// The dictionaries
+    pub dicts: Vec<PathBuf>,
+    /// The number of jobs, 0 to fuzz in this process
+    pub jobs: usize,
+    /// The number of worker processes, 0 to derive it from the jobs
+    pub workers: usize,
+    /// Merge the corpora into the first one
+    pub merge: bool,
+    /// Minimize the crashing input
+    pub minimize_crash: bool,
+    /// The timeout of each run, zero for no timeout
+    pub timeout: Duration,
+    /// The rss limit, in megabytes, 0 for no limit
+    pub rss_limit_mb: usize,
+    /// The prefix of the artifacts
+    pub artifact_prefix: String,
+    /// The path of the single artifact, overrides the prefix
+    pub exact_artifact_path: Option<PathBuf>,
+    /// The random seed, `None` to generate it
+    pub seed: Option<u64>,
+    /// Print the usage
+    pub help: bool,
+    /// The positional arguments: corpus directories, or files
+    pub inputs: Vec<PathBuf>,
+}
+
+impl Default for LibfuzzerOptions {
+    fn default() -> Self {
+        Self {
+            runs: None,
+            max_len: None,
+            len_control: DEFAULT_LEN_CONTROL,
+            dicts: vec![],
+            jobs: 0,
+            workers: 0,
+            merge: false,
+            minimize_crash: false,
+            timeout: DEFAULT_TIMEOUT,
+            rss_limit_mb: DEFAULT_RSS_LIMIT_MB,
+            artifact_prefix: String::new(),
+            exact_artifact_path: None,
+            seed: None,
+            help: false,
+            inputs: vec![],
+        }
+    }
+}
+
+/// Parse a numeric flag value, like libFuzzer's `atol`
+fn parse_num(name: &str, value: &str) -> Result<i64, Error> {
+    value
+        .parse()
+        .map_err(|_| Error::illegal_argument(format!("Invalid value for -{name}: {value}")))
+}
+
+/// Parse a numeric flag value, where negative values mean 0
+#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+fn parse_unsigned(name: &str, value: &str) -> Result<usize, Error> {
+    Ok(parse_num(name, value)?.max(0) as usize)
+}
+
+impl LibfuzzerOptions {
+    /// Parse the command line, `args[0]` being the program name
+    pub fn parse<IT, A>(args: IT) -> Result<Self, Error>
+    where
+        IT: IntoIterator<Item = A>,
+        A: AsRef<str>,
+    {
+        let mut options = Self::default();
+        for arg in args.into_iter().skip(1) {
+            let arg = arg.as_ref();
+            if arg.starts_with("--") {
+                log::info!("Ignoring flag {arg}");
+                continue;
+            }
+            let Some(flag) = arg.strip_prefix('-') else {
+                options.inputs.push(PathBuf::from(arg));
+                continue;
+            };
+            let Some((name, value)) = flag.split_once('=') else {
+                eprintln!("WARNING: did you mean '{arg}=1' (note the '=')? Ignoring it.");
+                continue;
+            };
+            options.set(name, value)?;
+        }
+        Ok(options)
+    }
+
+    /// Set the flag `name` to `value`
+    #[allow(clippy::cast_sign_loss)]
+    fn set(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        match name {
+            "runs" => {
+                let runs = parse_num(name, value)?;
+                self.runs = (runs >= 0).then_some(runs as u64);
+            }
+            "max_len" => {
+                let max_len = parse_unsigned(name, value)?;
+                self.max_len = (max_len > 0).then_some(max_len);
+            }
+            "len_control" => self.len_control = parse_unsigned(name, value)? as u64,
+            "dict" => self.dicts.push(PathBuf::from(value)),
+            "jobs" => self.jobs = parse_unsigned(name, value)?,
+            "workers" => self.workers = parse_unsigned(name, value)?,
+            "merge" => self.merge = parse_num(name, value)? != 0,
+            "minimize_crash" => self.minimize_crash = parse_num(name, value)? != 0,
+            "timeout" => self.timeout = Duration::from_secs(parse_unsigned(name, value)? as u64),
+            "rss_limit_mb" => self.rss_limit_mb = parse_unsigned(name, value)?,
+            "artifact_prefix" => self.artifact_prefix = value.to_string(),
+            "exact_artifact_path" => self.exact_artifact_path = Some(PathBuf::from(value)),
+            "seed" => {
+                let seed = parse_num(name, value)?;
+                self.seed = (seed != 0).then_some(seed as u64);
+            }
+            "help" => self.help = parse_num(name, value)? != 0,
+            _ => eprintln!("WARNING: unsupported flag -{name}, ignoring it."),
+        }
+        Ok(())
+    }
+
+    /// What to do with these options
+    #[must_use]
+    pub fn mode(&self) -> LibfuzzerMode {
+        if self.help {
+            LibfuzzerMode::Help
+        } else if self.merge {
+            LibfuzzerMode::Merge
+        } else if self.minimize_crash {
+            LibfuzzerMode::MinimizeCrash
+        } else if !self.inputs.is_empty() && self.inputs.iter().all(|input| !input.is_dir()) {
+            LibfuzzerMode::RunInputs
+        } else {
+            LibfuzzerMode::Fuzz
+        }
+    }
+
+    /// The corpus directories, for fuzzing and merging
+    pub fn dirs(&self) -> impl Iterator<Item = &PathBuf> {
+        self.inputs.iter().filter(|input| input.is_dir())
+    }
+
+    /// The number of worker processes to fuzz with, as libFuzzer does for `-jobs`
+    #[must_use]
+    pub fn worker_count(&self) -> usize {
+        if self.jobs == 0 {
+            1
+        } else if self.workers > 0 {
+            self.workers
+        } else {
+            let cores = std::thread::available_parallelism().map_or(1, usize::from);
+            self.jobs.min(cores / 2).max(1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::path::PathBuf;
+
+    use crate::options::{LibfuzzerMode, LibfuzzerOptions, DEFAULT_TIMEOUT};
+
+    #[test]
+    fn test_parse_libfuzzer_options() {
+        let options = LibfuzzerOptions::parse([
+            "./fuzzer",
+            "-runs=1000",
+            "-max_len=128",
+            "-dict=a.dict",
+            "-dict=b.dict",
+            "-timeout=5",
+            "-rss_limit_mb=0",
+            "-artifact_prefix=out/",
+            "-print_final_stats=1",
+            "--ignored",
+            "corpus",
+        ])
+        .unwrap();
+        assert_eq!(options.runs, Some(1000));
+        assert_eq!(options.max_len, Some(128));
+        assert_eq!(
+            options.dicts,
+            vec![PathBuf::from("a.dict"), PathBuf::from("b.dict")]
+        );
+        assert_eq!(options.timeout, Duration::from_secs(5));
+        assert_eq!(options.rss_limit_mb, 0);
+        assert_eq!(options.artifact_prefix, "out/");
+        assert_eq!(options.inputs, vec![PathBuf::from("corpus")]);
+        assert_eq!(options.mode(), LibfuzzerMode::RunInputs);
+
+        let options =
+            LibfuzzerOptions::parse(["./fuzzer", "-runs=-1", "-timeout=0", "-merge=1"]).unwrap();
+        assert_eq!(options.runs, None);
+        assert_eq!(options.timeout, Duration::ZERO);
+        assert_eq!(options.mode(), LibfuzzerMode::Merge);
+
+        let options = LibfuzzerOptions::parse(["./fuzzer", "-jobs=8", "-workers=3"]).unwrap();
+        assert_eq!(options.timeout, DEFAULT_TIMEOUT);
+        assert_eq!(options.worker_count(), 3);
+        assert_eq!(options.mode(), LibfuzzerMode::Fuzz);
+
+        assert!(LibfuzzerOptions::parse(["./fuzzer", "-runs=many"]).is_err());
+    }
+}
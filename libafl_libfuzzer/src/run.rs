@@ -0,0 +1,35 @@
+//! Running the positional files once each, as libFuzzer does when it is not given corpus directories
+
+use std::{fs, time::Instant};
+
+use libafl::Error;
+use libafl_targets::libfuzzer_test_one_input;
+
+use crate::options::LibfuzzerOptions;
+
+/// Run each input file once, e.g. to reproduce a crash
+pub fn run_inputs(options: &LibfuzzerOptions) -> Result<(), Error> {
+    eprintln!(
+        "INFO: running {} inputs 1 time(s) each.",
+        options.inputs.len()
+    );
+    for path in &options.inputs {
+        eprintln!("Running: {}", path.display());
+        let mut input = fs::read(path)?;
+        if let Some(max_len) = options.max_len {
+            input.truncate(max_len);
+        }
+        let start = Instant::now();
+        libfuzzer_test_one_input(&input);
+        eprintln!(
+            "Executed {} in {} ms",
+            path.display(),
+            start.elapsed().as_millis()
+        );
+    }
+    eprintln!("***");
+    eprintln!("*** NOTE: fuzzing was not performed, you have only");
+    eprintln!("***       executed the target code on a fixed set of inputs.");
+    eprintln!("***");
+    Ok(())
+}
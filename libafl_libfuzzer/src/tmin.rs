@@ -0,0 +1,130 @@
+//! `-minimize_crash=1`: minimize a crashing input, keeping it crashing
+
+use std::{fs, path::PathBuf};
+
+use libafl::Error;
+
+use crate::{
+    corpus::sha1_hex,
+    fork::{ForkedRun, ForkedRunner},
+    options::LibfuzzerOptions,
+};
+
+/// The default number of runs to minimize a crash with
+pub const DEFAULT_MINIMIZE_RUNS: u64 = 1 << 16;
+
+/// Minimize the crashing input, removing chunks of decreasing sizes while it keeps crashing,
+/// until no chunk can be removed or the runs are exhausted.
+/// Each smaller crashing input is written to `<artifact_prefix>minimized-from-<sha1>`, or to the exact artifact path.
+pub fn minimize_crash(options: &LibfuzzerOptions) -> Result<(), Error> {
+    let [path] = options.inputs.as_slice() else {
+        return Err(Error::illegal_argument(
+            "-minimize_crash=1 needs exactly one crashing input",
+        ));
+    };
+    let mut runner = ForkedRunner::new(options.timeout)?;
+    let mut best = fs::read(path)?;
+    if runner.run(&best)? != ForkedRun::Crash {
+        return Err(Error::illegal_argument(format!(
+            "The input {} did not crash",
+            path.display()
+        )));
+    }
+    eprintln!(
+        "CRASH_MIN: minimizing crash input: '{}' ({} bytes)",
+        path.display(),
+        best.len()
+    );
+
+    let dest = options.exact_artifact_path.clone().unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "{}minimized-from-{}",
+            options.artifact_prefix,
+            sha1_hex(&best)
+        ))
+    });
+    let runs = options.runs.unwrap_or(DEFAULT_MINIMIZE_RUNS);
+    let mut executed = 0;
+    let mut minimized = false;
+
+    'outer: loop {
+        let mut improved = false;
+        let mut chunk = best.len() / 2;
+        while chunk > 0 {
+            let mut start = 0;
+            while start < best.len() {
+                if executed >= runs {
+                    break 'outer;
+                }
+                executed += 1;
+
+                let end = (start + chunk).min(best.len());
+                let mut candidate = best[..start].to_vec();
+                candidate.extend_from_slice(&best[end..]);
+                if runner.run(&candidate)? == ForkedRun::Crash {
+                    best = candidate;
+                    improved = true;
+                    minimized = true;
+                    fs::write(&dest, &best)?;
+                    eprintln!(
+                        "CRASH_MIN: {} bytes, written to {}",
+                        best.len(),
+                        dest.display()
+                    );
+                } else {
+                    start += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+        if !improved {
+            break;
+        }
+    }
+
+    if minimized {
+        eprintln!(
+            "CRASH_MIN: failed to minimize beyond {} ({} bytes), exiting",
+            dest.display(),
+            best.len()
+        );
+    } else {
+        eprintln!("CRASH_MIN: could not minimize {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs};
+
+    use super::minimize_crash;
+    use crate::options::LibfuzzerOptions;
+
+    #[test]
+    fn test_minimize_crash() {
+        let dir = temp_dir().join(format!("libafl_libfuzzer_tmin_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (input, minimized) = (dir.join("input"), dir.join("minimized"));
+        let args = |input: &str| {
+            vec![
+                "./fuzzer".to_string(),
+                "-minimize_crash=1".to_string(),
+                format!("-exact_artifact_path={}", minimized.display()),
+                input.to_string(),
+            ]
+        };
+
+        // The test harness crashes on inputs containing `crash`
+        fs::write(&input, b"0123crash456789").unwrap();
+        let options = LibfuzzerOptions::parse(args(&input.to_string_lossy())).unwrap();
+        minimize_crash(&options).unwrap();
+        assert_eq!(fs::read(&minimized).unwrap(), b"crash");
+
+        fs::write(&input, b"fine").unwrap();
+        let options = LibfuzzerOptions::parse(args(&input.to_string_lossy())).unwrap();
+        assert!(minimize_crash(&options).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -4,6 +4,9 @@
 
 use alloc::{string::String, vec::Vec};
 
+pub mod mutators;
+pub use mutators::*;
+
 extern "C" {
     /// int LLVMFuzzerTestOneInput(const uint8_t *Data, size_t Size)
     fn LLVMFuzzerTestOneInput(data: *const u8, size: usize) -> i32;
@@ -0,0 +1,402 @@
+//! Mutators calling the `LLVMFuzzerCustomMutator` and `LLVMFuzzerCustomCrossOver` functions of libfuzzer harnesses,
+//! such as the structure-aware mutators of `libprotobuf-mutator`.
+//!
+//! The `LLVMFuzzerMutate` function, that custom mutators use to apply the default mutations,
+//! is provided here, backed by the `LibAFL` mutator given to the [`LLVMCustomMutator`] or the [`LLVMCustomCrossOver`].
+
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Debug},
+    marker::PhantomData,
+    ptr, slice,
+};
+
+use libafl::{
+    bolts::{rands::Rand, tuples::Named},
+    corpus::Corpus,
+    inputs::{BytesInput, HasBytesVec, Input, UsesInput},
+    mutators::{MutationResult, Mutator},
+    random_corpus_id,
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error,
+};
+
+extern "C" {
+    fn libafl_targets_has_libfuzzer_custom_mutator() -> i32;
+    fn libafl_targets_libfuzzer_custom_mutator(
+        data: *mut u8,
+        size: usize,
+        max_size: usize,
+        seed: u32,
+    ) -> usize;
+
+    fn libafl_targets_has_libfuzzer_custom_crossover() -> i32;
+    fn libafl_targets_libfuzzer_custom_crossover(
+        data1: *const u8,
+        size1: usize,
+        data2: *const u8,
+        size2: usize,
+        out: *mut u8,
+        max_out_size: usize,
+        seed: u32,
+    ) -> usize;
+}
+
+/// Returns if the harness defines `LLVMFuzzerCustomMutator`
+#[must_use]
+pub fn libfuzzer_has_custom_mutator() -> bool {
+    unsafe { libafl_targets_has_libfuzzer_custom_mutator() != 0 }
+}
+
+/// Returns if the harness defines `LLVMFuzzerCustomCrossOver`
+#[must_use]
+pub fn libfuzzer_has_custom_crossover() -> bool {
+    unsafe { libafl_targets_has_libfuzzer_custom_crossover() != 0 }
+}
+
+/// Mutates the bytes, at most `max_size` long, with the mutator and the state of the given context
+type MutateFn = unsafe fn(*mut (), &mut Vec<u8>, usize) -> Result<(), Error>;
+
+/// The `LibAFL` mutator used by `LLVMFuzzerMutate`, with its context, only set while a custom mutator runs
+static mut LLVM_FUZZER_MUTATE: Option<(MutateFn, *mut ())> = None;
+
+/// Mutates `bytes` with the mutator and the state of `context`, a `(&mut M, &mut S)`
+///
+/// # Safety
+/// `context` must point to a `(&mut M, &mut S)`, not otherwise borrowed.
+unsafe fn mutate_with<M, S>(
+    context: *mut (),
+    bytes: &mut Vec<u8>,
+    max_size: usize,
+) -> Result<(), Error>
+where
+    M: Mutator<BytesInput, S>,
+    S: HasMaxSize,
+{
+    let (mutator, state) = &mut *context.cast::<(&mut M, &mut S)>();
+    let mut input = BytesInput::new(core::mem::take(bytes));
+    let orig_max_size = state.max_size();
+    state.set_max_size(max_size);
+    let res = mutator.mutate(state, &mut input, 0);
+    state.set_max_size(orig_max_size);
+    *bytes = input.bytes().to_vec();
+    res.map(|_| ())
+}
+
+/// Restores the previous mutator of `LLVMFuzzerMutate` when dropped, even if the harness unwinds
+struct LLVMFuzzerMutateGuard {
+    prev: Option<(MutateFn, *mut ())>,
+}
+
+impl Drop for LLVMFuzzerMutateGuard {
+    fn drop(&mut self) {
+        unsafe {
+            LLVM_FUZZER_MUTATE = self.prev;
+        }
+    }
+}
+
+/// Run `f`, a call into the harness, with `mutator` backing `LLVMFuzzerMutate`
+fn with_llvm_fuzzer_mutate<M, S, R, F>(mutator: &mut M, state: &mut S, f: F) -> R
+where
+    M: Mutator<BytesInput, S>,
+    S: HasMaxSize,
+    F: FnOnce() -> R,
+{
+    let mut context = (mutator, state);
+    // Dropped before the context it points to
+    let _guard = unsafe {
+        let guard = LLVMFuzzerMutateGuard {
+            prev: LLVM_FUZZER_MUTATE,
+        };
+        LLVM_FUZZER_MUTATE = Some((mutate_with::<M, S>, ptr::addr_of_mut!(context).cast::<()>()));
+        guard
+    };
+    f()
+}
+
+/// Mutates `data`, `size` bytes long, in place, returning the new size, at most `max_size`.
+/// Called by custom mutators to apply the default mutations, it runs the `LibAFL` mutator of the
+/// running [`LLVMCustomMutator`] or [`LLVMCustomCrossOver`], and does nothing if called outside of them.
+///
+/// # Safety
+/// `data` must be valid for `max(size, max_size)` bytes.
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn LLVMFuzzerMutate(data: *mut u8, size: usize, max_size: usize) -> usize {
+    let Some((mutate, context)) = LLVM_FUZZER_MUTATE else {
+        return size;
+    };
+    let mut bytes = slice::from_raw_parts(data, size).to_vec();
+    if mutate(context, &mut bytes, max_size).is_err() {
+        return size;
+    }
+    let len = bytes.len().min(max_size);
+    ptr::copy_nonoverlapping(bytes.as_ptr(), data, len);
+    len
+}
+
+/// A [`Mutator`] calling the `LLVMFuzzerCustomMutator` of the harness.
+/// The mutator `M`, e.g. a havoc mutator, is used when the custom mutator calls `LLVMFuzzerMutate`.
+pub struct LLVMCustomMutator<M, S> {
+    mutator: M,
+    phantom: PhantomData<S>,
+}
+
+impl<M, S> Debug for LLVMCustomMutator<M, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LLVMCustomMutator").finish_non_exhaustive()
+    }
+}
+
+impl<M, S> LLVMCustomMutator<M, S> {
+    /// Creates a new [`LLVMCustomMutator`], with the given mutator backing `LLVMFuzzerMutate`.
+    /// Fails if the harness does not define `LLVMFuzzerCustomMutator`.
+    pub fn new(mutator: M) -> Result<Self, Error> {
+        if libfuzzer_has_custom_mutator() {
+            Ok(Self {
+                mutator,
+                phantom: PhantomData,
+            })
+        } else {
+            Err(Error::illegal_state(
+                "The harness does not define LLVMFuzzerCustomMutator",
+            ))
+        }
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for LLVMCustomMutator<M, S>
+where
+    I: HasBytesVec,
+    M: Mutator<BytesInput, S>,
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let seed = state.rand_mut().next() as u32;
+        let max_size = state.max_size();
+        let size = input.bytes().len();
+        let mut data = input.bytes().to_vec();
+        data.resize(size.max(max_size), 0);
+
+        let new_size = with_llvm_fuzzer_mutate(&mut self.mutator, state, || unsafe {
+            libafl_targets_libfuzzer_custom_mutator(data.as_mut_ptr(), size, max_size, seed)
+        });
+        if new_size == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        data.truncate(new_size.min(max_size));
+        *input.bytes_mut() = data;
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<M, S> Named for LLVMCustomMutator<M, S> {
+    fn name(&self) -> &str {
+        "LLVMCustomMutator"
+    }
+}
+
+/// A [`Mutator`] calling the `LLVMFuzzerCustomCrossOver` of the harness, with another corpus entry.
+/// The mutator `M`, e.g. a havoc mutator, is used when the custom crossover calls `LLVMFuzzerMutate`.
+pub struct LLVMCustomCrossOver<M, S> {
+    mutator: M,
+    phantom: PhantomData<S>,
+}
+
+impl<M, S> Debug for LLVMCustomCrossOver<M, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LLVMCustomCrossOver")
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M, S> LLVMCustomCrossOver<M, S> {
+    /// Creates a new [`LLVMCustomCrossOver`], with the given mutator backing `LLVMFuzzerMutate`.
+    /// Fails if the harness does not define `LLVMFuzzerCustomCrossOver`.
+    pub fn new(mutator: M) -> Result<Self, Error> {
+        if libfuzzer_has_custom_crossover() {
+            Ok(Self {
+                mutator,
+                phantom: PhantomData,
+            })
+        } else {
+            Err(Error::illegal_state(
+                "The harness does not define LLVMFuzzerCustomCrossOver",
+            ))
+        }
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for LLVMCustomCrossOver<M, S>
+where
+    I: Input + HasBytesVec,
+    M: Mutator<BytesInput, S>,
+    S: HasRand + HasMaxSize + HasCorpus + UsesInput<Input = I>,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        // We don't want to use the testcase we're already using for splicing
+        let idx = random_corpus_id!(state.corpus(), state.rand_mut());
+        if let Some(cur) = state.corpus().current() {
+            if idx == *cur {
+                return Ok(MutationResult::Skipped);
+            }
+        }
+        let other = state
+            .corpus()
+            .get(idx)?
+            .borrow_mut()
+            .load_input()?
+            .bytes()
+            .to_vec();
+
+        let seed = state.rand_mut().next() as u32;
+        let max_size = state.max_size();
+        let mut out = vec![0; max_size];
+        let data = input.bytes();
+        let new_size = with_llvm_fuzzer_mutate(&mut self.mutator, state, || unsafe {
+            libafl_targets_libfuzzer_custom_crossover(
+                data.as_ptr(),
+                data.len(),
+                other.as_ptr(),
+                other.len(),
+                out.as_mut_ptr(),
+                max_size,
+                seed,
+            )
+        });
+        if new_size == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        out.truncate(new_size.min(max_size));
+        *input.bytes_mut() = out;
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<M, S> Named for LLVMCustomCrossOver<M, S> {
+    fn name(&self) -> &str {
+        "LLVMCustomCrossOver"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::slice;
+
+    use libafl::{
+        bolts::rands::StdRand,
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        feedbacks::ConstFeedback,
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{MutationResult, Mutator},
+        state::{HasCorpus, StdState},
+        Error,
+    };
+
+    use super::{LLVMCustomCrossOver, LLVMCustomMutator, LLVMFuzzerMutate};
+
+    /// Appends a byte, for the custom mutators to apply with `LLVMFuzzerMutate`
+    struct AppendMutator;
+
+    impl<S> Mutator<BytesInput, S> for AppendMutator {
+        fn mutate(
+            &mut self,
+            _state: &mut S,
+            input: &mut BytesInput,
+            _stage_idx: i32,
+        ) -> Result<MutationResult, Error> {
+            input.bytes_mut().push(b'm');
+            Ok(MutationResult::Mutated)
+        }
+    }
+
+    #[no_mangle]
+    extern "C" fn LLVMFuzzerTestOneInput(_data: *const u8, _size: usize) -> i32 {
+        0
+    }
+
+    /// Applies the default mutations, then appends a `!`
+    #[no_mangle]
+    unsafe extern "C" fn LLVMFuzzerCustomMutator(
+        data: *mut u8,
+        size: usize,
+        max_size: usize,
+        _seed: u32,
+    ) -> usize {
+        let size = LLVMFuzzerMutate(data, size, max_size);
+        if size == max_size {
+            return size;
+        }
+        *data.add(size) = b'!';
+        size + 1
+    }
+
+    /// Concatenates both inputs, then applies the default mutations
+    #[no_mangle]
+    unsafe extern "C" fn LLVMFuzzerCustomCrossOver(
+        data1: *const u8,
+        size1: usize,
+        data2: *const u8,
+        size2: usize,
+        out: *mut u8,
+        max_out_size: usize,
+        _seed: u32,
+    ) -> usize {
+        let mut bytes: Vec<u8> = slice::from_raw_parts(data1, size1).to_vec();
+        bytes.extend_from_slice(slice::from_raw_parts(data2, size2));
+        bytes.truncate(max_out_size);
+        core::ptr::copy_nonoverlapping(bytes.as_ptr(), out, bytes.len());
+        LLVMFuzzerMutate(out, bytes.len(), max_out_size)
+    }
+
+    #[test]
+    fn test_llvm_custom_mutators() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"cd".to_vec())))
+            .unwrap();
+
+        let mut input = BytesInput::new(b"ab".to_vec());
+        let mut mutator = LLVMCustomMutator::new(AppendMutator).unwrap();
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.bytes(), b"abm!");
+
+        let mut input = BytesInput::new(b"ab".to_vec());
+        let mut crossover = LLVMCustomCrossOver::new(AppendMutator).unwrap();
+        assert_eq!(
+            crossover.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.bytes(), b"abcdm");
+
+        // Outside of a custom mutator, the bytes are left as they are
+        let mut bytes = *b"ab";
+        assert_eq!(unsafe { LLVMFuzzerMutate(bytes.as_mut_ptr(), 2, 2) }, 2);
+        assert_eq!(&bytes, b"ab");
+    }
+}
@@ -0,0 +1,145 @@
+//! `libafl-cmin`: minimize a corpus with the greedy algorithm of `afl-cmin`, in parallel.
+
+use std::{fs, path::PathBuf, thread};
+
+use afl_tools::{
+    input_files, output_file_names, run_tool, TargetOptions, Tool, ToolContext, ToolEventManager,
+    ToolFuzzer, ToolObservers, ToolState,
+};
+use clap::Parser;
+use libafl::{
+    bolts::cli::FuzzerOptions,
+    corpus::{CminWinners, Corpus, StdGreedyCorpusMinimizer, Testcase},
+    executors::{Executor, HasObservers},
+    inputs::{BytesInput, Input},
+    state::HasCorpus,
+    Error,
+};
+
+/// The commandline args of `libafl-cmin`
+#[derive(Debug, Parser)]
+#[command(
+    name = "libafl-cmin",
+    about = "Copy a minimal subset of the input corpora (-i) covering the same edges to the output directory (-o)"
+)]
+struct Opt {
+    #[command(flatten)]
+    options: FuzzerOptions,
+
+    #[command(flatten)]
+    target: TargetOptions,
+
+    /// only keep an input for each edge, ignoring the hitcounts
+    #[arg(short, long, help_heading = "Minimization Options")]
+    edges_only: bool,
+}
+
+/// Traces a shard of the corpus, returning the winners and the file of each input name
+struct CminShard {
+    inputs: Vec<PathBuf>,
+    edges_only: bool,
+}
+
+impl Tool for CminShard {
+    type Output = (CminWinners<String>, Vec<(String, PathBuf)>);
+
+    fn run<E>(self, mut executor: E) -> Result<Self::Output, Error>
+    where
+        E: Executor<ToolEventManager, ToolFuzzer>
+            + HasObservers<Observers = ToolObservers, State = ToolState>,
+    {
+        let mut ctx = ToolContext::new()?;
+        let mut names = vec![];
+        for path in self.inputs {
+            let input = BytesInput::from_file(&path)?;
+            let idx = ctx.state.corpus_mut().add(Testcase::new(input))?;
+            let name = ctx
+                .state
+                .corpus()
+                .get(idx)?
+                .borrow()
+                .input()
+                .as_ref()
+                .unwrap()
+                .generate_name(0);
+            names.push((name, path));
+        }
+
+        let observer = &executor.observers().0;
+        let minimizer = if self.edges_only {
+            StdGreedyCorpusMinimizer::new(observer)
+        } else {
+            StdGreedyCorpusMinimizer::with_hitcounts(observer)
+        };
+        let winners = minimizer.minimize_shard(
+            &mut ctx.fuzzer,
+            &mut executor,
+            &mut ctx.mgr,
+            &mut ctx.state,
+            0,
+            1,
+        )?;
+        Ok((winners, names))
+    }
+}
+
+pub fn main() -> Result<(), Error> {
+    let opt = Opt::parse();
+
+    let inputs = input_files(&opt.options.input)?;
+    let workers = opt.options.cores.ids.len().max(1);
+    println!("Tracing {} inputs with {workers} workers...", inputs.len());
+
+    let mut shards = vec![vec![]; workers];
+    for (i, input) in inputs.into_iter().enumerate() {
+        shards[i % workers].push(input);
+    }
+
+    let results = thread::scope(|scope| {
+        let handles: Vec<_> = shards
+            .into_iter()
+            .enumerate()
+            .map(|(worker, inputs)| {
+                let opt = &opt;
+                scope.spawn(move || {
+                    let shard = CminShard {
+                        inputs,
+                        edges_only: opt.edges_only,
+                    };
+                    run_tool(&opt.options, &opt.target, worker, shard)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("A worker panicked"))
+            .collect::<Result<Vec<_>, Error>>()
+    })?;
+
+    let mut winners = CminWinners::new();
+    let mut files = vec![];
+    for (shard_winners, mut shard_files) in results {
+        winners.merge(shard_winners);
+        files.append(&mut shard_files);
+    }
+
+    // Copy a file for each kept input, identical inputs have the same name
+    let mut kept = winners.kept();
+    let kept_files: Vec<PathBuf> = files
+        .into_iter()
+        .filter(|(name, _)| kept.remove(name))
+        .map(|(_, path)| path)
+        .collect();
+    fs::create_dir_all(&opt.options.output)?;
+    for (path, output_name) in kept_files.iter().zip(output_file_names(&kept_files)) {
+        fs::copy(path, opt.options.output.join(output_name))?;
+    }
+
+    println!(
+        "Kept {} inputs covering {} edges in {}",
+        winners.kept().len(),
+        winners.edges(),
+        opt.options.output.display()
+    );
+    Ok(())
+}
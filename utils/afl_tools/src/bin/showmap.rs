@@ -0,0 +1,91 @@
+//! `libafl-showmap`: write the coverage map of inputs, in the format of `afl-showmap`.
+
+use std::{fs, path::PathBuf, process};
+
+use afl_tools::{
+    input_files, output_file_names, run_tool, TargetOptions, Tool, ToolContext, ToolEventManager,
+    ToolFuzzer, ToolObservers, ToolState,
+};
+use clap::Parser;
+use libafl::{
+    bolts::cli::FuzzerOptions,
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::{BytesInput, Input},
+    observers::{showmap_dump, MapObserver},
+    Error,
+};
+
+/// The commandline args of `libafl-showmap`
+#[derive(Debug, Parser)]
+#[command(
+    name = "libafl-showmap",
+    about = "Write the coverage map of each input (-i), to a file, or to a directory if -i is a directory (-o)"
+)]
+struct Opt {
+    #[command(flatten)]
+    options: FuzzerOptions,
+
+    #[command(flatten)]
+    target: TargetOptions,
+}
+
+/// Runs each input, writing its map to the output
+struct Showmap {
+    inputs: Vec<PathBuf>,
+    outputs: Vec<PathBuf>,
+}
+
+impl Tool for Showmap {
+    type Output = Vec<ExitKind>;
+
+    fn run<E>(self, mut executor: E) -> Result<Self::Output, Error>
+    where
+        E: Executor<ToolEventManager, ToolFuzzer>
+            + HasObservers<Observers = ToolObservers, State = ToolState>,
+    {
+        let mut ctx = ToolContext::new()?;
+        let mut exit_kinds = vec![];
+        for (input, output) in self.inputs.iter().zip(&self.outputs) {
+            let input = BytesInput::from_file(input)?;
+            let exit_kind =
+                ctx.fuzzer
+                    .execute_input(&mut ctx.state, &mut executor, &mut ctx.mgr, &input)?;
+            let observer = &executor.observers().0;
+            fs::write(output, showmap_dump(observer))?;
+            println!(
+                "Captured {} tuples in {} (exit: {exit_kind:?})",
+                observer.count_bytes(),
+                output.display()
+            );
+            exit_kinds.push(exit_kind);
+        }
+        Ok(exit_kinds)
+    }
+}
+
+pub fn main() -> Result<(), Error> {
+    let opt = Opt::parse();
+
+    let inputs = input_files(&opt.options.input)?;
+    let outputs = if opt.options.input.len() == 1 && opt.options.input[0].is_file() {
+        vec![opt.options.output.clone()]
+    } else {
+        fs::create_dir_all(&opt.options.output)?;
+        output_file_names(&inputs)
+            .into_iter()
+            .map(|name| opt.options.output.join(name))
+            .collect()
+    };
+
+    let exit_kinds = run_tool(&opt.options, &opt.target, 0, Showmap { inputs, outputs })?;
+
+    // Like afl-showmap, report how a single input ended
+    if let [exit_kind] = exit_kinds[..] {
+        match exit_kind {
+            ExitKind::Timeout => process::exit(1),
+            ExitKind::Crash | ExitKind::Oom => process::exit(2),
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! `libafl-tmin`: minimize an input, keeping its coverage map, or keeping it crashing.
+
+use std::{fs, path::PathBuf};
+
+use afl_tools::{
+    run_tool, TargetOptions, Tool, ToolContext, ToolEventManager, ToolFuzzer, ToolObservers,
+    ToolState,
+};
+use clap::Parser;
+use libafl::{
+    bolts::{cli::FuzzerOptions, HasLen},
+    corpus::{Corpus, Testcase},
+    executors::{Executor, HasObservers},
+    feedbacks::{CrashFeedbackFactory, Feedback, FeedbackFactory},
+    inputs::{BytesInput, HasBytesVec, Input},
+    mutators::{havoc_mutations, StdScheduledMutator},
+    stages::{MapEqualityFactory, Stage, StdTMinMutationalStage},
+    state::HasCorpus,
+    Error,
+};
+
+/// The default number of mutations tried without progress before giving up
+const DEFAULT_RUNS: usize = 1024;
+
+/// The commandline args of `libafl-tmin`
+#[derive(Debug, Parser)]
+#[command(
+    name = "libafl-tmin",
+    about = "Minimize an input (-i) to the output file (-o), keeping its coverage map"
+)]
+struct Opt {
+    #[command(flatten)]
+    options: FuzzerOptions,
+
+    #[command(flatten)]
+    target: TargetOptions,
+
+    /// only keep the input crashing, instead of keeping its coverage map
+    #[arg(long, help_heading = "Minimization Options")]
+    crash: bool,
+}
+
+/// Minimizes a single input with a [`StdTMinMutationalStage`]
+struct Tmin {
+    input: BytesInput,
+    crash: bool,
+    runs: usize,
+}
+
+impl Tmin {
+    fn minimize<E, F, FF>(self, mut executor: E, factory: FF) -> Result<BytesInput, Error>
+    where
+        E: Executor<ToolEventManager, ToolFuzzer>
+            + HasObservers<Observers = ToolObservers, State = ToolState>,
+        F: Feedback<ToolState>,
+        FF: FeedbackFactory<F, ToolState, ToolObservers>,
+    {
+        let mut ctx = ToolContext::new()?;
+        let idx = ctx.state.corpus_mut().add(Testcase::new(self.input))?;
+
+        let mutator = StdScheduledMutator::new(havoc_mutations());
+        let mut stage = StdTMinMutationalStage::new(mutator, factory, self.runs);
+        stage.perform(
+            &mut ctx.fuzzer,
+            &mut executor,
+            &mut ctx.state,
+            &mut ctx.mgr,
+            idx,
+        )?;
+
+        let minimized = ctx.state.corpus().get(idx)?.borrow().input().clone();
+        Ok(minimized.unwrap())
+    }
+}
+
+impl Tool for Tmin {
+    type Output = BytesInput;
+
+    fn run<E>(self, executor: E) -> Result<Self::Output, Error>
+    where
+        E: Executor<ToolEventManager, ToolFuzzer>
+            + HasObservers<Observers = ToolObservers, State = ToolState>,
+    {
+        if self.crash {
+            self.minimize(executor, CrashFeedbackFactory::default())
+        } else {
+            let factory = MapEqualityFactory::new_from_observer(&executor.observers().0);
+            self.minimize(executor, factory)
+        }
+    }
+}
+
+pub fn main() -> Result<(), Error> {
+    let opt = Opt::parse();
+
+    let [input]: [PathBuf; 1] = opt
+        .options
+        .input
+        .clone()
+        .try_into()
+        .map_err(|_| Error::illegal_argument("libafl-tmin minimizes a single input file"))?;
+    let input = BytesInput::from_file(input)?;
+    let len = input.len();
+    let runs = if opt.options.iterations == 0 {
+        DEFAULT_RUNS
+    } else {
+        opt.options.iterations
+    };
+
+    let tmin = Tmin {
+        input,
+        crash: opt.crash,
+        runs,
+    };
+    let minimized = run_tool(&opt.options, &opt.target, 0, tmin)?;
+
+    fs::write(&opt.options.output, minimized.bytes())?;
+    println!(
+        "Minimized {len} to {} bytes in {}",
+        minimized.len(),
+        opt.options.output.display()
+    );
+    Ok(())
+}
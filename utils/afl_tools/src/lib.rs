@@ -0,0 +1,293 @@
+//! The code shared by `libafl-cmin`, `libafl-tmin` and `libafl-showmap`:
+//! the commandline options selecting the target and building the executor running it.
+
+use std::{
+    collections::HashSet,
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use clap::{Args, ValueEnum};
+use libafl::{
+    bolts::{
+        cli::FuzzerOptions,
+        current_nanos,
+        rands::StdRand,
+        shmem::{ShMem, ShMemProvider, UnixShMemProvider},
+        tuples::tuple_list,
+        AsMutSlice,
+    },
+    corpus::InMemoryCorpus,
+    events::SimpleEventManager,
+    executors::{
+        command::StdCommandConfigurator, CommandExecutor, Executor, ForkserverExecutor,
+        HasObservers, TimeoutForkserverExecutor,
+    },
+    fuzzer::StdFuzzer,
+    inputs::BytesInput,
+    monitors::NopMonitor,
+    observers::{HitcountsMapObserver, MapObserver, StdMapObserver},
+    schedulers::QueueScheduler,
+    state::StdState,
+    Error,
+};
+
+/// The default size of the coverage map, as in AFL++
+pub const DEFAULT_MAP_SIZE: usize = 65536;
+
+/// The name of the coverage map observer
+pub const MAP_OBSERVER_NAME: &str = "shared_mem";
+
+/// The state of the tools
+pub type ToolState =
+    StdState<BytesInput, InMemoryCorpus<BytesInput>, StdRand, InMemoryCorpus<BytesInput>>;
+/// The event manager of the tools, not reporting anything
+pub type ToolEventManager = SimpleEventManager<NopMonitor, ToolState>;
+/// The observer of the coverage map of the target
+pub type ToolObserver = HitcountsMapObserver<StdMapObserver<'static, u8, false>>;
+/// The observers of the executor of the tools
+pub type ToolObservers = (ToolObserver, ());
+/// The fuzzer of the tools, without feedback nor objective
+pub type ToolFuzzer = StdFuzzer<QueueScheduler<ToolState>, (), (), ToolObservers>;
+
+/// How to run the target
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetKind {
+    /// Through the AFL forkserver, as a [`ForkserverExecutor`]
+    Forkserver,
+    /// In a new process for each input, as a [`CommandExecutor`]
+    Command,
+}
+
+/// The options of the tools not in [`FuzzerOptions`]
+#[derive(Args, Clone, Debug)]
+pub struct TargetOptions {
+    /// how to run the target
+    #[arg(
+        long,
+        value_enum,
+        default_value = "forkserver",
+        help_heading = "Target Options"
+    )]
+    pub executor: TargetKind,
+
+    /// size of the coverage map, if the target does not report it
+    #[arg(long, default_value_t = DEFAULT_MAP_SIZE, help_heading = "Target Options")]
+    pub map_size: usize,
+}
+
+/// The state, fuzzer and event manager of a tool
+#[derive(Debug)]
+pub struct ToolContext {
+    /// The state, holding the inputs in its corpus
+    pub state: ToolState,
+    /// The fuzzer
+    pub fuzzer: ToolFuzzer,
+    /// The event manager
+    pub mgr: ToolEventManager,
+}
+
+impl ToolContext {
+    /// Creates a new [`ToolContext`], with empty corpora
+    pub fn new() -> Result<Self, Error> {
+        let state = StdState::new(
+            StdRand::with_seed(current_nanos()),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )?;
+        Ok(Self {
+            state,
+            fuzzer: StdFuzzer::new(QueueScheduler::new(), (), ()),
+            mgr: SimpleEventManager::new(NopMonitor::new()),
+        })
+    }
+}
+
+/// A tool, running on the executor built by [`run_tool`]
+pub trait Tool {
+    /// The result of the tool
+    type Output;
+
+    /// Run the tool with the given executor
+    fn run<E>(self, executor: E) -> Result<Self::Output, Error>
+    where
+        E: Executor<ToolEventManager, ToolFuzzer>
+            + HasObservers<Observers = ToolObservers, State = ToolState>;
+}
+
+/// The command line of the target: the harness set with `-H`, or the first argument after `--`,
+/// followed by the remaining arguments.
+pub fn target_cmdline(options: &FuzzerOptions) -> Result<Vec<String>, Error> {
+    let mut cmdline = vec![];
+    if let Some(harness) = &options.harness {
+        cmdline.push(harness.to_string_lossy().to_string());
+    }
+    cmdline.extend(options.harness_args.iter().cloned());
+    if cmdline.is_empty() {
+        return Err(Error::illegal_argument(
+            "No target given, pass it with -H or after --",
+        ));
+    }
+    Ok(cmdline)
+}
+
+/// The files of the given paths: files are returned as is, directories are listed (not recursively).
+pub fn input_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            let mut entries = vec![];
+            for entry in fs::read_dir(path)? {
+                let entry = entry?.path();
+                if entry.is_file() {
+                    entries.push(entry);
+                }
+            }
+            entries.sort();
+            files.append(&mut entries);
+        } else if path.is_file() {
+            files.push(path.clone());
+        } else {
+            return Err(Error::illegal_argument(format!(
+                "Input {} not found",
+                path.display()
+            )));
+        }
+    }
+    Ok(files)
+}
+
+/// Build the executor running the target, and run the tool with it.
+///
+/// Each `worker` gets its own coverage map and input file, so several workers can run in parallel threads.
+pub fn run_tool<T>(
+    options: &FuzzerOptions,
+    target: &TargetOptions,
+    worker: usize,
+    tool: T,
+) -> Result<T::Output, Error>
+where
+    T: Tool,
+{
+    let cmdline = target_cmdline(options)?;
+    let input_file = env::temp_dir().join(format!(".libafl_tools_{}_{worker}", process::id()));
+
+    // The coverage map shared with the target
+    let mut shmem_provider = UnixShMemProvider::new()?;
+    let mut shmem = shmem_provider.new_shmem(target.map_size)?;
+    let shmem_id = shmem.id().to_string();
+    let map = shmem.as_mut_slice();
+    // The shmem outlives the tool, which only runs within this function
+    let observer = HitcountsMapObserver::new(unsafe {
+        StdMapObserver::from_mut_ptr(MAP_OBSERVER_NAME, map.as_mut_ptr(), map.len())
+    });
+
+    let res = match target.executor {
+        TargetKind::Forkserver => {
+            let args = cmdline[1..].iter().map(|arg| {
+                if arg == "@@" {
+                    input_file.to_string_lossy().to_string()
+                } else {
+                    arg.clone()
+                }
+            });
+            let mut executor = ForkserverExecutor::builder()
+                .program(&cmdline[0])
+                .arg_input_file(&input_file)
+                .parse_afl_cmdline(args)
+                .debug_child(options.verbose)
+                .coverage_map_size(target.map_size)
+                .env("__AFL_SHM_ID", &shmem_id)
+                .env("AFL_MAP_SIZE", target.map_size.to_string())
+                .build(tuple_list!(observer))?;
+            if let Some(map_size) = executor.coverage_map_size() {
+                executor.observers_mut().0.downsize_map(map_size);
+            }
+            let executor = TimeoutForkserverExecutor::new(executor, options.timeout)?;
+            tool.run(executor)
+        }
+        TargetKind::Command => {
+            let mut builder = CommandExecutor::builder();
+            builder
+                .program(&cmdline[0])
+                .debug_child(options.verbose)
+                .env("__AFL_SHM_ID", &shmem_id)
+                .env("AFL_MAP_SIZE", target.map_size.to_string());
+            for arg in &cmdline[1..] {
+                if arg == "@@" {
+                    builder.arg_input_file(&input_file);
+                } else {
+                    builder.arg(arg);
+                }
+            }
+            let executor: CommandExecutor<
+                ToolEventManager,
+                ToolObservers,
+                ToolState,
+                StdCommandConfigurator,
+                ToolFuzzer,
+            > = builder.build(tuple_list!(observer))?;
+            tool.run(executor)
+        }
+    };
+
+    drop(fs::remove_file(&input_file));
+    res
+}
+
+/// The name of the given file, to name the outputs after the inputs
+#[must_use]
+pub fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || "input".to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+/// The names of the outputs of the given files, in order, like [`file_name`].
+/// Files of different directories with the same name get a numbered suffix, e.g. `name_1`,
+/// instead of overwriting each other's output.
+#[must_use]
+pub fn output_file_names(paths: &[PathBuf]) -> Vec<String> {
+    let mut used = HashSet::new();
+    paths
+        .iter()
+        .map(|path| {
+            let name = file_name(path);
+            let unique = (0..)
+                .map(|i| {
+                    if i == 0 {
+                        name.clone()
+                    } else {
+                        format!("{name}_{i}")
+                    }
+                })
+                .find(|candidate| !used.contains(candidate))
+                .unwrap();
+            if unique != name {
+                println!(
+                    "{} has the same name as another input, its output is {unique}",
+                    path.display()
+                );
+            }
+            used.insert(unique.clone());
+            unique
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::output_file_names;
+
+    #[test]
+    fn test_output_file_names() {
+        let paths = ["a/id", "b/id", "a/id_1", "c/id"].map(PathBuf::from);
+        assert_eq!(output_file_names(&paths), ["id", "id_1", "id_1_1", "id_2"]);
+    }
+}
@@ -0,0 +1,67 @@
+//! Runs `libafl-cmin` on a corpus with duplicate files.
+//!
+//! This binary doubles as the target: with `TARGET_ENV` set, it marks one map entry per input byte
+//! in the coverage map given by `__AFL_SHM_ID`.
+
+use std::{collections::BTreeSet, env, fs, process::Command};
+
+use libafl::bolts::{
+    shmem::{ShMemId, ShMemProvider, UnixShMemProvider},
+    AsMutSlice,
+};
+
+const TARGET_ENV: &str = "AFL_TOOLS_TEST_TARGET";
+
+fn target() {
+    let input = fs::read(env::args().nth(1).expect("no input file given")).unwrap();
+    let id = env::var("__AFL_SHM_ID").unwrap();
+    let size = env::var("AFL_MAP_SIZE").unwrap().parse().unwrap();
+    let mut shmem = UnixShMemProvider::new()
+        .unwrap()
+        .shmem_from_id_and_size(ShMemId::from_string(&id), size)
+        .unwrap();
+    let map = shmem.as_mut_slice();
+    for byte in input {
+        map[byte as usize] = 1;
+    }
+}
+
+fn main() {
+    if env::var_os(TARGET_ENV).is_some() {
+        target();
+        return;
+    }
+
+    let dir = env::temp_dir().join(format!("afl_tools_cmin_test_{}", std::process::id()));
+    let (input, output) = (dir.join("in"), dir.join("out"));
+    drop(fs::remove_dir_all(&dir));
+    fs::create_dir_all(&input).unwrap();
+    // `a` and `b` are duplicates, `d` is covered by them
+    fs::write(input.join("a"), "ab").unwrap();
+    fs::write(input.join("b"), "ab").unwrap();
+    fs::write(input.join("c"), "cd").unwrap();
+    fs::write(input.join("d"), "a").unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_libafl-cmin"))
+        .arg("-i")
+        .arg(&input)
+        .arg("-o")
+        .arg(&output)
+        .args(["--executor", "command", "--"])
+        .arg(env::current_exe().unwrap())
+        .arg("@@")
+        .env(TARGET_ENV, "1")
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let kept: Vec<_> = fs::read_dir(&output)
+        .unwrap()
+        .map(|entry| fs::read(entry.unwrap().path()).unwrap())
+        .collect();
+    let contents: BTreeSet<_> = kept.iter().cloned().collect();
+    assert_eq!(kept.len(), 2);
+    assert_eq!(contents, BTreeSet::from([b"ab".to_vec(), b"cd".to_vec()]));
+
+    fs::remove_dir_all(&dir).unwrap();
+}